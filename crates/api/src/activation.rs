@@ -0,0 +1,88 @@
+use std::os::fd::{FromRawFd, OwnedFd, RawFd};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// First fd of the `LISTEN_FDS` layout.
+pub const LISTEN_FDS_START: RawFd = 3;
+
+static TAKEN: AtomicBool = AtomicBool::new(false);
+
+/// A socket passed in at activation. `fd` converts into the std socket
+/// types, e.g. `UnixListener::from(listen_fd.fd)`.
+#[derive(Debug)]
+pub struct ListenFd {
+  pub name: String,
+  pub fd: OwnedFd,
+}
+
+/// Parses `LISTEN_PID`, `LISTEN_FDS` and `LISTEN_FDNAMES` as seen by the
+/// process `pid`. Returns the fd numbers with their names, or nothing when
+/// the variables are missing or meant for another process.
+pub fn parse_listen_fds(
+  listen_pid: Option<&str>,
+  listen_fds: Option<&str>,
+  listen_fdnames: Option<&str>,
+  pid: u32,
+) -> Result<Vec<(RawFd, String)>, String> {
+  let (Some(listen_pid), Some(listen_fds)) = (listen_pid, listen_fds) else {
+    return Ok(Vec::new());
+  };
+  let listen_pid: u32 = listen_pid
+    .trim()
+    .parse()
+    .map_err(|_| format!("invalid LISTEN_PID '{listen_pid}'"))?;
+  if listen_pid != pid {
+    return Ok(Vec::new());
+  }
+  let count: usize = listen_fds
+    .trim()
+    .parse()
+    .map_err(|_| format!("invalid LISTEN_FDS '{listen_fds}'"))?;
+
+  let names: Vec<String> = match listen_fdnames {
+    Some(names) => names.split(':').map(str::to_string).collect(),
+    None => vec!["unknown".to_string(); count],
+  };
+  if names.len() != count {
+    return Err(format!(
+      "LISTEN_FDNAMES has {} names for {count} fds",
+      names.len()
+    ));
+  }
+
+  Ok(
+    names
+      .into_iter()
+      .enumerate()
+      .map(|(idx, name)| (LISTEN_FDS_START + idx as RawFd, name))
+      .collect(),
+  )
+}
+
+/// Takes ownership of the sockets this process was activated with. Only the
+/// first call returns them; the fds are marked close-on-exec so they don't
+/// leak into children.
+pub fn listen_fds() -> Result<Vec<ListenFd>, String> {
+  let pid = std::process::id();
+  let var = |key: &str| std::env::var(key).ok();
+  let fds = parse_listen_fds(
+    var("LISTEN_PID").as_deref(),
+    var("LISTEN_FDS").as_deref(),
+    var("LISTEN_FDNAMES").as_deref(),
+    pid,
+  )?;
+  if fds.is_empty() || TAKEN.swap(true, Ordering::SeqCst) {
+    return Ok(Vec::new());
+  }
+
+  fds
+    .into_iter()
+    .map(|(fd, name)| {
+      if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+        return Err(format!("fd {fd}: {}", std::io::Error::last_os_error()));
+      }
+      // The handoff gives this process sole ownership of the fd.
+      let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+      Ok(ListenFd { name, fd })
+    })
+    .collect()
+}
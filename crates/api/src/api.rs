@@ -1,5 +1,7 @@
+pub mod activation;
 pub mod msg;
 pub mod transport;
 
+pub use activation::*;
 pub use msg::*;
 pub use transport::*;
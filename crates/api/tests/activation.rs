@@ -0,0 +1,42 @@
+use rind_api::activation::{LISTEN_FDS_START, parse_listen_fds};
+
+#[test]
+fn listen_fds_are_numbered_from_three_with_names() {
+  let fds = parse_listen_fds(Some("42"), Some("2"), Some("http:admin"), 42).unwrap();
+  assert_eq!(
+    fds,
+    vec![
+      (LISTEN_FDS_START, "http".to_string()),
+      (LISTEN_FDS_START + 1, "admin".to_string())
+    ]
+  );
+
+  let unnamed = parse_listen_fds(Some("42"), Some("1"), None, 42).unwrap();
+  assert_eq!(unnamed, vec![(3, "unknown".to_string())]);
+}
+
+#[test]
+fn listen_fds_for_other_processes_are_ignored() {
+  assert!(
+    parse_listen_fds(Some("41"), Some("2"), None, 42)
+      .unwrap()
+      .is_empty()
+  );
+  assert!(
+    parse_listen_fds(None, Some("2"), None, 42)
+      .unwrap()
+      .is_empty()
+  );
+  assert!(
+    parse_listen_fds(Some("42"), None, None, 42)
+      .unwrap()
+      .is_empty()
+  );
+}
+
+#[test]
+fn malformed_listen_fds_are_rejected() {
+  assert!(parse_listen_fds(Some("pid"), Some("1"), None, 42).is_err());
+  assert!(parse_listen_fds(Some("42"), Some("many"), None, 42).is_err());
+  assert!(parse_listen_fds(Some("42"), Some("2"), Some("only-one"), 42).is_err());
+}
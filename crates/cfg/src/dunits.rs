@@ -7,7 +7,8 @@ use rind_core::prelude::*;
 use rind_flow::transport::{
   TransportMethod, TransportProtocolId, TransportRoute, TransportRouteMetadata,
 };
-use rind_flow::{FlowFacet, FlowFacetMetadata, FlowImpulse, FlowImpulseMetadata};
+use rind_flow::triggers::validate_condition;
+use rind_flow::{FlowFacet, FlowFacetMetadata, FlowImpulse, FlowImpulseMetadata, FlowItem};
 use rind_ipc::FlowPayloadType;
 use rind_primitives::mounts::Mount;
 use rind_primitives::prelude::{Permission, Variable};
@@ -125,6 +126,33 @@ fn add_builtin_defs(metadata: &mut Metadata) {
       ]),
       ..Default::default()
     })
+    .insert::<FlowImpulse>(FlowImpulseMetadata {
+      name: "oom_killed".into(),
+      payload: FlowPayloadType::Json,
+      subscribers: Some(vec![
+        TransportMethod::Type(TransportProtocolId("route:rind:sys-uds".into())),
+        TransportMethod::Type(TransportProtocolId("route:rind:sys-shm".into())),
+      ]),
+      ..Default::default()
+    })
+    .insert::<FlowImpulse>(FlowImpulseMetadata {
+      name: "memory_pressure".into(),
+      payload: FlowPayloadType::Json,
+      subscribers: Some(vec![
+        TransportMethod::Type(TransportProtocolId("route:rind:sys-uds".into())),
+        TransportMethod::Type(TransportProtocolId("route:rind:sys-shm".into())),
+      ]),
+      ..Default::default()
+    })
+    .insert::<FlowImpulse>(FlowImpulseMetadata {
+      name: "socket_throttled".into(),
+      payload: FlowPayloadType::Json,
+      subscribers: Some(vec![
+        TransportMethod::Type(TransportProtocolId("route:rind:sys-uds".into())),
+        TransportMethod::Type(TransportProtocolId("route:rind:sys-shm".into())),
+      ]),
+      ..Default::default()
+    })
     .insert::<FlowImpulse>(FlowImpulseMetadata {
       name: "boot".into(),
       payload: FlowPayloadType::String,
@@ -198,6 +226,8 @@ pub fn create_units_metadata<P: AsRef<Path>>(
     }),
   )?;
 
+  validate_unit_conditions(&metadata)?;
+
   EXTENSIONS.with(|extensions| {
     extensions
       .get()
@@ -213,6 +243,55 @@ pub fn create_units_metadata<P: AsRef<Path>>(
   Ok(Void)
 }
 
+/// Rejects units whose conditions can never be evaluated, such as a `regex`
+/// that doesn't compile, naming the unit so it can be fixed.
+fn validate_unit_conditions(metadata: &Metadata) -> CoreResult<Void> {
+  fn check<'a>(
+    group: &Ustr,
+    name: &Ustr,
+    conditions: impl IntoIterator<Item = &'a Option<Vec<FlowItem>>>,
+  ) -> CoreResult<Void> {
+    for cond in conditions.into_iter().flatten().flatten() {
+      validate_condition(cond)
+        .map_err(|e| CoreError::Custom(format!("unit {group}@{name}: {e}")))?;
+    }
+    Ok(Void)
+  }
+
+  for group in metadata.groups() {
+    for svc in metadata
+      .get_in_group::<Service>(group.clone())
+      .into_iter()
+      .flatten()
+    {
+      check(&group, &svc.name, [&svc.start_on, &svc.stop_on])?;
+    }
+    for socket in metadata
+      .get_in_group::<Socket>(group.clone())
+      .into_iter()
+      .flatten()
+    {
+      check(&group, &socket.name, [&socket.start_on, &socket.stop_on])?;
+    }
+    for facet in metadata
+      .get_in_group::<FlowFacet>(group.clone())
+      .into_iter()
+      .flatten()
+    {
+      check(&group, &facet.name, [&facet.after])?;
+    }
+    for impulse in metadata
+      .get_in_group::<FlowImpulse>(group.clone())
+      .into_iter()
+      .flatten()
+    {
+      check(&group, &impulse.name, [&impulse.after])?;
+    }
+  }
+
+  Ok(Void)
+}
+
 pub fn build_indexes(ctx: &mut OrchestratorContext<'_>, scope: &str) -> CoreResult<Void> {
   ctx.metadata.ensure_index_for_type::<Service>(scope)?;
   ctx.metadata.ensure_index_for_type::<Mount>(scope)?;
@@ -13,6 +13,7 @@ use std::path::PathBuf;
 use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::user::{handle_ipc_login, handle_ipc_logout, handle_ipc_run0};
 use rind_core::prelude::*;
@@ -21,7 +22,8 @@ use rind_core::types::Ustr;
 use rind_flow::{FacetGraph, FlowFacet, FlowImpulse};
 use rind_ipc::payloads::{ScopeCreatePayload, ScopeDestroyPayload};
 use rind_ipc::ser::{
-  MountSerialized, ServiceSerialized, UnitItemsSerialized, UnitSerialized, serialize_many,
+  ExitRecordSerialized, InstanceUsageSerialized, MountSerialized, ServiceSerialized,
+  TimerSerialized, UnitItemsSerialized, UnitSerialized, serialize_many,
 };
 use rind_primitives::mounts::{Mount, is_mounted};
 use rind_primitives::permissions::PERM_LOGIN;
@@ -31,7 +33,11 @@ use rind_primitives::permissions::{
 use rind_primitives::scopes::ScopeStore;
 use rind_primitives::variables::VariableHeap;
 use rind_services::sockets::{Socket, handle_ipc_start_socket, handle_ipc_stop_socket};
-use rind_services::{Service, handle_ipc_start, handle_ipc_stop};
+use rind_services::timers::{Timer, TimerMode, TimerStamps};
+use rind_services::{
+  ExitHistory, ExitRecord, ExitStatus, Service, StopOutcome, effective_capabilities,
+  handle_ipc_reload, handle_ipc_start, handle_ipc_stop,
+};
 
 pub const IPC_RUNTIME_ID: &str = "ipc";
 
@@ -65,6 +71,26 @@ pub fn payload_msg(mut payload: RuntimePayload) -> Result<Message, CoreError> {
   payload.get::<Message>("message")
 }
 
+fn serialize_exit_record(record: &ExitRecord) -> ExitRecordSerialized {
+  let (signal, core_dumped) = match record.status {
+    ExitStatus::Code(_) => (None, false),
+    ExitStatus::Signal {
+      signal,
+      core_dumped,
+    } => (Some(signal), core_dumped),
+  };
+  ExitRecordSerialized {
+    instance: record.instance.clone(),
+    code: record.status.code(),
+    signal,
+    core_dumped,
+    trigger: format!("{:?}", record.trigger),
+    stop: record.stop.map(|x| format!("{x:?}")),
+    runtime_ms: record.runtime_ms,
+    timestamp: record.timestamp,
+  }
+}
+
 fn build_ipc_list_response(
   payload: ListPayload,
   ctx: &mut RuntimeContext<'_>,
@@ -93,14 +119,14 @@ fn build_ipc_list_response(
       .metadata
       .group_items::<Service>(scope, group)
       .unwrap_or_default();
-    let ser_instances: HashMap<Ustr, (String, Vec<u32>)> = services
+    let ser_instances: HashMap<Ustr, (String, Vec<u32>, Option<StopOutcome>)> = services
       .iter()
       .filter_map(|ser| {
         let scoped = Ustr::from(format!("{}:{}@{}", group, ser.name, scope));
         ctx.registry.as_one::<Service>("*", scoped).ok().map(|x| {
           (
             ser.name.clone(),
-            (x.instances.last_state(), x.instances.pid()),
+            (x.instances.last_state(), x.instances.pid(), x.last_stop),
           )
         })
       })
@@ -138,6 +164,13 @@ fn build_ipc_list_response(
               .get(svc.name())
               .map_or(None, |x| if x.1.is_empty() { None } else { Some(x.1[0]) }),
             restart: svc.restart.as_ref().map_or(false, |_| true),
+            last_stop: ser_instances
+              .get(svc.name())
+              .and_then(|x| x.2)
+              .map(|x| format!("{x:?}")),
+            capabilities: None,
+            usage: Vec::new(),
+            history: Vec::new(),
           })
           .collect(),
         sockets: ctx
@@ -169,15 +202,11 @@ fn build_ipc_list_response(
           .map(|st| FacetSerialized {
             name: st.name.clone(),
             instances: sm
-              .facets
               .get(&Ustr::from(format!(
                 "{}:{}@{}",
                 payload.name, st.name, scope
               )))
-              .or_else(|| {
-                sm.facets
-                  .get(&Ustr::from(format!("{}:{}", payload.name, st.name)))
-              })
+              .or_else(|| sm.get(&Ustr::from(format!("{}:{}", payload.name, st.name))))
               .map_or(Default::default(), |x| {
                 x.iter()
                   .map(|x| ser_to_vec(&x.payload.to_json(), false))
@@ -228,6 +257,39 @@ fn build_ipc_list_response(
         last_state: service.instances.last_state(),
         pid: service.instances.pid().get(0).cloned(),
         restart: service.metadata.restart.as_ref().map_or(false, |_| true),
+        last_stop: service.last_stop.map(|x| format!("{x:?}")),
+        capabilities: service
+          .instances
+          .pid()
+          .first()
+          .and_then(|pid| effective_capabilities(*pid)),
+        usage: service
+          .instances
+          .iter()
+          .filter_map(|inst| {
+            let usage = inst.cgroup_usage()?;
+            Some(InstanceUsageSerialized {
+              instance: inst.key.clone(),
+              memory_current: usage.memory_current,
+              cpu_usage_usec: usage.cpu_usage_usec,
+              cpu_user_usec: usage.cpu_user_usec,
+              cpu_system_usec: usage.cpu_system_usec,
+              io_read_bytes: usage.io_read_bytes,
+              io_write_bytes: usage.io_write_bytes,
+              pids_current: usage.pids_current,
+            })
+          })
+          .collect(),
+        history: ctx
+          .registry
+          .singleton::<ExitHistory>(ExitHistory::KEY)
+          .map(|history| {
+            history
+              .get(payload.name.as_str())
+              .map(serialize_exit_record)
+              .collect()
+          })
+          .unwrap_or_default(),
         run: service
           .metadata
           .run
@@ -264,9 +326,62 @@ fn build_ipc_list_response(
       }
       .serialize(),
     )
+  } else if payload.unit_type == "timer" {
+    let Some(timer_meta) = ctx
+      .registry
+      .metadata
+      .find::<Timer>("*", payload.name.clone())
+    else {
+      return Err(CoreError::MetadataNotFound(format!(
+        "Timer not found: {}",
+        payload.name
+      )));
+    };
+
+    let epoch_secs = |at: SystemTime| at.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let timer = ctx
+      .registry
+      .as_one::<Timer>("*", Ustr::from(payload.name.as_str()))
+      .ok();
+    let last_elapse = timer.and_then(|x| x.last_elapse).or_else(|| {
+      ctx
+        .registry
+        .singleton::<TimerStamps>(TimerStamps::KEY)
+        .and_then(|stamps| stamps.last(payload.name.as_str()))
+    });
+
+    Message::from_type(MessageType::Ok).with(
+      TimerSerialized {
+        name: timer_meta.name.clone(),
+        schedule: timer_meta.schedule(),
+        active: timer.is_some(),
+        repeat: timer.map_or(timer_meta.repeat || timer_meta.on_calendar.is_some(), |x| {
+          !matches!(x.mode, TimerMode::Once(_))
+        }),
+        persistent: timer_meta.persistent,
+        next_elapse: timer.and_then(|x| x.next_elapse).map(epoch_secs),
+        last_elapse: last_elapse.map(epoch_secs),
+        unit: timer_meta.unit.clone(),
+        running: timer.is_some_and(|x| x.running),
+        runs: timer_meta
+          .unit
+          .as_ref()
+          .and_then(|unit| {
+            let history = ctx.registry.singleton::<ExitHistory>(ExitHistory::KEY)?;
+            Some(
+              history
+                .get(unit.as_str())
+                .map(serialize_exit_record)
+                .collect(),
+            )
+          })
+          .unwrap_or_default(),
+      }
+      .serialize(),
+    )
   } else if payload.unit_type == "facet" && !payload.name.is_empty() {
     let name_ustr = Ustr::from(payload.name.as_str());
-    let instances = sm.facets.get(&name_ustr);
+    let instances = sm.get(&name_ustr);
     let Some(def) = ctx
       .registry
       .metadata
@@ -296,10 +411,8 @@ fn build_ipc_list_response(
       .serialize(),
     )
   } else if payload.unit_type == "facet" {
-    let facets = &sm.facets;
-
     Message::from_type(MessageType::Ok).with(ser_to_vec(
-      &facets
+      &sm
         .iter()
         .filter_map(|(name, inst)| {
           let def = ctx
@@ -481,9 +594,8 @@ fn build_ipc_list_response(
         let active_facets = facets
           .iter()
           .filter(|(scope, s)| {
-            sm.facets
-              .get(&Ustr::from(format!("{group}:{}@{}", s.name, scope)))
-              .or_else(|| sm.facets.get(&Ustr::from(format!("{group}:{}", s.name))))
+            sm.get(&Ustr::from(format!("{group}:{}@{}", s.name, scope)))
+              .or_else(|| sm.get(&Ustr::from(format!("{group}:{}", s.name))))
               .is_some()
           })
           .count();
@@ -764,6 +876,7 @@ impl IpcRuntime {
     ipcsrc.register("run0", handle_ipc_run0, PermissionExpr::All);
     ipcsrc.register("start_service", handle_ipc_start, PermissionExpr::All);
     ipcsrc.register("stop_service", handle_ipc_stop, PermissionExpr::All);
+    ipcsrc.register("reload_service", handle_ipc_reload, PermissionExpr::All);
     ipcsrc.register("start_socket", handle_ipc_start_socket, PermissionExpr::All);
     ipcsrc.register("stop_socket", handle_ipc_stop_socket, PermissionExpr::All);
     ipcsrc.register("start", handle_ipc_start_unknown, PermissionExpr::All);
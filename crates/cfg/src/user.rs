@@ -391,7 +391,7 @@ impl UserRuntime {
         .registry
         .singleton_mut::<FacetGraph>(FacetGraph::KEY)
         .ok_or_else(|| CoreError::InvalidState("state machine store not found".into()))?;
-      if let Some(users) = sm.facets.get_mut(&key) {
+      if let Some(users) = sm.get_mut(&key) {
         for user in users.iter_mut() {
           let username = user.payload.get_json_field_as::<String>("username").ok_or(
             CoreError::MissingField {
@@ -65,10 +65,64 @@ payload = "json"
 after = [{ facet = "test:user_session" }]
 branch = ["tty:seat"]
 
+[[facet]]
+name = "heartbeat"
+payload = "json"
+branch = ["peer"]
+ttl = "60s"
+
+[[facet]]
+name = "uptime"
+payload = "string"
+auto-payload = { eval = "/bin/sh", args = ["-c", "date +%s%N"], refresh = "1m" }
+
+[[facet]]
+name = "mounts"
+payload = "json"
+branch = ["mount"]
+auto-payload = { eval = "/bin/sh", args = ["-c", "echo /; echo /home"], insert = "mount", many = true }
+
+[[facet]]
+name = "link"
+payload = "json"
+branch = ["ifname"]
+
+[[service]]
+name = "link_worker"
+run.exec = "/bin/sh"
+run.args = ["-c", "sleep 1"]
+start-on = [{ facet = "test:link", debounce = "200ms" }]
+restart = false
+
 [[timer]]
 name = "tick"
 duration = "5s"
 
+[[timer]]
+name = "job_tick"
+duration = "5s"
+repeat = true
+unit = "test:job"
+
+[[service]]
+name = "job"
+type = "job"
+run.exec = "/bin/sh"
+run.args = ["-c", "exit 3"]
+restart = false
+
+[[timer]]
+name = "broken_tick"
+duration = "5s"
+repeat = true
+unit = "test:broken_job"
+
+[[service]]
+name = "broken_job"
+type = "job"
+run.exec = "/nonexistent/rind-broken-job"
+restart = false
+
 [[socket]]
 name = "listener"
 type = "tcp"
@@ -199,8 +253,8 @@ fn flow_runtime_reconciles_dependent_state_and_remove() {
       let sm = registry
         .singleton::<FacetGraph>(FacetGraph::KEY)
         .expect("state machine should exist");
-      assert!(sm.facets.contains_key(&Ustr::from("test:base")));
-      assert!(sm.facets.contains_key(&Ustr::from("test:derived")));
+      assert!(sm.contains_key(&Ustr::from("test:base")));
+      assert!(sm.contains_key(&Ustr::from("test:derived")));
     })
     .expect("state assertions should succeed");
 
@@ -222,8 +276,8 @@ fn flow_runtime_reconciles_dependent_state_and_remove() {
       let sm = registry
         .singleton::<FacetGraph>(FacetGraph::KEY)
         .expect("state machine should exist");
-      assert!(!sm.facets.contains_key(&Ustr::from("test:base")));
-      assert!(!sm.facets.contains_key(&Ustr::from("test:derived")));
+      assert!(!sm.contains_key(&Ustr::from("test:base")));
+      assert!(!sm.contains_key(&Ustr::from("test:derived")));
     })
     .expect("remove assertions should succeed");
 
@@ -327,6 +381,100 @@ fn timer_runtime_start_and_finish_cleans_instance() {
   let _ = runtime.send(RuntimeCommand::Stop);
 }
 
+#[test]
+fn timer_unit_runs_job_and_rearms_after_it_exits() {
+  let (runtime, metadata, mut resources, context_id) = setup_runtime_with_metadata();
+
+  for action in ["start", "finish_timer"] {
+    runtime
+      .dispatch(
+        "timer",
+        action,
+        rind_core::rpayload!({ "name": Ustr::from("test:job_tick") }),
+        context_id,
+      )
+      .expect("timer action should queue");
+    flush(&runtime, context_id, &metadata, &mut resources);
+  }
+
+  let pid = runtime
+    .with_instances(|instances| {
+      let registry = InstanceRegistry::new(&metadata, instances);
+      let timer = registry
+        .as_one::<Timer>("units", "test:job_tick")
+        .expect("timer should wait for its job");
+      assert!(timer.running);
+      assert!(timer.awaiting_job);
+      registry
+        .as_one::<Service>("units", "test:job")
+        .expect("job should start on elapse")
+        .instances
+        .as_one()
+        .and_then(|inst| inst.pid())
+        .unwrap_or_default()
+    })
+    .expect("timer inspection should succeed");
+  assert_ne!(pid, 0);
+
+  runtime
+    .dispatch(
+      "timer",
+      "reconcile_timers",
+      rind_core::rpayload!({
+        "service": Ustr::from("test:job@units"),
+        "action": ServiceEventKind::Exited { code: 3 }
+      }),
+      context_id,
+    )
+    .expect("job exit should queue");
+  flush(&runtime, context_id, &metadata, &mut resources);
+
+  runtime
+    .with_instances(|instances| {
+      let registry = InstanceRegistry::new(&metadata, instances);
+      let timer = registry
+        .as_one::<Timer>("units", "test:job_tick")
+        .expect("repeating timer should stay armed");
+      assert!(!timer.running);
+      assert!(!timer.awaiting_job);
+      assert_eq!(timer.last_exit, Some(3));
+    })
+    .expect("timer rearm assertion should succeed");
+
+  let _ = runtime.send(RuntimeCommand::Stop);
+}
+
+#[test]
+fn timer_rearms_when_its_unit_fails_to_start() {
+  let (runtime, metadata, mut resources, context_id) = setup_runtime_with_metadata();
+
+  for action in ["start", "finish_timer"] {
+    runtime
+      .dispatch(
+        "timer",
+        action,
+        rind_core::rpayload!({ "name": Ustr::from("test:broken_tick") }),
+        context_id,
+      )
+      .expect("timer action should queue");
+    flush(&runtime, context_id, &metadata, &mut resources);
+  }
+
+  runtime
+    .with_instances(|instances| {
+      let registry = InstanceRegistry::new(&metadata, instances);
+      let timer = registry
+        .as_one::<Timer>("units", "test:broken_tick")
+        .expect("repeating timer should stay armed");
+      assert!(!timer.running);
+      assert!(!timer.awaiting_job);
+      assert_eq!(timer.last_exit, None);
+    })
+    .expect("timer rearm assertion should succeed");
+
+  let _ = runtime.send(RuntimeCommand::Stop);
+}
+
 #[test]
 fn service_runtime_start_and_child_exit_updates_instance_group() {
   let (runtime, metadata, mut resources, context_id) = setup_runtime_with_metadata();
@@ -653,7 +801,7 @@ fn race_like_dispatch_churn_keeps_runtime_consistent() {
       let sm = registry
         .singleton::<FacetGraph>(FacetGraph::KEY)
         .expect("state machine should exist");
-      if let Some(branches) = sm.facets.get(&Ustr::from("test:base")) {
+      if let Some(branches) = sm.get(&Ustr::from("test:base")) {
         for b in branches {
           assert_eq!(b.name, Ustr::from("test:base"));
         }
@@ -708,15 +856,15 @@ fn facet_transcendence_with_branch_mapping() {
         .singleton::<FacetGraph>(FacetGraph::KEY)
         .expect("state machine should exist");
       assert!(
-        sm.facets.contains_key(&Ustr::from("test:user_session")),
+        sm.contains_key(&Ustr::from("test:user_session")),
         "user_session should be in graph"
       );
       assert!(
-        sm.facets.contains_key(&Ustr::from("test:niri_active")),
+        sm.contains_key(&Ustr::from("test:niri_active")),
         "niri_active should be transcended from user_session: facets={:?}",
-        sm.facets.keys().collect::<Vec<_>>()
+        sm.keys().collect::<Vec<_>>()
       );
-      if let Some(branches) = sm.facets.get(&Ustr::from("test:niri_active")) {
+      if let Some(branches) = sm.get(&Ustr::from("test:niri_active")) {
         let first = branches.first().expect("niri_active should have a branch");
         if let rind_ipc::FlowPayload::Json(j) = &first.payload {
           let obj = j.into_json();
@@ -738,3 +886,180 @@ fn facet_transcendence_with_branch_mapping() {
 
   let _ = runtime.send(RuntimeCommand::Stop);
 }
+
+#[test]
+fn facet_branches_expire_after_their_ttl() {
+  let (runtime, metadata, mut resources, context_id) = setup_runtime_with_metadata();
+  let heartbeat = Ustr::from("test:heartbeat");
+
+  for (peer, ttl) in [("a", Some("1")), ("b", None)] {
+    let mut payload =
+      FlowRuntimePayload::new("test:heartbeat").payload(serde_json::json!({ "peer": peer }));
+    if let Some(ttl) = ttl {
+      payload = payload.ttl(ttl);
+    }
+    runtime
+      .dispatch("flow", "set_facet", payload.into(), context_id)
+      .expect("set_facet should queue");
+    flush(&runtime, context_id, &metadata, &mut resources);
+  }
+
+  runtime
+    .with_instances(|instances| {
+      let registry = InstanceRegistry::new(&metadata, instances);
+      let sm = registry
+        .singleton::<FacetGraph>(FacetGraph::KEY)
+        .expect("facet graph should exist");
+      let ttl_of = |peer: &str| {
+        sm.expiry(&heartbeat, &[format!("\"{peer}\"")])
+          .map(|e| e.ttl)
+      };
+      assert_eq!(ttl_of("a"), Some(std::time::Duration::from_secs(1)));
+      assert_eq!(ttl_of("b"), Some(std::time::Duration::from_secs(60)));
+    })
+    .expect("expiry assertions should succeed");
+
+  std::thread::sleep(std::time::Duration::from_millis(1100));
+  runtime
+    .dispatch("flow", "expire_facets", Default::default(), context_id)
+    .expect("expire_facets should queue");
+  flush(&runtime, context_id, &metadata, &mut resources);
+
+  runtime
+    .with_instances(|instances| {
+      let registry = InstanceRegistry::new(&metadata, instances);
+      let sm = registry
+        .singleton::<FacetGraph>(FacetGraph::KEY)
+        .expect("facet graph should exist");
+      let peers: Vec<String> = sm[&heartbeat]
+        .iter()
+        .map(|b| b.payload.to_string_payload())
+        .collect();
+      assert_eq!(peers, [r#"{"peer":"b"}"#]);
+      assert!(sm.expiry(&heartbeat, &["\"b\"".to_string()]).is_some());
+      assert!(sm.next_expiry().is_some());
+    })
+    .expect("expired branch should be reverted");
+
+  let _ = runtime.send(RuntimeCommand::Stop);
+}
+
+#[test]
+fn debounced_start_on_waits_for_the_facet_to_settle() {
+  let (runtime, metadata, mut resources, context_id) = setup_runtime_with_metadata();
+
+  for (runtime_id, action) in [
+    ("flow", "bootstrap"),
+    ("services", "bootstrap"),
+    ("events", "watch_events"),
+  ] {
+    runtime
+      .dispatch(runtime_id, action, Default::default(), context_id)
+      .expect("setup should queue");
+  }
+  runtime
+    .dispatch(
+      "flow",
+      "set_facet",
+      FlowRuntimePayload::new("test:link")
+        .payload(serde_json::json!({ "ifname": "eth0" }))
+        .into(),
+      context_id,
+    )
+    .expect("set_facet should queue");
+  flush(&runtime, context_id, &metadata, &mut resources);
+  runtime
+    .dispatch("events", "drain_events", Default::default(), context_id)
+    .expect("drain_events should queue");
+  for _ in 0..3 {
+    flush(&runtime, context_id, &metadata, &mut resources);
+  }
+
+  let started = |runtime: &RuntimeHandle| {
+    runtime
+      .with_instances(|instances| {
+        let registry = InstanceRegistry::new(&metadata, instances);
+        registry
+          .as_one::<Service>("units", "test:link_worker")
+          .is_ok_and(|service| !service.instances.0.is_empty())
+      })
+      .expect("service lookup should succeed")
+  };
+  assert!(!started(&runtime), "debounce should hold the start back");
+
+  std::thread::sleep(std::time::Duration::from_millis(250));
+  runtime
+    .dispatch("flow", "wake_conditions", Default::default(), context_id)
+    .expect("wake_conditions should queue");
+  for _ in 0..3 {
+    flush(&runtime, context_id, &metadata, &mut resources);
+  }
+  assert!(started(&runtime), "settled facet should start the service");
+
+  let _ = runtime.send(RuntimeCommand::Stop);
+}
+
+#[test]
+fn eval_auto_payloads_set_facets_and_refresh() {
+  let (runtime, metadata, mut resources, context_id) = setup_runtime_with_metadata();
+  runtime
+    .dispatch("flow", "bootstrap", Default::default(), context_id)
+    .expect("flow bootstrap should queue");
+  flush(&runtime, context_id, &metadata, &mut resources);
+
+  let payloads = |runtime: &RuntimeHandle, name: &str| {
+    runtime
+      .with_instances(|instances| {
+        let registry = InstanceRegistry::new(&metadata, instances);
+        let sm = registry
+          .singleton::<FacetGraph>(FacetGraph::KEY)
+          .expect("facet graph should exist");
+        sm.get(&Ustr::from(name))
+          .map(|branches| {
+            branches
+              .iter()
+              .map(|b| b.payload.to_string_payload())
+              .collect::<Vec<_>>()
+          })
+          .unwrap_or_default()
+      })
+      .expect("facet lookup should succeed")
+  };
+  // commands run on worker threads, so poll until their output lands
+  let mut drain_until = |done: &dyn Fn(&RuntimeHandle) -> bool| {
+    for _ in 0..100 {
+      runtime
+        .dispatch("flow", "drain_evals", Default::default(), context_id)
+        .expect("drain_evals should queue");
+      flush(&runtime, context_id, &metadata, &mut resources);
+      if done(&runtime) {
+        return;
+      }
+      std::thread::sleep(std::time::Duration::from_millis(20));
+    }
+    panic!("auto-payload commands didn't finish");
+  };
+
+  drain_until(&|rt| {
+    !payloads(rt, "test:uptime").is_empty() && !payloads(rt, "test:mounts").is_empty()
+  });
+  assert_eq!(
+    payloads(&runtime, "test:mounts"),
+    [r#"{"mount":"/"}"#, r#"{"mount":"/home"}"#]
+  );
+  let first = payloads(&runtime, "test:uptime");
+  assert_eq!(first.len(), 1);
+
+  runtime
+    .dispatch(
+      "flow",
+      "refresh_auto_payload",
+      rind_core::rpayload!({ "name": Ustr::from("test:uptime") }),
+      context_id,
+    )
+    .expect("refresh should queue");
+  drain_until(&|rt| payloads(rt, "test:uptime") != first);
+  assert_eq!(payloads(&runtime, "test:uptime").len(), 1);
+
+  let _ = runtime.send(RuntimeCommand::Stop);
+}
@@ -15,6 +15,7 @@ use rind_core::{
   types::Void,
 };
 
+use crate::print::format_timestamp;
 use crate::report_error;
 
 const RLOG_MAGIC: u32 = 0x524C4F47;
@@ -527,23 +528,3 @@ pub fn write_log_entry(sink: &mut OutputSink, entry: &LogEntry) -> Result<Void,
   }
   Ok(Void)
 }
-
-fn format_timestamp(timestamp: u64) -> String {
-  unsafe {
-    #[allow(deprecated)]
-    let t = timestamp as libc::time_t;
-    let mut tm: libc::tm = std::mem::zeroed();
-    libc::localtime_r(&t, &mut tm);
-    let mut buf = [0u8; 64];
-    let fmt = std::ffi::CString::new("%d/%m/%y %H:%M:%S").unwrap();
-    libc::strftime(
-      buf.as_mut_ptr() as *mut libc::c_char,
-      buf.len(),
-      fmt.as_ptr(),
-      &tm,
-    );
-    std::ffi::CStr::from_ptr(buf.as_ptr() as *const libc::c_char)
-      .to_string_lossy()
-      .to_string()
-  }
-}
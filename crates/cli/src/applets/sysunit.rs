@@ -42,6 +42,13 @@ enum Command {
     #[arg(long)]
     scope: Option<String>,
   },
+  Reload {
+    #[arg(name = "NAME")]
+    name: String,
+
+    #[arg(long)]
+    scope: Option<String>,
+  },
   Show {
     #[arg(name = "NAME")]
     name: Option<String>,
@@ -58,6 +65,9 @@ enum Command {
     #[arg(short = 'm', long)]
     mount: bool,
 
+    #[arg(long)]
+    timer: bool,
+
     #[arg(short = 'c', long)]
     facet: bool,
 
@@ -172,12 +182,25 @@ pub fn main() {
         }
       );
     }
+    Command::Reload { name, scope } => {
+      let name = crate::apply_scope_name(&name, scope.as_deref());
+      handle_send!(
+        "reload_service",
+        &rind_ipc::payloads::SSPayload {
+          force: false,
+          name,
+          persist: false,
+          unit_type: "service".into()
+        }
+      );
+    }
     Command::Show {
       name,
       unit,
       service,
       socket,
       mount,
+      timer,
       facet,
       network,
       port,
@@ -201,6 +224,8 @@ pub fn main() {
               "facet"
             } else if socket {
               "socket"
+            } else if timer {
+              "timer"
             } else if port && network {
               "netport"
             } else if network {
@@ -229,7 +254,8 @@ pub fn main() {
       }
 
       use rind_ipc::ser::{
-        FacetSerialized, ServiceSerialized, SocketSerialized, UnitItemsSerialized, UnitSerialized,
+        FacetSerialized, ServiceSerialized, SocketSerialized, TimerSerialized, UnitItemsSerialized,
+        UnitSerialized,
       };
 
       if unit {
@@ -287,6 +313,12 @@ pub fn main() {
             .parse_payload::<SocketSerialized>()
             .expect("Failed to parse"),
         );
+      } else if timer {
+        crate::print::print_timer(
+          &result
+            .parse_payload::<TimerSerialized>()
+            .expect("Failed to parse"),
+        );
       } else if r#type.is_some()
         && let Some(ref ty) = r#type
         && !ty.is_empty()
@@ -1,7 +1,8 @@
 use owo_colors::OwoColorize;
 use rind_ipc::ser::{
-  FacetSerialized, IpcListComponent, IpcListPrinter, ServiceSerialized, SocketSerialized,
-  UnitItemsSerialized, UnitSerialized, deser_from_vec,
+  ExitRecordSerialized, FacetSerialized, InstanceUsageSerialized, IpcListComponent, IpcListPrinter,
+  ServiceSerialized, SocketSerialized, TimerSerialized, UnitItemsSerialized, UnitSerialized,
+  deser_from_vec,
 };
 
 pub fn print_ipc_list(list: &IpcListComponent) {
@@ -407,6 +408,131 @@ pub fn print_service(service: &ServiceSerialized) {
   if let Some(after) = &service.after {
     println!("   {}: {}", "After".bold(), after.join(", ").blue());
   }
+
+  if let Some(last_stop) = &service.last_stop {
+    let outcome = match last_stop.as_str() {
+      "Clean" => last_stop.green().to_string(),
+      _ => last_stop.yellow().to_string(),
+    };
+    println!("   {}: {}", "Last stop".bold(), outcome);
+  }
+
+  if let Some(caps) = &service.capabilities {
+    let caps = if caps.is_empty() {
+      "none".dimmed().to_string()
+    } else {
+      caps.join(", ").cyan().to_string()
+    };
+    println!("   {}: {}", "Capabilities".bold(), caps);
+  }
+
+  if !service.usage.is_empty() {
+    println!("   {}:", "Resources".bold());
+    for usage in &service.usage {
+      println!("     {}: {}", usage.instance.dimmed(), format_usage(usage));
+    }
+  }
+
+  if !service.history.is_empty() {
+    println!("   {}:", "History".bold());
+    for record in service.history.iter().rev() {
+      println!(
+        "     {} {}: {}",
+        format_timestamp(record.timestamp).dimmed(),
+        record.instance.dimmed(),
+        format_exit(record)
+      );
+    }
+  }
+}
+
+pub fn format_timestamp(timestamp: u64) -> String {
+  unsafe {
+    #[allow(deprecated)]
+    let t = timestamp as libc::time_t;
+    let mut tm: libc::tm = std::mem::zeroed();
+    libc::localtime_r(&t, &mut tm);
+    let mut buf = [0u8; 64];
+    let fmt = std::ffi::CString::new("%d/%m/%y %H:%M:%S").unwrap();
+    libc::strftime(
+      buf.as_mut_ptr() as *mut libc::c_char,
+      buf.len(),
+      fmt.as_ptr(),
+      &tm,
+    );
+    std::ffi::CStr::from_ptr(buf.as_ptr() as *const libc::c_char)
+      .to_string_lossy()
+      .to_string()
+  }
+}
+
+fn format_exit(record: &ExitRecordSerialized) -> String {
+  let status = match record.signal {
+    Some(signal) => {
+      let name = nix::sys::signal::Signal::try_from(signal)
+        .map_or_else(|_| format!("signal {signal}"), |s| s.as_str().to_string());
+      let core = if record.core_dumped {
+        " (core dumped)"
+      } else {
+        ""
+      };
+      format!("{name}{core}").red().to_string()
+    }
+    None if record.code == 0 => "exit 0".green().to_string(),
+    None => format!("exit {}", record.code).red().to_string(),
+  };
+  let mut parts = vec![status, record.trigger.to_lowercase().yellow().to_string()];
+  if let Some(stop) = &record.stop {
+    parts.push(format!("stop {}", stop.to_lowercase()));
+  }
+  parts.push(format!(
+    "ran {}",
+    format!("{:.1}s", record.runtime_ms as f64 / 1000.0).cyan()
+  ));
+  parts.join(", ")
+}
+
+fn format_bytes(bytes: u64) -> String {
+  const UNITS: [&str; 5] = ["B", "K", "M", "G", "T"];
+  let mut value = bytes as f64;
+  let mut unit = 0;
+  while value >= 1024.0 && unit < UNITS.len() - 1 {
+    value /= 1024.0;
+    unit += 1;
+  }
+  if unit == 0 {
+    format!("{bytes}B")
+  } else {
+    format!("{value:.1}{}", UNITS[unit])
+  }
+}
+
+fn format_usage(usage: &InstanceUsageSerialized) -> String {
+  let mut parts = Vec::new();
+  if let Some(memory) = usage.memory_current {
+    parts.push(format!("memory {}", format_bytes(memory).cyan()));
+  }
+  if let Some(cpu) = usage.cpu_usage_usec {
+    parts.push(format!(
+      "cpu {}",
+      format!("{:.2}s", cpu as f64 / 1_000_000.0).cyan()
+    ));
+  }
+  if let (Some(read), Some(write)) = (usage.io_read_bytes, usage.io_write_bytes) {
+    parts.push(format!(
+      "io {} read / {} written",
+      format_bytes(read).cyan(),
+      format_bytes(write).cyan()
+    ));
+  }
+  if let Some(tasks) = usage.pids_current {
+    parts.push(format!("tasks {}", tasks.to_string().cyan()));
+  }
+  if parts.is_empty() {
+    "no accounting data".dimmed().to_string()
+  } else {
+    parts.join(", ")
+  }
 }
 
 pub fn print_socket(socket: &SocketSerialized) {
@@ -428,3 +554,55 @@ pub fn print_socket(socket: &SocketSerialized) {
     socket.listen.green()
   );
 }
+
+pub fn print_timer(timer: &TimerSerialized) {
+  let (dot, state) = if timer.active {
+    (
+      "●".green().bold().to_string(),
+      "Active".green().bold().to_string(),
+    )
+  } else {
+    ("●".white().to_string(), "Inactive".white().to_string())
+  };
+
+  println!("{} {}", dot, timer.name.bold().white());
+  println!("   {}: {}", "State".bold(), state);
+
+  let mut schedule = vec![timer.schedule.cyan().to_string()];
+  if timer.repeat {
+    schedule.push("repeating".yellow().to_string());
+  }
+  if timer.persistent {
+    schedule.push("persistent".yellow().to_string());
+  }
+  println!("   {}: {}", "Schedule".bold(), schedule.join(", "));
+
+  let elapse = |at: Option<u64>| {
+    at.map_or("n/a".dimmed().to_string(), |at| {
+      format_timestamp(at).green().to_string()
+    })
+  };
+  println!("   {}: {}", "Next elapse".bold(), elapse(timer.next_elapse));
+  println!("   {}: {}", "Last elapse".bold(), elapse(timer.last_elapse));
+
+  if let Some(unit) = &timer.unit {
+    let running = if timer.running {
+      format!(" ({})", "running".green())
+    } else {
+      String::new()
+    };
+    println!("   {}: {}{}", "Unit".bold(), unit.blue(), running);
+  }
+
+  if !timer.runs.is_empty() {
+    println!("   {}:", "Runs".bold());
+    for record in timer.runs.iter().rev() {
+      println!(
+        "     {} {}: {}",
+        format_timestamp(record.timestamp).dimmed(),
+        record.instance.dimmed(),
+        format_exit(record)
+      );
+    }
+  }
+}
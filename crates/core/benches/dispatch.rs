@@ -1,9 +1,11 @@
 use criterion::{Criterion, criterion_group, criterion_main};
 use rind_core::context::RuntimeContext;
 use rind_core::logging::LogHandle;
-use rind_core::prelude::Resources;
+use rind_core::prelude::{Resources, StatePersistence, Ustr};
 use rind_core::registry::MetadataRegistry;
 use rind_core::runtime::{Runtime, RuntimeDispatcher, RuntimePayload, start_runtime};
+use rind_flow::triggers::check_condition;
+use rind_flow::{FacetGraph, FlowInstance, FlowItem, FlowPayload, FlowType, condition_is_active};
 
 struct BenchRuntime;
 impl Runtime for BenchRuntime {
@@ -59,5 +61,63 @@ fn bench_payload(c: &mut Criterion) {
   });
 }
 
-criterion_group!(benches, bench_dispatch, bench_payload);
+/// A login storm: one facet per device, plus one facet branched per session.
+fn branched_facet_graph(devices: usize, sessions: usize) -> FacetGraph {
+  let path = std::env::temp_dir().join(format!("rind-bench-{}", std::process::id()));
+  let mut sm = FacetGraph::from_persistence(StatePersistence::new(path));
+  for i in 0..devices {
+    let name = Ustr::from(format!("dev:disk{i}@static"));
+    let branch = FlowInstance {
+      name: name.clone(),
+      payload: FlowPayload::None(false),
+      r#type: FlowType::Facet,
+    };
+    sm.insert(name, vec![branch]);
+  }
+  let name = Ustr::from("rind:user_session");
+  let branches = (0..sessions)
+    .map(|i| FlowInstance {
+      name: name.clone(),
+      payload: FlowPayload::from_json(Some(serde_json::json!({ "id": i, "seat": "seat0" }))),
+      r#type: FlowType::Facet,
+    })
+    .collect();
+  sm.insert(name, branches);
+  sm
+}
+
+// What `condition_is_active` did before `FacetGraph` kept an index.
+fn linear_is_active(sm: &FacetGraph, cond: &FlowItem) -> bool {
+  sm.values()
+    .flatten()
+    .any(|branch| check_condition(cond, branch))
+}
+
+fn bench_conditions(c: &mut Criterion) {
+  let sm = branched_facet_graph(4000, 4000);
+  let conds: Vec<FlowItem> = vec![
+    FlowItem::Simple(Ustr::from("dev:disk3999")),
+    FlowItem::Simple(Ustr::from("rind:user_session")),
+    FlowItem::Simple(Ustr::from("net:missing")),
+  ];
+
+  c.bench_function("conditions_linear_scan", |b| {
+    b.iter(|| {
+      conds
+        .iter()
+        .filter(|cond| linear_is_active(&sm, cond))
+        .count()
+    })
+  });
+  c.bench_function("conditions_indexed", |b| {
+    b.iter(|| {
+      conds
+        .iter()
+        .filter(|cond| condition_is_active(&sm, cond, None))
+        .count()
+    })
+  });
+}
+
+criterion_group!(benches, bench_dispatch, bench_payload, bench_conditions);
 criterion_main!(benches);
@@ -55,6 +55,10 @@ impl StatePersistence {
     Self { path, tx }
   }
 
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+
   pub fn load(&self) -> Result<StateSnapshot, CoreError> {
     let path = &self.path;
     if !path.exists() {
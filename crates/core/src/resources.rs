@@ -119,6 +119,13 @@ impl Resources {
     self.fd.insert(res, fd.into());
   }
 
+  pub fn timer(&self, res: i32) -> Option<&TimerFd> {
+    match self.fd.get(&res) {
+      Some(FdLoc::Timer(timer)) => Some(timer),
+      _ => None,
+    }
+  }
+
   pub fn terminate(&mut self, res: i32) {
     self.unwatched_fds.remove(&res);
     if self.watched_fds.remove(&res) {
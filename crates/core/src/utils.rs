@@ -1,6 +1,7 @@
 use libc::{SO_PEERCRED, SOL_SOCKET, getsockopt, ucred};
 use std::os::unix::net::UnixStream;
 use std::path::PathBuf;
+use std::time::Duration;
 use std::{collections::HashMap, os::fd::AsRawFd};
 
 use crate::rslvns;
@@ -155,3 +156,27 @@ macro_rules! rslvns {
     (parent, child, scope)
   }};
 }
+
+/// Parses `30`, `30s`, `5m`, `2h` or `1d` into a duration; bare numbers are seconds.
+pub fn parse_duration(s: &str) -> Option<Duration> {
+  let s = s.trim();
+  if s.is_empty() {
+    return None;
+  }
+  if let Some(ms) = s.strip_suffix("ms") {
+    return ms.parse().ok().map(Duration::from_millis);
+  }
+
+  let (num_str, unit) = s.split_at(s.len() - 1);
+  if let Ok(num) = num_str.parse::<u64>() {
+    match unit {
+      "s" => Some(Duration::from_secs(num)),
+      "m" => Some(Duration::from_secs(num * 60)),
+      "h" => Some(Duration::from_secs(num * 3600)),
+      "d" => Some(Duration::from_secs(num * 86400)),
+      _ => s.parse().ok().map(Duration::from_secs),
+    }
+  } else {
+    s.parse().ok().map(Duration::from_secs)
+  }
+}
@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rind_core::prelude::*;
+use rind_core::reexports::bincode_next;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FacetExpiry {
+  pub ttl: Duration,
+  pub deadline: SystemTime,
+}
+
+/// Deadlines of facet branches with a time-to-live, by facet key and branch
+/// key. Deadlines are wall-clock so restored facets keep expiring on time
+/// after a restart.
+#[derive(Clone)]
+pub struct FacetExpiries {
+  persistence: StatePersistence,
+  deadlines: HashMap<Ustr, HashMap<Vec<String>, FacetExpiry>>,
+}
+
+impl FacetExpiries {
+  pub fn new(persistence: StatePersistence) -> Self {
+    Self {
+      persistence,
+      deadlines: HashMap::new(),
+    }
+  }
+
+  pub fn load(&mut self) -> CoreResult<Void> {
+    let cfg = bincode_next::config::standard();
+    for (key, entries) in self.persistence.load()? {
+      let branches = entries
+        .iter()
+        .filter_map(|entry| {
+          let ((branch, ttl_ms, deadline_ms), _) =
+            bincode_next::serde::decode_from_slice::<(Vec<String>, u64, u64), _>(&entry.data, cfg)
+              .ok()?;
+          let expiry = FacetExpiry {
+            ttl: Duration::from_millis(ttl_ms),
+            deadline: UNIX_EPOCH + Duration::from_millis(deadline_ms),
+          };
+          Some((branch, expiry))
+        })
+        .collect();
+      self.deadlines.insert(Ustr::from(key), branches);
+    }
+    Ok(Void)
+  }
+
+  /// Pushes the deadline of a branch `ttl` past `now`.
+  pub fn refresh(&mut self, key: &Ustr, branch: Vec<String>, ttl: Duration, now: SystemTime) {
+    self.deadlines.entry(key.clone()).or_default().insert(
+      branch,
+      FacetExpiry {
+        ttl,
+        deadline: now + ttl,
+      },
+    );
+  }
+
+  pub fn clear(&mut self, key: &Ustr, branch: &[String]) {
+    if let Some(branches) = self.deadlines.get_mut(key) {
+      branches.remove(branch);
+      if branches.is_empty() {
+        self.deadlines.remove(key);
+      }
+    }
+  }
+
+  pub fn clear_facet(&mut self, key: &Ustr) {
+    self.deadlines.remove(key);
+  }
+
+  pub fn retain_facets(&mut self, mut keep: impl FnMut(&Ustr) -> bool) {
+    self.deadlines.retain(|key, _| keep(key));
+  }
+
+  pub fn get(&self, key: &Ustr, branch: &[String]) -> Option<FacetExpiry> {
+    self.deadlines.get(key)?.get(branch).copied()
+  }
+
+  pub fn next_deadline(&self) -> Option<SystemTime> {
+    self
+      .deadlines
+      .values()
+      .flat_map(|branches| branches.values())
+      .map(|expiry| expiry.deadline)
+      .min()
+  }
+
+  /// Removes and returns every branch whose deadline is at or before `now`.
+  pub fn take_expired(&mut self, now: SystemTime) -> Vec<(Ustr, Vec<String>)> {
+    let mut out = Vec::new();
+    for (key, branches) in self.deadlines.iter_mut() {
+      branches.retain(|branch, expiry| {
+        if expiry.deadline <= now {
+          out.push((key.clone(), branch.clone()));
+          return false;
+        }
+        true
+      });
+    }
+    self.deadlines.retain(|_, branches| !branches.is_empty());
+    out
+  }
+
+  fn snapshot(&self) -> StateSnapshot {
+    let cfg = bincode_next::config::standard();
+    let millis = |d: Duration| d.as_millis() as u64;
+    self
+      .deadlines
+      .iter()
+      // impermanent facets aren't restored, so neither are their deadlines
+      .filter(|(key, _)| !key.contains("!@") && !key.ends_with("!"))
+      .map(|(key, branches)| {
+        let entries = branches
+          .iter()
+          .filter_map(|(branch, expiry)| {
+            let deadline = expiry.deadline.duration_since(UNIX_EPOCH).ok()?;
+            let data = bincode_next::serde::encode_to_vec(
+              (branch, millis(expiry.ttl), millis(deadline)),
+              cfg,
+            )
+            .ok()?;
+            Some(StateEntry { data })
+          })
+          .collect();
+        (key.to_string(), entries)
+      })
+      .collect()
+  }
+
+  pub fn save_sync(&self) -> CoreResult<Void> {
+    self.persistence.save_sync(&self.snapshot())
+  }
+}
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use rind_core::prelude::*;
+
+use crate::triggers::payload_signature;
+use crate::{FlowEdge, FlowInstance, FlowItem, FlowPayload};
+
+/// A debounce or hold window of a filtered condition running out. Replayed as
+/// a facet event so the units using the condition re-evaluate.
+#[derive(Debug, Clone)]
+pub struct FilterWakeup {
+  pub at: Instant,
+  pub name: Ustr,
+  pub payload: FlowPayload,
+  pub action: FlowAction,
+}
+
+/// Debounce, hold and edge state of facet leaves carrying `debounce`, `hold`
+/// or `on`. Keyed by the leaf itself, so units sharing a condition share its state.
+#[derive(Clone, Default)]
+pub struct ConditionFilters {
+  states: HashMap<String, FilterState>,
+  wakeups: Vec<FilterWakeup>,
+}
+
+#[derive(Clone)]
+struct FilterState {
+  /// Whether the facet matched at the last observation, and since when.
+  raw: bool,
+  since: Instant,
+  /// The debounced, held state.
+  level: bool,
+  /// Payload of the last matching branch.
+  payload: Option<FlowPayload>,
+  /// Last transition of `level`, with the event it was observed for.
+  edge: Option<(FlowEdge, Option<EventStamp>)>,
+}
+
+type EventStamp = (Ustr, String);
+
+fn stamp(event: Option<&FlowInstance>) -> Option<EventStamp> {
+  event.map(|e| (e.name.clone(), payload_signature(&Some(e.payload.clone()))))
+}
+
+fn state_key(leaf: &FlowItem) -> String {
+  serde_json::to_string(leaf).unwrap_or_default()
+}
+
+fn window(spec: &Option<Ustr>) -> Duration {
+  spec
+    .as_ref()
+    .and_then(|s| parse_duration(s.as_str()))
+    .unwrap_or_default()
+}
+
+impl ConditionFilters {
+  /// Feeds the current match of `leaf` into its state; `matched` is the
+  /// payload of a matching branch, `event` what is being evaluated.
+  pub fn observe(
+    &mut self,
+    leaf: &FlowItem,
+    matched: Option<FlowPayload>,
+    event: Option<&FlowInstance>,
+    now: Instant,
+  ) {
+    let FlowItem::Detailed {
+      facet: Some(name),
+      debounce,
+      hold,
+      ..
+    } = leaf
+    else {
+      return;
+    };
+    let (debounce, hold) = (window(debounce), window(hold));
+
+    // unseen conditions start cleared, so facets restored at boot still rise
+    let state = self
+      .states
+      .entry(state_key(leaf))
+      .or_insert_with(|| FilterState {
+        raw: false,
+        since: now,
+        level: false,
+        payload: None,
+        edge: None,
+      });
+
+    let raw = matched.is_some();
+    if raw != state.raw {
+      state.raw = raw;
+      state.since = now;
+    }
+    let elapsed = now.saturating_duration_since(state.since);
+    let was = state.level;
+    state.level = if raw {
+      was || elapsed >= debounce
+    } else {
+      was && elapsed < hold
+    };
+
+    let payload_changed =
+      raw && was && state.level && payload_signature(&matched) != payload_signature(&state.payload);
+    let edge = match (was, state.level) {
+      (false, true) => Some(FlowEdge::Rising),
+      (true, false) => Some(FlowEdge::Falling),
+      (true, true) if payload_changed => Some(FlowEdge::Change),
+      _ => None,
+    };
+    let stamp = stamp(event);
+    match edge {
+      Some(edge) => state.edge = Some((edge, stamp)),
+      // a later event that moved nothing retires the edge
+      None if state.edge.as_ref().is_some_and(|(_, seen)| *seen != stamp) => state.edge = None,
+      None => {}
+    }
+    if matched.is_some() {
+      state.payload = matched;
+    }
+
+    let due = if raw && !state.level {
+      Some((state.since + debounce, FlowAction::Apply))
+    } else if !raw && state.level {
+      Some((state.since + hold, FlowAction::Revert))
+    } else {
+      None
+    };
+    if let Some((at, action)) = due {
+      let payload = state.payload.clone().unwrap_or(FlowPayload::None(false));
+      let pending = self
+        .wakeups
+        .iter()
+        .any(|w| w.at == at && w.name == *name && w.action == action);
+      if !pending {
+        self.wakeups.push(FilterWakeup {
+          at,
+          name: name.clone(),
+          payload,
+          action,
+        });
+      }
+    }
+  }
+
+  /// The debounced, held state of `leaf`, once it has been observed.
+  pub fn level(&self, leaf: &FlowItem) -> Option<bool> {
+    self.states.get(&state_key(leaf)).map(|state| state.level)
+  }
+
+  /// Whether the transition `leaf` waits `on` was observed for `event`.
+  pub fn edge(&self, leaf: &FlowItem, event: Option<&FlowInstance>) -> bool {
+    let FlowItem::Detailed { on: Some(on), .. } = leaf else {
+      return false;
+    };
+    let stamp = stamp(event);
+    self
+      .states
+      .get(&state_key(leaf))
+      .and_then(|state| state.edge.as_ref())
+      .is_some_and(|(edge, seen)| (*on == FlowEdge::Change || on == edge) && *seen == stamp)
+  }
+
+  pub fn next_wakeup(&self) -> Option<Instant> {
+    self.wakeups.iter().map(|w| w.at).min()
+  }
+
+  /// Removes and returns every wakeup at or before `now`.
+  pub fn take_due(&mut self, now: Instant) -> Vec<FilterWakeup> {
+    let (due, pending) = std::mem::take(&mut self.wakeups)
+      .into_iter()
+      .partition(|w| w.at <= now);
+    self.wakeups = pending;
+    due
+  }
+}
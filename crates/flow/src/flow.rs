@@ -1,20 +1,30 @@
+pub mod expiry;
+pub mod filters;
+pub mod index;
 pub mod shm_tp;
 pub mod transport;
 pub mod triggers;
 
 use rind_primitives::scopes::GLOBAL_SCOPE_STORE;
 use serde::{Deserialize, Serialize};
+use std::borrow::Borrow;
 use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use rind_core::prelude::*;
 use rind_core::reexports::*;
 pub use rind_ipc::{FlowJson, FlowMatchOperation, FlowPayload, FlowPayloadType};
 
+use crate::expiry::{FacetExpiries, FacetExpiry};
+use crate::filters::{ConditionFilters, FilterWakeup};
+use crate::index::FacetIndex;
 use crate::transport::{TransportMethod, setup_transport_endpoint, transport_id};
 use crate::triggers::{
-  branch_target_key, check_condition, default_payload_for_type, json_branch_key, map_json_payload,
-  merge_json, payload_compatible, payload_signature, payload_to_filter,
+  branch_target_key, check_condition, default_payload_for_type, eval_values, json_branch_key,
+  map_json_payload, merge_json, payload_compatible, payload_signature, payload_to_filter, run_eval,
 };
 use rind_primitives::prelude::ScopeStore;
 use rind_primitives::prelude::VariableHeap;
@@ -23,8 +33,24 @@ pub const FLOW_RUNTIME_ID: &str = "flow";
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum FlowItem {
   Simple(Ustr),
+  // composite variants need their key, so they come before `Detailed`, which accepts any table
+  All {
+    all: Vec<FlowItem>,
+  },
+  Any {
+    any: Vec<FlowItem>,
+  },
+  Not {
+    not: Box<FlowItem>,
+  },
+  /// Holds when at least `count` items of `of` hold.
+  Count {
+    count: usize,
+    of: Vec<FlowItem>,
+  },
   Detailed {
     #[serde(alias = "state")]
     facet: Option<Ustr>,
@@ -32,23 +58,56 @@ pub enum FlowItem {
     impulse: Option<Ustr>,
     target: Option<FlowMatchOperation>,
     branch: Option<FlowMatchOperation>,
+    /// How long the facet must stay matched before the item holds.
+    debounce: Option<Ustr>,
+    /// How long the item keeps holding after the facet stops matching.
+    hold: Option<Ustr>,
+    /// Hold only on this transition of the (debounced, held) state.
+    on: Option<FlowEdge>,
   },
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowEdge {
+  Rising,
+  Falling,
+  /// Either edge, or a different matching payload while held.
+  Change,
+}
+
 impl FlowItem {
-  pub fn name(&self) -> &Ustr {
+  /// Whether this is a facet leaf with `debounce`, `hold` or `on`, which
+  /// `FacetGraph::observe_conditions` has to track over time.
+  pub fn is_filtered(&self) -> bool {
+    matches!(
+      self,
+      FlowItem::Detailed { facet: Some(_), debounce, hold, on, .. }
+        if debounce.is_some() || hold.is_some() || on.is_some()
+    )
+  }
+
+  /// Names of every facet and impulse referenced by this item, nested ones included.
+  pub fn names(&self) -> Vec<&Ustr> {
+    let mut out = Vec::new();
+    self.collect_names(&mut out);
+    out
+  }
+
+  fn collect_names<'a>(&'a self, out: &mut Vec<&'a Ustr>) {
     match self {
-      FlowItem::Simple(s) => s,
-      FlowItem::Detailed {
-        facet,
-        impulse,
-        target: _,
-        branch: _,
-      } => {
-        if let Some(facet) = facet {
-          facet
-        } else {
-          impulse.as_ref().unwrap()
+      FlowItem::Simple(s) => out.push(s),
+      FlowItem::All { all: items }
+      | FlowItem::Any { any: items }
+      | FlowItem::Count { of: items, .. } => {
+        for item in items {
+          item.collect_names(out);
+        }
+      }
+      FlowItem::Not { not } => not.collect_names(out),
+      FlowItem::Detailed { facet, impulse, .. } => {
+        if let Some(name) = facet.as_ref().or(impulse.as_ref()) {
+          out.push(name);
         }
       }
     }
@@ -125,8 +184,14 @@ pub struct AutoPayloadConfig {
   pub insert: Option<AutoPayloadInsert>,
   #[serde(default)]
   pub many: bool,
+  /// How long `eval` may run before it's killed, 10s unless set.
+  pub timeout: Option<String>,
+  /// Re-runs `eval` this often to keep the facet current.
+  pub refresh: Option<String>,
 }
 
+const DEFAULT_EVAL_TIMEOUT: Duration = Duration::from_secs(10);
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum InverseBranchingConfig {
@@ -157,7 +222,7 @@ impl InverseBranchingConfig {
 #[model(
   meta_name = name,
   meta_fields(
-    name, payload, stop_on, after, branch, auto_payload, subscribers, broadcast, permissions
+    name, payload, stop_on, after, branch, auto_payload, ttl, subscribers, broadcast, permissions
   ),
   derive_metadata(Debug, Clone, Default)
 )]
@@ -170,6 +235,8 @@ pub struct FlowFacet {
   pub branch: Option<Vec<Ustr>>,
   #[serde(rename = "auto-payload")]
   pub auto_payload: Option<AutoPayloadConfig>,
+  /// Branches revert on their own this long after they were last set.
+  pub ttl: Option<Ustr>,
   pub subscribers: Option<Vec<TransportMethod>>,
   pub broadcast: Option<Vec<Ustr>>,
   pub permissions: Option<Vec<Ustr>>,
@@ -192,22 +259,201 @@ pub struct FlowImpulse {
 
 #[derive(Clone)]
 pub struct FacetGraph {
-  pub facets: HashMap<Ustr, Vec<FlowInstance>>,
+  /// Only changed through the methods below, which keep `index` in step.
+  facets: HashMap<Ustr, Vec<FlowInstance>>,
+  index: FacetIndex,
+  expiries: FacetExpiries,
+  filters: ConditionFilters,
   persistence: StatePersistence,
   persistence_root: PathBuf,
   scoped_persistence: HashMap<Ustr, StatePersistence>,
 }
 
+impl<Q> std::ops::Index<&Q> for FacetGraph
+where
+  Ustr: Borrow<Q>,
+  Q: Hash + Eq + ?Sized,
+{
+  type Output = Vec<FlowInstance>;
+
+  fn index(&self, key: &Q) -> &Self::Output {
+    &self.facets[key]
+  }
+}
+
 impl FacetGraph {
   pub const KEY: &str = "runtime:facet_graph";
 
   pub fn from_persistence(persistence: StatePersistence) -> Self {
+    let expiries = FacetExpiries::new(StatePersistence::new(
+      persistence.path().with_extension("ttl"),
+    ));
     Self {
+      expiries,
       persistence: persistence,
       persistence_root: state_root_path(),
       scoped_persistence: HashMap::new(),
       facets: Default::default(),
+      index: FacetIndex::default(),
+      filters: ConditionFilters::default(),
+    }
+  }
+
+  pub fn insert(&mut self, key: Ustr, branches: Vec<FlowInstance>) {
+    self.index.insert(&key);
+    self.facets.insert(key, branches);
+  }
+
+  pub fn remove(&mut self, key: &str) -> Option<Vec<FlowInstance>> {
+    let key = Ustr::from(key);
+    self.index.remove(&key);
+    self.facets.remove(&key)
+  }
+
+  pub fn get<Q>(&self, key: &Q) -> Option<&Vec<FlowInstance>>
+  where
+    Ustr: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    self.facets.get(key)
+  }
+
+  /// Branches of `key` for in-place edits; its branch positions are rebuilt on next lookup.
+  pub fn get_mut<Q>(&mut self, key: &Q) -> Option<&mut Vec<FlowInstance>>
+  where
+    Ustr: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    self.index.forget_branches(key);
+    self.facets.get_mut(key)
+  }
+
+  pub fn contains_key<Q>(&self, key: &Q) -> bool
+  where
+    Ustr: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    self.facets.contains_key(key)
+  }
+
+  pub fn keys(&self) -> impl Iterator<Item = &Ustr> {
+    self.facets.keys()
+  }
+
+  pub fn values(&self) -> impl Iterator<Item = &Vec<FlowInstance>> {
+    self.facets.values()
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item = (&Ustr, &Vec<FlowInstance>)> {
+    self.facets.iter()
+  }
+
+  /// Every branch of `name` across scopes, matched the way conditions match names.
+  pub fn branches_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a FlowInstance> {
+    self
+      .index
+      .keys(name)
+      .filter_map(|key| self.facets.get(key))
+      .flatten()
+  }
+
+  /// Arms the branch's time-to-live from now, or clears it when `ttl` is `None`.
+  pub fn expire_after(&mut self, key: &Ustr, branch: Vec<String>, ttl: Option<Duration>) {
+    match ttl {
+      Some(ttl) => self.expiries.refresh(key, branch, ttl, SystemTime::now()),
+      None => self.expiries.clear(key, &branch),
+    }
+  }
+
+  pub fn clear_expiries(&mut self, key: &Ustr) {
+    self.expiries.clear_facet(key);
+  }
+
+  pub fn expiry(&self, key: &Ustr, branch: &[String]) -> Option<FacetExpiry> {
+    self.expiries.get(key, branch)
+  }
+
+  pub fn next_expiry(&self) -> Option<SystemTime> {
+    self.expiries.next_deadline()
+  }
+
+  pub fn take_expired(&mut self, now: SystemTime) -> Vec<(Ustr, Vec<String>)> {
+    self.expiries.take_expired(now)
+  }
+
+  /// Feeds every leaf of `items` carrying `debounce`, `hold` or `on` into its
+  /// filter state. Units call this before evaluating their conditions.
+  pub fn observe_conditions(&mut self, items: &[FlowItem], event: Option<&FlowInstance>) {
+    let now = Instant::now();
+    for item in items {
+      self.observe_condition(item, event, now);
+    }
+  }
+
+  fn observe_condition(&mut self, item: &FlowItem, event: Option<&FlowInstance>, now: Instant) {
+    match item {
+      FlowItem::All { all: items }
+      | FlowItem::Any { any: items }
+      | FlowItem::Count { of: items, .. } => {
+        for item in items {
+          self.observe_condition(item, event, now);
+        }
+      }
+      FlowItem::Not { not } => self.observe_condition(not, event, now),
+      FlowItem::Detailed {
+        facet: Some(name), ..
+      } if item.is_filtered() => {
+        let matched = matching_branch(self, item, name, None).map(|b| b.payload.clone());
+        self.filters.observe(item, matched, event, now);
+      }
+      _ => {}
+    }
+  }
+
+  pub fn condition_filters(&self) -> &ConditionFilters {
+    &self.filters
+  }
+
+  pub fn next_wakeup(&self) -> Option<Instant> {
+    self.filters.next_wakeup()
+  }
+
+  pub fn take_wakeups(&mut self, now: Instant) -> Vec<FilterWakeup> {
+    self.filters.take_due(now)
+  }
+
+  pub(crate) fn branch_position(
+    &mut self,
+    key: &Ustr,
+    branch_keys: &[Ustr],
+    branch_key: &[String],
+  ) -> Option<usize> {
+    let branches = self.facets.get(key).map(Vec::as_slice).unwrap_or_default();
+    self
+      .index
+      .branch_position(key, branches, branch_keys, branch_key)
+  }
+
+  pub(crate) fn push_branch(
+    &mut self,
+    key: &Ustr,
+    branch_key: Vec<String>,
+    instance: FlowInstance,
+  ) {
+    if !self.facets.contains_key(key) {
+      self.insert(key.clone(), Vec::new());
     }
+    let branches = self.facets.get_mut(key).expect("facet inserted above");
+    branches.push(instance);
+    self
+      .index
+      .record_branch(key, branch_key, branches.len() - 1);
+  }
+
+  /// The branch at `position`, edited without touching the cached branch
+  /// positions, so the edit must keep its branch key.
+  pub(crate) fn branch_at_mut(&mut self, key: &Ustr, position: usize) -> Option<&mut FlowInstance> {
+    self.facets.get_mut(key)?.get_mut(position)
   }
 
   pub fn load_from_persistence(&mut self) -> Result<Void, CoreError> {
@@ -244,6 +490,11 @@ impl FacetGraph {
         }
       }
     }
+    self.index.rebuild(&self.facets);
+    let _ = self.expiries.load();
+    self
+      .expiries
+      .retain_facets(|key| self.facets.contains_key(key));
     Ok(Void)
   }
 
@@ -284,7 +535,7 @@ impl FacetGraph {
         .map(FlowInstance::from)
         .filter(|x| !x.name.as_str().is_empty())
         .collect::<Vec<_>>();
-      self.facets.insert(key, vals);
+      self.insert(key, vals);
     }
     Ok(Void)
   }
@@ -294,6 +545,10 @@ impl FacetGraph {
     self.facets.retain(|k, _| {
       scope == "static" && !k.as_str().contains('@') || !k.as_str().ends_with(&suffix)
     });
+    self.index.rebuild(&self.facets);
+    self
+      .expiries
+      .retain_facets(|key| self.facets.contains_key(key));
 
     if scope != "static" {
       let scope_dir = self.persistence_root.join(scope);
@@ -324,6 +579,7 @@ impl FacetGraph {
       let persistence = self.persistence_for_scope(scope.as_str());
       persistence.save_sync(&snapshot)?;
     }
+    self.expiries.save_sync()?;
 
     Ok(Void)
   }
@@ -349,13 +605,33 @@ impl FacetGraph {
 pub struct FlowRuntime {
   inverse_transcendence_index: HashMap<Ustr, HashSet<Ustr>>,
   transcendence_index: HashMap<Ustr, HashSet<Ustr>>,
+  /// Wall-clock timer armed for the earliest facet branch deadline.
+  expiry_fd: Option<i32>,
+  /// Monotonic timer armed for the earliest condition debounce or hold wakeup.
+  wakeup_fd: Option<i32>,
+  /// Last output of each `eval` auto-payload, by facet name.
+  evaluated: HashMap<Ustr, Vec<FlowPayload>>,
+  /// Facets whose command is still running, so refreshes don't pile up.
+  evaluating: HashSet<Ustr>,
+  eval_tx: mpsc::Sender<(Ustr, Option<String>)>,
+  eval_rx: mpsc::Receiver<(Ustr, Option<String>)>,
+  /// Interval timers re-running `eval`, by facet name.
+  refresh_fds: HashMap<Ustr, i32>,
 }
 
 impl Default for FlowRuntime {
   fn default() -> Self {
+    let (eval_tx, eval_rx) = mpsc::channel();
     Self {
       inverse_transcendence_index: HashMap::new(),
       transcendence_index: HashMap::new(),
+      expiry_fd: None,
+      wakeup_fd: None,
+      evaluated: HashMap::new(),
+      evaluating: HashSet::new(),
+      eval_tx,
+      eval_rx,
+      refresh_fds: HashMap::new(),
     }
   }
 }
@@ -431,6 +707,241 @@ impl FlowRuntime {
       .and_then(|d| d.subscribers.clone())
   }
 
+  /// Points the expiry timer at the earliest branch deadline and the wakeup
+  /// timer at the earliest condition wakeup, creating each on first use and
+  /// disarming it once nothing is due.
+  fn rearm_timer(
+    &mut self,
+    registry: &InstanceRegistry,
+    resources: &mut Resources,
+  ) -> CoreResult<Void> {
+    use nix::sys::time::TimeSpec;
+    use nix::sys::timerfd::{ClockId, Expiration, TimerSetTimeFlags};
+
+    let (expiry, wakeup) = registry
+      .singleton::<FacetGraph>(FacetGraph::KEY)
+      .map_or((None, None), |sm| (sm.next_expiry(), sm.next_wakeup()));
+
+    // TTL deadlines are persisted wall-clock times, so the timer is canceled
+    // when the clock is set and `expiry_tick` checks them against the new time
+    if let Some(tfd) = Self::timer(
+      &mut self.expiry_fd,
+      expiry.is_some(),
+      ClockId::CLOCK_REALTIME,
+      "expiry_tick",
+      resources,
+    )? {
+      match expiry {
+        Some(deadline) => {
+          // a deadline already past still needs a non-zero value, zero disarms
+          let at = deadline
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .max(Duration::from_nanos(1));
+          tfd
+            .set(
+              Expiration::OneShot(TimeSpec::from(at)),
+              TimerSetTimeFlags::TFD_TIMER_ABSTIME | TimerSetTimeFlags::TFD_TIMER_CANCEL_ON_SET,
+            )
+            .map_err(CoreError::custom)?;
+        }
+        None => tfd.unset().map_err(CoreError::custom)?,
+      }
+    }
+
+    if let Some(tfd) = Self::timer(
+      &mut self.wakeup_fd,
+      wakeup.is_some(),
+      ClockId::CLOCK_MONOTONIC,
+      "wakeup_tick",
+      resources,
+    )? {
+      match wakeup {
+        Some(at) => {
+          let after = at
+            .saturating_duration_since(Instant::now())
+            .max(Duration::from_millis(1));
+          tfd
+            .set(
+              Expiration::OneShot(TimeSpec::from(after)),
+              TimerSetTimeFlags::empty(),
+            )
+            .map_err(CoreError::custom)?;
+        }
+        None => tfd.unset().map_err(CoreError::custom)?,
+      }
+    }
+    Ok(Void)
+  }
+
+  /// The timer in `slot`, created on `clock` and wired to `action` the first
+  /// time it is `needed`.
+  fn timer<'r>(
+    slot: &mut Option<i32>,
+    needed: bool,
+    clock: nix::sys::timerfd::ClockId,
+    action: &'static str,
+    resources: &'r mut Resources,
+  ) -> CoreResult<Option<&'r nix::sys::timerfd::TimerFd>> {
+    use nix::sys::timerfd::{TimerFd, TimerFlags};
+    use std::os::fd::{AsFd, AsRawFd};
+
+    let fd = match *slot {
+      Some(fd) => fd,
+      None if !needed => return Ok(None),
+      None => {
+        let tfd = TimerFd::new(clock, TimerFlags::TFD_NONBLOCK | TimerFlags::TFD_CLOEXEC)
+          .map_err(CoreError::custom)?;
+        let fd = tfd.as_fd().as_raw_fd();
+        resources.own(fd, tfd);
+        resources.action(fd, ("flow", action));
+        *slot = Some(fd);
+        fd
+      }
+    };
+    Ok(resources.timer(fd))
+  }
+
+  /// Runs the `eval` of an auto-payload facet on a worker thread. The output
+  /// comes back through `drain_evals`.
+  fn spawn_eval(&mut self, name: Ustr, cfg: &AutoPayloadConfig, notifier: Option<Notifier>) {
+    let Some(cmd) = cfg.eval.clone() else {
+      return;
+    };
+    if !self.evaluating.insert(name.clone()) {
+      return;
+    }
+    let args = cfg.args.clone().unwrap_or_default();
+    let timeout = cfg
+      .timeout
+      .as_deref()
+      .and_then(parse_duration)
+      .unwrap_or(DEFAULT_EVAL_TIMEOUT);
+    let tx = self.eval_tx.clone();
+    std::thread::spawn(move || {
+      let out = run_eval(&cmd, &args, timeout);
+      let _ = tx.send((name, out));
+      if let Some(n) = &notifier {
+        let _ = n.notify();
+      }
+    });
+  }
+
+  /// Runs every `eval` auto-payload once and arms the `refresh` timers.
+  fn start_auto_payloads(
+    &mut self,
+    metadata: &MetadataRegistry,
+    resources: &mut Resources,
+    notifier: Option<Notifier>,
+  ) -> CoreResult<Void> {
+    use nix::sys::time::TimeSpec;
+    use nix::sys::timerfd::{ClockId, Expiration, TimerFd, TimerFlags, TimerSetTimeFlags};
+    use std::os::fd::{AsFd, AsRawFd};
+
+    let Some(items) = metadata.items::<FlowFacet>("*") else {
+      return Ok(Void);
+    };
+    for (group, def) in items {
+      let Some(cfg) = def.auto_payload.as_ref().filter(|cfg| cfg.eval.is_some()) else {
+        continue;
+      };
+      let name = Ustr::from(format!("{group}:{}", def.name));
+      self.spawn_eval(name.clone(), cfg, notifier.clone());
+
+      let Some(spec) = cfg.refresh.as_deref() else {
+        continue;
+      };
+      if self.refresh_fds.contains_key(&name) {
+        continue;
+      }
+      let every = parse_duration(spec)
+        .filter(|every| !every.is_zero())
+        .ok_or_else(|| CoreError::InvalidState(format!("invalid auto-payload refresh: {spec}")))?;
+      let tfd = TimerFd::new(
+        ClockId::CLOCK_MONOTONIC,
+        TimerFlags::TFD_NONBLOCK | TimerFlags::TFD_CLOEXEC,
+      )
+      .map_err(CoreError::custom)?;
+      tfd
+        .set(
+          Expiration::Interval(TimeSpec::from(every)),
+          TimerSetTimeFlags::empty(),
+        )
+        .map_err(CoreError::custom)?;
+      let fd = tfd.as_fd().as_raw_fd();
+      resources.own(fd, tfd);
+      let action: ResourceAction = ("flow", "refresh_auto_payload").into();
+      let facet = name.clone();
+      resources.action(fd, action.payload(move |p| p.insert("name", facet.clone())));
+      self.refresh_fds.insert(name, fd);
+    }
+    Ok(Void)
+  }
+
+  /// Brings an `eval` facet in line with its command's latest output, as kept
+  /// in `evaluated`: new payloads are set, and JSON branches the output no
+  /// longer has are removed.
+  fn apply_evaluated(
+    &mut self,
+    metadata: &MetadataRegistry,
+    sm: &mut FacetGraph,
+    name: Ustr,
+    variables: Option<&VariableHeap>,
+    event_bus: &EventBus,
+    dispatch: &RuntimeDispatcher,
+  ) -> Result<Void, CoreError> {
+    let Some(def) = metadata.find::<FlowFacet>("*", name.as_str()) else {
+      return Ok(Void);
+    };
+    let payloads = self.evaluated.get(&name).cloned().unwrap_or_default();
+    let mut guard = HashSet::new();
+    let current = sm.get(&name).cloned().unwrap_or_default();
+    if def.payload == FlowPayloadType::Json {
+      let branch_keys = branch_keys_for(&def);
+      let key_of = |payload: &FlowPayload| match payload {
+        FlowPayload::Json(json) => json_branch_key(&json.into_json(), &branch_keys),
+        _ => None,
+      };
+      let fresh: HashSet<Vec<String>> = payloads.iter().filter_map(key_of).collect();
+      for branch in &current {
+        if key_of(&branch.payload).is_some_and(|key| !fresh.contains(&key)) {
+          self.remove_facet(
+            metadata,
+            sm,
+            name.as_str(),
+            payload_to_filter(&branch.payload),
+            variables,
+            &mut guard,
+            event_bus,
+            dispatch,
+          )?;
+        }
+      }
+    }
+
+    let unchanged: HashSet<String> = current
+      .iter()
+      .map(|branch| payload_signature(&Some(branch.payload.clone())))
+      .collect();
+    for payload in payloads {
+      if unchanged.contains(&payload_signature(&Some(payload.clone()))) {
+        continue;
+      }
+      self.set_facet(
+        metadata,
+        sm,
+        name.clone(),
+        Some(payload),
+        None,
+        variables,
+        &mut guard,
+        event_bus,
+        dispatch,
+      )?;
+    }
+    Ok(Void)
+  }
+
   fn save_facet_graph(&self, sm: &mut FacetGraph) -> Result<Void, CoreError> {
     sm.save_all_scopes()?;
     Ok(Void)
@@ -451,6 +962,7 @@ impl FlowRuntime {
     sm: &mut FacetGraph,
     name: impl Into<Ustr>,
     payload: Option<FlowPayload>,
+    ttl: Option<Duration>,
     variables: Option<&VariableHeap>,
     guard: &mut HashSet<Ustr>,
     event_bus: &EventBus,
@@ -476,7 +988,14 @@ impl FlowRuntime {
       )));
     }
 
-    let branches = def.branch.clone();
+    let ttl = match (ttl, def.ttl.as_ref()) {
+      (Some(ttl), _) => Some(ttl),
+      (None, Some(spec)) => Some(parse_duration(spec).ok_or_else(|| {
+        guard.remove(&guard_key);
+        CoreError::InvalidState(format!("invalid facet ttl: {spec}"))
+      })?),
+      (None, None) => None,
+    };
 
     let instance = FlowInstance {
       name: name.clone(),
@@ -499,44 +1018,33 @@ impl FlowRuntime {
       def.subscribers.as_deref(),
     );
 
-    let entry = sm.facets.entry(name).or_default();
     match &instance.payload {
       FlowPayload::String(_) | FlowPayload::Bytes(_) | FlowPayload::None(_) => {
-        entry.clear();
-        entry.push(instance.clone());
+        sm.insert(name.clone(), vec![instance.clone()]);
+        sm.expire_after(&name, Vec::new(), ttl);
       }
       FlowPayload::Json(new_json) => {
-        let branch_keys = branches
-          .as_ref()
-          .map(|b| {
-            b.iter()
-              .map(|key| Ustr::from(branch_target_key(key.as_str())))
-              .collect::<Vec<Ustr>>()
-          })
-          .unwrap_or_else(|| vec!["id".into()]);
-
+        let branch_keys = branch_keys_for(&def);
         let new_key = json_branch_key(&new_json.into_json(), &branch_keys).ok_or_else(|| {
           guard.remove(&guard_key);
           CoreError::InvalidState("invalid JSON branch keys".into())
         })?;
 
-        let mut found = false;
-        for branch in entry.iter_mut() {
-          if let FlowPayload::Json(json) = &mut branch.payload {
-            let mut existing_json = json.into_json();
-            let existing_key = json_branch_key(&existing_json, &branch_keys);
-            if existing_key == Some(new_key.clone()) {
+        match sm.branch_position(&name, &branch_keys, &new_key) {
+          Some(pos) => {
+            if let Some(FlowInstance {
+              payload: FlowPayload::Json(json),
+              ..
+            }) = sm.branch_at_mut(&name, pos)
+            {
+              let mut existing_json = json.into_json();
               merge_json(&mut existing_json, &new_json.into_json());
               *json = FlowJson(existing_json.to_string());
-              found = true;
-              break;
             }
           }
+          None => sm.push_branch(&name, new_key.clone(), instance.clone()),
         }
-
-        if !found {
-          entry.push(instance.clone());
-        }
+        sm.expire_after(&name, new_key, ttl);
       }
     }
 
@@ -576,7 +1084,7 @@ impl FlowRuntime {
     event_bus: &EventBus,
     dispatch: &RuntimeDispatcher,
   ) -> CoreResult<Void> {
-    if let Some(branches) = sm.facets.remove(name) {
+    if let Some(branches) = sm.remove(name) {
       let (to_keep, to_remove): (Vec<_>, Vec<_>) = if let Some(filter) = &filter {
         branches
           .into_iter()
@@ -585,6 +1093,20 @@ impl FlowRuntime {
         (Vec::new(), branches)
       };
 
+      let key = Ustr::from(name);
+      if to_keep.is_empty() {
+        sm.clear_expiries(&key);
+      } else if let Some(def) = metadata.find::<FlowFacet>("*", name) {
+        let branch_keys = branch_keys_for(&def);
+        for branch in &to_remove {
+          if let FlowPayload::Json(json) = &branch.payload
+            && let Some(id) = json_branch_key(&json.into_json(), &branch_keys)
+          {
+            sm.expire_after(&key, id, None);
+          }
+        }
+      }
+
       for mut branch in to_remove {
         branch.r#type = FlowType::Facet;
         let guard_key = Ustr::from(format!(
@@ -637,7 +1159,7 @@ impl FlowRuntime {
       }
 
       if !to_keep.is_empty() {
-        sm.facets.insert(Ustr::from(name.to_string()), to_keep);
+        sm.insert(key, to_keep);
       }
     }
 
@@ -756,7 +1278,12 @@ impl FlowRuntime {
       }
 
       let source_payload = if def.auto_payload.is_some() {
-        let payloads = auto_payloads_for(&def, Some(&source.payload), variables);
+        let payloads = auto_payloads_for(
+          &def,
+          Some(&source.payload),
+          variables,
+          self.evaluated.get(&full_name),
+        );
         let Some(first) = payloads.first().cloned() else {
           continue;
         };
@@ -779,6 +1306,7 @@ impl FlowRuntime {
           sm,
           full_name,
           Some(payload),
+          None,
           variables,
           guard,
           event_bus,
@@ -816,11 +1344,7 @@ impl FlowRuntime {
     }
     let mut obj = serde_json::Map::new();
     obj.insert(branch_target_key(branch_spec).to_string(), current.clone());
-    Some(FlowMatchOperation::Options {
-      binary: None,
-      contains: None,
-      r#as: Some(serde_json::Value::Object(obj)),
-    })
+    Some(FlowMatchOperation::subset(serde_json::Value::Object(obj)))
   }
 
   fn reconcile_inverse_transcendence_for_source(
@@ -850,19 +1374,15 @@ impl FlowRuntime {
       let auto_activate = def.auto_payload.is_some();
 
       for payload in if auto_activate {
-        auto_payloads_for(&def, None, variables)
+        auto_payloads_for(&def, None, variables, self.evaluated.get(&full_name))
       } else {
-        sm.facets.get(&full_name).map_or(Vec::new(), |x| {
+        sm.get(&full_name).map_or(Vec::new(), |x| {
           x.iter().map(|x| x.payload.clone()).collect()
         })
       } {
         let should_activate = auto_activate
           && deps.iter().all(|cfg| {
-            let branches = sm
-              .facets
-              .get(cfg.name())
-              .map(|v| v.as_slice())
-              .unwrap_or(&[]);
+            let branches = sm.get(cfg.name()).map(|v| v.as_slice()).unwrap_or(&[]);
             if let Some(branch_spec) = cfg.branch() {
               let filter = self.branch_filter_from_payload(&payload, branch_spec.as_str());
               !branches.iter().any(|b| {
@@ -884,7 +1404,6 @@ impl FlowRuntime {
           });
 
         let currently_active = sm
-          .facets
           .get(&full_name)
           .map(|branches| {
             branches
@@ -899,6 +1418,7 @@ impl FlowRuntime {
             sm,
             full_name.clone(),
             Some(payload.clone()),
+            None,
             variables,
             guard,
             event_bus,
@@ -989,7 +1509,7 @@ impl FlowRuntime {
         continue;
       };
       for cond in after {
-        if let Some(name) = self.condition_name(cond) {
+        for name in self.condition_names(cond) {
           self
             .transcendence_index
             .entry(name)
@@ -1000,24 +1520,39 @@ impl FlowRuntime {
     }
   }
 
-  fn condition_name(&self, cond: &FlowItem) -> Option<Ustr> {
+  fn condition_names(&self, cond: &FlowItem) -> Vec<Ustr> {
     match cond {
-      FlowItem::Simple(name) => Some(name.clone()),
-      FlowItem::Detailed {
-        facet: state,
-        impulse: _,
-        target: _,
-        branch: _,
-      } => state.clone(),
+      FlowItem::Simple(name) => vec![name.clone()],
+      FlowItem::All { all: items }
+      | FlowItem::Any { any: items }
+      | FlowItem::Count { of: items, .. } => items
+        .iter()
+        .flat_map(|item| self.condition_names(item))
+        .collect(),
+      FlowItem::Not { not } => self.condition_names(not),
+      FlowItem::Detailed { facet: state, .. } => state.iter().cloned().collect(),
     }
   }
 }
 
 #[runtime("flow")]
 impl FlowRuntime {
-  fn set_facet(&mut self, name: Ustr, #[optional] payload: serde_json::Value) {
+  fn set_facet(
+    &mut self,
+    name: Ustr,
+    // the macro reads arguments out of a binding named `payload`, so it goes last
+    #[optional] ttl: Ustr,
+    #[optional] payload: serde_json::Value,
+  ) {
     let has_payload = payload.as_ref().map(|_| true);
     let flow_payload = FlowPayload::from_json(payload);
+    let ttl = match ttl {
+      Some(spec) => Some(
+        parse_duration(&spec)
+          .ok_or_else(|| CoreError::InvalidState(format!("invalid facet ttl: {spec}")))?,
+      ),
+      None => None,
+    };
     ctx
       .registry
       .singleton_handle::<(&mut FacetGraph, &mut VariableHeap), _>(
@@ -1029,6 +1564,7 @@ impl FlowRuntime {
             sm,
             name.clone(),
             has_payload.map(|_| flow_payload.clone()),
+            ttl,
             Some(&*vh),
             &mut guard,
             ctx.event_bus,
@@ -1038,6 +1574,8 @@ impl FlowRuntime {
         },
       )?;
 
+    self.rearm_timer(&ctx.registry, ctx.resources)?;
+
     let mut fields = HashMap::new();
     fields.insert("name".to_string(), name.to_string());
     fields.insert("payload".into(), flow_payload.to_string_payload());
@@ -1056,11 +1594,7 @@ impl FlowRuntime {
   ) {
     let filter_json = filter.or(payload);
     let filter = filter_json.and_then(|v| match v {
-      serde_json::Value::Object(b) => Some(FlowMatchOperation::Options {
-        binary: None,
-        contains: None,
-        r#as: Some(b.into()),
-      }),
+      serde_json::Value::Object(b) => Some(FlowMatchOperation::subset(b.into())),
       _ => serde_json::from_value(v).ok(),
     });
 
@@ -1084,6 +1618,8 @@ impl FlowRuntime {
         },
       )?;
 
+    self.rearm_timer(&ctx.registry, ctx.resources)?;
+
     let mut fields = HashMap::new();
     fields.insert("name".to_string(), name.to_string());
     fields.insert("payload".into(), format!("{filter:?}"));
@@ -1095,7 +1631,6 @@ impl FlowRuntime {
 
     if let Some(sm) = ctx.registry.singleton::<FacetGraph>(FacetGraph::KEY) {
       let should_drop_scope = sm
-        .facets
         .get(&name)
         .map(|branches| branches.is_empty())
         .unwrap_or(true);
@@ -1158,7 +1693,6 @@ impl FlowRuntime {
           let mut guard = HashSet::new();
 
           let existing_states = sm
-            .facets
             .values()
             .flat_map(|branches| branches.iter().cloned())
             .collect::<Vec<_>>();
@@ -1187,6 +1721,197 @@ impl FlowRuntime {
           self.save_facet_graph(sm)
         },
       )?;
+    // restored branches may carry deadlines, some already past
+    self.rearm_timer(&ctx.registry, ctx.resources)?;
+    self.start_auto_payloads(ctx.registry.metadata, ctx.resources, ctx.notifier.clone())?;
+  }
+
+  fn refresh_auto_payload(&mut self, name: Ustr) {
+    if let Some(tfd) = self
+      .refresh_fds
+      .get(&name)
+      .and_then(|fd| ctx.resources.timer(*fd))
+    {
+      let _ = nix::unistd::read(tfd, &mut [0u8; 8]);
+    }
+    let Some(def) = ctx.registry.metadata.find::<FlowFacet>("*", name.as_str()) else {
+      return Ok(None);
+    };
+    if let Some(cfg) = &def.auto_payload {
+      self.spawn_eval(name, cfg, ctx.notifier.clone());
+    }
+  }
+
+  /// Applies the output of finished `eval` commands. Facets set by `after`
+  /// or `stop-on` only keep it for their next reconcile.
+  fn drain_evals(&mut self) {
+    let mut done = Vec::new();
+    while let Ok(result) = self.eval_rx.try_recv() {
+      done.push(result);
+    }
+    if done.is_empty() {
+      return Ok(None);
+    }
+
+    ctx
+      .registry
+      .singleton_handle::<(&mut FacetGraph, &mut VariableHeap), _>(
+        (FacetGraph::KEY.into(), VariableHeap::KEY.into()),
+        |_, (sm, vh)| {
+          let mut guard = HashSet::new();
+          let mut dependents = false;
+          for (name, out) in done {
+            self.evaluating.remove(&name);
+            let Some(def) = ctx.registry.metadata.find::<FlowFacet>("*", name.as_str()) else {
+              continue;
+            };
+            let Some(out) = out else {
+              log.log(
+                LogLevel::Warn,
+                "flow-runtime",
+                "auto-payload command failed",
+                [("name".to_string(), name.to_string())].into(),
+              );
+              continue;
+            };
+            self
+              .evaluated
+              .insert(name.clone(), eval_payloads_for(&def, &out));
+
+            if def.after.is_some() || def.stop_on.is_some() {
+              dependents |= def.stop_on.is_some();
+              continue;
+            }
+            self.apply_evaluated(
+              ctx.registry.metadata,
+              sm,
+              name,
+              Some(&*vh),
+              ctx.event_bus,
+              dispatch,
+            )?;
+          }
+          if dependents {
+            self.reconcile_inverse_transcendence_all(
+              ctx.registry.metadata,
+              sm,
+              Some(&*vh),
+              &mut guard,
+              ctx.event_bus,
+              dispatch,
+            )?;
+          }
+          self.save_facet_graph(sm)
+        },
+      )?;
+    self.rearm_timer(&ctx.registry, ctx.resources)?;
+  }
+
+  fn expiry_tick(&mut self) {
+    if let Some(tfd) = self.expiry_fd.and_then(|fd| ctx.resources.timer(fd))
+      && let Err(nix::errno::Errno::ECANCELED) = nix::unistd::read(tfd, &mut [0u8; 8])
+    {
+      self.rearm_timer(&ctx.registry, ctx.resources)?;
+      log.log(
+        LogLevel::Info,
+        "flow-runtime",
+        "wall clock changed, re-armed facet expiries",
+        HashMap::new(),
+      );
+    }
+    FlowRuntime::actions.expire_facets().dispatch(dispatch)?;
+  }
+
+  fn wakeup_tick(&mut self) {
+    if let Some(tfd) = self.wakeup_fd.and_then(|fd| ctx.resources.timer(fd)) {
+      let _ = nix::unistd::read(tfd, &mut [0u8; 8]);
+    }
+    FlowRuntime::actions.wake_conditions().dispatch(dispatch)?;
+  }
+
+  /// Re-arms the timer after units observed filtered conditions.
+  fn arm_timer(&mut self) {
+    self.rearm_timer(&ctx.registry, ctx.resources)?;
+  }
+
+  /// Re-evaluates units whose condition's debounce or hold window ran out.
+  fn wake_conditions(&mut self) {
+    let due = ctx
+      .registry
+      .singleton_mut::<FacetGraph>(FacetGraph::KEY)
+      .map(|sm| sm.take_wakeups(Instant::now()))
+      .unwrap_or_default();
+
+    for wakeup in due {
+      let trigger = EmitTrigger {
+        name: Some(wakeup.name),
+        flow_type: Some(FlowType::Facet),
+        payload: Some(wakeup.payload),
+        action: wakeup.action,
+        ..Default::default()
+      };
+      dispatch.dispatch(
+        "events",
+        "evaluate_triggers",
+        RuntimePayload::default().insert("trigger", trigger),
+      )?;
+    }
+    self.rearm_timer(&ctx.registry, ctx.resources)?;
+  }
+
+  fn expire_facets(&mut self) {
+    let expired = ctx
+      .registry
+      .singleton_handle::<(&mut FacetGraph, &mut VariableHeap), _>(
+        (FacetGraph::KEY.into(), VariableHeap::KEY.into()),
+        |_, (sm, vh)| {
+          let expired = sm.take_expired(SystemTime::now());
+          for (key, branch) in &expired {
+            let Some(def) = ctx.registry.metadata.find::<FlowFacet>("*", key.as_str()) else {
+              continue;
+            };
+            let filter = if branch.is_empty() {
+              None
+            } else {
+              match sm.branch_position(key, &branch_keys_for(&def), branch) {
+                Some(pos) => payload_to_filter(&sm[key][pos].payload),
+                None => continue,
+              }
+            };
+            let mut guard = HashSet::new();
+            self.remove_facet(
+              ctx.registry.metadata,
+              sm,
+              key.as_str(),
+              filter,
+              Some(&*vh),
+              &mut guard,
+              ctx.event_bus,
+              dispatch,
+            )?;
+          }
+          self.save_facet_graph(sm)?;
+          Ok(expired)
+        },
+      )?;
+
+    for (key, branch) in expired {
+      log.log(
+        LogLevel::Info,
+        "flow-runtime",
+        "facet expired",
+        [
+          ("name".to_string(), key.to_string()),
+          ("branch".to_string(), branch.join(",")),
+        ]
+        .into(),
+      );
+    }
+    self.rearm_timer(&ctx.registry, ctx.resources)?;
+
+    if let Some(notifier) = &ctx.notifier {
+      notifier.notify()?;
+    }
   }
 }
 
@@ -1220,16 +1945,50 @@ pub fn condition_is_active(
   cond: &FlowItem,
   payload: Option<&FlowPayload>,
 ) -> bool {
-  for branches in sm.facets.values() {
-    for branch in branches {
-      let mut state = branch.clone();
-      state.r#type = FlowType::Facet;
-      if check_condition(cond, &state) && payload_compatible(payload, &state.payload) {
-        return true;
+  match cond {
+    FlowItem::All { all } => all.iter().all(|c| condition_is_active(sm, c, payload)),
+    FlowItem::Any { any } => any.iter().any(|c| condition_is_active(sm, c, payload)),
+    FlowItem::Not { not } => !condition_is_active(sm, not, payload),
+    FlowItem::Count { count, of } => {
+      of.iter()
+        .filter(|c| condition_is_active(sm, c, payload))
+        .count()
+        >= *count
+    }
+    FlowItem::Simple(name)
+    | FlowItem::Detailed {
+      facet: Some(name), ..
+    } => {
+      // filtered leaves report their debounced, held state once observed
+      if cond.is_filtered()
+        && let Some(level) = sm.filters.level(cond)
+      {
+        return level;
       }
+      matching_branch(sm, cond, name, payload).is_some()
     }
+    // impulses are never active
+    FlowItem::Detailed { .. } => false,
   }
-  false
+}
+
+/// The first branch of `name` satisfying the leaf `cond`, modifiers aside.
+fn matching_branch<'a>(
+  sm: &'a FacetGraph,
+  cond: &FlowItem,
+  name: &'a str,
+  payload: Option<&FlowPayload>,
+) -> Option<&'a FlowInstance> {
+  sm.branches_named(name).find(|branch| {
+    let state = if branch.r#type == FlowType::Facet {
+      std::borrow::Cow::Borrowed(*branch)
+    } else {
+      let mut state = (*branch).clone();
+      state.r#type = FlowType::Facet;
+      std::borrow::Cow::Owned(state)
+    };
+    check_condition(cond, &state) && payload_compatible(payload, &state.payload)
+  })
 }
 
 pub fn condition_matches(
@@ -1238,6 +1997,28 @@ pub fn condition_matches(
   event: Option<&FlowInstance>,
   payload: Option<&FlowPayload>,
 ) -> bool {
+  match cond {
+    FlowItem::All { all } => return all.iter().all(|c| condition_matches(sm, c, event, payload)),
+    FlowItem::Any { any } => return any.iter().any(|c| condition_matches(sm, c, event, payload)),
+    FlowItem::Not { not } => return !condition_matches(sm, not, event, payload),
+    FlowItem::Count { count, of } => {
+      return of
+        .iter()
+        .filter(|c| condition_matches(sm, c, event, payload))
+        .count()
+        >= *count;
+    }
+    FlowItem::Detailed {
+      facet: Some(_),
+      on: Some(_),
+      ..
+    } => return sm.filters.edge(cond, event),
+    // a matching event alone doesn't get past `debounce`
+    FlowItem::Detailed { .. } if cond.is_filtered() => {
+      return condition_is_active(sm, cond, payload);
+    }
+    FlowItem::Simple(_) | FlowItem::Detailed { .. } => {}
+  }
   if let Some(event) = event {
     if check_condition(cond, event) && payload_compatible(payload, &event.payload) {
       return true;
@@ -1246,56 +2027,78 @@ pub fn condition_matches(
   condition_is_active(sm, cond, payload)
 }
 
-fn payloads_from_toml(
+/// Keys telling the JSON branches of a facet apart, `id` unless configured.
+fn branch_keys_for(def: &FlowFacetMetadata) -> Vec<Ustr> {
+  def
+    .branch
+    .as_ref()
+    .map(|b| {
+      b.iter()
+        .map(|key| Ustr::from(branch_target_key(key.as_str())))
+        .collect()
+    })
+    .unwrap_or_else(|| vec!["id".into()])
+}
+
+fn payload_from_value(
   def: &FlowFacetMetadata,
   cfg: &AutoPayloadConfig,
-  toml_value: toml::Value,
+  value: serde_json::Value,
 ) -> FlowPayload {
+  let text = || match &value {
+    serde_json::Value::String(s) => s.clone(),
+    other => other.to_string(),
+  };
   match def.payload {
     FlowPayloadType::Json => match &cfg.insert {
-      Some(AutoPayloadInsert::One(key)) if key == "root" => FlowPayload::Json(
-        serde_json::to_value(toml_value)
-          .unwrap_or(serde_json::Value::Bool(true))
-          .to_string()
-          .into(),
-      ),
-      Some(AutoPayloadInsert::One(key)) => FlowPayload::Json(
-        serde_json::json!({ key: serde_json::to_value(toml_value).unwrap_or(serde_json::Value::Bool(true)) })
-        .to_string()
-        .into(),
-      ),
+      Some(AutoPayloadInsert::One(key)) if key == "root" => {
+        FlowPayload::Json(value.to_string().into())
+      }
+      Some(AutoPayloadInsert::One(key)) => {
+        FlowPayload::Json(serde_json::json!({ key: value }).to_string().into())
+      }
       Some(AutoPayloadInsert::Many(keys)) => {
         let mut obj = serde_json::Map::new();
         for (i, key) in keys.iter().enumerate() {
-          if let Some(value) = &toml_value.get(i) {
-            obj.insert(key.clone(), serde_json::to_value(value).unwrap_or(serde_json::Value::Bool(true)));
+          if let Some(value) = value.get(i) {
+            obj.insert(key.clone(), value.clone());
           }
         }
-        FlowPayload::Json(
-          serde_json::Value::Object(obj).to_string().into(),
-        )
+        FlowPayload::Json(serde_json::Value::Object(obj).to_string().into())
       }
       None => FlowPayload::Json(serde_json::json!({ "value": "none" }).to_string().into()),
     },
-    FlowPayloadType::String => FlowPayload::String(toml_value.to_string()),
-    FlowPayloadType::Bytes => FlowPayload::Bytes(toml_value.to_string().as_bytes().to_vec()),
+    FlowPayloadType::String => FlowPayload::String(text()),
+    FlowPayloadType::Bytes => FlowPayload::Bytes(text().into_bytes()),
     FlowPayloadType::None => FlowPayload::None(false),
   }
 }
 
+/// Payloads of an auto-payload facet. `eval` facets use the last output of
+/// their command, collected off the main loop, in `evaluated`.
 fn auto_payloads_for(
   def: &FlowFacetMetadata,
   _payload: Option<&FlowPayload>,
   variables: Option<&VariableHeap>,
+  evaluated: Option<&Vec<FlowPayload>>,
 ) -> Vec<FlowPayload> {
   let Some(cfg) = &def.auto_payload else {
     return vec![];
   };
+  if cfg.eval.is_some() {
+    return match evaluated {
+      Some(payloads) => payloads.clone(),
+      None => vec![default_payload_for_type(def.payload)],
+    };
+  }
   let Some(variable) = &cfg.variable else {
     return vec![default_payload_for_type(def.payload)];
   };
 
-  let Some(value) = variables.and_then(|v| v.get(variable)) else {
+  let Some(value) = variables
+    .and_then(|v| v.get(variable))
+    .and_then(|v| serde_json::to_value(v).ok())
+  else {
     return vec![default_payload_for_type(def.payload)];
   };
 
@@ -1305,13 +2108,24 @@ fn auto_payloads_for(
     };
     value
       .iter()
-      .map(|x| payloads_from_toml(def, cfg, x.clone()))
+      .map(|x| payload_from_value(def, cfg, x.clone()))
       .collect()
   } else {
-    vec![payloads_from_toml(def, cfg, value)]
+    vec![payload_from_value(def, cfg, value)]
   }
 }
 
+/// Payloads of an `eval` auto-payload from its command's stdout.
+pub fn eval_payloads_for(def: &FlowFacetMetadata, stdout: &str) -> Vec<FlowPayload> {
+  let Some(cfg) = &def.auto_payload else {
+    return vec![];
+  };
+  eval_values(stdout, cfg.many)
+    .into_iter()
+    .map(|value| payload_from_value(def, cfg, value))
+    .collect()
+}
+
 fn transcendent_payload_for(
   def: &FlowFacetMetadata,
   source_payload: &FlowPayload,
@@ -1349,6 +2163,7 @@ pub struct FlowRuntimePayload<'a> {
   pub name: &'a str,
   pub payload: Option<serde_json::Value>,
   pub filter: Option<serde_json::Value>,
+  pub ttl: Option<&'a str>,
 }
 
 impl<'a> FlowRuntimePayload<'a> {
@@ -1368,6 +2183,11 @@ impl<'a> FlowRuntimePayload<'a> {
     self.filter = Some(v.into());
     self
   }
+
+  pub fn ttl(mut self, ttl: &'a str) -> Self {
+    self.ttl = Some(ttl);
+    self
+  }
 }
 
 impl<'a> Into<RuntimePayload> for FlowRuntimePayload<'a> {
@@ -1382,6 +2202,10 @@ impl<'a> Into<RuntimePayload> for FlowRuntimePayload<'a> {
       p = p.insert("filter", p1);
     }
 
+    if let Some(ttl) = self.ttl {
+      p = p.insert::<Ustr>("ttl", ttl.into());
+    }
+
     p
   }
 }
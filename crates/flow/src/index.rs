@@ -0,0 +1,109 @@
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+
+use rind_core::prelude::*;
+
+use crate::triggers::json_branch_key;
+use crate::{FlowInstance, FlowPayload};
+
+/// Lookup tables kept next to `FacetGraph::facets`, so condition checks and
+/// branch merges touch one facet instead of scanning all of them.
+#[derive(Clone, Default)]
+pub struct FacetIndex {
+  /// Unscoped facet name to every key carrying it (`name`, `name@scope`, ...).
+  names: HashMap<Ustr, HashSet<Ustr>>,
+  /// Facet key to the position of each JSON branch, by branch key. Built on demand.
+  branches: HashMap<Ustr, BranchPositions>,
+}
+
+#[derive(Clone)]
+struct BranchPositions {
+  keys: Vec<Ustr>,
+  positions: HashMap<Vec<String>, usize>,
+}
+
+pub fn unscoped_name(key: &str) -> Ustr {
+  Ustr::from(rslvns!(snorm key))
+}
+
+impl FacetIndex {
+  pub fn insert(&mut self, key: &Ustr) {
+    self.branches.remove(key);
+    self
+      .names
+      .entry(unscoped_name(key))
+      .or_default()
+      .insert(key.clone());
+  }
+
+  pub fn remove(&mut self, key: &Ustr) {
+    self.branches.remove(key);
+    let name = unscoped_name(key);
+    let Some(keys) = self.names.get_mut(&name) else {
+      return;
+    };
+    keys.remove(key);
+    if keys.is_empty() {
+      self.names.remove(&name);
+    }
+  }
+
+  /// Drops the cached branch positions of `key`, whose branches may be edited in place.
+  pub fn forget_branches<Q>(&mut self, key: &Q)
+  where
+    Ustr: Borrow<Q>,
+    Q: Hash + Eq + ?Sized,
+  {
+    self.branches.remove(key);
+  }
+
+  pub fn rebuild(&mut self, facets: &HashMap<Ustr, Vec<FlowInstance>>) {
+    self.names.clear();
+    self.branches.clear();
+    for key in facets.keys() {
+      self.insert(key);
+    }
+  }
+
+  pub fn keys(&self, name: &str) -> impl Iterator<Item = &Ustr> {
+    self.names.get(&unscoped_name(name)).into_iter().flatten()
+  }
+
+  pub fn branch_position(
+    &mut self,
+    key: &Ustr,
+    branches: &[FlowInstance],
+    branch_keys: &[Ustr],
+    branch_key: &[String],
+  ) -> Option<usize> {
+    let stale = self
+      .branches
+      .get(key)
+      .is_none_or(|idx| idx.keys != branch_keys);
+    if stale {
+      let mut positions = HashMap::new();
+      for (i, branch) in branches.iter().enumerate() {
+        if let FlowPayload::Json(json) = &branch.payload
+          && let Some(k) = json_branch_key(&json.into_json(), branch_keys)
+        {
+          positions.entry(k).or_insert(i);
+        }
+      }
+      self.branches.insert(
+        key.clone(),
+        BranchPositions {
+          keys: branch_keys.to_vec(),
+          positions,
+        },
+      );
+    }
+    self.branches[key].positions.get(branch_key).copied()
+  }
+
+  pub fn record_branch(&mut self, key: &Ustr, branch_key: Vec<String>, position: usize) {
+    if let Some(idx) = self.branches.get_mut(key) {
+      idx.positions.entry(branch_key).or_insert(position);
+    }
+  }
+}
@@ -316,7 +316,7 @@ impl TransportRuntime {
               .registry
               .singleton::<FacetGraph>(FacetGraph::KEY)
               .ok_or_else(|| CoreError::InvalidState("state machine store not found".into()))?;
-            let exists = sm.facets.contains_key(&Ustr::from(state_name.as_str()));
+            let exists = sm.contains_key(&Ustr::from(state_name.as_str()));
             response.payload = Some(FlowPayload::from_json(Some(serde_json::json!(exists))));
           }
         }
@@ -26,14 +26,21 @@ fn same_flow_name(a: &Ustr, b: &Ustr) -> bool {
   rslvns!(snorm a) == rslvns!(snorm b)
 }
 
+/// Whether `trigger` satisfies `cond`. Composite items report whether the
+/// trigger touches any of their leaves; use `condition_matches` to evaluate them.
 pub fn check_condition(cond: &FlowItem, trigger: &FlowInstance) -> bool {
   match cond {
     FlowItem::Simple(name) => same_flow_name(name, &trigger.name),
+    FlowItem::All { all: items }
+    | FlowItem::Any { any: items }
+    | FlowItem::Count { of: items, .. } => items.iter().any(|item| check_condition(item, trigger)),
+    FlowItem::Not { not } => check_condition(not, trigger),
     FlowItem::Detailed {
       facet: state,
       impulse: signal,
       target,
       branch,
+      ..
     } => {
       if let Some(state_name) = state {
         if trigger.r#type != FlowType::Facet || !same_flow_name(state_name, &trigger.name) {
@@ -79,20 +86,156 @@ pub fn match_operation(matcher: &FlowMatchOperation, payload: &FlowPayload) -> b
       binary,
       contains,
       r#as,
+      path,
+      ne,
+      gt,
+      ge,
+      lt,
+      le,
+      regex,
     } => {
       if let Some(true) = binary {
-        matches!(payload, FlowPayload::Bytes(_))
-      } else if let Some(needle) = contains {
-        payload.to_string_payload().contains(needle.as_str())
-      } else if let Some(filter) = r#as {
-        subset_match(filter, &payload.to_json())
-      } else {
-        false
+        return matches!(payload, FlowPayload::Bytes(_));
+      }
+      let value = match path {
+        Some(path) => match json_path(&payload.to_json(), path) {
+          Some(v) => v.clone(),
+          None => return false,
+        },
+        None => payload.to_json(),
+      };
+      let text = match (path, &value) {
+        (Some(_), serde_json::Value::String(s)) => s.clone(),
+        (Some(_), v) => v.to_string(),
+        (None, _) => payload.to_string_payload(),
+      };
+
+      let mut matched = false;
+      if let Some(needle) = contains {
+        if !text.contains(needle.as_str()) {
+          return false;
+        }
+        matched = true;
+      }
+      if let Some(filter) = r#as {
+        if !subset_match(filter, &value) {
+          return false;
+        }
+        matched = true;
+      }
+      if let Some(pattern) = regex {
+        if !regex_match(pattern, &text) {
+          return false;
+        }
+        matched = true;
+      }
+      if let Some(other) = ne {
+        if compare_json(&value, other) == Some(std::cmp::Ordering::Equal) {
+          return false;
+        }
+        matched = true;
+      }
+      use std::cmp::Ordering::*;
+      for (bound, accepted) in [
+        (gt, &[Greater][..]),
+        (ge, &[Greater, Equal][..]),
+        (lt, &[Less][..]),
+        (le, &[Less, Equal][..]),
+      ] {
+        let Some(bound) = bound else {
+          continue;
+        };
+        match compare_json(&value, bound) {
+          Some(ord) if accepted.contains(&ord) => matched = true,
+          _ => return false,
+        }
       }
+      matched
     }
   }
 }
 
+/// Walks `path` (`a/b/0`, same segments as branch specs) through objects and arrays.
+pub fn json_path<'a>(value: &'a serde_json::Value, path: &str) -> Option<&'a serde_json::Value> {
+  let mut current = value;
+  for segment in path.split('/').filter(|p| !p.is_empty()) {
+    current = match current {
+      serde_json::Value::Array(arr) => arr.get(segment.parse::<usize>().ok()?)?,
+      _ => current.get(segment)?,
+    };
+  }
+  Some(current)
+}
+
+/// Orders a payload value against a bound. Numbers compare numerically (numeric
+/// strings included, since string payloads carry their value as text), strings
+/// lexically; anything else only compares equal to itself.
+pub fn compare_json(
+  value: &serde_json::Value,
+  bound: &serde_json::Value,
+) -> Option<std::cmp::Ordering> {
+  fn as_number(v: &serde_json::Value) -> Option<f64> {
+    match v {
+      serde_json::Value::Number(n) => n.as_f64(),
+      serde_json::Value::String(s) => s.trim().parse().ok(),
+      _ => None,
+    }
+  }
+  match (value, bound) {
+    (v, b @ serde_json::Value::Number(_)) => as_number(v)?.partial_cmp(&as_number(b)?),
+    (serde_json::Value::String(v), serde_json::Value::String(b)) => Some(v.cmp(b)),
+    (v, b) if v == b => Some(std::cmp::Ordering::Equal),
+    _ => None,
+  }
+}
+
+/// Compiles every `regex` in `cond`, nested items included, so a bad pattern
+/// is reported when its unit loads instead of silently never matching.
+pub fn validate_condition(cond: &FlowItem) -> Result<(), String> {
+  match cond {
+    FlowItem::Simple(_) => Ok(()),
+    FlowItem::All { all: items }
+    | FlowItem::Any { any: items }
+    | FlowItem::Count { of: items, .. } => items.iter().try_for_each(validate_condition),
+    FlowItem::Not { not } => validate_condition(not),
+    FlowItem::Detailed { target, branch, .. } => [target, branch]
+      .into_iter()
+      .flatten()
+      .try_for_each(validate_operation),
+  }
+}
+
+pub fn validate_operation(matcher: &FlowMatchOperation) -> Result<(), String> {
+  match matcher {
+    FlowMatchOperation::Options {
+      regex: Some(pattern),
+      ..
+    } => compile_regex(pattern)
+      .map(|_| ())
+      .map_err(|e| format!("invalid regex {pattern:?}: {e}")),
+    _ => Ok(()),
+  }
+}
+
+/// Only valid patterns are cached; an invalid one never matches.
+fn compile_regex(pattern: &Ustr) -> Result<regex::Regex, regex::Error> {
+  static CACHE: OnceCell<std::sync::Mutex<HashMap<Ustr, regex::Regex>>> = OnceCell::new();
+  let mut cache = CACHE
+    .get_or_init(Default::default)
+    .lock()
+    .unwrap_or_else(|e| e.into_inner());
+  if let Some(re) = cache.get(pattern) {
+    return Ok(re.clone());
+  }
+  let re = regex::Regex::new(pattern.as_str())?;
+  cache.insert(pattern.clone(), re.clone());
+  Ok(re)
+}
+
+fn regex_match(pattern: &Ustr, text: &str) -> bool {
+  compile_regex(pattern).is_ok_and(|re| re.is_match(text))
+}
+
 pub fn trigger_events(
   triggers: Vec<Trigger>,
   sm: Option<&FacetGraph>,
@@ -123,7 +266,7 @@ pub fn trigger_events(
       match &trigger.payload {
         Some(serde_json::Value::String(s)) => {
           if let Some((state_name, path)) = s.rsplit_once(':') {
-            if let Some(branches) = sm.facets.get(state_name) {
+            if let Some(branches) = sm.get(state_name) {
               for branch in branches {
                 let mut resolved_trigger = trigger.clone();
                 let resolved = resolve_path(branch, path);
@@ -149,7 +292,7 @@ pub fn trigger_events(
           }
 
           if let Some(state_name) = primary_state {
-            if let Some(branches) = sm.facets.get(state_name.as_str()) {
+            if let Some(branches) = sm.get(state_name.as_str()) {
               for branch in branches {
                 let mut resolved_trigger = trigger.clone();
                 let mut new_map = map.clone();
@@ -160,7 +303,7 @@ pub fn trigger_events(
                         let branch_to_use = if s_name == state_name {
                           Some(branch)
                         } else {
-                          sm.facets.get(s_name).and_then(|b| b.first())
+                          sm.get(s_name).and_then(|b| b.first())
                         };
                         if let Some(b) = branch_to_use {
                           *v = serde_json::Value::String(resolve_path(b, path));
@@ -336,11 +479,7 @@ pub fn payload_signature(payload: &Option<FlowPayload>) -> String {
 
 pub fn payload_to_filter(payload: &FlowPayload) -> Option<FlowMatchOperation> {
   match payload {
-    FlowPayload::Json(i) => Some(FlowMatchOperation::Options {
-      binary: None,
-      contains: None,
-      r#as: Some(i.into_json()),
-    }),
+    FlowPayload::Json(i) => Some(FlowMatchOperation::subset(i.into_json())),
     FlowPayload::String(i) => Some(FlowMatchOperation::Eq(Ustr::from(i.as_str()))),
     FlowPayload::Bytes(_) | FlowPayload::None(_) => None,
   }
@@ -355,14 +494,124 @@ pub fn default_payload_for_type(t: FlowPayloadType) -> FlowPayload {
   }
 }
 
-pub fn run_eval(cmd: &str, args: Option<Vec<String>>) -> String {
-  let out = std::process::Command::new(cmd)
-    .args(args.unwrap_or_default())
-    .output();
-  match out {
-    Ok(o) => String::from_utf8(o.stdout).unwrap_or_default(),
-    Err(_) => String::new(),
+/// Children started by `run_eval`, by pid, with their exit code once reaped.
+/// Both `run_eval` and the reaper wait for children while holding this, so
+/// whichever reaps an eval child records it here and the other never waits
+/// on, or signals, a pid it lost.
+#[derive(Default)]
+pub struct EvalChildren(HashMap<i32, Option<i32>>);
+
+impl EvalChildren {
+  pub fn lock() -> std::sync::MutexGuard<'static, EvalChildren> {
+    static CHILDREN: OnceCell<std::sync::Mutex<EvalChildren>> = OnceCell::new();
+    CHILDREN
+      .get_or_init(Default::default)
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+  }
+
+  /// Records the exit of `pid` if `run_eval` started it, in which case the
+  /// reaper leaves it alone.
+  pub fn claim(&mut self, pid: i32, code: i32) -> bool {
+    match self.0.get_mut(&pid) {
+      Some(slot) => {
+        *slot = Some(code);
+        true
+      }
+      None => false,
+    }
+  }
+
+  /// The exit code of `pid`, reaping it here unless the reaper already did.
+  fn reap(&mut self, pid: i32) -> Option<i32> {
+    use rind_core::reexports::nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
+    use rind_core::reexports::nix::unistd::Pid;
+
+    let code = match self.0.get(&pid)? {
+      Some(code) => *code,
+      None => match waitpid(Pid::from_raw(pid), Some(WaitPidFlag::WNOHANG)) {
+        Ok(WaitStatus::Exited(_, code)) => code,
+        Ok(WaitStatus::Signaled(_, signal, _)) => 128 + signal as i32,
+        Ok(_) => return None,
+        // waited on elsewhere, so there is nothing left to collect or kill
+        Err(_) => -1,
+      },
+    };
+    self.0.remove(&pid);
+    Some(code)
+  }
+}
+
+/// Runs an auto-payload command and returns its stdout, or `None` when it
+/// can't start, fails or runs past `timeout`. Blocks, so call it off the main loop.
+pub fn run_eval(cmd: &str, args: &[String], timeout: std::time::Duration) -> Option<String> {
+  use rind_core::reexports::nix::sys::signal::{Signal, killpg};
+  use rind_core::reexports::nix::unistd::Pid;
+  use std::io::Read;
+  use std::os::unix::process::CommandExt;
+  use std::process::{Command, Stdio};
+
+  let (pid, mut stdout) = {
+    // registered before the reaper can see it exit
+    let mut children = EvalChildren::lock();
+    let mut child = Command::new(cmd)
+      .args(args)
+      // own group, so a timeout takes down whatever the command started too
+      .process_group(0)
+      .stdin(Stdio::null())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::null())
+      .spawn()
+      .ok()?;
+    let stdout = child.stdout.take()?;
+    let pid = child.id() as i32;
+    children.0.insert(pid, None);
+    (pid, stdout)
+  };
+  // drain stdout alongside, a full pipe would stall the command until the timeout
+  let reader = std::thread::spawn(move || {
+    let mut out = String::new();
+    stdout.read_to_string(&mut out).map(|_| out)
+  });
+
+  let deadline = std::time::Instant::now() + timeout;
+  let mut killed = false;
+  let code = loop {
+    {
+      let mut children = EvalChildren::lock();
+      if let Some(code) = children.reap(pid) {
+        break code;
+      }
+      if !killed && std::time::Instant::now() >= deadline {
+        // still unreaped under the lock, so the group is still the command's
+        let _ = killpg(Pid::from_raw(pid), Signal::SIGKILL);
+        killed = true;
+      }
+    }
+    std::thread::sleep(std::time::Duration::from_millis(10));
+  };
+
+  let out = reader.join().ok()?.ok()?;
+  (!killed && code == 0).then_some(out)
+}
+
+pub fn eval_values(stdout: &str, many: bool) -> Vec<serde_json::Value> {
+  let value = |text: &str| {
+    serde_json::from_str(text).unwrap_or_else(|_| serde_json::Value::String(text.to_string()))
+  };
+  let text = stdout.trim();
+  if !many {
+    return vec![value(text)];
+  }
+  if let Ok(serde_json::Value::Array(items)) = serde_json::from_str(text) {
+    return items;
   }
+  text
+    .lines()
+    .map(str::trim)
+    .filter(|line| !line.is_empty())
+    .map(value)
+    .collect()
 }
 
 pub fn branch_target_key(spec: &str) -> &str {
@@ -0,0 +1,76 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rind_core::prelude::{StatePersistence, Ustr};
+use rind_flow::expiry::FacetExpiries;
+
+fn temp_path(tag: &str) -> std::path::PathBuf {
+  std::env::temp_dir().join(format!("rind-expiry-{tag}-{}", std::process::id()))
+}
+
+#[test]
+fn expiries_take_only_due_branches() {
+  let mut expiries = FacetExpiries::new(StatePersistence::new(temp_path("due")));
+  let key = Ustr::from("net:peer");
+  let now = UNIX_EPOCH + Duration::from_secs(1_000);
+
+  expiries.refresh(&key, vec!["a".into()], Duration::from_secs(5), now);
+  expiries.refresh(&key, vec!["b".into()], Duration::from_secs(30), now);
+  assert_eq!(expiries.next_deadline(), Some(now + Duration::from_secs(5)));
+
+  // refreshing pushes the deadline out again
+  expiries.refresh(
+    &key,
+    vec!["a".into()],
+    Duration::from_secs(5),
+    now + Duration::from_secs(4),
+  );
+  assert!(
+    expiries
+      .take_expired(now + Duration::from_secs(6))
+      .is_empty()
+  );
+
+  let expired = expiries.take_expired(now + Duration::from_secs(10));
+  assert_eq!(expired, vec![(key.clone(), vec!["a".to_string()])]);
+  assert!(expiries.get(&key, &["a".to_string()]).is_none());
+  assert!(expiries.get(&key, &["b".to_string()]).is_some());
+
+  expiries.clear(&key, &["b".to_string()]);
+  assert_eq!(expiries.next_deadline(), None);
+}
+
+#[test]
+fn expiries_roundtrip_through_persistence() {
+  let path = temp_path("persist");
+  let now = SystemTime::now();
+  let mut expiries = FacetExpiries::new(StatePersistence::new(&path));
+  expiries.refresh(
+    &Ustr::from("net:peer"),
+    vec!["\"a\"".into()],
+    Duration::from_secs(90),
+    now,
+  );
+  expiries.refresh(
+    &Ustr::from("net:probe!"),
+    Vec::new(),
+    Duration::from_secs(90),
+    now,
+  );
+  expiries.save_sync().expect("save should succeed");
+
+  let mut restored = FacetExpiries::new(StatePersistence::new(&path));
+  restored.load().expect("load should succeed");
+  let expiry = restored
+    .get(&Ustr::from("net:peer"), &["\"a\"".to_string()])
+    .expect("deadline should be restored");
+  assert_eq!(expiry.ttl, Duration::from_secs(90));
+  let millis = |t: SystemTime| t.duration_since(UNIX_EPOCH).unwrap().as_millis();
+  assert_eq!(
+    millis(expiry.deadline),
+    millis(now + Duration::from_secs(90))
+  );
+  // impermanent facets aren't persisted
+  assert!(restored.get(&Ustr::from("net:probe!"), &[]).is_none());
+
+  let _ = std::fs::remove_file(path);
+}
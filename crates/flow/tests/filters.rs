@@ -0,0 +1,115 @@
+use std::time::{Duration, Instant};
+
+use rind_core::prelude::{FlowAction, StatePersistence, Ustr};
+use rind_flow::filters::ConditionFilters;
+use rind_flow::{FacetGraph, FlowInstance, FlowItem, FlowPayload, FlowType, condition_matches};
+
+fn leaf(value: serde_json::Value) -> FlowItem {
+  serde_json::from_value(value).expect("leaf should parse")
+}
+
+fn event(name: &str, payload: &str) -> FlowInstance {
+  FlowInstance {
+    name: Ustr::from(name),
+    payload: FlowPayload::String(payload.into()),
+    r#type: FlowType::Facet,
+  }
+}
+
+fn up(payload: &str) -> Option<FlowPayload> {
+  Some(FlowPayload::String(payload.into()))
+}
+
+#[test]
+fn debounce_waits_for_a_stable_match() {
+  let cond = leaf(serde_json::json!({ "facet": "net:up", "debounce": "300ms" }));
+  assert!(cond.is_filtered());
+  let mut filters = ConditionFilters::default();
+  let t0 = Instant::now();
+  let ms = |n| t0 + Duration::from_millis(n);
+
+  filters.observe(&cond, up("eth0"), None, t0);
+  assert_eq!(filters.level(&cond), Some(false));
+  assert_eq!(filters.next_wakeup(), Some(ms(300)));
+
+  // a flap restarts the window
+  filters.observe(&cond, None, None, ms(100));
+  filters.observe(&cond, up("eth0"), None, ms(200));
+  filters.observe(&cond, up("eth0"), None, ms(400));
+  assert_eq!(filters.level(&cond), Some(false));
+
+  let due = filters.take_due(ms(450));
+  assert_eq!(due.len(), 1);
+  assert_eq!(due[0].action, FlowAction::Apply);
+  assert_eq!(filters.next_wakeup(), Some(ms(500)));
+
+  filters.observe(&cond, up("eth0"), None, ms(500));
+  assert_eq!(filters.level(&cond), Some(true));
+}
+
+#[test]
+fn hold_keeps_the_level_and_delays_the_falling_edge() {
+  let cond = leaf(serde_json::json!({ "facet": "net:up", "hold": "1s", "on": "falling" }));
+  let mut filters = ConditionFilters::default();
+  let t0 = Instant::now();
+  let (set, cleared, woke) = (
+    event("net:up", "eth0"),
+    event("net:up", "cleared"),
+    event("net:up", "woke"),
+  );
+
+  filters.observe(&cond, up("eth0"), Some(&set), t0);
+  assert!(!filters.edge(&cond, Some(&set)), "rising doesn't count");
+
+  filters.observe(&cond, None, Some(&cleared), t0 + Duration::from_millis(10));
+  assert_eq!(filters.level(&cond), Some(true));
+  assert!(!filters.edge(&cond, Some(&cleared)));
+  let wakeup = filters
+    .take_due(t0 + Duration::from_secs(2))
+    .pop()
+    .expect("hold should schedule a wakeup");
+  assert_eq!(wakeup.action, FlowAction::Revert);
+  assert_eq!(wakeup.payload.to_string_payload(), "eth0");
+
+  filters.observe(&cond, None, Some(&woke), t0 + Duration::from_secs(2));
+  assert_eq!(filters.level(&cond), Some(false));
+  assert!(filters.edge(&cond, Some(&woke)));
+  // only for the event that moved it
+  assert!(!filters.edge(&cond, Some(&set)));
+  filters.observe(&cond, None, Some(&set), t0 + Duration::from_secs(3));
+  assert!(!filters.edge(&cond, Some(&woke)));
+}
+
+#[test]
+fn change_edge_fires_on_a_new_matching_payload() {
+  let cond = leaf(serde_json::json!({ "facet": "net:addr", "on": "change" }));
+  let mut filters = ConditionFilters::default();
+  let t0 = Instant::now();
+  let (a, b) = (event("net:addr", "a"), event("net:addr", "b"));
+
+  filters.observe(&cond, up("a"), Some(&a), t0);
+  assert!(filters.edge(&cond, Some(&a)));
+  filters.observe(&cond, up("b"), Some(&b), t0);
+  assert!(filters.edge(&cond, Some(&b)));
+  filters.observe(&cond, up("b"), Some(&a), t0);
+  assert!(!filters.edge(&cond, Some(&a)));
+}
+
+#[test]
+fn filtered_leaves_ignore_a_bare_matching_event() {
+  let path = std::env::temp_dir().join(format!("rind-filters-{}", std::process::id()));
+  let mut sm = FacetGraph::from_persistence(StatePersistence::new(path));
+  let cond = leaf(serde_json::json!({ "facet": "net:up", "debounce": "5s" }));
+  let set = event("net:up", "eth0");
+  sm.insert(Ustr::from("net:up"), vec![set.clone()]);
+
+  assert!(condition_matches(
+    &sm,
+    &FlowItem::Simple(Ustr::from("net:up")),
+    Some(&set),
+    None
+  ));
+  sm.observe_conditions(std::slice::from_ref(&cond), Some(&set));
+  assert!(!condition_matches(&sm, &cond, Some(&set), None));
+  assert!(sm.next_wakeup().is_some());
+}
@@ -0,0 +1,72 @@
+use rind_core::prelude::{StatePersistence, Ustr};
+use rind_flow::{FacetGraph, FlowInstance, FlowItem, FlowPayload, FlowType, condition_is_active};
+
+fn graph(tag: &str) -> FacetGraph {
+  let path = std::env::temp_dir().join(format!("rind-index-{tag}-{}", std::process::id()));
+  FacetGraph::from_persistence(StatePersistence::new(path))
+}
+
+fn branch(name: &str, payload: &str) -> FlowInstance {
+  FlowInstance {
+    name: Ustr::from(name),
+    payload: FlowPayload::String(payload.into()),
+    r#type: FlowType::Facet,
+  }
+}
+
+fn payloads<'a>(sm: &'a FacetGraph, name: &'a str) -> Vec<String> {
+  let mut out: Vec<String> = sm
+    .branches_named(name)
+    .map(|b| b.payload.to_string_payload())
+    .collect();
+  out.sort();
+  out
+}
+
+#[test]
+fn branches_named_spans_scopes() {
+  let mut sm = graph("scopes");
+  sm.insert(
+    Ustr::from("rind:session"),
+    vec![branch("rind:session", "a")],
+  );
+  sm.insert(
+    Ustr::from("rind:session@user1"),
+    vec![branch("rind:session@user1", "b")],
+  );
+  sm.insert(Ustr::from("rind:other"), vec![branch("rind:other", "c")]);
+
+  assert_eq!(payloads(&sm, "rind:session"), ["a", "b"]);
+  assert_eq!(payloads(&sm, "rind:session@static"), ["a", "b"]);
+
+  sm.remove("rind:session@user1");
+  assert_eq!(payloads(&sm, "rind:session"), ["a"]);
+  assert!(!condition_is_active(
+    &sm,
+    &FlowItem::Simple(Ustr::from("rind:missing")),
+    None
+  ));
+}
+
+#[test]
+fn graph_edits_keep_the_index_in_step() {
+  let mut sm = graph("edits");
+  sm.insert(
+    Ustr::from("net:up@user1"),
+    vec![branch("net:up@user1", "eth0")],
+  );
+  assert_eq!(payloads(&sm, "net:up"), ["eth0"]);
+
+  sm.get_mut("net:up@user1")
+    .expect("facet inserted above")
+    .push(branch("net:up@user1", "wlan0"));
+  assert_eq!(payloads(&sm, "net:up"), ["eth0", "wlan0"]);
+
+  sm.remove("net:up@user1");
+  assert!(!sm.contains_key("net:up@user1"));
+  assert!(!condition_is_active(
+    &sm,
+    &FlowItem::Simple(Ustr::from("net:up")),
+    None
+  ));
+}
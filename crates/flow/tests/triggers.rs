@@ -1,9 +1,13 @@
-use rind_core::prelude::Ustr;
+use rind_core::prelude::{StatePersistence, Ustr};
 use rind_flow::triggers::{
-  branch_source_key, branch_target_key, json_branch_key, map_json_payload, match_operation,
-  merge_json, subset_match,
+  EvalChildren, branch_source_key, branch_target_key, check_condition, eval_values,
+  json_branch_key, json_path, map_json_payload, match_operation, merge_json, run_eval,
+  subset_match, validate_condition,
+};
+use rind_flow::{
+  FacetGraph, FlowInstance, FlowItem, FlowMatchOperation, FlowPayload, FlowType,
+  condition_is_active, condition_matches,
 };
-use rind_flow::{FlowMatchOperation, FlowPayload};
 
 #[test]
 fn subset_match_nested() {
@@ -23,6 +27,13 @@ fn match_operation_variants() {
       binary: Some(true),
       contains: None,
       r#as: None,
+      path: None,
+      ne: None,
+      gt: None,
+      ge: None,
+      lt: None,
+      le: None,
+      regex: None,
     },
     &FlowPayload::Bytes(vec![1, 2]),
   ));
@@ -31,6 +42,13 @@ fn match_operation_variants() {
       binary: None,
       contains: Some(Ustr::from("ell")),
       r#as: None,
+      path: None,
+      ne: None,
+      gt: None,
+      ge: None,
+      lt: None,
+      le: None,
+      regex: None,
     },
     &FlowPayload::String("hello".to_string()),
   ));
@@ -94,3 +112,262 @@ fn map_json_payload_correct_spec_succeeds() {
   assert_eq!(obj.get("tty"), Some(&serde_json::json!("tty1")));
   assert!(obj.get("seat").is_none());
 }
+
+fn json_payload(value: serde_json::Value) -> FlowPayload {
+  FlowPayload::from_json(Some(value))
+}
+
+fn predicate(toml: &str) -> FlowMatchOperation {
+  toml::from_str(toml).expect("predicate should parse")
+}
+
+#[test]
+fn match_operation_compares_payload_fields() {
+  let payload = json_payload(serde_json::json!({"load": {"avg": 1.5}, "ifaces": ["lo", "eth0"]}));
+  assert_eq!(
+    json_path(&payload.to_json(), "ifaces/1"),
+    Some(&serde_json::json!("eth0"))
+  );
+
+  assert!(match_operation(
+    &predicate("path = \"load/avg\"\nlt = 2"),
+    &payload
+  ));
+  assert!(match_operation(
+    &predicate("path = \"load/avg\"\nge = 1.5\nle = 1.5"),
+    &payload
+  ));
+  assert!(!match_operation(
+    &predicate("path = \"load/avg\"\ngt = 2"),
+    &payload
+  ));
+  assert!(!match_operation(
+    &predicate("path = \"load/missing\"\nlt = 2"),
+    &payload
+  ));
+  assert!(match_operation(
+    &predicate("path = \"ifaces/1\"\nregex = \"^eth[0-9]+$\""),
+    &payload
+  ));
+  assert!(match_operation(
+    &predicate("path = \"ifaces/0\"\nne = \"eth0\""),
+    &payload
+  ));
+
+  // string payloads carry numbers as text
+  assert!(match_operation(
+    &predicate("gt = 10"),
+    &FlowPayload::String("42".into())
+  ));
+  assert!(match_operation(
+    &predicate("lt = \"b\""),
+    &FlowPayload::String("a".into())
+  ));
+  assert!(!match_operation(
+    &predicate("lt = 1"),
+    &FlowPayload::String("nan-ish".into())
+  ));
+  assert!(!match_operation(
+    &predicate("regex = \"([\""),
+    &FlowPayload::String("x".into())
+  ));
+}
+
+#[test]
+fn composite_items_parse_from_toml() {
+  #[derive(serde::Deserialize)]
+  #[serde(rename_all = "kebab-case")]
+  struct Unit {
+    start_on: Vec<FlowItem>,
+  }
+  let unit: Unit = toml::from_str(
+    r#"
+start-on = [
+  { all = [
+    "net:up",
+    { not = { facet = "power:battery" } },
+    { facet = "sys:load", branch = { path = "avg", lt = 2 } },
+  ] },
+  { count = 2, of = ["a", "b", "c"] },
+]
+"#,
+  )
+  .expect("conditions should parse");
+
+  let FlowItem::All { all } = &unit.start_on[0] else {
+    panic!("expected all, got {:?}", unit.start_on[0]);
+  };
+  assert_eq!(all[0], FlowItem::Simple(Ustr::from("net:up")));
+  assert!(matches!(&all[1], FlowItem::Not { .. }));
+  assert!(matches!(
+    &all[2],
+    FlowItem::Detailed {
+      branch: Some(_),
+      ..
+    }
+  ));
+  assert!(matches!(&unit.start_on[1], FlowItem::Count { count: 2, of } if of.len() == 3));
+
+  let names: Vec<&str> = unit.start_on[0]
+    .names()
+    .iter()
+    .map(|n| n.as_str())
+    .collect();
+  assert_eq!(names, ["net:up", "power:battery", "sys:load"]);
+}
+
+#[test]
+fn invalid_regex_is_reported_from_nested_items() {
+  #[derive(serde::Deserialize)]
+  #[serde(rename_all = "kebab-case")]
+  struct Unit {
+    start_on: Vec<FlowItem>,
+  }
+  let unit: Unit = toml::from_str(
+    r#"
+start-on = [
+  { facet = "net:iface", branch = { path = "iface", regex = "^eth[0-9]+$" } },
+  { any = [ "a", { not = { facet = "b", target = { regex = "([" } } } ] },
+]
+"#,
+  )
+  .expect("conditions should parse");
+
+  assert!(validate_condition(&unit.start_on[0]).is_ok());
+  let err = validate_condition(&unit.start_on[1]).expect_err("bad pattern should be reported");
+  assert!(err.contains("(["), "{err}");
+}
+
+#[test]
+fn composite_items_evaluate_against_facets() {
+  let state_path = std::env::temp_dir().join(format!("rind-conditions-{}", std::process::id()));
+  let mut sm = FacetGraph::from_persistence(StatePersistence::new(state_path));
+  let facet = |name: &str, payload: FlowPayload| FlowInstance {
+    name: Ustr::from(name),
+    payload,
+    r#type: FlowType::Facet,
+  };
+  sm.insert(
+    Ustr::from("net:up"),
+    vec![facet("net:up", FlowPayload::None(false))],
+  );
+  sm.insert(
+    Ustr::from("sys:load"),
+    vec![facet(
+      "sys:load",
+      json_payload(serde_json::json!({"avg": 0.5})),
+    )],
+  );
+
+  let rule: FlowItem = serde_json::from_value(serde_json::json!({
+    "all": [
+      "net:up",
+      { "not": { "facet": "power:battery" } },
+      { "facet": "sys:load", "branch": { "path": "avg", "lt": 2 } },
+    ]
+  }))
+  .unwrap();
+  assert!(condition_is_active(&sm, &rule, None));
+
+  sm.insert(
+    Ustr::from("power:battery"),
+    vec![facet("power:battery", FlowPayload::None(false))],
+  );
+  assert!(!condition_is_active(&sm, &rule, None));
+
+  let two_of: FlowItem = serde_json::from_value(serde_json::json!({
+    "count": 2, "of": ["net:up", "power:battery", "disk:full"]
+  }))
+  .unwrap();
+  assert!(condition_is_active(&sm, &two_of, None));
+
+  let any_impulse: FlowItem = serde_json::from_value(serde_json::json!({
+    "any": [{ "impulse": "net:ready" }, "disk:full"]
+  }))
+  .unwrap();
+  let event = FlowInstance {
+    name: Ustr::from("net:ready"),
+    payload: FlowPayload::None(false),
+    r#type: FlowType::Impulse,
+  };
+  assert!(check_condition(&any_impulse, &event));
+  assert!(!condition_is_active(&sm, &any_impulse, None));
+  assert!(condition_matches(&sm, &any_impulse, Some(&event), None));
+}
+
+#[test]
+fn eval_values_parse_json_text_and_lines() {
+  use serde_json::json;
+  assert_eq!(eval_values("6.1.0-rc1\n", false), [json!("6.1.0-rc1")]);
+  assert_eq!(
+    eval_values(r#"{"used": 42}"#, false),
+    [json!({ "used": 42 })]
+  );
+  assert_eq!(
+    eval_values("/ 42\n\n/home 7\n", true),
+    [json!("/ 42"), json!("/home 7")]
+  );
+  assert_eq!(eval_values("[1, \"a\"]", true), [json!(1), json!("a")]);
+  assert!(eval_values("  \n", true).is_empty());
+}
+
+#[test]
+fn run_eval_returns_stdout_and_honours_the_timeout() {
+  let sh = |script: &str, timeout_ms| {
+    run_eval(
+      "/bin/sh",
+      &["-c".to_string(), script.to_string()],
+      std::time::Duration::from_millis(timeout_ms),
+    )
+  };
+  assert_eq!(sh("echo up", 2_000).as_deref(), Some("up\n"));
+  assert_eq!(sh("echo partial; exit 1", 2_000), None);
+
+  let started = std::time::Instant::now();
+  assert_eq!(sh("sleep 5", 100), None);
+  assert!(started.elapsed() < std::time::Duration::from_secs(2));
+}
+
+#[test]
+fn run_eval_hands_off_with_a_reaper_waiting_on_every_child() {
+  use rind_core::reexports::nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
+  use std::sync::Arc;
+  use std::sync::atomic::{AtomicBool, Ordering};
+
+  // reaps the way pid 1's reaper does, racing `run_eval` for its children
+  let stop = Arc::new(AtomicBool::new(false));
+  let reaper = std::thread::spawn({
+    let stop = stop.clone();
+    move || {
+      while !stop.load(Ordering::Relaxed) {
+        let mut evals = EvalChildren::lock();
+        match waitpid(None, Some(WaitPidFlag::WNOHANG)) {
+          Ok(WaitStatus::Exited(pid, code)) => {
+            evals.claim(pid.as_raw(), code);
+          }
+          Ok(WaitStatus::Signaled(pid, signal, _)) => {
+            evals.claim(pid.as_raw(), 128 + signal as i32);
+          }
+          _ => {}
+        }
+        drop(evals);
+        std::thread::sleep(std::time::Duration::from_millis(1));
+      }
+    }
+  });
+
+  let sh = |script: &str| {
+    run_eval(
+      "/bin/sh",
+      &["-c".to_string(), script.to_string()],
+      std::time::Duration::from_secs(2),
+    )
+  };
+  for _ in 0..20 {
+    assert_eq!(sh("echo up").as_deref(), Some("up\n"));
+  }
+  assert_eq!(sh("echo partial; exit 1"), None);
+
+  stop.store(true, Ordering::Relaxed);
+  reaper.join().unwrap();
+}
@@ -153,6 +153,7 @@ impl Orchestrator for PumpOrchestrator {
     ctx.dispatch("reaper", "timeout_sweep", Default::default())?;
     ctx.dispatch("events", "drain_events", Default::default())?;
     ctx.dispatch("transport", "drain_incoming", Default::default())?;
+    ctx.dispatch("flow", "drain_evals", Default::default())?;
     ctx.dispatch("ipc", "drain_requests", Default::default())?;
     Ok(Void)
   }
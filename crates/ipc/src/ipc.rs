@@ -147,15 +147,41 @@ pub enum TransportMessageAction {
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum FlowMatchOperation {
   Eq(Ustr),
   Options {
     binary: Option<bool>,
     contains: Option<Ustr>,
     r#as: Option<serde_json::Value>,
+    path: Option<Ustr>,
+    ne: Option<serde_json::Value>,
+    gt: Option<serde_json::Value>,
+    ge: Option<serde_json::Value>,
+    lt: Option<serde_json::Value>,
+    le: Option<serde_json::Value>,
+    regex: Option<Ustr>,
   },
 }
 
+impl FlowMatchOperation {
+  /// Matches payloads containing `filter` as a JSON subset.
+  pub fn subset(filter: serde_json::Value) -> Self {
+    FlowMatchOperation::Options {
+      binary: None,
+      contains: None,
+      r#as: Some(filter),
+      path: None,
+      ne: None,
+      gt: None,
+      ge: None,
+      lt: None,
+      le: None,
+      regex: None,
+    }
+  }
+}
+
 #[derive(Debug, Serialize, Deserialize, Default, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum FlowPayloadType {
@@ -49,6 +49,36 @@ pub struct ServiceSerialized {
   pub restart: bool,
   pub run: Vec<Ustr>,
   pub pid: Option<u32>,
+  pub last_stop: Option<String>,
+  pub capabilities: Option<Vec<Ustr>>,
+  pub usage: Vec<InstanceUsageSerialized>,
+  pub history: Vec<ExitRecordSerialized>,
+}
+
+/// Cgroup accounting of one running instance.
+#[derive(Serialize, Deserialize, Default)]
+pub struct InstanceUsageSerialized {
+  pub instance: Ustr,
+  pub memory_current: Option<u64>,
+  pub cpu_usage_usec: Option<u64>,
+  pub cpu_user_usec: Option<u64>,
+  pub cpu_system_usec: Option<u64>,
+  pub io_read_bytes: Option<u64>,
+  pub io_write_bytes: Option<u64>,
+  pub pids_current: Option<u64>,
+}
+
+/// One entry of a service's exit history.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ExitRecordSerialized {
+  pub instance: Ustr,
+  pub code: i32,
+  pub signal: Option<i32>,
+  pub core_dumped: bool,
+  pub trigger: String,
+  pub stop: Option<String>,
+  pub runtime_ms: u64,
+  pub timestamp: u64,
 }
 
 #[derive(Serialize, Deserialize)]
@@ -60,6 +90,22 @@ pub struct SocketSerialized {
   pub active: bool,
 }
 
+#[derive(Serialize, Deserialize, Default)]
+pub struct TimerSerialized {
+  pub name: Ustr,
+  pub schedule: Ustr,
+  pub active: bool,
+  pub repeat: bool,
+  pub persistent: bool,
+  /// Seconds since the Unix epoch.
+  pub next_elapse: Option<u64>,
+  pub last_elapse: Option<u64>,
+  pub unit: Option<Ustr>,
+  pub running: bool,
+  /// Exit history of `unit`.
+  pub runs: Vec<ExitRecordSerialized>,
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct FacetSerialized {
   pub name: Ustr,
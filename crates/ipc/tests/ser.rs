@@ -1,5 +1,6 @@
 use rind_ipc::ser::{
-  SerializeSerialized, ServiceSerialized, UnitItemsSerialized, UnitSerialized, serialize_many,
+  ExitRecordSerialized, InstanceUsageSerialized, SerializeSerialized, ServiceSerialized,
+  UnitItemsSerialized, UnitSerialized, serialize_many,
 };
 
 #[test]
@@ -35,6 +36,22 @@ fn serialize_many_and_nested_types() {
     run: vec!["hello".to_string().into()],
     pid: Some(1),
     description: None,
+    last_stop: Some("Clean".to_string()),
+    capabilities: Some(vec!["net_bind_service".to_string().into()]),
+    usage: vec![InstanceUsageSerialized {
+      instance: "svc".to_string().into(),
+      memory_current: Some(4096),
+      pids_current: Some(1),
+      ..Default::default()
+    }],
+    history: vec![ExitRecordSerialized {
+      instance: "svc".to_string().into(),
+      code: 139,
+      signal: Some(11),
+      core_dumped: true,
+      trigger: "Exited".to_string(),
+      ..Default::default()
+    }],
   }];
   let out = serialize_many(&services);
   assert!(!out.is_empty());
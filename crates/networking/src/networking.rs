@@ -156,7 +156,7 @@ fn inject_ipc_list(
           {
             for cfg in cfgs {
               let config = {
-                if let Some(instances) = sm.facets.get(&Ustr::from(NETWORKING_CONFIGURED_STATE)) {
+                if let Some(instances) = sm.get(&Ustr::from(NETWORKING_CONFIGURED_STATE)) {
                   instances.iter().find(|i| {
                     if let Some(obj) = i.payload.to_json().as_object() {
                       obj.get("name").and_then(|v| v.as_str()) == Some(cfg.name.as_str())
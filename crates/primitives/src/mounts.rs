@@ -3,7 +3,7 @@ use std::{
   ffi::CString,
   fs::File,
   io::{BufRead, BufReader},
-  path::PathBuf,
+  path::{Path, PathBuf},
   sync::Arc,
 };
 
@@ -14,7 +14,7 @@ use nix::{
 use rind_core::prelude::*;
 use serde::{Deserialize, Serialize};
 
-#[model(meta_name = target, meta_fields(target, source, fstype, flags, data, create, after, rind_broadcast), derive_metadata(Debug, Default))]
+#[model(meta_name = target, meta_fields(target, source, fstype, flags, data, create, propagation, after, rind_broadcast), derive_metadata(Debug, Default))]
 pub struct Mount {
   pub source: Option<Ustr>,
   pub target: Ustr,
@@ -22,6 +22,7 @@ pub struct Mount {
   pub flags: Option<Vec<String>>,
   pub data: Option<String>,
   pub create: Option<bool>,
+  pub propagation: Option<Ustr>,
   pub after: Option<Vec<Ustr>>,
   #[serde(default)]
   pub rind_broadcast: bool,
@@ -81,6 +82,18 @@ impl MountRuntime {
         "Failed to mount target",
         fields,
       );
+    } else if let Some(propagation) = &target.propagation
+      && let Err(e) = set_propagation(Path::new(target.target.as_str()), propagation, false)
+    {
+      let mut fields = HashMap::new();
+      fields.insert("target".to_string(), target.target.to_string());
+      fields.insert("error".to_string(), e.to_string());
+      log.log(
+        LogLevel::Error,
+        "mount-runtime",
+        "Failed to set mount propagation",
+        fields,
+      );
     }
 
     if target.rind_broadcast
@@ -349,16 +362,50 @@ pub struct NamespaceMountEntry {
   pub data: Option<String>,
   #[serde(default)]
   pub create: Option<bool>,
+  #[serde(default)]
+  pub propagation: Option<String>,
+}
+
+/// Maps `private`, `slave`, `shared` or `unbindable` to its mount flag.
+pub fn propagation_flag(name: &str) -> Option<MsFlags> {
+  match name {
+    "private" => Some(MsFlags::MS_PRIVATE),
+    "slave" => Some(MsFlags::MS_SLAVE),
+    "shared" => Some(MsFlags::MS_SHARED),
+    "unbindable" => Some(MsFlags::MS_UNBINDABLE),
+    _ => None,
+  }
+}
+
+pub fn set_propagation(target: &Path, name: &str, recursive: bool) -> std::io::Result<()> {
+  let mut flags = propagation_flag(name).ok_or_else(|| {
+    std::io::Error::new(
+      std::io::ErrorKind::InvalidInput,
+      format!("unknown mount propagation '{name}'"),
+    )
+  })?;
+  if recursive {
+    flags |= MsFlags::MS_REC;
+  }
+  mount::<str, Path, str, str>(None, target, None, flags, None)?;
+  Ok(())
 }
 
 pub fn mount_all_in_namespace(entries: &[NamespaceMountEntry]) -> std::io::Result<()> {
+  mount_all_in_root(Path::new("/"), entries)
+}
+
+/// Applies `entries` with their targets resolved below `root`; sources stay
+/// host paths so binds can reach outside a service rootfs.
+pub fn mount_all_in_root(root: &Path, entries: &[NamespaceMountEntry]) -> std::io::Result<()> {
   for entry in entries {
+    let target_path = root.join(entry.target.trim_start_matches('/'));
     if entry.create.unwrap_or(false) {
-      let _ = std::fs::create_dir_all(&entry.target);
+      let _ = std::fs::create_dir_all(&target_path);
     }
     let flags = parse_mount_flags(entry.flags.as_deref());
     let source = entry.source.as_deref().and_then(|s| CString::new(s).ok());
-    let target = CString::new(entry.target.as_str())
+    let target = CString::new(target_path.as_os_str().as_encoded_bytes())
       .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
     let fstype = entry.fstype.as_deref().and_then(|s| CString::new(s).ok());
     let data = entry.data.as_deref().and_then(|s| CString::new(s).ok());
@@ -382,6 +429,9 @@ pub fn mount_all_in_namespace(entries: &[NamespaceMountEntry]) -> std::io::Resul
       }
       return Err(err);
     }
+    if let Some(propagation) = &entry.propagation {
+      set_propagation(&target_path, propagation, true)?;
+    }
   }
   Ok(())
 }
@@ -0,0 +1,468 @@
+//! Minimal rtnetlink and nf_tables clients, enough to wire a service's
+//! network namespace to the host without shelling out to `ip` or `nft`.
+
+use std::ffi::CString;
+use std::io::{Error, ErrorKind};
+use std::net::IpAddr;
+use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+const NLMSG_HDRLEN: usize = 16;
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+const NLA_F_NESTED: u16 = 1 << 15;
+
+const IFLA_IFNAME: u16 = 3;
+const IFLA_MASTER: u16 = 10;
+const IFLA_LINKINFO: u16 = 18;
+const IFLA_NET_NS_PID: u16 = 19;
+const IFLA_INFO_KIND: u16 = 1;
+const IFLA_INFO_DATA: u16 = 2;
+const VETH_INFO_PEER: u16 = 1;
+
+const IFA_LOCAL: u16 = 2;
+const IFA_ADDRESS: u16 = 1;
+
+const RTA_GATEWAY: u16 = 5;
+
+const NFNL_SUBSYS_NFTABLES: u16 = 10;
+const NFNL_MSG_BATCH_BEGIN: u16 = 0x10;
+const NFNL_MSG_BATCH_END: u16 = 0x11;
+const NFT_MSG_NEWTABLE: u16 = 0;
+const NFT_MSG_DELTABLE: u16 = 2;
+const NFT_MSG_NEWCHAIN: u16 = 3;
+const NFT_MSG_NEWRULE: u16 = 6;
+const NFTA_TABLE_NAME: u16 = 1;
+const NFTA_CHAIN_TABLE: u16 = 1;
+const NFTA_CHAIN_NAME: u16 = 3;
+const NFTA_CHAIN_HOOK: u16 = 4;
+const NFTA_CHAIN_POLICY: u16 = 5;
+const NFTA_CHAIN_TYPE: u16 = 7;
+const NFTA_HOOK_HOOKNUM: u16 = 1;
+const NFTA_HOOK_PRIORITY: u16 = 2;
+const NFTA_RULE_TABLE: u16 = 1;
+const NFTA_RULE_CHAIN: u16 = 2;
+const NFTA_RULE_EXPRESSIONS: u16 = 4;
+const NFTA_LIST_ELEM: u16 = 1;
+const NFTA_EXPR_NAME: u16 = 1;
+const NFTA_EXPR_DATA: u16 = 2;
+const NFTA_DATA_VALUE: u16 = 1;
+const NFT_REG_1: u32 = 1;
+const NF_INET_POST_ROUTING: u32 = 4;
+const NF_ACCEPT: u32 = 1;
+const NFT_PAYLOAD_NETWORK_HEADER: u32 = 1;
+const NFT_CMP_EQ: u32 = 0;
+
+/// Builds one netlink message: header, fixed family struct, attributes.
+pub struct NlMessage {
+  buf: Vec<u8>,
+  nests: Vec<usize>,
+}
+
+impl NlMessage {
+  pub fn new(msg_type: u16, flags: u16) -> Self {
+    let mut buf = vec![0u8; NLMSG_HDRLEN];
+    buf[4..6].copy_from_slice(&msg_type.to_ne_bytes());
+    buf[6..8].copy_from_slice(&flags.to_ne_bytes());
+    Self {
+      buf,
+      nests: Vec::new(),
+    }
+  }
+
+  pub fn bytes(&mut self, data: &[u8]) -> &mut Self {
+    self.buf.extend_from_slice(data);
+    self.align();
+    self
+  }
+
+  pub fn attr(&mut self, attr_type: u16, data: &[u8]) -> &mut Self {
+    let len = (4 + data.len()) as u16;
+    self.buf.extend_from_slice(&len.to_ne_bytes());
+    self.buf.extend_from_slice(&attr_type.to_ne_bytes());
+    self.bytes(data)
+  }
+
+  pub fn attr_str(&mut self, attr_type: u16, value: &str) -> &mut Self {
+    let mut data = value.as_bytes().to_vec();
+    data.push(0);
+    self.attr(attr_type, &data)
+  }
+
+  pub fn attr_u32(&mut self, attr_type: u16, value: u32) -> &mut Self {
+    self.attr(attr_type, &value.to_ne_bytes())
+  }
+
+  /// nf_tables integers travel in network byte order.
+  pub fn attr_be32(&mut self, attr_type: u16, value: u32) -> &mut Self {
+    self.attr(attr_type, &value.to_be_bytes())
+  }
+
+  pub fn nest(&mut self, attr_type: u16) -> &mut Self {
+    self.nests.push(self.buf.len());
+    self.buf.extend_from_slice(&0u16.to_ne_bytes());
+    self.buf.extend_from_slice(&attr_type.to_ne_bytes());
+    self
+  }
+
+  pub fn end(&mut self) -> &mut Self {
+    if let Some(start) = self.nests.pop() {
+      let len = (self.buf.len() - start) as u16;
+      self.buf[start..start + 2].copy_from_slice(&len.to_ne_bytes());
+    }
+    self
+  }
+
+  fn align(&mut self) {
+    while !self.buf.len().is_multiple_of(4) {
+      self.buf.push(0);
+    }
+  }
+
+  fn finish(mut self, seq: u32) -> Vec<u8> {
+    let len = self.buf.len() as u32;
+    self.buf[0..4].copy_from_slice(&len.to_ne_bytes());
+    self.buf[8..12].copy_from_slice(&seq.to_ne_bytes());
+    self.buf
+  }
+}
+
+const NETLINK_TIMEOUT_SECS: libc::time_t = 5;
+
+pub struct NetlinkSocket {
+  fd: OwnedFd,
+  seq: u32,
+}
+
+impl NetlinkSocket {
+  pub fn open(protocol: libc::c_int) -> std::io::Result<Self> {
+    let fd = unsafe {
+      libc::socket(
+        libc::AF_NETLINK,
+        libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+        protocol,
+      )
+    };
+    if fd < 0 {
+      return Err(Error::last_os_error());
+    }
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+    let mut addr: libc::sockaddr_nl = unsafe { std::mem::zeroed() };
+    addr.nl_family = libc::AF_NETLINK as u16;
+    let bound = unsafe {
+      libc::bind(
+        fd.as_raw_fd(),
+        &addr as *const libc::sockaddr_nl as *const libc::sockaddr,
+        std::mem::size_of::<libc::sockaddr_nl>() as u32,
+      )
+    };
+    if bound < 0 {
+      return Err(Error::last_os_error());
+    }
+    // a kernel that never answers must not hang the caller
+    let timeout = libc::timeval {
+      tv_sec: NETLINK_TIMEOUT_SECS,
+      tv_usec: 0,
+    };
+    let set = unsafe {
+      libc::setsockopt(
+        fd.as_raw_fd(),
+        libc::SOL_SOCKET,
+        libc::SO_RCVTIMEO,
+        &timeout as *const libc::timeval as *const libc::c_void,
+        std::mem::size_of::<libc::timeval>() as u32,
+      )
+    };
+    if set < 0 {
+      return Err(Error::last_os_error());
+    }
+    Ok(Self { fd, seq: 1 })
+  }
+
+  pub fn route() -> std::io::Result<Self> {
+    Self::open(libc::NETLINK_ROUTE)
+  }
+
+  pub fn netfilter() -> std::io::Result<Self> {
+    Self::open(libc::NETLINK_NETFILTER)
+  }
+
+  /// Sends the messages in one datagram and waits until every one that
+  /// asked for an ACK has been acknowledged.
+  pub fn transact(&mut self, messages: Vec<NlMessage>, acked: usize) -> std::io::Result<()> {
+    let mut out = Vec::new();
+    let first = self.seq;
+    for msg in messages {
+      out.extend(msg.finish(self.seq));
+      self.seq = self.seq.wrapping_add(1);
+    }
+    let sent = unsafe { libc::send(self.fd.as_raw_fd(), out.as_ptr().cast(), out.len(), 0) };
+    if sent < 0 {
+      return Err(Error::last_os_error());
+    }
+
+    let mut pending = acked;
+    let mut buf = vec![0u8; 8192];
+    while pending > 0 {
+      let n = unsafe { libc::recv(self.fd.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+      if n < 0 {
+        return Err(Error::last_os_error());
+      }
+      let mut data = &buf[..n as usize];
+      while data.len() >= NLMSG_HDRLEN {
+        let len = u32::from_ne_bytes(data[0..4].try_into().unwrap()) as usize;
+        let msg_type = u16::from_ne_bytes(data[4..6].try_into().unwrap());
+        let seq = u32::from_ne_bytes(data[8..12].try_into().unwrap());
+        if len < NLMSG_HDRLEN || len > data.len() {
+          break;
+        }
+        if seq >= first && msg_type == NLMSG_ERROR && len >= NLMSG_HDRLEN + 4 {
+          let code = i32::from_ne_bytes(data[16..20].try_into().unwrap());
+          if code != 0 {
+            return Err(Error::from_raw_os_error(-code));
+          }
+          pending = pending.saturating_sub(1);
+        } else if msg_type == NLMSG_DONE {
+          pending = pending.saturating_sub(1);
+        }
+        data = &data[(len + 3) & !3..];
+      }
+    }
+    Ok(())
+  }
+
+  fn request(&mut self, msg: NlMessage) -> std::io::Result<()> {
+    self.transact(vec![msg], 1)
+  }
+}
+
+const REQUEST_ACK: u16 = (libc::NLM_F_REQUEST | libc::NLM_F_ACK) as u16;
+const CREATE_EXCL: u16 = REQUEST_ACK | (libc::NLM_F_CREATE | libc::NLM_F_EXCL) as u16;
+
+fn ifinfomsg(index: i32, flags: u32, change: u32) -> [u8; 16] {
+  let mut raw = [0u8; 16];
+  raw[0] = libc::AF_UNSPEC as u8;
+  raw[4..8].copy_from_slice(&index.to_ne_bytes());
+  raw[8..12].copy_from_slice(&flags.to_ne_bytes());
+  raw[12..16].copy_from_slice(&change.to_ne_bytes());
+  raw
+}
+
+fn addr_bytes(addr: IpAddr) -> (u8, Vec<u8>) {
+  match addr {
+    IpAddr::V4(v4) => (libc::AF_INET as u8, v4.octets().to_vec()),
+    IpAddr::V6(v6) => (libc::AF_INET6 as u8, v6.octets().to_vec()),
+  }
+}
+
+/// Parses `10.0.0.2/24` (a bare address gets a host prefix).
+pub fn parse_cidr(cidr: &str) -> Option<(IpAddr, u8)> {
+  let (addr, prefix) = match cidr.split_once('/') {
+    Some((addr, prefix)) => (
+      addr.trim().parse::<IpAddr>().ok()?,
+      prefix.trim().parse().ok()?,
+    ),
+    None => {
+      let addr = cidr.trim().parse::<IpAddr>().ok()?;
+      (addr, if addr.is_ipv4() { 32 } else { 128 })
+    }
+  };
+  let max = if addr.is_ipv4() { 32 } else { 128 };
+  (prefix <= max).then_some((addr, prefix))
+}
+
+pub fn link_index(name: &str) -> std::io::Result<u32> {
+  let c_name = CString::new(name).map_err(|_| Error::from(ErrorKind::InvalidInput))?;
+  match unsafe { libc::if_nametoindex(c_name.as_ptr()) } {
+    0 => Err(Error::last_os_error()),
+    index => Ok(index),
+  }
+}
+
+/// Creates `host` in the current namespace with its peer `peer` placed
+/// straight into the network namespace of `peer_pid`.
+pub fn create_veth(host: &str, peer: &str, peer_pid: u32) -> std::io::Result<()> {
+  if host.len() >= libc::IFNAMSIZ || peer.len() >= libc::IFNAMSIZ {
+    return Err(Error::new(
+      ErrorKind::InvalidInput,
+      "interface name too long",
+    ));
+  }
+  let mut msg = NlMessage::new(libc::RTM_NEWLINK, CREATE_EXCL);
+  msg
+    .bytes(&ifinfomsg(0, 0, 0))
+    .attr_str(IFLA_IFNAME, host)
+    .nest(IFLA_LINKINFO)
+    .attr_str(IFLA_INFO_KIND, "veth")
+    .nest(IFLA_INFO_DATA)
+    .nest(VETH_INFO_PEER)
+    .bytes(&ifinfomsg(0, 0, 0))
+    .attr_str(IFLA_IFNAME, peer)
+    .attr_u32(IFLA_NET_NS_PID, peer_pid)
+    .end()
+    .end()
+    .end();
+  NetlinkSocket::route()?.request(msg)
+}
+
+pub fn delete_link(name: &str) -> std::io::Result<()> {
+  let index = link_index(name)?;
+  let mut msg = NlMessage::new(libc::RTM_DELLINK, REQUEST_ACK);
+  msg.bytes(&ifinfomsg(index as i32, 0, 0));
+  NetlinkSocket::route()?.request(msg)
+}
+
+pub fn set_link_up(name: &str) -> std::io::Result<()> {
+  let index = link_index(name)?;
+  let up = libc::IFF_UP as u32;
+  let mut msg = NlMessage::new(libc::RTM_NEWLINK, REQUEST_ACK);
+  msg.bytes(&ifinfomsg(index as i32, up, up));
+  NetlinkSocket::route()?.request(msg)
+}
+
+/// Enslaves `name` to a bridge.
+pub fn set_link_master(name: &str, master: &str) -> std::io::Result<()> {
+  let index = link_index(name)?;
+  let master = link_index(master)?;
+  let mut msg = NlMessage::new(libc::RTM_NEWLINK, REQUEST_ACK);
+  msg
+    .bytes(&ifinfomsg(index as i32, 0, 0))
+    .attr_u32(IFLA_MASTER, master);
+  NetlinkSocket::route()?.request(msg)
+}
+
+pub fn add_address(name: &str, addr: IpAddr, prefix: u8) -> std::io::Result<()> {
+  let index = link_index(name)?;
+  let (family, raw) = addr_bytes(addr);
+  let mut header = [0u8; 8];
+  header[0] = family;
+  header[1] = prefix;
+  header[4..8].copy_from_slice(&index.to_ne_bytes());
+  let mut msg = NlMessage::new(libc::RTM_NEWADDR, CREATE_EXCL);
+  msg
+    .bytes(&header)
+    .attr(IFA_LOCAL, &raw)
+    .attr(IFA_ADDRESS, &raw);
+  NetlinkSocket::route()?.request(msg)
+}
+
+pub fn add_default_route(gateway: IpAddr) -> std::io::Result<()> {
+  let (family, raw) = addr_bytes(gateway);
+  let header = [
+    family,
+    0, // dst_len
+    0, // src_len
+    0, // tos
+    libc::RT_TABLE_MAIN,
+    libc::RTPROT_BOOT,
+    libc::RT_SCOPE_UNIVERSE,
+    libc::RTN_UNICAST,
+    0,
+    0,
+    0,
+    0,
+  ];
+  let mut msg = NlMessage::new(libc::RTM_NEWROUTE, CREATE_EXCL);
+  msg.bytes(&header).attr(RTA_GATEWAY, &raw);
+  NetlinkSocket::route()?.request(msg)
+}
+
+fn nft_message(msg_type: u16, flags: u16, family: u8) -> NlMessage {
+  let mut msg = NlMessage::new((NFNL_SUBSYS_NFTABLES << 8) | msg_type, flags);
+  msg.bytes(&[family, 0, 0, 0]);
+  msg
+}
+
+fn nft_batch(mut body: Vec<NlMessage>) -> std::io::Result<()> {
+  let batch_header = [libc::AF_UNSPEC as u8, 0, 0, NFNL_SUBSYS_NFTABLES as u8];
+  let mut begin = NlMessage::new(NFNL_MSG_BATCH_BEGIN, libc::NLM_F_REQUEST as u16);
+  begin.bytes(&batch_header);
+  let mut end = NlMessage::new(NFNL_MSG_BATCH_END, libc::NLM_F_REQUEST as u16);
+  end.bytes(&batch_header);
+
+  let acked = body.len();
+  let mut messages = vec![begin];
+  messages.append(&mut body);
+  messages.push(end);
+  NetlinkSocket::netfilter()?.transact(messages, acked)
+}
+
+fn nft_expr<'a>(msg: &'a mut NlMessage, name: &str) -> &'a mut NlMessage {
+  msg
+    .nest(NFTA_LIST_ELEM | NLA_F_NESTED)
+    .attr_str(NFTA_EXPR_NAME, name)
+    .nest(NFTA_EXPR_DATA | NLA_F_NESTED)
+}
+
+/// Creates an `ip` table named `table` whose postrouting chain masquerades
+/// traffic from `source/prefix`. Dropping the table undoes everything.
+pub fn add_masquerade(table: &str, source: std::net::Ipv4Addr, prefix: u8) -> std::io::Result<()> {
+  let family = libc::NFPROTO_IPV4 as u8;
+  let mask = if prefix == 0 {
+    0
+  } else {
+    u32::MAX << (32 - prefix as u32)
+  };
+  let network = u32::from(source) & mask;
+
+  let mut new_table = nft_message(NFT_MSG_NEWTABLE, CREATE_EXCL, family);
+  new_table.attr_str(NFTA_TABLE_NAME, table);
+
+  let mut chain = nft_message(NFT_MSG_NEWCHAIN, CREATE_EXCL, family);
+  chain
+    .attr_str(NFTA_CHAIN_TABLE, table)
+    .attr_str(NFTA_CHAIN_NAME, "postrouting")
+    .nest(NFTA_CHAIN_HOOK | NLA_F_NESTED)
+    .attr_be32(NFTA_HOOK_HOOKNUM, NF_INET_POST_ROUTING)
+    .attr_be32(NFTA_HOOK_PRIORITY, 100)
+    .end()
+    .attr_be32(NFTA_CHAIN_POLICY, NF_ACCEPT)
+    .attr_str(NFTA_CHAIN_TYPE, "nat");
+
+  let mut rule = nft_message(
+    NFT_MSG_NEWRULE,
+    REQUEST_ACK | (libc::NLM_F_CREATE | libc::NLM_F_APPEND) as u16,
+    family,
+  );
+  rule
+    .attr_str(NFTA_RULE_TABLE, table)
+    .attr_str(NFTA_RULE_CHAIN, "postrouting")
+    .nest(NFTA_RULE_EXPRESSIONS | NLA_F_NESTED);
+  // ip saddr & mask == network
+  nft_expr(&mut rule, "payload")
+    .attr_be32(1, NFT_REG_1) // NFTA_PAYLOAD_DREG
+    .attr_be32(2, NFT_PAYLOAD_NETWORK_HEADER) // NFTA_PAYLOAD_BASE
+    .attr_be32(3, 12) // NFTA_PAYLOAD_OFFSET
+    .attr_be32(4, 4) // NFTA_PAYLOAD_LEN
+    .end()
+    .end();
+  nft_expr(&mut rule, "bitwise")
+    .attr_be32(1, NFT_REG_1) // NFTA_BITWISE_SREG
+    .attr_be32(2, NFT_REG_1) // NFTA_BITWISE_DREG
+    .attr_be32(3, 4) // NFTA_BITWISE_LEN
+    .nest(4 | NLA_F_NESTED) // NFTA_BITWISE_MASK
+    .attr(NFTA_DATA_VALUE, &mask.to_be_bytes())
+    .end()
+    .nest(5 | NLA_F_NESTED) // NFTA_BITWISE_XOR
+    .attr(NFTA_DATA_VALUE, &[0; 4])
+    .end()
+    .end()
+    .end();
+  nft_expr(&mut rule, "cmp")
+    .attr_be32(1, NFT_REG_1) // NFTA_CMP_SREG
+    .attr_be32(2, NFT_CMP_EQ) // NFTA_CMP_OP
+    .nest(3 | NLA_F_NESTED) // NFTA_CMP_DATA
+    .attr(NFTA_DATA_VALUE, &network.to_be_bytes())
+    .end()
+    .end()
+    .end();
+  nft_expr(&mut rule, "masq").end().end();
+  rule.end();
+
+  nft_batch(vec![new_table, chain, rule])
+}
+
+pub fn delete_nft_table(table: &str) -> std::io::Result<()> {
+  let mut msg = nft_message(NFT_MSG_DELTABLE, REQUEST_ACK, libc::NFPROTO_IPV4 as u8);
+  msg.attr_str(NFTA_TABLE_NAME, table);
+  nft_batch(vec![msg])
+}
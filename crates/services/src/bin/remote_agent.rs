@@ -0,0 +1,40 @@
+use rind_services::RemoteAgent;
+use rind_services::remote::read_token;
+
+const DEFAULT_AGENT_ADDR: &str = "127.0.0.1:7420";
+
+fn main() {
+  let mut args = std::env::args().skip(1);
+  let addr = args
+    .next()
+    .or_else(|| std::env::var("RIND_AGENT_ADDR").ok())
+    .unwrap_or_else(|| DEFAULT_AGENT_ADDR.to_string());
+  let token_file = args
+    .next()
+    .or_else(|| std::env::var("RIND_AGENT_TOKEN_FILE").ok());
+
+  let token = match token_file.as_deref().map(read_token).transpose() {
+    Ok(token) => token,
+    Err(e) => {
+      eprintln!("failed to read remote agent token: {e}");
+      std::process::exit(1);
+    }
+  };
+
+  let agent = match RemoteAgent::bind(&addr, token) {
+    Ok(agent) => agent,
+    Err(e) => {
+      eprintln!("failed to bind remote agent on {addr}: {e}");
+      std::process::exit(1);
+    }
+  };
+
+  if let Ok(local) = agent.local_addr() {
+    eprintln!("rind remote agent listening on {local}");
+  }
+
+  if let Err(e) = agent.serve() {
+    eprintln!("remote agent stopped: {e}");
+    std::process::exit(1);
+  }
+}
@@ -0,0 +1,452 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rind_core::prelude::*;
+
+const WEEKDAYS: [&str; 7] = [
+  "monday",
+  "tuesday",
+  "wednesday",
+  "thursday",
+  "friday",
+  "saturday",
+  "sunday",
+];
+
+const SHORTHANDS: [(&str, &str); 10] = [
+  ("minutely", "*-*-* *:*:00"),
+  ("hourly", "*-*-* *:00:00"),
+  ("daily", "*-*-* 00:00:00"),
+  ("weekly", "Mon *-*-* 00:00:00"),
+  ("monthly", "*-*-01 00:00:00"),
+  ("quarterly", "*-01,04,07,10-01 00:00:00"),
+  ("semiannually", "*-01,07-01 00:00:00"),
+  ("semi-annually", "*-01,07-01 00:00:00"),
+  ("yearly", "*-01-01 00:00:00"),
+  ("annually", "*-01-01 00:00:00"),
+];
+
+/// One comma-separated item of a calendar field: `5`, `1..5`, `0/15`, `8..18/2`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CalendarRange {
+  start: u32,
+  end: u32,
+  step: u32,
+}
+
+impl CalendarRange {
+  fn next(&self, from: u32) -> Option<u32> {
+    if from <= self.start {
+      return Some(self.start);
+    }
+    let value = self.start + (from - self.start).div_ceil(self.step) * self.step;
+    (value <= self.end).then_some(value)
+  }
+}
+
+/// A set of allowed values for one component; empty means `*`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CalendarField(Vec<CalendarRange>);
+
+impl CalendarField {
+  fn parse(s: &str, min: u32, max: u32, what: &str) -> CoreResult<Self> {
+    if s == "*" {
+      return Ok(Self::default());
+    }
+
+    let invalid = || CoreError::Custom(format!("invalid calendar {what}: {s}"));
+    let number = |v: &str| v.parse::<u32>().map_err(|_| invalid());
+
+    let mut ranges = Vec::new();
+    for item in s.split(',') {
+      let (base, step) = match item.split_once('/') {
+        Some((base, step)) => (base, Some(number(step)?)),
+        None => (item, None),
+      };
+      let (start, end) = match base.split_once("..") {
+        _ if base == "*" => (min, max),
+        Some((start, end)) => (number(start)?, number(end)?),
+        None if step.is_some() => (number(base)?, max),
+        None => (number(base)?, number(base)?),
+      };
+      if start < min || end > max || start > end || step == Some(0) {
+        return Err(invalid());
+      }
+      ranges.push(CalendarRange {
+        start,
+        end,
+        step: step.unwrap_or(1),
+      });
+    }
+    Ok(Self(ranges))
+  }
+
+  /// Smallest allowed value at or after `from`.
+  fn next(&self, from: u32) -> Option<u32> {
+    if self.0.is_empty() {
+      return Some(from);
+    }
+    self.0.iter().filter_map(|r| r.next(from)).min()
+  }
+
+  fn matches(&self, value: u32) -> bool {
+    self.next(value) == Some(value)
+  }
+}
+
+/// A parsed `OnCalendar`-style expression: `[weekdays] [date] [time] [UTC]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarSpec {
+  /// Bit 0 is Monday.
+  pub weekdays: u8,
+  pub years: CalendarField,
+  pub months: CalendarField,
+  pub days: CalendarField,
+  pub hours: CalendarField,
+  pub minutes: CalendarField,
+  pub seconds: CalendarField,
+  pub utc: bool,
+}
+
+pub fn parse_calendar(s: &str) -> CoreResult<CalendarSpec> {
+  let mut tokens: Vec<&str> = s.split_whitespace().collect();
+  let utc = tokens.last().is_some_and(|t| t.eq_ignore_ascii_case("utc"));
+  if utc {
+    tokens.pop();
+  }
+
+  if let [single] = tokens.as_slice()
+    && let Some((_, expanded)) = SHORTHANDS
+      .iter()
+      .find(|(name, _)| single.eq_ignore_ascii_case(name))
+  {
+    tokens = expanded.split_whitespace().collect();
+  }
+  if tokens.is_empty() {
+    return Err(CoreError::Custom(format!("empty calendar expression: {s}")));
+  }
+
+  let mut spec = CalendarSpec {
+    weekdays: 0x7f,
+    years: CalendarField::default(),
+    months: CalendarField::default(),
+    days: CalendarField::default(),
+    hours: CalendarField::parse("0", 0, 23, "hour")?,
+    minutes: CalendarField::parse("0", 0, 59, "minute")?,
+    seconds: CalendarField::parse("0", 0, 59, "second")?,
+    utc,
+  };
+
+  let mut rest = tokens.as_slice();
+  if let [first, tail @ ..] = rest
+    && first.starts_with(|c: char| c.is_ascii_alphabetic())
+  {
+    spec.weekdays = parse_weekdays(first)?;
+    rest = tail;
+  }
+  if let [date, tail @ ..] = rest
+    && date.contains('-')
+  {
+    let parts: Vec<&str> = date.split('-').collect();
+    let (year, month, day) = match parts.as_slice() {
+      [year, month, day] => (*year, *month, *day),
+      [month, day] => ("*", *month, *day),
+      _ => return Err(CoreError::Custom(format!("invalid calendar date: {date}"))),
+    };
+    spec.years = CalendarField::parse(year, 1970, 9999, "year")?;
+    spec.months = CalendarField::parse(month, 1, 12, "month")?;
+    spec.days = CalendarField::parse(day, 1, 31, "day")?;
+    rest = tail;
+  }
+  if let [time, tail @ ..] = rest
+    && time.contains(':')
+  {
+    let parts: Vec<&str> = time.split(':').collect();
+    let (hour, minute, second) = match parts.as_slice() {
+      [hour, minute, second] => (*hour, *minute, *second),
+      [hour, minute] => (*hour, *minute, "0"),
+      _ => return Err(CoreError::Custom(format!("invalid calendar time: {time}"))),
+    };
+    spec.hours = CalendarField::parse(hour, 0, 23, "hour")?;
+    spec.minutes = CalendarField::parse(minute, 0, 59, "minute")?;
+    spec.seconds = CalendarField::parse(second, 0, 59, "second")?;
+    rest = tail;
+  }
+  if let Some(extra) = rest.first() {
+    return Err(CoreError::Custom(format!(
+      "unexpected calendar component: {extra}"
+    )));
+  }
+
+  Ok(spec)
+}
+
+fn parse_weekdays(s: &str) -> CoreResult<u8> {
+  let day = |name: &str| {
+    let name = name.to_ascii_lowercase();
+    WEEKDAYS
+      .iter()
+      .position(|full| name.len() >= 3 && full.starts_with(&name))
+      .ok_or_else(|| CoreError::Custom(format!("invalid weekday: {name}")))
+  };
+
+  let mut mask = 0u8;
+  for item in s.split(',') {
+    match item.split_once("..").or_else(|| item.split_once('-')) {
+      Some((from, to)) => {
+        let (mut d, to) = (day(from)?, day(to)?);
+        loop {
+          mask |= 1 << d;
+          if d == to {
+            break;
+          }
+          d = (d + 1) % 7;
+        }
+      }
+      None => mask |= 1 << day(item)?,
+    }
+  }
+  Ok(mask)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Civil {
+  year: u32,
+  month: u32,
+  day: u32,
+  hour: u32,
+  minute: u32,
+  second: u32,
+}
+
+impl Civil {
+  fn date(year: u32, month: u32, day: u32) -> Self {
+    Self {
+      year,
+      month,
+      day,
+      hour: 0,
+      minute: 0,
+      second: 0,
+    }
+  }
+
+  fn next_month(self) -> Self {
+    if self.month == 12 {
+      Self::date(self.year + 1, 1, 1)
+    } else {
+      Self::date(self.year, self.month + 1, 1)
+    }
+  }
+
+  fn next_day(self) -> Self {
+    if self.day >= days_in_month(self.year, self.month) {
+      self.next_month()
+    } else {
+      Self::date(self.year, self.month, self.day + 1)
+    }
+  }
+
+  fn next_hour(self) -> Self {
+    if self.hour == 23 {
+      return self.next_day();
+    }
+    Self {
+      hour: self.hour + 1,
+      minute: 0,
+      second: 0,
+      ..self
+    }
+  }
+
+  fn next_minute(self) -> Self {
+    if self.minute == 59 {
+      return self.next_hour();
+    }
+    Self {
+      minute: self.minute + 1,
+      second: 0,
+      ..self
+    }
+  }
+
+  fn next_second(self) -> Self {
+    if self.second == 59 {
+      return self.next_minute();
+    }
+    Self {
+      second: self.second + 1,
+      ..self
+    }
+  }
+
+  /// 0 is Monday.
+  fn weekday(&self) -> u32 {
+    (days_from_civil(self.year, self.month, self.day) + 3).rem_euclid(7) as u32
+  }
+}
+
+fn is_leap(year: u32) -> bool {
+  year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+  match month {
+    2 if is_leap(year) => 29,
+    2 => 28,
+    4 | 6 | 9 | 11 => 30,
+    _ => 31,
+  }
+}
+
+fn days_from_civil(year: u32, month: u32, day: u32) -> i64 {
+  let y = year as i64 - (month <= 2) as i64;
+  let era = y.div_euclid(400);
+  let yoe = y - era * 400;
+  let mp = (month as i64 + 9) % 12;
+  let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+  let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+  era * 146097 + doe - 719468
+}
+
+fn civil_from_days(days: i64) -> (u32, u32, u32) {
+  let z = days + 719468;
+  let era = z.div_euclid(146097);
+  let doe = z - era * 146097;
+  let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+  let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+  let mp = (5 * doy + 2) / 153;
+  let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+  let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+  let year = (yoe + era * 400 + (month <= 2) as i64) as u32;
+  (year, month, day)
+}
+
+impl CalendarSpec {
+  /// Next wall-clock time strictly after `after` that matches the expression,
+  /// or `None` if it never matches again.
+  pub fn next_elapse(&self, after: SystemTime) -> Option<SystemTime> {
+    let after = after.duration_since(UNIX_EPOCH).ok()?.as_secs() as i64;
+    let mut candidate = self.to_civil(after + 1)?;
+
+    // Local candidates can map before `after` around DST changes; step past them.
+    for _ in 0..8 {
+      let found = self.next_civil(candidate)?;
+      let at = self.to_epoch(found)?;
+      if at > after {
+        return Some(UNIX_EPOCH + Duration::from_secs(at as u64));
+      }
+      candidate = found.next_second();
+    }
+    None
+  }
+
+  fn next_civil(&self, mut c: Civil) -> Option<Civil> {
+    let limit = c.year + 400;
+    while c.year <= limit {
+      if !self.years.matches(c.year) {
+        c = Civil::date(self.years.next(c.year)?, 1, 1);
+        continue;
+      }
+      if !self.months.matches(c.month) {
+        c = match self.months.next(c.month).filter(|m| *m <= 12) {
+          Some(month) => Civil::date(c.year, month, 1),
+          None => Civil::date(c.year + 1, 1, 1),
+        };
+        continue;
+      }
+      if !self.days.matches(c.day) {
+        let dim = days_in_month(c.year, c.month);
+        c = match self.days.next(c.day).filter(|d| *d <= dim) {
+          Some(day) => Civil::date(c.year, c.month, day),
+          None => c.next_month(),
+        };
+        continue;
+      }
+      if self.weekdays & (1 << c.weekday()) == 0 {
+        c = c.next_day();
+        continue;
+      }
+      if !self.hours.matches(c.hour) {
+        c = match self.hours.next(c.hour).filter(|h| *h <= 23) {
+          Some(hour) => Civil {
+            hour,
+            minute: 0,
+            second: 0,
+            ..c
+          },
+          None => c.next_day(),
+        };
+        continue;
+      }
+      if !self.minutes.matches(c.minute) {
+        c = match self.minutes.next(c.minute).filter(|m| *m <= 59) {
+          Some(minute) => Civil {
+            minute,
+            second: 0,
+            ..c
+          },
+          None => c.next_hour(),
+        };
+        continue;
+      }
+      if !self.seconds.matches(c.second) {
+        c = match self.seconds.next(c.second).filter(|s| *s <= 59) {
+          Some(second) => Civil { second, ..c },
+          None => c.next_minute(),
+        };
+        continue;
+      }
+      return Some(c);
+    }
+    None
+  }
+
+  fn to_civil(&self, secs: i64) -> Option<Civil> {
+    if self.utc {
+      let (year, month, day) = civil_from_days(secs.div_euclid(86400));
+      let rem = secs.rem_euclid(86400) as u32;
+      return Some(Civil {
+        year,
+        month,
+        day,
+        hour: rem / 3600,
+        minute: rem % 3600 / 60,
+        second: rem % 60,
+      });
+    }
+
+    let time = secs as libc::time_t;
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() {
+      return None;
+    }
+    Some(Civil {
+      year: (tm.tm_year + 1900) as u32,
+      month: (tm.tm_mon + 1) as u32,
+      day: tm.tm_mday as u32,
+      hour: tm.tm_hour as u32,
+      minute: tm.tm_min as u32,
+      second: tm.tm_sec.min(59) as u32,
+    })
+  }
+
+  fn to_epoch(&self, c: Civil) -> Option<i64> {
+    if self.utc {
+      return Some(
+        days_from_civil(c.year, c.month, c.day) * 86400
+          + (c.hour * 3600 + c.minute * 60 + c.second) as i64,
+      );
+    }
+
+    let mut tm: libc::tm = unsafe { std::mem::zeroed() };
+    tm.tm_year = c.year as i32 - 1900;
+    tm.tm_mon = c.month as i32 - 1;
+    tm.tm_mday = c.day as i32;
+    tm.tm_hour = c.hour as i32;
+    tm.tm_min = c.minute as i32;
+    tm.tm_sec = c.second as i32;
+    tm.tm_isdst = -1;
+    let time = unsafe { libc::mktime(&mut tm) };
+    (time != -1).then_some(time as i64)
+  }
+}
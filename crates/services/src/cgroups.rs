@@ -0,0 +1,242 @@
+use crate::services::ServiceCgroup;
+use nix::fcntl::{OFlag, open};
+use nix::sys::stat::Mode;
+use nix::sys::uio::pread;
+use rind_core::prelude::*;
+use std::os::fd::{AsFd, OwnedFd};
+use std::path::Path;
+
+pub const CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+/// PSI trigger armed on `memory.pressure` unless a service sets its own:
+/// 150ms of partial stall within a 2s window. Without `CAP_SYS_RESOURCE`
+/// the kernel only accepts windows that are multiples of 2s.
+pub const DEFAULT_MEMORY_PRESSURE: &str = "some 150000 2000000";
+
+/// Controllers enabled on a best-effort basis so `memory.current`, `io.stat`
+/// and `pids.current` exist even without limits.
+const ACCOUNTING_CONTROLLERS: [&str; 3] = ["memory", "io", "pids"];
+
+fn write_interface(dir: &Path, file: &str, value: &str) -> CoreResult<Void> {
+  let path = dir.join(file);
+  std::fs::write(&path, value)
+    .map_err(|e| CoreError::IoError(format!("cgroup {}: {e}", path.display())))?;
+  Ok(Void)
+}
+
+fn read_list(path: &Path) -> Vec<String> {
+  std::fs::read_to_string(path)
+    .map(|s| s.split_whitespace().map(str::to_string).collect())
+    .unwrap_or_default()
+}
+
+/// Enables controllers in every ancestor's `cgroup.subtree_control` from the
+/// cgroup root down to `path`'s parent. Paths outside the root are left alone.
+pub fn enable_controllers(path: &Path, required: &[&str]) -> CoreResult<Void> {
+  let Ok(relative) = path.strip_prefix(CGROUP_ROOT) else {
+    return Ok(Void);
+  };
+  let mut dirs = vec![Path::new(CGROUP_ROOT).to_path_buf()];
+  for part in relative.parent().into_iter().flat_map(Path::components) {
+    let next = dirs[dirs.len() - 1].join(part);
+    dirs.push(next);
+  }
+
+  for dir in dirs {
+    let available = read_list(&dir.join("cgroup.controllers"));
+    let enabled = read_list(&dir.join("cgroup.subtree_control"));
+    let accounting = ACCOUNTING_CONTROLLERS
+      .iter()
+      .filter(|c| !required.contains(c));
+    for controller in required.iter().chain(accounting) {
+      if enabled.iter().any(|c| c == controller) {
+        continue;
+      }
+      let needed = required.contains(controller);
+      if !available.iter().any(|c| c == controller) {
+        if needed {
+          return Err(CoreError::InvalidState(format!(
+            "cgroup controller '{controller}' is not available in {}",
+            dir.display()
+          )));
+        }
+        continue;
+      }
+      let written = write_interface(&dir, "cgroup.subtree_control", &format!("+{controller}"));
+      if needed {
+        written?;
+      }
+    }
+  }
+  Ok(Void)
+}
+
+/// Creates the cgroup at `path`, applies the configured limits and moves
+/// `pid` into it. Any write the kernel rejects fails the call.
+pub fn apply_cgroup(
+  path: Option<&Path>,
+  cgroup: Option<&ServiceCgroup>,
+  pid: u32,
+) -> CoreResult<Void> {
+  let Some(path) = path else {
+    return Ok(Void);
+  };
+  let settings = cgroup.map(ServiceCgroup::settings).unwrap_or_default();
+  let mut required: Vec<&str> = settings.iter().map(|(c, _, _)| *c).collect();
+  required.sort_unstable();
+  required.dedup();
+
+  std::fs::create_dir_all(path)?;
+  enable_controllers(path, &required)?;
+  for (_, file, value) in settings {
+    write_interface(path, file, value)?;
+  }
+  write_interface(path, "cgroup.procs", &pid.to_string())
+}
+
+/// Resource consumption of one cgroup. Counters whose interface file is
+/// missing (controller not enabled) stay `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CgroupUsage {
+  pub memory_current: Option<u64>,
+  pub cpu_usage_usec: Option<u64>,
+  pub cpu_user_usec: Option<u64>,
+  pub cpu_system_usec: Option<u64>,
+  pub io_read_bytes: Option<u64>,
+  pub io_write_bytes: Option<u64>,
+  pub pids_current: Option<u64>,
+}
+
+/// Reads a flat-keyed file such as `cpu.stat`.
+fn stat_field(contents: &str, key: &str) -> Option<u64> {
+  contents.lines().find_map(|line| {
+    let (name, value) = line.split_once(' ')?;
+    (name == key).then(|| value.trim().parse().ok())?
+  })
+}
+
+/// Sums `rbytes` and `wbytes` over every device line of `io.stat`.
+pub fn parse_io_stat(contents: &str) -> (u64, u64) {
+  let mut read = 0;
+  let mut write = 0;
+  for field in contents.split_whitespace() {
+    match field.split_once('=') {
+      Some(("rbytes", v)) => read += v.parse::<u64>().unwrap_or(0),
+      Some(("wbytes", v)) => write += v.parse::<u64>().unwrap_or(0),
+      _ => {}
+    }
+  }
+  (read, write)
+}
+
+impl CgroupUsage {
+  pub fn read(path: &Path) -> Option<Self> {
+    if !path.is_dir() {
+      return None;
+    }
+    let read = |file: &str| std::fs::read_to_string(path.join(file)).ok();
+    let single = |file: &str| read(file).and_then(|s| s.trim().parse().ok());
+
+    let cpu = read("cpu.stat").unwrap_or_default();
+    let io = read("io.stat").map(|s| parse_io_stat(&s));
+    Some(Self {
+      memory_current: single("memory.current"),
+      cpu_usage_usec: stat_field(&cpu, "usage_usec"),
+      cpu_user_usec: stat_field(&cpu, "user_usec"),
+      cpu_system_usec: stat_field(&cpu, "system_usec"),
+      io_read_bytes: io.map(|(r, _)| r),
+      io_write_bytes: io.map(|(_, w)| w),
+      pids_current: single("pids.current"),
+    })
+  }
+}
+
+/// Counters from `memory.events`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryEvents {
+  pub high: u64,
+  pub max: u64,
+  pub oom: u64,
+  pub oom_kill: u64,
+}
+
+impl MemoryEvents {
+  pub fn parse(contents: &str) -> Self {
+    Self {
+      high: stat_field(contents, "high").unwrap_or(0),
+      max: stat_field(contents, "max").unwrap_or(0),
+      oom: stat_field(contents, "oom").unwrap_or(0),
+      oom_kill: stat_field(contents, "oom_kill").unwrap_or(0),
+    }
+  }
+
+  /// Re-reads an fd from [`open_memory_events`]. Reading also clears the
+  /// pending poll notification.
+  pub fn read_fd(fd: impl AsFd) -> Option<Self> {
+    read_from_start(fd).map(|s| Self::parse(&s))
+  }
+}
+
+/// Stall averages from a PSI file such as `memory.pressure`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Pressure {
+  pub some_avg10: f64,
+  pub full_avg10: f64,
+}
+
+impl Pressure {
+  pub fn parse(contents: &str) -> Self {
+    let avg10 = |kind: &str| {
+      contents
+        .lines()
+        .find(|line| line.starts_with(kind))
+        .and_then(|line| {
+          line
+            .split_whitespace()
+            .find_map(|field| field.strip_prefix("avg10="))
+        })
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0.0)
+    };
+    Self {
+      some_avg10: avg10("some"),
+      full_avg10: avg10("full"),
+    }
+  }
+
+  pub fn read_fd(fd: impl AsFd) -> Option<Self> {
+    read_from_start(fd).map(|s| Self::parse(&s))
+  }
+}
+
+fn read_from_start(fd: impl AsFd) -> Option<String> {
+  let mut buf = [0u8; 1024];
+  let n = pread(fd, &mut buf, 0).ok()?;
+  Some(String::from_utf8_lossy(&buf[..n]).into_owned())
+}
+
+/// Opens the cgroup's `memory.events`. The kernel flags it with `EPOLLPRI`
+/// whenever a counter changes.
+pub fn open_memory_events(cgroup: &Path) -> std::io::Result<OwnedFd> {
+  let fd = open(
+    &cgroup.join("memory.events"),
+    OFlag::O_RDONLY | OFlag::O_NONBLOCK | OFlag::O_CLOEXEC,
+    Mode::empty(),
+  )?;
+  Ok(fd)
+}
+
+/// Arms a PSI trigger such as `"some 150000 2000000"` on a pressure file.
+/// The returned fd raises `EPOLLPRI` each time the stall threshold is hit
+/// within the window.
+pub fn open_psi_trigger(file: &Path, trigger: &str) -> std::io::Result<OwnedFd> {
+  let fd = open(
+    file,
+    OFlag::O_RDWR | OFlag::O_NONBLOCK | OFlag::O_CLOEXEC,
+    Mode::empty(),
+  )?;
+  // The kernel replaces the last byte with a terminator.
+  let spec = format!("{trigger}\0");
+  nix::unistd::write(&fd, spec.as_bytes())?;
+  Ok(fd)
+}
@@ -1,36 +1,216 @@
-use crate::executors::{Executor, ExecutorContext, InstanceHandle};
+use crate::executors::{ChannelReader, Executor, ExecutorContext, InstanceHandle, watch_exit_fd};
+use nix::fcntl::OFlag;
 use nix::sys::signal::Signal;
+use nix::unistd::pipe2;
 use rind_core::prelude::*;
-use std::sync::mpsc::{Receiver, Sender};
-use std::sync::{Arc, Mutex};
+use rind_core::reexports::once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::os::fd::OwnedFd;
+use std::sync::atomic::{AtomicI32, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, RwLock};
 use std::thread;
 
+// In-process instances get ids from their own pid space, see `is_virtual_pid`.
+pub const IMA_PID_FLAG: u32 = 1 << 29;
+// Exit code of a module that panicked.
+pub const IMA_PANIC_CODE: i32 = 101;
+
+static NEXT_IMA_ID: AtomicU32 = AtomicU32::new(1);
+static IMA_MODULES: Lazy<RwLock<ImaModules>> = Lazy::new(Default::default);
+
+fn next_ima_id() -> u32 {
+  loop {
+    let id = NEXT_IMA_ID.fetch_add(1, Ordering::Relaxed) & (IMA_PID_FLAG - 1);
+    if id != 0 {
+      return id;
+    }
+  }
+}
+
+pub fn is_ima_pid(pid: u32) -> bool {
+  pid & IMA_PID_FLAG != 0 && pid & super::remote::REMOTE_PID_FLAG == 0
+}
+
+/// Entry point of an in-process module. The return value is the exit code.
+pub type ImaEntry = fn(&mut ImaContext) -> i32;
+
+#[derive(Default)]
+pub struct ImaModules(pub HashMap<Ustr, ImaEntry>);
+
+impl ImaModules {
+  pub fn insert(&mut self, name: impl Into<Ustr>, entry: ImaEntry) {
+    self.0.insert(name.into(), entry);
+  }
+
+  /// Looks up `name` in the registered modules, then asks plugins through
+  /// the `ImaModules` act extension.
+  pub fn resolve(name: &str) -> Option<ImaEntry> {
+    if let Some(entry) = IMA_MODULES.read().ok().and_then(|m| m.0.get(name).copied()) {
+      return Some(entry);
+    }
+
+    EXTENSIONS.with(|extensions| {
+      let extensions = extensions.get()?;
+      let mut modules = ImaModules::default();
+      extensions.act(name, &mut modules).ok()?;
+      modules.0.get(name).copied()
+    })
+  }
+}
+
+pub fn register_ima_module(name: impl Into<Ustr>, entry: ImaEntry) {
+  if let Ok(mut modules) = IMA_MODULES.write() {
+    modules.insert(name, entry);
+  }
+}
+
+pub struct ImaContext {
+  pub name: Ustr,
+  pub args: Vec<Ustr>,
+  pub envs: HashMap<Ustr, Ustr>,
+  pub stdout: Box<dyn Write + Send>,
+  pub stderr: Box<dyn Write + Send>,
+  pub stdin: Box<dyn Read + Send>,
+  cancel: Arc<AtomicI32>,
+}
+
+impl ImaContext {
+  /// The signal `kill` was called with, if any. Modules are expected to poll
+  /// this and return (conventionally `128 + signal`) once it is set.
+  ///
+  /// A thread can't be killed, so `SIGKILL` doesn't wait for the module: the
+  /// instance exits with `128 + 9` right away and the thread is abandoned.
+  /// It keeps running until it returns, its output and exit code discarded.
+  pub fn cancelled(&self) -> Option<Signal> {
+    match self.cancel.load(Ordering::SeqCst) {
+      0 => None,
+      sig => Signal::try_from(sig).ok(),
+    }
+  }
+}
+
+struct ChannelWriter(Sender<Vec<u8>>);
+
+impl Write for ChannelWriter {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    self
+      .0
+      .send(buf.to_vec())
+      .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    Ok(())
+  }
+}
+
+/// Write end of the exit pipe, shared by the module thread and a forced
+/// `SIGKILL`. Only the first exit code gets through.
+type ExitWriter = Arc<Mutex<Option<std::fs::File>>>;
+
+fn report_exit(exit: &ExitWriter, code: i32) {
+  if let Some(mut file) = exit.lock().ok().and_then(|mut exit| exit.take()) {
+    let _ = file.write_all(&code.to_be_bytes());
+  }
+}
+
 pub struct ImaHandle {
-  pub join_handle: Option<thread::JoinHandle<CoreResult<Void>>>,
-  pub stdout_rx: Arc<Mutex<Receiver<Vec<u8>>>>,
-  pub stderr_rx: Arc<Mutex<Receiver<Vec<u8>>>>,
-  pub stdin_tx: Sender<Vec<u8>>,
+  pub id: u32,
+  pub join_handle: Option<thread::JoinHandle<()>>,
+  pub stdout_rx: Option<Mutex<Receiver<Vec<u8>>>>,
+  pub stderr_rx: Option<Mutex<Receiver<Vec<u8>>>>,
+  pub stdin_tx: Option<Sender<Vec<u8>>>,
+  pub exit_fd: Option<OwnedFd>,
+  cancel: Arc<AtomicI32>,
+  exit: ExitWriter,
+}
+
+impl ImaHandle {
+  /// Runs `entry` on a dedicated thread. The exit code is written to
+  /// `exit_fd` as a big-endian i32 once the module returns.
+  pub fn start(
+    name: impl Into<Ustr>,
+    entry: ImaEntry,
+    args: Vec<Ustr>,
+    envs: HashMap<Ustr, Ustr>,
+  ) -> CoreResult<Self> {
+    let name = name.into();
+    let id = next_ima_id();
+    let cancel = Arc::new(AtomicI32::new(0));
+    let (exit_read, exit_write) = pipe2(OFlag::O_CLOEXEC).map_err(CoreError::System)?;
+    let exit: ExitWriter = Arc::new(Mutex::new(Some(std::fs::File::from(exit_write))));
+    let thread_exit = exit.clone();
+    let (stdout_tx, stdout_rx) = mpsc::channel::<Vec<u8>>();
+    let (stderr_tx, stderr_rx) = mpsc::channel::<Vec<u8>>();
+    let (stdin_tx, stdin_rx) = mpsc::channel::<Vec<u8>>();
+
+    let mut ctx = ImaContext {
+      name: name.clone(),
+      args,
+      envs,
+      stdout: Box::new(ChannelWriter(stdout_tx)),
+      stderr: Box::new(ChannelWriter(stderr_tx)),
+      stdin: Box::new(ChannelReader::new(stdin_rx)),
+      cancel: cancel.clone(),
+    };
+
+    let join_handle = thread::Builder::new()
+      .name(format!("ima:{name}"))
+      .spawn(move || {
+        let code = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| entry(&mut ctx)))
+          .unwrap_or(IMA_PANIC_CODE);
+        drop(ctx);
+        report_exit(&thread_exit, code);
+      })?;
+
+    Ok(Self {
+      id,
+      join_handle: Some(join_handle),
+      stdout_rx: Some(Mutex::new(stdout_rx)),
+      stderr_rx: Some(Mutex::new(stderr_rx)),
+      stdin_tx: Some(stdin_tx),
+      exit_fd: Some(exit_read),
+      cancel,
+      exit,
+    })
+  }
+
+  pub fn take_exit_fd(&mut self) -> Option<OwnedFd> {
+    self.exit_fd.take()
+  }
 }
 
 impl InstanceHandle for ImaHandle {
   fn pid(&self) -> Option<u32> {
-    None
+    Some(self.id | IMA_PID_FLAG)
   }
 
-  fn kill(&mut self, _signal: Signal) -> CoreResult<Void> {
+  fn kill(&mut self, signal: Signal) -> CoreResult<Void> {
+    self.cancel.store(signal as i32, Ordering::SeqCst);
+    if signal == Signal::SIGKILL {
+      // hard stop, see `ImaContext::cancelled`
+      report_exit(&self.exit, 128 + Signal::SIGKILL as i32);
+      self.join_handle.take();
+    }
     Ok(Void)
   }
 
   fn take_stdout(&mut self) -> Option<Box<dyn std::io::Read + Send>> {
-    None
+    let rx = self.stdout_rx.take()?.into_inner().ok()?;
+    Some(Box::new(ChannelReader::new(rx)))
   }
 
   fn take_stderr(&mut self) -> Option<Box<dyn std::io::Read + Send>> {
-    None
+    let rx = self.stderr_rx.take()?.into_inner().ok()?;
+    Some(Box::new(ChannelReader::new(rx)))
   }
 
   fn take_stdin(&mut self) -> Option<Box<dyn std::io::Write + Send>> {
-    None
+    let tx = self.stdin_tx.take()?;
+    Some(Box::new(ChannelWriter(tx)))
   }
 }
 
@@ -41,9 +221,24 @@ impl Executor for ImaExecutor {
     "ima"
   }
 
-  fn spawn(&self, _ctx: ExecutorContext) -> CoreResult<Box<dyn InstanceHandle>> {
-    Err(CoreError::Custom(
-      "ImaExecutor implementation in progress".into(),
-    ))
+  fn spawn(&self, ctx: ExecutorContext) -> CoreResult<Box<dyn InstanceHandle>> {
+    let name = ctx.run.exec.clone();
+    let entry = ImaModules::resolve(name.as_str())
+      .ok_or_else(|| CoreError::not_found("ima module", &name))?;
+
+    let mut handle = ImaHandle::start(name.clone(), entry, ctx.args, ctx.envs)?;
+    let pid = handle.pid().unwrap_or_default();
+    if let Some(exit_fd) = handle.take_exit_fd() {
+      watch_exit_fd(ctx.resources, exit_fd, pid);
+    }
+
+    let mut fields = HashMap::new();
+    fields.insert("service".to_string(), ctx.registry_key.to_string());
+    fields.insert("module".to_string(), name.to_string());
+    ctx
+      .log
+      .log(LogLevel::Info, "ima-executor", "module started", fields);
+
+    Ok(Box::new(handle))
   }
 }
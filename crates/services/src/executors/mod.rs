@@ -1,3 +1,4 @@
+use crate::namespaces::NamespaceNetwork;
 use crate::services::*;
 use nix::sys::signal::{Signal, kill};
 use nix::unistd::Pid;
@@ -9,15 +10,99 @@ use rind_primitives::prelude::VariableHeap;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs::File;
+use std::io::Read;
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
 use std::process::Child;
+use std::sync::mpsc::Receiver;
 
 pub mod ima;
 pub mod native;
 pub mod remote;
 
-pub use ima::ImaExecutor;
+pub use ima::{ImaContext, ImaEntry, ImaExecutor, ImaModules, register_ima_module};
 pub use native::NativeExecutor;
-pub use remote::RemoteExecutor;
+pub use remote::{RemoteAgent, RemoteExecutor};
+
+// Reported when an executor loses track of its instance before it exits.
+pub const LOST_EXIT_CODE: i32 = 255;
+
+/// Pids handed out by executors that don't fork locally (remote, ima).
+/// They are never signalled or reaped directly, only through their handle.
+pub fn is_virtual_pid(pid: u32) -> bool {
+  remote::is_remote_pid(pid) || ima::is_ima_pid(pid)
+}
+
+/// Hands the read end of an exit pipe to the main loop, which dispatches
+/// `services.executor_exited` once the exit code (`i32` be) is written.
+pub(crate) fn watch_exit_fd(resources: &mut Resources, exit_fd: OwnedFd, pid: u32) {
+  let raw = exit_fd.as_raw_fd();
+  resources.own(raw, exit_fd);
+  resources.action(
+    raw,
+    ResourceAction::from(("services", "executor_exited"))
+      .payload(move |p| p.insert("pid", pid as i32)),
+  );
+}
+
+/// First fd of the `LISTEN_FDS` layout.
+pub const LISTEN_FDS_START: RawFd = 3;
+
+/// Moves socket activation fds to 3, 4, ... in order, without `FD_CLOEXEC`,
+/// as `LISTEN_FDS` consumers expect. Runs between fork and exec.
+pub(crate) fn install_listen_fds(fds: &mut [RawFd]) -> std::io::Result<()> {
+  let end = LISTEN_FDS_START + fds.len() as RawFd;
+  // Park fds that already sit inside the target range above it first, so a
+  // dup2 never clobbers a socket that still has to be moved.
+  for fd in fds.iter_mut() {
+    if (LISTEN_FDS_START..end).contains(fd) {
+      let parked = unsafe { libc::fcntl(*fd, libc::F_DUPFD_CLOEXEC, end) };
+      if parked < 0 {
+        return Err(std::io::Error::last_os_error());
+      }
+      *fd = parked;
+    }
+  }
+  for (idx, fd) in fds.iter().enumerate() {
+    if unsafe { libc::dup2(*fd, LISTEN_FDS_START + idx as RawFd) } < 0 {
+      return Err(std::io::Error::last_os_error());
+    }
+  }
+  Ok(())
+}
+
+pub(crate) struct ChannelReader {
+  rx: Receiver<Vec<u8>>,
+  buf: Vec<u8>,
+  pos: usize,
+}
+
+impl ChannelReader {
+  pub(crate) fn new(rx: Receiver<Vec<u8>>) -> Self {
+    Self {
+      rx,
+      buf: Vec::new(),
+      pos: 0,
+    }
+  }
+}
+
+impl Read for ChannelReader {
+  fn read(&mut self, out: &mut [u8]) -> std::io::Result<usize> {
+    while self.pos >= self.buf.len() {
+      match self.rx.recv() {
+        Ok(chunk) => {
+          self.buf = chunk;
+          self.pos = 0;
+        }
+        Err(_) => return Ok(0),
+      }
+    }
+    let n = out.len().min(self.buf.len() - self.pos);
+    out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+    self.pos += n;
+    Ok(n)
+  }
+}
 
 pub trait InstanceHandle: Send + Sync {
   fn pid(&self) -> Option<u32>;
@@ -70,6 +155,7 @@ pub struct SupervisorHandle {
   pub stderr: Option<File>,
   pub stdin: Option<File>,
   pub _namespace_fds: Vec<File>,
+  pub _network: NamespaceNetwork,
 }
 
 impl InstanceHandle for SupervisorHandle {
@@ -104,13 +190,33 @@ impl InstanceHandle for SupervisorHandle {
   }
 }
 
+/// One veth link into a service's network namespace. `name` and `address`
+/// are the namespace end; the `host-*`, `bridge` and `nat` settings are
+/// applied to the host end.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NamespaceNetworkConfig {
+  #[serde(default = "NamespaceNetworkConfig::default_name")]
   pub name: String,
+  #[serde(default = "NamespaceNetworkConfig::default_method")]
   pub method: String,
   pub address: Option<String>,
   pub gateway: Option<String>,
   pub dns: Option<Vec<String>>,
+  #[serde(rename = "host-address")]
+  pub host_address: Option<String>,
+  pub bridge: Option<String>,
+  #[serde(default)]
+  pub nat: bool,
+}
+
+impl NamespaceNetworkConfig {
+  fn default_name() -> String {
+    "eth0".into()
+  }
+
+  fn default_method() -> String {
+    "static".into()
+  }
 }
 
 pub struct ExecutorContext<'a> {
@@ -1,8 +1,10 @@
-use crate::executors::{Executor, ExecutorContext, InstanceHandle, ProcessHandle};
+use crate::executors::{
+  Executor, ExecutorContext, InstanceHandle, ProcessHandle, install_listen_fds,
+};
 use crate::{ServiceType, namespaces};
 use rind_core::prelude::*;
 use rind_core::utils::read_env_file;
-use std::os::fd::RawFd;
+use std::os::fd::BorrowedFd;
 use std::os::unix::process::CommandExt;
 use std::path::PathBuf;
 use std::process::Command;
@@ -20,8 +22,9 @@ impl Executor for NativeExecutor {
     let args = ctx.args.clone();
     let mut envs = ctx.envs.clone();
     let branch_key = ctx.branch_ctx.and_then(|c| c.key.as_ref());
-    namespaces::validate_namespaces(ctx.isolation.namespaces.as_ref())?;
-    let pre_exec_fds = ctx
+    let connection_fd = ctx.branch_ctx.and_then(|c| c.connection_fd);
+    namespaces::validate_namespaces(ctx.isolation.namespaces.as_ref(), &ctx.namespace_networks)?;
+    let mut pre_exec_fds = ctx
       .sockets_map
       .get(&rslvns!(snorm ctx.registry_key).to_ustr())
       .map(|s| s.fds.clone())
@@ -63,7 +66,7 @@ impl Executor for NativeExecutor {
       envs.insert(Ustr::from("RIND_BRANCH_KEY"), key.clone());
     }
 
-    if namespaces::needs_supervisor(&ctx.isolation) {
+    if namespaces::needs_supervisor(&ctx.isolation) || !ctx.namespace_networks.is_empty() {
       let join_namespace_fds = namespaces::persisted_namespace_fds(ctx.isolation.scope.as_ref());
       return namespaces::spawn_supervised(
         ctx.run.exec.to_string(),
@@ -71,7 +74,8 @@ impl Executor for NativeExecutor {
         envs,
         working_dir,
         uid_gid,
-        pre_exec_fds.into_iter().collect::<Vec<_>>(),
+        pre_exec_fds,
+        connection_fd,
         ctx.isolation,
         ctx.cgroup_path,
         join_namespace_fds,
@@ -119,6 +123,14 @@ impl Executor for NativeExecutor {
       .stdout(Stdio::piped())
       .stderr(Stdio::piped());
 
+    if let Some(fd) = connection_fd {
+      // The fd stays owned by `Resources` until the start action returns.
+      let connection = unsafe { BorrowedFd::borrow_raw(fd) };
+      cmd
+        .stdin(connection.try_clone_to_owned()?)
+        .stdout(connection.try_clone_to_owned()?);
+    }
+
     if let Some(dir) = &working_dir {
       cmd.current_dir(dir.as_str());
     }
@@ -135,7 +147,7 @@ impl Executor for NativeExecutor {
     unsafe {
       cmd.pre_exec(move || {
         libc::setsid();
-        namespaces::apply_namespace_setup(namespaces.as_ref())?;
+        namespaces::apply_namespace_setup(namespaces.as_ref(), &[])?;
 
         for (k, v) in &envs {
           let ck = std::ffi::CString::new(k.as_str()).unwrap();
@@ -153,16 +165,7 @@ impl Executor for NativeExecutor {
           }
         }
 
-        for (idx, fd) in pre_exec_fds.iter().enumerate() {
-          let target_fd = (3 + idx) as RawFd;
-          if *fd != target_fd && libc::dup2(*fd, target_fd) < 0 {
-            return Err(std::io::Error::last_os_error());
-          }
-          let flags = libc::fcntl(target_fd, libc::F_GETFD);
-          if flags >= 0 {
-            let _ = libc::fcntl(target_fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC);
-          }
-        }
+        install_listen_fds(&mut pre_exec_fds)?;
         Ok(Void)
       });
     }
@@ -1,37 +1,284 @@
-use crate::executors::{Executor, ExecutorContext, InstanceHandle};
-use nix::sys::signal::Signal;
+use crate::executors::{
+  ChannelReader, Executor, ExecutorContext, InstanceHandle, LOST_EXIT_CODE, watch_exit_fd,
+};
+use nix::fcntl::OFlag;
+use nix::sys::signal::{Signal, kill};
+use nix::unistd::{Pid, pipe2};
 use rind_core::prelude::*;
-use std::net::TcpStream;
-use std::sync::mpsc::{Receiver, Sender};
+use rind_core::reexports::bincode_next;
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::os::fd::OwnedFd;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// Remote instances get ids from their own pid space so they never collide
+// with local children in the pid map (linux caps pid_max at 2^22).
+pub const REMOTE_PID_FLAG: u32 = 1 << 30;
+pub fn is_remote_pid(pid: u32) -> bool {
+  pid & REMOTE_PID_FLAG != 0
+}
+
+/// Largest payload either side accepts; bigger frames drop the connection.
+pub const MAX_FRAME_LEN: usize = 1 << 20;
+/// Bounds connecting, authenticating and the spawn ack, which run on the
+/// instance's worker thread.
+pub const REMOTE_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+static NEXT_REMOTE_ID: AtomicU32 = AtomicU32::new(1);
+
+fn next_remote_id() -> u32 {
+  loop {
+    let id = NEXT_REMOTE_ID.fetch_add(1, Ordering::Relaxed) & (REMOTE_PID_FLAG - 1);
+    if id != 0 {
+      return id;
+    }
+  }
+}
+
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+  // controller -> agent
+  Spawn = 1,
+  Signal = 2,
+  Stdin = 3,
+  StdinClose = 4,
+  Auth = 5,
+  // agent -> controller
+  Spawned = 16,
+  Stdout = 17,
+  Stderr = 18,
+  Exit = 19,
+  Error = 20,
+}
+
+impl TryFrom<u8> for FrameKind {
+  type Error = CoreError;
+
+  fn try_from(value: u8) -> Result<Self, CoreError> {
+    Ok(match value {
+      1 => FrameKind::Spawn,
+      2 => FrameKind::Signal,
+      3 => FrameKind::Stdin,
+      4 => FrameKind::StdinClose,
+      5 => FrameKind::Auth,
+      16 => FrameKind::Spawned,
+      17 => FrameKind::Stdout,
+      18 => FrameKind::Stderr,
+      19 => FrameKind::Exit,
+      20 => FrameKind::Error,
+      k => {
+        return Err(CoreError::InvalidState(format!(
+          "unknown remote frame: {k}"
+        )));
+      }
+    })
+  }
+}
+
+/// Frames are `[kind: u8][len: u32 be][payload]`.
+pub fn encode_frame(kind: FrameKind, payload: &[u8]) -> Vec<u8> {
+  let mut out = Vec::with_capacity(5 + payload.len());
+  out.push(kind as u8);
+  out.extend_from_slice(&(payload.len() as u32).to_be_bytes());
+  out.extend_from_slice(payload);
+  out
+}
+
+pub fn read_frame(reader: &mut impl Read) -> CoreResult<Option<(FrameKind, Vec<u8>)>> {
+  let mut header = [0u8; 5];
+  match reader.read_exact(&mut header) {
+    Ok(()) => {}
+    Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+    Err(e) => return Err(e.into()),
+  }
+  let kind = FrameKind::try_from(header[0])?;
+  let len = u32::from_be_bytes([header[1], header[2], header[3], header[4]]) as usize;
+  if len > MAX_FRAME_LEN {
+    return Err(CoreError::InvalidState(format!(
+      "remote frame of {len} bytes exceeds {MAX_FRAME_LEN}"
+    )));
+  }
+  let mut payload = vec![0u8; len];
+  reader.read_exact(&mut payload)?;
+  Ok(Some((kind, payload)))
+}
+
+fn frame_i32(payload: &[u8]) -> CoreResult<i32> {
+  let bytes: [u8; 4] = payload
+    .try_into()
+    .map_err(|_| CoreError::InvalidState("malformed remote frame".into()))?;
+  Ok(i32::from_be_bytes(bytes))
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RemoteSpawn {
+  pub exec: String,
+  pub args: Vec<String>,
+  pub envs: Vec<(String, String)>,
+  pub working_dir: Option<String>,
+}
+
+impl RemoteSpawn {
+  pub fn encode(&self) -> CoreResult<Vec<u8>> {
+    bincode_next::serde::encode_to_vec(self, bincode_next::config::standard())
+      .map_err(CoreError::custom)
+  }
+
+  pub fn decode(bytes: &[u8]) -> CoreResult<Self> {
+    bincode_next::serde::decode_from_slice(bytes, bincode_next::config::standard())
+      .map(|(spawn, _)| spawn)
+      .map_err(CoreError::custom)
+  }
+}
+
+struct FrameWriter {
+  tx: Sender<Vec<u8>>,
+}
+
+impl Write for FrameWriter {
+  fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+    let buf = &buf[..buf.len().min(MAX_FRAME_LEN)];
+    self
+      .tx
+      .send(encode_frame(FrameKind::Stdin, buf))
+      .map_err(|_| std::io::Error::from(std::io::ErrorKind::BrokenPipe))?;
+    Ok(buf.len())
+  }
+
+  fn flush(&mut self) -> std::io::Result<()> {
+    Ok(())
+  }
+}
+
+impl Drop for FrameWriter {
+  fn drop(&mut self) {
+    let _ = self.tx.send(encode_frame(FrameKind::StdinClose, &[]));
+  }
+}
+
+/// Constant-time, so a wrong token doesn't leak how much of it matched.
+fn token_matches(expected: &[u8], given: &[u8]) -> bool {
+  expected.len() == given.len()
+    && expected
+      .iter()
+      .zip(given)
+      .fold(0, |acc, (a, b)| acc | (a ^ b))
+      == 0
+}
 
 pub struct RemoteHandle {
   pub tx: Sender<Vec<u8>>,
-  pub stdout_rx: Arc<Mutex<Receiver<Vec<u8>>>>,
-  pub stderr_rx: Arc<Mutex<Receiver<Vec<u8>>>>,
-  pub exit_rx: Arc<Mutex<Receiver<i32>>>,
-  pub remote_pid: Option<u32>,
+  pub stdout_rx: Option<Mutex<Receiver<Vec<u8>>>>,
+  pub stderr_rx: Option<Mutex<Receiver<Vec<u8>>>>,
+  pub exit_fd: Option<OwnedFd>,
+  /// Rind-side id, the pid map sees it tagged with `REMOTE_PID_FLAG`.
+  pub id: u32,
+  pub host: String,
+}
+
+impl RemoteHandle {
+  /// Connects to an agent, authenticates with `token` and spawns `spawn` on
+  /// it, waiting for the agent's answer. The exit code is written to
+  /// `exit_fd` as a big-endian i32 once the remote process is gone.
+  pub fn connect(addr: &str, token: Option<&str>, spawn: &RemoteSpawn) -> CoreResult<Self> {
+    let (tx, rx) = mpsc::channel();
+    let handle = Self::start(addr, token.map(String::from), spawn, move |spawned| {
+      let _ = tx.send(spawned);
+    })?;
+    rx.recv()
+      .map_err(|_| CoreError::InvalidState(format!("remote agent {addr} went away")))??;
+    Ok(handle)
+  }
+
+  /// Like `connect`, but returns right away: connecting and the handshake
+  /// run on a worker thread, which hands `report` the remote pid or the
+  /// error. A failed handshake shows up as an exit with `LOST_EXIT_CODE`.
+  pub fn start(
+    addr: &str,
+    token: Option<String>,
+    spawn: &RemoteSpawn,
+    report: impl FnOnce(CoreResult<u32>) + Send + 'static,
+  ) -> CoreResult<Self> {
+    let spawn = spawn.encode()?;
+    if spawn.len() > MAX_FRAME_LEN {
+      return Err(CoreError::InvalidState(format!(
+        "remote spawn for {addr} exceeds {MAX_FRAME_LEN} bytes"
+      )));
+    }
+
+    let (exit_read, exit_write) = pipe2(OFlag::O_CLOEXEC).map_err(CoreError::System)?;
+    let (tx, frames_rx) = mpsc::channel::<Vec<u8>>();
+    let (stdout_tx, stdout_rx) = mpsc::channel::<Vec<u8>>();
+    let (stderr_tx, stderr_rx) = mpsc::channel::<Vec<u8>>();
+
+    let host = addr.to_string();
+    std::thread::spawn(move || {
+      let mut exit_write = std::fs::File::from(exit_write);
+      let code = match open_remote(&host, token.as_deref(), &spawn) {
+        Ok((stream, remote_pid)) => {
+          report(Ok(remote_pid));
+          forward_frames(stream, frames_rx, stdout_tx, stderr_tx)
+        }
+        Err(e) => {
+          report(Err(e));
+          LOST_EXIT_CODE
+        }
+      };
+      let _ = exit_write.write_all(&code.to_be_bytes());
+    });
+
+    Ok(Self {
+      tx,
+      stdout_rx: Some(Mutex::new(stdout_rx)),
+      stderr_rx: Some(Mutex::new(stderr_rx)),
+      exit_fd: Some(exit_read),
+      id: next_remote_id(),
+      host: addr.to_string(),
+    })
+  }
+
+  pub fn take_exit_fd(&mut self) -> Option<OwnedFd> {
+    self.exit_fd.take()
+  }
 }
 
 impl InstanceHandle for RemoteHandle {
   fn pid(&self) -> Option<u32> {
-    self.remote_pid
+    Some(self.id | REMOTE_PID_FLAG)
   }
 
-  fn kill(&mut self, _signal: Signal) -> CoreResult<Void> {
+  fn kill(&mut self, signal: Signal) -> CoreResult<Void> {
+    self
+      .tx
+      .send(encode_frame(
+        FrameKind::Signal,
+        &(signal as i32).to_be_bytes(),
+      ))
+      .map_err(|_| CoreError::InvalidState("remote connection closed".into()))?;
     Ok(Void)
   }
 
   fn take_stdout(&mut self) -> Option<Box<dyn std::io::Read + Send>> {
-    None
+    let rx = self.stdout_rx.take()?.into_inner().ok()?;
+    Some(Box::new(ChannelReader::new(rx)))
   }
 
   fn take_stderr(&mut self) -> Option<Box<dyn std::io::Read + Send>> {
-    None
+    let rx = self.stderr_rx.take()?.into_inner().ok()?;
+    Some(Box::new(ChannelReader::new(rx)))
   }
 
   fn take_stdin(&mut self) -> Option<Box<dyn std::io::Write + Send>> {
-    None
+    Some(Box::new(FrameWriter {
+      tx: self.tx.clone(),
+    }))
   }
 }
 
@@ -43,10 +290,342 @@ impl Executor for RemoteExecutor {
   }
 
   fn spawn(&self, ctx: ExecutorContext) -> CoreResult<Box<dyn InstanceHandle>> {
-    let addr = ctx.run.exec.as_str();
-    let mut _stream = TcpStream::connect(addr)?;
-    Err(CoreError::Custom(
-      "RemoteExecutor implementation in progress".into(),
-    ))
+    let addr = ctx.run.remote.as_ref().ok_or_else(|| {
+      CoreError::InvalidState(format!(
+        "service '{}' uses the remote executor without run.remote",
+        ctx.service.metadata.name
+      ))
+    })?;
+
+    let spawn = RemoteSpawn {
+      exec: ctx.run.exec.to_string(),
+      args: ctx.args.iter().map(|x| x.to_string()).collect(),
+      envs: ctx
+        .envs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect(),
+      working_dir: ctx
+        .service
+        .metadata
+        .working_dir
+        .as_ref()
+        .map(|x| x.to_string()),
+    };
+
+    let token = match &ctx.run.remote_token {
+      Some(path) => Some(read_token(path.as_str())?),
+      None => None,
+    };
+    let log = ctx.log.clone();
+    let service = ctx.registry_key.to_string();
+    let remote = addr.to_string();
+    let mut handle = RemoteHandle::start(addr.as_str(), token, &spawn, move |spawned| {
+      let mut fields = std::collections::HashMap::new();
+      fields.insert("service".to_string(), service);
+      fields.insert("remote".to_string(), remote);
+      match spawned {
+        Ok(remote_pid) => {
+          fields.insert("pid".to_string(), remote_pid.to_string());
+          log.log(
+            LogLevel::Info,
+            "remote-executor",
+            "remote process spawned",
+            fields,
+          );
+        }
+        Err(e) => {
+          fields.insert("error".to_string(), e.to_string());
+          log.log(
+            LogLevel::Error,
+            "remote-executor",
+            "remote spawn failed",
+            fields,
+          );
+        }
+      }
+    })?;
+    let pid = handle.pid().unwrap_or_default();
+    if let Some(exit_fd) = handle.take_exit_fd() {
+      watch_exit_fd(ctx.resources, exit_fd, pid);
+    }
+
+    Ok(Box::new(handle))
+  }
+}
+
+fn connect_timeout(addr: &str) -> CoreResult<TcpStream> {
+  let mut last = None;
+  for sockaddr in addr.to_socket_addrs()? {
+    match TcpStream::connect_timeout(&sockaddr, REMOTE_HANDSHAKE_TIMEOUT) {
+      Ok(stream) => return Ok(stream),
+      Err(e) => last = Some(e),
+    }
+  }
+  Err(match last {
+    Some(e) => e.into(),
+    None => CoreError::InvalidState(format!("remote address {addr} did not resolve")),
+  })
+}
+
+/// Connects, authenticates and sends the spawn frame, returning the stream
+/// and the remote pid once the agent acknowledged it.
+fn open_remote(addr: &str, token: Option<&str>, spawn: &[u8]) -> CoreResult<(TcpStream, u32)> {
+  let mut stream = connect_timeout(addr)?;
+  stream.set_nodelay(true)?;
+  stream.set_read_timeout(Some(REMOTE_HANDSHAKE_TIMEOUT))?;
+  stream.set_write_timeout(Some(REMOTE_HANDSHAKE_TIMEOUT))?;
+  if let Some(token) = token {
+    stream.write_all(&encode_frame(FrameKind::Auth, token.as_bytes()))?;
+  }
+  stream.write_all(&encode_frame(FrameKind::Spawn, spawn))?;
+
+  let remote_pid = match read_frame(&mut stream)? {
+    Some((FrameKind::Spawned, payload)) => frame_i32(&payload)? as u32,
+    Some((FrameKind::Error, payload)) => {
+      return Err(CoreError::Custom(format!(
+        "remote agent {addr}: {}",
+        String::from_utf8_lossy(&payload)
+      )));
+    }
+    _ => {
+      return Err(CoreError::InvalidState(format!(
+        "remote agent {addr} did not acknowledge spawn"
+      )));
+    }
+  };
+  // past the handshake the process may stay silent for as long as it likes
+  stream.set_read_timeout(None)?;
+  stream.set_write_timeout(None)?;
+  Ok((stream, remote_pid))
+}
+
+/// Sends queued frames to the agent and routes its output until the `Exit`
+/// frame, whose code is returned.
+fn forward_frames(
+  mut stream: TcpStream,
+  frames_rx: Receiver<Vec<u8>>,
+  stdout_tx: Sender<Vec<u8>>,
+  stderr_tx: Sender<Vec<u8>>,
+) -> i32 {
+  let Ok(mut writer) = stream.try_clone() else {
+    return LOST_EXIT_CODE;
+  };
+  std::thread::spawn(move || {
+    while let Ok(frame) = frames_rx.recv() {
+      if writer.write_all(&frame).is_err() {
+        break;
+      }
+    }
+  });
+
+  let mut code = LOST_EXIT_CODE;
+  while let Ok(Some((kind, payload))) = read_frame(&mut stream) {
+    match kind {
+      FrameKind::Stdout => {
+        let _ = stdout_tx.send(payload);
+      }
+      FrameKind::Stderr => {
+        let _ = stderr_tx.send(payload);
+      }
+      FrameKind::Exit => {
+        code = frame_i32(&payload).unwrap_or(LOST_EXIT_CODE);
+        break;
+      }
+      _ => {}
+    }
+  }
+  let _ = stream.shutdown(std::net::Shutdown::Both);
+  code
+}
+
+/// Reads a pre-shared token from `path`, ignoring surrounding whitespace.
+pub fn read_token(path: &str) -> CoreResult<String> {
+  let token = std::fs::read_to_string(path)?.trim().to_string();
+  if token.is_empty() {
+    return Err(CoreError::InvalidState(format!(
+      "remote token file {path} is empty"
+    )));
+  }
+  Ok(token)
+}
+
+/// Far side of the remote executor: every connection carries exactly one
+/// process, from the spawn frame to its exit frame. With a token, the first
+/// frame of every connection must be an `Auth` frame carrying it.
+pub struct RemoteAgent {
+  listener: TcpListener,
+  token: Option<Arc<str>>,
+}
+
+impl RemoteAgent {
+  /// Binds the agent. Without a token it only accepts loopback addresses, as
+  /// anyone reaching it could run anything as the agent's user.
+  pub fn bind(addr: &str, token: Option<String>) -> CoreResult<Self> {
+    let listener = TcpListener::bind(addr)?;
+    if token.is_none() && !listener.local_addr()?.ip().is_loopback() {
+      return Err(CoreError::InvalidState(format!(
+        "refusing to listen on {addr} without a token"
+      )));
+    }
+    Ok(Self {
+      listener,
+      token: token.map(Arc::from),
+    })
+  }
+
+  pub fn local_addr(&self) -> CoreResult<std::net::SocketAddr> {
+    Ok(self.listener.local_addr()?)
+  }
+
+  pub fn serve(&self) -> CoreResult<Void> {
+    for stream in self.listener.incoming() {
+      let Ok(stream) = stream else { continue };
+      let token = self.token.clone();
+      std::thread::spawn(move || {
+        let _ = serve_connection(stream, token.as_deref());
+      });
+    }
+    Ok(Void)
+  }
+}
+
+fn send_frame(writer: &Arc<Mutex<TcpStream>>, kind: FrameKind, payload: &[u8]) -> bool {
+  let Ok(mut stream) = writer.lock() else {
+    return false;
+  };
+  stream.write_all(&encode_frame(kind, payload)).is_ok()
+}
+
+fn pump_output(
+  mut reader: impl Read + Send + 'static,
+  writer: Arc<Mutex<TcpStream>>,
+  kind: FrameKind,
+) -> std::thread::JoinHandle<()> {
+  std::thread::spawn(move || {
+    let mut buf = [0u8; 4096];
+    loop {
+      match reader.read(&mut buf) {
+        Ok(0) | Err(_) => break,
+        Ok(n) => {
+          if !send_frame(&writer, kind, &buf[..n]) {
+            break;
+          }
+        }
+      }
+    }
+  })
+}
+
+/// Reads the `Auth` (when `token` is set) and `Spawn` frames, answering
+/// with an `Error` frame if either is missing or wrong.
+fn handshake(stream: &mut TcpStream, token: Option<&str>) -> CoreResult<Vec<u8>> {
+  let refuse = |stream: &mut TcpStream, message: &str| {
+    let _ = stream.write_all(&encode_frame(FrameKind::Error, message.as_bytes()));
+    Err(CoreError::InvalidState(message.into()))
+  };
+
+  let mut frame = read_frame(stream)?;
+  if let Some((FrameKind::Auth, given)) = &frame {
+    if token.is_some_and(|token| !token_matches(token.as_bytes(), given)) {
+      return refuse(stream, "authentication failed");
+    }
+    frame = read_frame(stream)?;
+  } else if token.is_some() {
+    return refuse(stream, "authentication required");
+  }
+
+  match frame {
+    Some((FrameKind::Spawn, payload)) => Ok(payload),
+    _ => refuse(stream, "expected spawn frame"),
+  }
+}
+
+pub fn serve_connection(mut stream: TcpStream, token: Option<&str>) -> CoreResult<Void> {
+  stream.set_nodelay(true)?;
+  stream.set_read_timeout(Some(REMOTE_HANDSHAKE_TIMEOUT))?;
+  let payload = handshake(&mut stream, token)?;
+  stream.set_read_timeout(None)?;
+  let writer = Arc::new(Mutex::new(stream.try_clone()?));
+
+  let spawned = RemoteSpawn::decode(&payload).and_then(|spawn| {
+    let mut cmd = Command::new(&spawn.exec);
+    cmd
+      .args(&spawn.args)
+      .envs(spawn.envs.iter().map(|(k, v)| (k, v)))
+      .stdin(Stdio::piped())
+      .stdout(Stdio::piped())
+      .stderr(Stdio::piped())
+      .process_group(0);
+    if let Some(dir) = &spawn.working_dir {
+      cmd.current_dir(dir);
+    }
+    Ok(cmd.spawn()?)
+  });
+
+  let mut child = match spawned {
+    Ok(child) => child,
+    Err(e) => {
+      send_frame(&writer, FrameKind::Error, e.to_string().as_bytes());
+      return Err(e);
+    }
+  };
+  let pid = child.id() as i32;
+  send_frame(&writer, FrameKind::Spawned, &pid.to_be_bytes());
+
+  let mut pumps = Vec::new();
+  if let Some(stdout) = child.stdout.take() {
+    pumps.push(pump_output(stdout, writer.clone(), FrameKind::Stdout));
+  }
+  if let Some(stderr) = child.stderr.take() {
+    pumps.push(pump_output(stderr, writer.clone(), FrameKind::Stderr));
+  }
+
+  let mut stdin = child.stdin.take();
+  let exited = Arc::new(AtomicBool::new(false));
+  let exited_reader = exited.clone();
+  std::thread::spawn(move || {
+    loop {
+      match read_frame(&mut stream) {
+        Ok(Some((FrameKind::Signal, payload))) => {
+          if let Ok(sig) =
+            frame_i32(&payload).and_then(|s| Signal::try_from(s).map_err(CoreError::System))
+          {
+            let _ = kill(Pid::from_raw(-pid), sig);
+          }
+        }
+        Ok(Some((FrameKind::Stdin, payload))) => {
+          if let Some(stdin) = stdin.as_mut() {
+            let _ = stdin.write_all(&payload);
+          }
+        }
+        Ok(Some((FrameKind::StdinClose, _))) => {
+          stdin = None;
+        }
+        Ok(Some(_)) => {}
+        // The controller went away, take the process down with it.
+        Ok(None) | Err(_) => {
+          if !exited_reader.load(Ordering::SeqCst) {
+            let _ = kill(Pid::from_raw(-pid), Signal::SIGTERM);
+          }
+          break;
+        }
+      }
+    }
+  });
+
+  let status = child.wait()?;
+  exited.store(true, Ordering::SeqCst);
+  for pump in pumps {
+    let _ = pump.join();
+  }
+  let code = status
+    .code()
+    .or_else(|| status.signal().map(|s| 128 + s))
+    .unwrap_or(-1);
+  send_frame(&writer, FrameKind::Exit, &code.to_be_bytes());
+  if let Ok(stream) = writer.lock() {
+    let _ = stream.shutdown(std::net::Shutdown::Both);
   }
+  Ok(Void)
 }
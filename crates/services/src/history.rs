@@ -0,0 +1,112 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use rind_core::prelude::*;
+
+use crate::services::{ServiceRuntime, StopOutcome};
+
+/// Exit records kept per service; the oldest is dropped first.
+pub const EXIT_HISTORY_LEN: usize = 32;
+
+/// How an instance's main process ended, as reported by `waitpid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitStatus {
+  Code(i32),
+  Signal { signal: i32, core_dumped: bool },
+}
+
+impl ExitStatus {
+  /// Shell-style exit code; signals map to `128 + signal`.
+  pub fn code(&self) -> i32 {
+    match *self {
+      Self::Code(code) => code,
+      Self::Signal { signal, .. } => 128 + signal,
+    }
+  }
+}
+
+/// What brought an instance down.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitTrigger {
+  /// The process exited or crashed on its own.
+  #[default]
+  Exited,
+  /// Stopped on request (`sysunit stop`, a stop trigger, shutdown).
+  Manual,
+  /// A `stop-on` condition matched or the instance's branch went away.
+  Flow,
+  /// A service it runs `after` stopped or exited.
+  Dependency,
+  /// The watchdog expired.
+  Watchdog,
+  /// The kernel OOM-killed a process in the instance cgroup.
+  Oom,
+  /// A notify service never sent `READY=1` within its start timeout.
+  StartTimeout,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExitRecord {
+  pub instance: Ustr,
+  pub status: ExitStatus,
+  pub trigger: ExitTrigger,
+  pub stop: Option<StopOutcome>,
+  pub runtime_ms: u64,
+  /// Seconds since the Unix epoch.
+  pub timestamp: u64,
+}
+
+impl ExitRecord {
+  pub fn new(
+    instance: Ustr,
+    status: ExitStatus,
+    trigger: ExitTrigger,
+    stop: Option<StopOutcome>,
+    runtime: Duration,
+  ) -> Self {
+    Self {
+      instance,
+      status,
+      trigger,
+      stop,
+      runtime_ms: runtime.as_millis() as u64,
+      timestamp: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs()),
+    }
+  }
+}
+
+/// Bounded exit history per service, kept as a registry singleton so it
+/// outlives the `Service` instances dropped by `reload_units`.
+#[derive(Debug, Default)]
+pub struct ExitHistory {
+  records: HashMap<Ustr, VecDeque<ExitRecord>>,
+}
+
+impl ExitHistory {
+  pub const KEY: &str = "runtime:exit_history";
+
+  pub fn push(&mut self, service: &str, record: ExitRecord) {
+    let records = self
+      .records
+      .entry(ServiceRuntime::ensure_scoped_name(service))
+      .or_default();
+    if records.len() >= EXIT_HISTORY_LEN {
+      records.pop_front();
+    }
+    records.push_back(record);
+  }
+
+  /// Records for `service`, oldest first.
+  pub fn get(&self, service: &str) -> impl Iterator<Item = &ExitRecord> {
+    self
+      .records
+      .get(&ServiceRuntime::ensure_scoped_name(service))
+      .into_iter()
+      .flatten()
+  }
+}
@@ -1,15 +1,25 @@
+pub mod calendar;
+pub mod cgroups;
 pub mod events;
 pub mod executors;
+pub mod history;
 pub mod namespaces;
+pub mod notify;
 pub mod reaper;
+pub mod seccomp;
 pub mod services;
 pub mod sockets;
 pub mod timers;
 
+pub use calendar::*;
+pub use cgroups::*;
 pub use events::*;
 pub use executors::*;
+pub use history::*;
 pub use namespaces::*;
+pub use notify::*;
 pub use reaper::*;
+pub use seccomp::*;
 pub use services::*;
 pub use sockets::*;
 pub use timers::*;
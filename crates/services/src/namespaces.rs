@@ -1,15 +1,25 @@
-use crate::executors::{InstanceHandle, NamespaceNetworkConfig, SupervisorHandle};
-use crate::services::{
-  CapabilityPolicy, SeccompPolicy, ServiceCgroup, ServiceIsolation, ServiceNamespaces,
+use crate::cgroups::apply_cgroup;
+use crate::executors::{
+  InstanceHandle, NamespaceNetworkConfig, SupervisorHandle, install_listen_fds,
 };
+use crate::seccomp::SeccompProgram;
+use crate::services::{CapabilityPolicy, ServiceIsolation, ServiceNamespaces};
+use nix::mount::{MntFlags, MsFlags, mount, umount2};
+use nix::sys::statvfs::{FsFlags, statvfs};
+use nix::unistd::{chdir, pivot_root};
 use rind_core::prelude::*;
-use rind_primitives::mounts::{NamespaceMountEntry, mount_all_in_namespace};
+use rind_primitives::mounts::{
+  NamespaceMountEntry, mount_all_in_namespace, mount_all_in_root, propagation_flag, set_propagation,
+};
+use rind_primitives::utils::netlink;
 use rind_primitives::utils::networking::setup_namespace_loopback;
 use std::collections::{HashMap, HashSet};
 use std::ffi::CString;
 use std::fs::File;
+use std::net::IpAddr;
 use std::os::fd::{AsRawFd, FromRawFd, RawFd};
-use std::path::PathBuf;
+use std::os::unix::fs::symlink;
+use std::path::{Path, PathBuf};
 use std::sync::LazyLock;
 use std::sync::Mutex;
 
@@ -43,12 +53,116 @@ pub fn namespace_unshare_flags(ns: &ServiceNamespaces) -> libc::c_int {
   flags
 }
 
-pub fn validate_namespaces(ns: Option<&ServiceNamespaces>) -> CoreResult<Void> {
-  let _ = ns;
+fn validate_rootfs(ns: &ServiceNamespaces) -> CoreResult<Void> {
+  if let Some(propagation) = &ns.propagation
+    && propagation_flag(propagation).is_none()
+  {
+    return Err(CoreError::InvalidState(format!(
+      "unknown mount propagation '{propagation}'"
+    )));
+  }
+  let Some(rootfs) = &ns.rootfs else {
+    return match ns.overlay {
+      Some(_) => Err(CoreError::InvalidState(
+        "namespaces.overlay needs a rootfs".to_string(),
+      )),
+      None => Ok(Void),
+    };
+  };
+  let mut dirs = vec![rootfs.as_str()];
+  if let Some(overlay) = &ns.overlay {
+    dirs.extend([overlay.upper.as_str(), overlay.work.as_str()]);
+  }
+  for dir in dirs {
+    let path = Path::new(dir);
+    if !path.is_absolute() || !path.is_dir() {
+      return Err(CoreError::InvalidState(format!(
+        "rootfs directory '{dir}' must be an existing absolute path"
+      )));
+    }
+  }
+  // pivot_root refuses to move a root whose mount is shared.
+  if ns
+    .propagation
+    .as_ref()
+    .is_some_and(|p| p.as_str() == "shared")
+  {
+    return Err(CoreError::InvalidState(
+      "a rootfs needs private or slave mount propagation".to_string(),
+    ));
+  }
+  Ok(Void)
+}
+
+pub fn validate_namespaces(
+  ns: Option<&ServiceNamespaces>,
+  networks: &[NamespaceNetworkConfig],
+) -> CoreResult<Void> {
+  if let Some(ns) = ns {
+    validate_rootfs(ns)?;
+  }
+  if networks.is_empty() {
+    return Ok(Void);
+  }
+  if !ns.is_some_and(|ns| ns.net) {
+    return Err(CoreError::InvalidState(
+      "service network links need `namespaces.net = true`".to_string(),
+    ));
+  }
+  for net in networks {
+    match net.method.as_str() {
+      "static" | "none" => {}
+      other => {
+        return Err(CoreError::InvalidState(format!(
+          "namespace network '{}': unsupported method '{other}'",
+          net.name
+        )));
+      }
+    }
+    for cidr in [&net.address, &net.host_address].into_iter().flatten() {
+      if netlink::parse_cidr(cidr).is_none() {
+        return Err(CoreError::InvalidState(format!(
+          "namespace network '{}': invalid address '{cidr}'",
+          net.name
+        )));
+      }
+    }
+    if let Some(gateway) = &net.gateway
+      && gateway.parse::<IpAddr>().is_err()
+    {
+      return Err(CoreError::InvalidState(format!(
+        "namespace network '{}': invalid gateway '{gateway}'",
+        net.name
+      )));
+    }
+    if net.nat
+      && !net
+        .address
+        .as_deref()
+        .and_then(netlink::parse_cidr)
+        .is_some_and(|(addr, _)| addr.is_ipv4())
+    {
+      return Err(CoreError::InvalidState(format!(
+        "namespace network '{}': nat needs an IPv4 address",
+        net.name
+      )));
+    }
+  }
   Ok(Void)
 }
 
-pub unsafe fn apply_namespace_setup(ns: Option<&ServiceNamespaces>) -> std::io::Result<()> {
+/// Unshares the namespaces in `ns` and sets up the hostname, mounts and
+/// rootfs for the calling process.
+///
+/// # Safety
+///
+/// Must run in a freshly forked, single-threaded child before it execs, like
+/// a `pre_exec` hook or the namespace supervisor. It changes process-wide
+/// namespace and mount state under every other thread's feet otherwise.
+pub unsafe fn apply_namespace_setup(
+  ns: Option<&ServiceNamespaces>,
+  mounts: &[NamespaceMountEntry],
+) -> std::io::Result<()> {
   let Some(ns) = ns else {
     return Ok(());
   };
@@ -58,20 +172,10 @@ pub unsafe fn apply_namespace_setup(ns: Option<&ServiceNamespaces>) -> std::io::
     return Err(std::io::Error::last_os_error());
   }
 
-  if ns.mount || ns.mount_private || ns.rootfs.is_some() {
-    let target = CString::new("/").expect("literal path");
-    if unsafe {
-      libc::mount(
-        std::ptr::null(),
-        target.as_ptr(),
-        std::ptr::null(),
-        (libc::MS_REC | libc::MS_PRIVATE) as libc::c_ulong,
-        std::ptr::null(),
-      )
-    } < 0
-    {
-      return Err(std::io::Error::last_os_error());
-    }
+  let mount_ns = ns.mount || ns.mount_private || ns.rootfs.is_some();
+  if mount_ns {
+    let propagation = ns.propagation.as_ref().map_or("private", |p| p.as_str());
+    set_propagation(Path::new("/"), propagation, true)?;
   }
 
   if let Some(hostname) = &ns.hostname {
@@ -82,17 +186,221 @@ pub unsafe fn apply_namespace_setup(ns: Option<&ServiceNamespaces>) -> std::io::
   }
 
   if let Some(rootfs) = &ns.rootfs {
-    let root = CString::new(rootfs.as_str())
-      .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidInput))?;
-    let slash = CString::new("/").expect("literal path");
-    if unsafe { libc::chroot(root.as_ptr()) } < 0 {
-      return Err(std::io::Error::last_os_error());
+    enter_rootfs(ns, Path::new(rootfs.as_str()), mounts)?;
+  } else if mount_ns {
+    mount_all_in_namespace(mounts)?;
+  }
+
+  Ok(())
+}
+
+const DEV_NODES: [&str; 6] = ["null", "zero", "full", "random", "urandom", "tty"];
+
+const DEV_LINKS: [(&str, &str); 5] = [
+  ("fd", "/proc/self/fd"),
+  ("stdin", "/proc/self/fd/0"),
+  ("stdout", "/proc/self/fd/1"),
+  ("stderr", "/proc/self/fd/2"),
+  ("ptmx", "pts/ptmx"),
+];
+
+/// Builds the service root and pivots into it. The image is bound read-only,
+/// or overlaid when an upper dir is configured, then the API filesystems and
+/// namespace mounts are placed on top before the old root is detached.
+fn enter_rootfs(
+  ns: &ServiceNamespaces,
+  root: &Path,
+  mounts: &[NamespaceMountEntry],
+) -> std::io::Result<()> {
+  match &ns.overlay {
+    Some(overlay) => {
+      let data = format!(
+        "lowerdir={},upperdir={},workdir={}",
+        root.display(),
+        overlay.upper,
+        overlay.work
+      );
+      mount(
+        Some("overlay"),
+        root,
+        Some("overlay"),
+        MsFlags::empty(),
+        Some(data.as_str()),
+      )?;
     }
-    if unsafe { libc::chdir(slash.as_ptr()) } < 0 {
-      return Err(std::io::Error::last_os_error());
+    None => mount::<Path, Path, str, str>(
+      Some(root),
+      root,
+      None,
+      MsFlags::MS_BIND | MsFlags::MS_REC,
+      None,
+    )?,
+  }
+
+  mount_api_filesystems(root)?;
+  mount_all_in_root(root, mounts)?;
+  if ns.overlay.is_none() {
+    remount_readonly(root)?;
+  }
+
+  // Stacking the old root under the new one avoids needing a put_old
+  // directory inside a read-only image.
+  chdir(root)?;
+  pivot_root(".", ".")?;
+  umount2(".", MntFlags::MNT_DETACH)?;
+  chdir("/")?;
+  Ok(())
+}
+
+/// Mounts a fresh `/proc`, binds a read-only `/sys` and gives the root a
+/// private `/dev` holding only the basic device nodes. Directories missing
+/// from the image are skipped.
+fn mount_api_filesystems(root: &Path) -> std::io::Result<()> {
+  let proc = root.join("proc");
+  if proc.is_dir() {
+    mount(
+      Some("proc"),
+      &proc,
+      Some("proc"),
+      MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC,
+      None::<&str>,
+    )?;
+  }
+
+  let sys = root.join("sys");
+  if sys.is_dir() {
+    bind_mount(Path::new("/sys"), &sys)?;
+    // MS_REC brings cgroup, debugfs and friends along, each still writable
+    for mount_point in mounts_under(&sys)? {
+      remount_readonly(&mount_point)?;
     }
   }
 
+  let dev = root.join("dev");
+  if !dev.is_dir() {
+    return Ok(());
+  }
+  mount(
+    Some("tmpfs"),
+    &dev,
+    Some("tmpfs"),
+    MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC,
+    Some("mode=755"),
+  )?;
+  for node in DEV_NODES {
+    let source = Path::new("/dev").join(node);
+    if !source.exists() {
+      continue;
+    }
+    let target = dev.join(node);
+    File::create(&target)?;
+    mount::<Path, Path, str, str>(Some(&source), &target, None, MsFlags::MS_BIND, None)?;
+  }
+  for (name, target) in DEV_LINKS {
+    symlink(target, dev.join(name))?;
+  }
+
+  std::fs::create_dir(dev.join("pts"))?;
+  mount(
+    Some("devpts"),
+    &dev.join("pts"),
+    Some("devpts"),
+    MsFlags::MS_NOSUID | MsFlags::MS_NOEXEC,
+    Some("newinstance,ptmxmode=0666,mode=0620"),
+  )?;
+  std::fs::create_dir(dev.join("shm"))?;
+  mount(
+    Some("tmpfs"),
+    &dev.join("shm"),
+    Some("tmpfs"),
+    MsFlags::MS_NOSUID | MsFlags::MS_NODEV,
+    Some("mode=1777"),
+  )?;
+  Ok(())
+}
+
+fn bind_mount(source: &Path, target: &Path) -> std::io::Result<()> {
+  mount::<Path, Path, str, str>(
+    Some(source),
+    target,
+    None,
+    MsFlags::MS_BIND | MsFlags::MS_REC,
+    None,
+  )?;
+  Ok(())
+}
+
+/// Lists `path` and every mount point below it, parents first.
+fn mounts_under(path: &Path) -> std::io::Result<Vec<PathBuf>> {
+  let mountinfo = std::fs::read_to_string("/proc/self/mountinfo")?;
+  Ok(
+    mountinfo
+      .lines()
+      .filter_map(|line| line.split(' ').nth(4))
+      .map(|field| PathBuf::from(unescape_mount_path(field)))
+      .filter(|mount_point| mount_point.starts_with(path))
+      .collect(),
+  )
+}
+
+/// Undoes the octal escapes (`\040` for a space) of a mountinfo path.
+fn unescape_mount_path(field: &str) -> String {
+  let bytes = field.as_bytes();
+  let mut out = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    let escape = bytes.get(i + 1..i + 4).filter(|_| bytes[i] == b'\\');
+    match escape
+      .and_then(|digits| std::str::from_utf8(digits).ok())
+      .and_then(|digits| u8::from_str_radix(digits, 8).ok())
+    {
+      Some(byte) => {
+        out.push(byte);
+        i += 4;
+      }
+      None => {
+        out.push(bytes[i]);
+        i += 1;
+      }
+    }
+  }
+  String::from_utf8_lossy(&out).into_owned()
+}
+
+/// Remounts a bind mount read-only. Flags already on the mount are carried
+/// over because a user namespace may not clear locked ones.
+fn remount_readonly(path: &Path) -> std::io::Result<()> {
+  let current = statvfs(path)?.flags();
+  let mut flags = MsFlags::MS_REMOUNT | MsFlags::MS_BIND | MsFlags::MS_RDONLY;
+  for (st, ms) in [
+    (FsFlags::ST_NOSUID, MsFlags::MS_NOSUID),
+    (FsFlags::ST_NODEV, MsFlags::MS_NODEV),
+    (FsFlags::ST_NOEXEC, MsFlags::MS_NOEXEC),
+    (FsFlags::ST_NOATIME, MsFlags::MS_NOATIME),
+    (FsFlags::ST_NODIRATIME, MsFlags::MS_NODIRATIME),
+    (FsFlags::ST_RELATIME, MsFlags::MS_RELATIME),
+  ] {
+    if current.contains(st) {
+      flags |= ms;
+    }
+  }
+  mount::<str, Path, str, str>(None, path, None, flags, None)?;
+  Ok(())
+}
+
+/// Mounts a `/proc` for the new pid namespace over the rootfs one. Runs
+/// in the namespace's first process.
+fn mount_pid_proc() -> std::io::Result<()> {
+  if !Path::new("/proc").is_dir() {
+    return Ok(());
+  }
+  mount(
+    Some("proc"),
+    "/proc",
+    Some("proc"),
+    MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC,
+    None::<&str>,
+  )?;
   Ok(())
 }
 
@@ -157,75 +465,79 @@ pub fn write_user_maps(pid: u32, uid: u32, gid: u32) -> CoreResult<Void> {
   Ok(Void)
 }
 
-pub fn apply_cgroup(
-  path: Option<&std::path::Path>,
-  cgroup: Option<&ServiceCgroup>,
-  pid: u32,
-) -> CoreResult<Void> {
-  let Some(path) = path else {
-    return Ok(Void);
-  };
-  std::fs::create_dir_all(path)?;
-  if let Some(cgroup) = cgroup {
-    if let Some(mem) = &cgroup.memory_max {
-      let _ = std::fs::write(path.join("memory.max"), mem.as_str());
-    }
-    if let Some(cpu) = &cgroup.cpu_max {
-      let _ = std::fs::write(path.join("cpu.max"), cpu.as_str());
-    }
-    if let Some(pids) = &cgroup.pids_max {
-      let _ = std::fs::write(path.join("pids.max"), pids.as_str());
-    }
-  }
-  std::fs::write(path.join("cgroup.procs"), pid.to_string())?;
-  Ok(Void)
-}
+/// Capability names indexed by their number, without the `cap_` prefix.
+pub const CAPABILITY_NAMES: [&str; 41] = [
+  "chown",
+  "dac_override",
+  "dac_read_search",
+  "fowner",
+  "fsetid",
+  "kill",
+  "setgid",
+  "setuid",
+  "setpcap",
+  "linux_immutable",
+  "net_bind_service",
+  "net_broadcast",
+  "net_admin",
+  "net_raw",
+  "ipc_lock",
+  "ipc_owner",
+  "sys_module",
+  "sys_rawio",
+  "sys_chroot",
+  "sys_ptrace",
+  "sys_pacct",
+  "sys_admin",
+  "sys_boot",
+  "sys_nice",
+  "sys_resource",
+  "sys_time",
+  "sys_tty_config",
+  "mknod",
+  "lease",
+  "audit_write",
+  "audit_control",
+  "setfcap",
+  "mac_override",
+  "mac_admin",
+  "syslog",
+  "wake_alarm",
+  "block_suspend",
+  "audit_read",
+  "perfmon",
+  "bpf",
+  "checkpoint_restore",
+];
 
 pub fn cap_number(name: &str) -> Option<i32> {
-  match name.trim().to_ascii_lowercase().as_str() {
-    "chown" => Some(0),
-    "dac_override" => Some(1),
-    "dac_read_search" => Some(2),
-    "fowner" => Some(3),
-    "fsetid" => Some(4),
-    "kill" => Some(5),
-    "setgid" => Some(6),
-    "setuid" => Some(7),
-    "setpcap" => Some(8),
-    "linux_immutable" => Some(9),
-    "net_bind_service" => Some(10),
-    "net_broadcast" => Some(11),
-    "net_admin" => Some(12),
-    "net_raw" => Some(13),
-    "ipc_lock" => Some(14),
-    "ipc_owner" => Some(15),
-    "sys_module" => Some(16),
-    "sys_rawio" => Some(17),
-    "sys_chroot" => Some(18),
-    "sys_ptrace" => Some(19),
-    "sys_pacct" => Some(20),
-    "sys_admin" => Some(21),
-    "sys_boot" => Some(22),
-    "sys_nice" => Some(23),
-    "sys_resource" => Some(24),
-    "sys_time" => Some(25),
-    "sys_tty_config" => Some(26),
-    "mknod" => Some(27),
-    "lease" => Some(28),
-    "audit_write" => Some(29),
-    "audit_control" => Some(30),
-    "setfcap" => Some(31),
-    "mac_override" => Some(32),
-    "mac_admin" => Some(33),
-    "syslog" => Some(34),
-    "wake_alarm" => Some(35),
-    "block_suspend" => Some(36),
-    "audit_read" => Some(37),
-    "perfmon" => Some(38),
-    "bpf" => Some(39),
-    "checkpoint_restore" => Some(40),
-    _ => None,
-  }
+  let name = name.trim().to_ascii_lowercase();
+  let name = name.strip_prefix("cap_").unwrap_or(&name);
+  CAPABILITY_NAMES
+    .iter()
+    .position(|cap| *cap == name)
+    .map(|idx| idx as i32)
+}
+
+/// Names of the capabilities set in a 64-bit mask, as in `/proc/<pid>/status`.
+pub fn cap_names(mask: u64) -> Vec<Ustr> {
+  (0..64)
+    .filter(|bit| mask & (1u64 << bit) != 0)
+    .map(|bit| match CAPABILITY_NAMES.get(bit) {
+      Some(name) => Ustr::from(*name),
+      None => Ustr::from(format!("cap_{bit}")),
+    })
+    .collect()
+}
+
+/// Effective capability set of a running process, read from `CapEff`.
+pub fn effective_capabilities(pid: u32) -> Option<Vec<Ustr>> {
+  let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+  let mask = status
+    .lines()
+    .find_map(|line| line.strip_prefix("CapEff:"))
+    .and_then(|hex| u64::from_str_radix(hex.trim(), 16).ok())?;
+  Some(cap_names(mask))
 }
 
 pub fn cap_numbers(names: &[Ustr]) -> Vec<i32> {
@@ -240,10 +552,14 @@ pub fn drop_bounding_set(caps: Option<&CapabilityPolicy>) -> std::io::Result<()>
     return Ok(());
   };
 
-  let keep: HashSet<i32> = cap_numbers(&caps.keep).into_iter().collect();
+  let keep: HashSet<i32> = cap_numbers(&caps.keep)
+    .into_iter()
+    .chain(cap_numbers(&caps.ambient))
+    .chain(cap_numbers(&caps.inheritable))
+    .collect();
 
-  if caps.drop.iter().any(|cap| cap.as_str() == "all") {
-    for cap in 0..=40 {
+  if caps.drops_all() {
+    for cap in 0..CAPABILITY_NAMES.len() as i32 {
       if keep.contains(&cap) {
         continue;
       }
@@ -268,28 +584,148 @@ pub fn drop_bounding_set(caps: Option<&CapabilityPolicy>) -> std::io::Result<()>
   Ok(())
 }
 
-pub fn apply_seccomp(seccomp: Option<&SeccompPolicy>) -> std::io::Result<()> {
-  let Some(seccomp) = seccomp else {
+#[repr(C)]
+struct CapUserHeader {
+  version: u32,
+  pid: libc::c_int,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+struct CapUserData {
+  effective: u32,
+  permitted: u32,
+  inheritable: u32,
+}
+
+const LINUX_CAPABILITY_VERSION_3: u32 = 0x2008_0522;
+
+/// Current (effective, permitted, inheritable) sets of the calling thread.
+fn capget_self() -> std::io::Result<(u64, u64, u64)> {
+  let mut header = CapUserHeader {
+    version: LINUX_CAPABILITY_VERSION_3,
+    pid: 0,
+  };
+  let mut data = [CapUserData::default(); 2];
+  if unsafe { libc::syscall(libc::SYS_capget, &mut header, data.as_mut_ptr()) } < 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+  let join = |lo: u32, hi: u32| lo as u64 | ((hi as u64) << 32);
+  Ok((
+    join(data[0].effective, data[1].effective),
+    join(data[0].permitted, data[1].permitted),
+    join(data[0].inheritable, data[1].inheritable),
+  ))
+}
+
+fn capset_self(effective: u64, permitted: u64, inheritable: u64) -> std::io::Result<()> {
+  let mut header = CapUserHeader {
+    version: LINUX_CAPABILITY_VERSION_3,
+    pid: 0,
+  };
+  let split = |mask: u64, hi: bool| if hi { (mask >> 32) as u32 } else { mask as u32 };
+  let data = [false, true].map(|hi| CapUserData {
+    effective: split(effective, hi),
+    permitted: split(permitted, hi),
+    inheritable: split(inheritable, hi),
+  });
+  if unsafe { libc::syscall(libc::SYS_capset, &mut header, data.as_ptr()) } < 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+  Ok(())
+}
+
+fn cap_mask(names: &[Ustr]) -> u64 {
+  cap_numbers(names)
+    .into_iter()
+    .fold(0, |mask, cap| mask | (1u64 << cap))
+}
+
+/// `SECBIT_*` flag for a securebits name such as `noroot-locked`.
+pub fn securebit_flag(name: &str) -> Option<libc::c_ulong> {
+  let bit = match name.trim().to_ascii_lowercase().replace('_', "-").as_str() {
+    "noroot" => 0,
+    "noroot-locked" => 1,
+    "no-setuid-fixup" => 2,
+    "no-setuid-fixup-locked" => 3,
+    "keep-caps" => 4,
+    "keep-caps-locked" => 5,
+    "no-cap-ambient-raise" => 6,
+    "no-cap-ambient-raise-locked" => 7,
+    _ => return None,
+  };
+  Some(1 << bit)
+}
+
+const SECBIT_KEEP_CAPS: libc::c_ulong = 1 << 4;
+
+/// Runs before the uid switch: sets securebits, adding `keep-caps` when
+/// `keep`, `ambient` or `inheritable` capabilities must survive `setuid`.
+pub fn prepare_capabilities(
+  caps: Option<&CapabilityPolicy>,
+  switches_user: bool,
+) -> std::io::Result<()> {
+  let Some(caps) = caps else {
     return Ok(());
   };
-  if unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } < 0 {
+  let mut bits = caps
+    .securebits
+    .iter()
+    .filter_map(|name| securebit_flag(name.as_str()))
+    .fold(0, |bits, flag| bits | flag);
+  if switches_user
+    && !(caps.keep.is_empty() && caps.ambient.is_empty() && caps.inheritable.is_empty())
+  {
+    bits |= SECBIT_KEEP_CAPS;
+  }
+  if bits != 0 && unsafe { libc::prctl(libc::PR_SET_SECUREBITS, bits, 0, 0, 0) } < 0 {
     return Err(std::io::Error::last_os_error());
   }
-  match seccomp.profile.as_ref().map(|p| p.as_str()) {
-    Some("strict") => {
-      if unsafe { libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_STRICT, 0, 0, 0) } < 0 {
-        return Err(std::io::Error::last_os_error());
-      }
-      Ok(())
+  Ok(())
+}
+
+/// Runs after the uid switch: narrows the effective and permitted sets,
+/// fills the inheritable set, raises ambient capabilities and finally sets
+/// `no_new_privs`. A non-root process only keeps the capabilities the
+/// policy names, since `keep-caps` carried the whole root set over.
+pub fn apply_capabilities(caps: Option<&CapabilityPolicy>) -> std::io::Result<()> {
+  let Some(caps) = caps else {
+    return Ok(());
+  };
+  let (_, current, _) = capget_self()?;
+  let ambient = cap_mask(&caps.ambient);
+  let inheritable = cap_mask(&caps.inheritable) | ambient;
+  let permitted = if caps.drops_all() || unsafe { libc::geteuid() } != 0 {
+    cap_mask(&caps.keep) | inheritable
+  } else {
+    current & !cap_mask(&caps.drop)
+  } & current;
+  capset_self(permitted, permitted, inheritable)?;
+
+  for cap in cap_numbers(&caps.ambient) {
+    let raised = unsafe {
+      libc::prctl(
+        libc::PR_CAP_AMBIENT,
+        libc::PR_CAP_AMBIENT_RAISE,
+        cap as libc::c_ulong,
+        0,
+        0,
+      )
+    };
+    if raised < 0 {
+      return Err(std::io::Error::last_os_error());
     }
-    Some(profile) => Err(std::io::Error::new(
-      std::io::ErrorKind::InvalidInput,
-      format!("unsupported seccomp profile '{profile}'"),
-    )),
-    None if seccomp.path.is_some() => Err(std::io::Error::new(
-      std::io::ErrorKind::InvalidInput,
-      "seccomp filter file loading is not implemented",
-    )),
+  }
+
+  if caps.no_new_privileges && unsafe { libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) } < 0 {
+    return Err(std::io::Error::last_os_error());
+  }
+  Ok(())
+}
+
+pub fn apply_seccomp(seccomp: Option<&SeccompProgram>) -> std::io::Result<()> {
+  match seccomp {
+    Some(program) => program.install(),
     None => Ok(()),
   }
 }
@@ -369,6 +805,13 @@ pub fn join_ns_fds(fds: &[File]) -> std::io::Result<()> {
   Ok(())
 }
 
+/// What `exec_service` locks the process down with. Built before forking, so
+/// the child doesn't have to allocate.
+struct ExecLockdown {
+  capabilities: Option<CapabilityPolicy>,
+  seccomp: Option<SeccompProgram>,
+}
+
 fn exec_service(
   exec: &str,
   args: &[Ustr],
@@ -376,28 +819,22 @@ fn exec_service(
   cwd: Option<&Ustr>,
   uid_gid: Option<(u32, u32)>,
   pre_exec_fds: &[RawFd],
-  isolation: &ServiceIsolation,
+  lockdown: &ExecLockdown,
 ) -> ! {
   if let Some(dir) = cwd {
     let _ = std::env::set_current_dir(dir.as_str());
   }
 
-  for (idx, fd) in pre_exec_fds.iter().enumerate() {
-    let target_fd = (3 + idx) as RawFd;
-    if *fd != target_fd {
-      unsafe {
-        libc::dup2(*fd, target_fd);
-      }
-    }
-    unsafe {
-      let flags = libc::fcntl(target_fd, libc::F_GETFD);
-      if flags >= 0 {
-        let _ = libc::fcntl(target_fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC);
-      }
-    }
+  let mut listen_fds = pre_exec_fds.to_vec();
+  if install_listen_fds(&mut listen_fds).is_err() {
+    unsafe { libc::_exit(126) }
+  }
+
+  if drop_bounding_set(lockdown.capabilities.as_ref()).is_err() {
+    unsafe { libc::_exit(126) }
   }
 
-  if drop_bounding_set(isolation.capabilities.as_ref()).is_err() {
+  if prepare_capabilities(lockdown.capabilities.as_ref(), uid_gid.is_some()).is_err() {
     unsafe { libc::_exit(126) }
   }
 
@@ -412,7 +849,11 @@ fn exec_service(
     }
   }
 
-  if apply_seccomp(isolation.seccomp.as_ref()).is_err() {
+  if apply_capabilities(lockdown.capabilities.as_ref()).is_err() {
+    unsafe { libc::_exit(126) }
+  }
+
+  if apply_seccomp(lockdown.seccomp.as_ref()).is_err() {
     unsafe {
       libc::_exit(126);
     }
@@ -430,8 +871,10 @@ fn exec_service(
     .filter_map(|(k, v)| CString::new(format!("{}={}", k.as_str(), v.as_str())).ok())
     .collect::<Vec<_>>();
 
-  let pid_cstr = CString::new(format!("LISTEN_PID={}", unsafe { libc::getpid() })).unwrap();
-  c_envs.push(pid_cstr);
+  if envs.contains_key("LISTEN_FDS") {
+    let pid_cstr = CString::new(format!("LISTEN_PID={}", unsafe { libc::getpid() })).unwrap();
+    c_envs.push(pid_cstr);
+  }
 
   let mut envp = c_envs.iter().map(|e| e.as_ptr()).collect::<Vec<_>>();
   envp.push(std::ptr::null());
@@ -442,9 +885,120 @@ fn exec_service(
   }
 }
 
-fn apply_namespace_network(networks: &[NamespaceNetworkConfig]) {
-  let _ = networks;
+/// Runs inside the new network namespace, after the host end was created:
+/// brings links up, assigns addresses and installs the default route.
+fn apply_namespace_network(networks: &[NamespaceNetworkConfig]) -> std::io::Result<()> {
   setup_namespace_loopback();
+  for net in networks {
+    if net.method == "static"
+      && let Some((addr, prefix)) = net.address.as_deref().and_then(netlink::parse_cidr)
+    {
+      netlink::add_address(&net.name, addr, prefix)?;
+    }
+    netlink::set_link_up(&net.name)?;
+    if let Some(gateway) = net.gateway.as_deref().and_then(|gw| gw.parse().ok()) {
+      netlink::add_default_route(gateway)?;
+    }
+  }
+  Ok(())
+}
+
+const IP_FORWARD: &str = "/proc/sys/net/ipv4/ip_forward";
+
+/// NAT links alive, and `ip_forward` as it was before the first one.
+static IP_FORWARD_USERS: Mutex<(usize, Option<String>)> = Mutex::new((0, None));
+
+fn acquire_ip_forward() -> std::io::Result<()> {
+  let mut users = IP_FORWARD_USERS
+    .lock()
+    .expect("ip_forward users lock poisoned");
+  if users.0 == 0 {
+    let previous = std::fs::read_to_string(IP_FORWARD)?;
+    std::fs::write(IP_FORWARD, "1")?;
+    users.1 = Some(previous.trim().to_string());
+  }
+  users.0 += 1;
+  Ok(())
+}
+
+/// Restores `ip_forward` once the last NAT link is gone.
+fn release_ip_forward() {
+  let mut users = IP_FORWARD_USERS
+    .lock()
+    .expect("ip_forward users lock poisoned");
+  users.0 = users.0.saturating_sub(1);
+  if users.0 == 0
+    && let Some(previous) = users.1.take()
+    && previous != "1"
+  {
+    let _ = std::fs::write(IP_FORWARD, previous);
+  }
+}
+
+struct HostLink {
+  name: String,
+  nat_table: Option<String>,
+}
+
+/// Host ends of a service's veth links. Dropping it removes the NAT tables
+/// and links, which also destroys the peers inside the namespace.
+#[derive(Default)]
+pub struct NamespaceNetwork {
+  links: Vec<HostLink>,
+}
+
+impl NamespaceNetwork {
+  pub fn setup(pid: u32, networks: &[NamespaceNetworkConfig]) -> CoreResult<Self> {
+    let mut network = NamespaceNetwork::default();
+    for (idx, net) in networks.iter().enumerate() {
+      let host = format!("ve{pid}n{idx}");
+      netlink::create_veth(&host, &net.name, pid)?;
+      network.links.push(HostLink {
+        name: host.clone(),
+        nat_table: None,
+      });
+
+      if let Some((addr, prefix)) = net.host_address.as_deref().and_then(netlink::parse_cidr) {
+        netlink::add_address(&host, addr, prefix)?;
+      }
+      if let Some(bridge) = &net.bridge {
+        netlink::set_link_master(&host, bridge)?;
+      }
+      netlink::set_link_up(&host)?;
+
+      if net.nat
+        && let Some((IpAddr::V4(addr), prefix)) =
+          net.address.as_deref().and_then(netlink::parse_cidr)
+      {
+        acquire_ip_forward()?;
+        let table = format!("rind_{host}");
+        if let Err(e) = netlink::add_masquerade(&table, addr, prefix) {
+          release_ip_forward();
+          return Err(e.into());
+        }
+        if let Some(link) = network.links.last_mut() {
+          link.nat_table = Some(table);
+        }
+      }
+    }
+    Ok(network)
+  }
+
+  pub fn host_links(&self) -> Vec<&str> {
+    self.links.iter().map(|link| link.name.as_str()).collect()
+  }
+}
+
+impl Drop for NamespaceNetwork {
+  fn drop(&mut self) {
+    for link in self.links.drain(..) {
+      if let Some(table) = &link.nat_table {
+        let _ = netlink::delete_nft_table(table);
+        release_ip_forward();
+      }
+      let _ = netlink::delete_link(&link.name);
+    }
+  }
 }
 
 fn wait_and_exit(pid: libc::pid_t) -> ! {
@@ -481,7 +1035,7 @@ fn run_pid_init(
   cwd: Option<Ustr>,
   uid_gid: Option<(u32, u32)>,
   pre_exec_fds: Vec<RawFd>,
-  isolation: ServiceIsolation,
+  lockdown: ExecLockdown,
 ) -> ! {
   let child = unsafe { libc::fork() };
   if child < 0 {
@@ -497,7 +1051,7 @@ fn run_pid_init(
       cwd.as_ref(),
       uid_gid,
       &pre_exec_fds,
-      &isolation,
+      &lockdown,
     );
   }
 
@@ -540,12 +1094,22 @@ pub fn spawn_supervised(
   cwd: Option<Ustr>,
   uid_gid: Option<(u32, u32)>,
   pre_exec_fds: Vec<RawFd>,
+  stdio_fd: Option<RawFd>,
   isolation: ServiceIsolation,
   cgroup_path: Option<PathBuf>,
   join_namespace_fds: Vec<File>,
   namespace_mounts: Vec<NamespaceMountEntry>,
   namespace_networks: Vec<NamespaceNetworkConfig>,
 ) -> CoreResult<Box<dyn InstanceHandle>> {
+  let lockdown = ExecLockdown {
+    capabilities: isolation.capabilities.clone(),
+    seccomp: isolation
+      .seccomp
+      .as_ref()
+      .map(SeccompProgram::prepare)
+      .transpose()?
+      .flatten(),
+  };
   let (stdin_r, stdin_w) = pipe()?;
   let (stdout_r, stdout_w) = pipe()?;
   let (stderr_r, stderr_w) = pipe()?;
@@ -569,6 +1133,10 @@ pub fn spawn_supervised(
       libc::dup2(stdin_r, libc::STDIN_FILENO);
       libc::dup2(stdout_w, libc::STDOUT_FILENO);
       libc::dup2(stderr_w, libc::STDERR_FILENO);
+      if let Some(fd) = stdio_fd {
+        libc::dup2(fd, libc::STDIN_FILENO);
+        libc::dup2(fd, libc::STDOUT_FILENO);
+      }
     }
 
     let ns = isolation.namespaces.clone().unwrap_or_default();
@@ -593,21 +1161,21 @@ pub fn spawn_supervised(
       }
     }
 
-    if join_namespace_fds.is_empty() && unsafe { apply_namespace_setup(Some(&ns)) }.is_err() {
+    let setup = if join_namespace_fds.is_empty() {
+      unsafe { apply_namespace_setup(Some(&ns), &namespace_mounts) }
+    } else if ns.mount || ns.mount_private || ns.rootfs.is_some() {
+      mount_all_in_namespace(&namespace_mounts)
+    } else {
+      Ok(())
+    };
+    if setup.is_err() {
       unsafe {
         libc::_exit(125);
       }
     }
 
-    if ns.net || ns.mount || ns.mount_private || ns.rootfs.is_some() {
-      if ns.net {
-        apply_namespace_network(&namespace_networks);
-      }
-      if !namespace_mounts.is_empty() {
-        if mount_all_in_namespace(&namespace_mounts).is_err() {
-          unsafe { libc::_exit(125) }
-        }
-      }
+    if ns.net {
+      setup_namespace_loopback();
     }
 
     let _ = write_all_fd(ready_w, b"R");
@@ -618,6 +1186,16 @@ pub fn spawn_supervised(
       }
     }
 
+    if ns.net
+      && join_namespace_fds.is_empty()
+      && !namespace_networks.is_empty()
+      && apply_namespace_network(&namespace_networks).is_err()
+    {
+      unsafe {
+        libc::_exit(125);
+      }
+    }
+
     let service_uid_gid = if ns.user { Some((0, 0)) } else { uid_gid };
 
     if ns.pid {
@@ -628,6 +1206,11 @@ pub fn spawn_supervised(
         }
       }
       if init_pid == 0 {
+        if ns.rootfs.is_some() && mount_pid_proc().is_err() {
+          unsafe {
+            libc::_exit(125);
+          }
+        }
         if ns.init {
           run_pid_init(
             exec,
@@ -636,7 +1219,7 @@ pub fn spawn_supervised(
             cwd,
             service_uid_gid,
             pre_exec_fds,
-            isolation,
+            lockdown,
           );
         } else {
           exec_service(
@@ -646,7 +1229,7 @@ pub fn spawn_supervised(
             cwd.as_ref(),
             service_uid_gid,
             &pre_exec_fds,
-            &isolation,
+            &lockdown,
           );
         }
       }
@@ -660,7 +1243,7 @@ pub fn spawn_supervised(
       cwd.as_ref(),
       service_uid_gid,
       &pre_exec_fds,
-      &isolation,
+      &lockdown,
     );
   }
 
@@ -700,6 +1283,22 @@ pub fn spawn_supervised(
       .filter_map(|fd| fd.try_clone().ok())
       .collect()
   };
+  let creates_network = join_namespace_fds.is_empty()
+    && isolation.namespaces.as_ref().is_some_and(|ns| ns.net)
+    && !namespace_networks.is_empty();
+  let network = if creates_network {
+    match NamespaceNetwork::setup(supervisor_pid, &namespace_networks) {
+      Ok(network) => network,
+      Err(err) => {
+        // The supervisor sees EOF instead of 'G' and exits.
+        close_fd(ready_r);
+        close_fd(go_w);
+        return Err(err);
+      }
+    }
+  } else {
+    NamespaceNetwork::default()
+  };
   store_persisted_namespaces(isolation.scope.as_ref(), &namespace_fds);
   write_all_fd(go_w, b"G")?;
 
@@ -712,6 +1311,7 @@ pub fn spawn_supervised(
     stderr: Some(unsafe { File::from_raw_fd(stderr_r) }),
     stdin: Some(unsafe { File::from_raw_fd(stdin_w) }),
     _namespace_fds: namespace_fds,
+    _network: network,
   };
 
   Ok(Box::new(handle))
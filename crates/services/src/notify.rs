@@ -0,0 +1,188 @@
+use std::mem::ManuallyDrop;
+use std::os::fd::{AsRawFd, OwnedFd, RawFd};
+use std::os::unix::fs::{PermissionsExt, chown};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use rind_core::prelude::*;
+
+pub const NOTIFY_DIR: &str = "/run/rind-notify";
+
+static NEXT_NOTIFY_ID: AtomicU32 = AtomicU32::new(1);
+
+/// A single assignment from an sd_notify style datagram.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotifyCommand {
+  Ready,
+  Stopping,
+  Watchdog,
+  Status(String),
+  MainPid(u32),
+}
+
+/// Parses newline separated `KEY=VALUE` pairs. Unknown keys and malformed
+/// values are ignored, as the protocol allows.
+pub fn parse_notify_message(buf: &[u8]) -> Vec<NotifyCommand> {
+  String::from_utf8_lossy(buf)
+    .lines()
+    .filter_map(|line| {
+      let (key, value) = line.split_once('=')?;
+      match key {
+        "READY" if value == "1" => Some(NotifyCommand::Ready),
+        "STOPPING" if value == "1" => Some(NotifyCommand::Stopping),
+        "WATCHDOG" if value == "1" => Some(NotifyCommand::Watchdog),
+        "STATUS" => Some(NotifyCommand::Status(value.to_string())),
+        "MAINPID" => value.parse().ok().map(NotifyCommand::MainPid),
+        _ => None,
+      }
+    })
+    .collect()
+}
+
+/// Per-instance datagram socket handed to the service as `NOTIFY_SOCKET`.
+/// Senders are identified through `SO_PASSCRED`, see `drain_notify_fd`.
+/// Dropping it unlinks `path`, unless it was handed over by `into_fd`.
+pub struct NotifySocket {
+  pub path: PathBuf,
+  pub socket: UnixDatagram,
+}
+
+impl NotifySocket {
+  /// Binds the socket in `dir`, writable by root and by `group` only. A
+  /// service that drops privileges must keep that group to notify.
+  pub fn bind_in(dir: &Path, registry_key: &str, group: Option<u32>) -> CoreResult<Self> {
+    std::fs::create_dir_all(dir)?;
+    let name = registry_key
+      .chars()
+      .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+      .collect::<String>();
+    let id = NEXT_NOTIFY_ID.fetch_add(1, Ordering::Relaxed);
+    let path = dir.join(format!("{name}.{id}.sock"));
+    let _ = std::fs::remove_file(&path);
+
+    let socket = UnixDatagram::bind(&path)?;
+    socket.set_nonblocking(true)?;
+    let on: libc::c_int = 1;
+    if unsafe {
+      libc::setsockopt(
+        socket.as_raw_fd(),
+        libc::SOL_SOCKET,
+        libc::SO_PASSCRED,
+        &on as *const libc::c_int as *const libc::c_void,
+        std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+      )
+    } < 0
+    {
+      return Err(std::io::Error::last_os_error().into());
+    }
+    let mode = match group {
+      Some(gid) => {
+        chown(&path, None, Some(gid))?;
+        0o660
+      }
+      None => 0o600,
+    };
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(mode))?;
+    Ok(Self { path, socket })
+  }
+
+  pub fn bind(registry_key: &str, group: Option<u32>) -> CoreResult<Self> {
+    Self::bind_in(Path::new(NOTIFY_DIR), registry_key, group)
+  }
+
+  pub fn raw_fd(&self) -> RawFd {
+    self.socket.as_raw_fd()
+  }
+
+  /// Hands over the socket, and with it the job of unlinking `path`.
+  pub fn into_fd(self) -> (PathBuf, OwnedFd) {
+    let this = ManuallyDrop::new(self);
+    // `this` is never dropped, so each field is moved out exactly once
+    unsafe {
+      (
+        std::ptr::read(&this.path),
+        std::ptr::read(&this.socket).into(),
+      )
+    }
+  }
+}
+
+impl Drop for NotifySocket {
+  fn drop(&mut self) {
+    let _ = std::fs::remove_file(&self.path);
+  }
+}
+
+/// Drains every pending datagram on a non-blocking notify socket, keeping
+/// those whose sender pid `accept` allows. Datagrams without credentials
+/// are dropped.
+pub fn drain_notify_fd(fd: RawFd, accept: impl Fn(u32) -> bool) -> Vec<NotifyCommand> {
+  let mut commands = Vec::new();
+  let mut buf = [0u8; 4096];
+  let mut control = [0u8; 64];
+  loop {
+    let mut iov = libc::iovec {
+      iov_base: buf.as_mut_ptr() as *mut libc::c_void,
+      iov_len: buf.len(),
+    };
+    let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = control.len() as _;
+    let n = unsafe { libc::recvmsg(fd, &mut msg, libc::MSG_DONTWAIT) };
+    if n <= 0 {
+      break;
+    }
+    if sender_pid(&msg).is_some_and(&accept) {
+      commands.extend(parse_notify_message(&buf[..n as usize]));
+    }
+  }
+  commands
+}
+
+fn sender_pid(msg: &libc::msghdr) -> Option<u32> {
+  let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(msg) };
+  while !cmsg.is_null() {
+    let header = unsafe { &*cmsg };
+    if header.cmsg_level == libc::SOL_SOCKET && header.cmsg_type == libc::SCM_CREDENTIALS {
+      let creds = unsafe { std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::ucred) };
+      return u32::try_from(creds.pid).ok().filter(|pid| *pid != 0);
+    }
+    cmsg = unsafe { libc::CMSG_NXTHDR(msg, cmsg) };
+  }
+  None
+}
+
+fn parent_pid(pid: u32) -> Option<u32> {
+  let stat = std::fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+  // the command name may hold spaces and parens, fields resume after the last `)`
+  let (_, rest) = stat.rsplit_once(')')?;
+  rest.split_whitespace().nth(1)?.parse().ok()
+}
+
+/// Whether `pid` belongs to an instance: one of `roots` or a descendant of
+/// one, or a member of its `cgroup`, which also covers daemons that were
+/// reparented away from the process rind forked.
+pub fn pid_in_instance(pid: u32, roots: &[u32], cgroup: Option<&Path>) -> bool {
+  if let Some(cgroup) = cgroup
+    && let Ok(procs) = std::fs::read_to_string(cgroup.join("cgroup.procs"))
+    && procs.lines().any(|line| line.trim().parse() == Ok(pid))
+  {
+    return true;
+  }
+
+  let mut current = pid;
+  // bounded in case of a pid reused into a cycle while walking
+  for _ in 0..64 {
+    if roots.contains(&current) {
+      return true;
+    }
+    match parent_pid(current) {
+      Some(parent) if parent > 1 => current = parent,
+      _ => return false,
+    }
+  }
+  false
+}
@@ -3,6 +3,7 @@ use std::collections::HashMap;
 use nix::sys::wait::{WaitPidFlag, WaitStatus, waitpid};
 
 use rind_core::prelude::*;
+use rind_flow::triggers::EvalChildren;
 
 #[derive(Default)]
 pub struct ReaperRuntime;
@@ -11,7 +12,20 @@ pub struct ReaperRuntime;
 impl ReaperRuntime {
   fn reap_once() {
     loop {
-      match waitpid(None, Some(WaitPidFlag::WNOHANG)) {
+      // held across the wait, so `run_eval` never polls a child reaped here
+      let mut evals = EvalChildren::lock();
+      let status = waitpid(None, Some(WaitPidFlag::WNOHANG));
+      let exit = match status {
+        Ok(WaitStatus::Exited(pid, code)) => Some((pid, code)),
+        Ok(WaitStatus::Signaled(pid, signal, _)) => Some((pid, 128 + signal as i32)),
+        _ => None,
+      };
+      if exit.is_some_and(|(pid, code)| evals.claim(pid.as_raw(), code)) {
+        continue;
+      }
+      drop(evals);
+
+      match status {
         Ok(WaitStatus::Exited(pid, code)) => {
           let mut fields = HashMap::new();
           fields.insert("pid".to_string(), pid.as_raw().to_string());
@@ -26,7 +40,7 @@ impl ReaperRuntime {
               .insert("code", code),
           )?;
         }
-        Ok(WaitStatus::Signaled(pid, signal, _)) => {
+        Ok(WaitStatus::Signaled(pid, signal, core_dumped)) => {
           let code = 128 + signal as i32;
           let mut fields = HashMap::new();
           fields.insert("pid".to_string(), pid.as_raw().to_string());
@@ -38,7 +52,9 @@ impl ReaperRuntime {
             "child_exited",
             RuntimePayload::default()
               .insert("pid", pid.as_raw())
-              .insert("code", code),
+              .insert("code", code)
+              .insert("signal", signal as i32)
+              .insert("core_dumped", core_dumped),
           )?;
         }
         Ok(WaitStatus::StillAlive) | Err(nix::errno::Errno::ECHILD) => break,
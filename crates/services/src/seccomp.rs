@@ -0,0 +1,955 @@
+use std::collections::BTreeSet;
+use std::io::{Error, ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+use crate::services::SeccompPolicy;
+
+/// What the filter does with a matched syscall.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum SeccompAction {
+  /// Fail the syscall with this errno.
+  Errno(i32),
+  /// Kill the whole process with SIGSYS.
+  Kill,
+  /// Allow the syscall but log it to the audit log.
+  Log,
+}
+
+impl Default for SeccompAction {
+  fn default() -> Self {
+    Self::Errno(libc::EPERM)
+  }
+}
+
+const ERRNO_NAMES: &[(&str, i32)] = &[
+  ("EPERM", libc::EPERM),
+  ("ENOENT", libc::ENOENT),
+  ("EIO", libc::EIO),
+  ("EAGAIN", libc::EAGAIN),
+  ("ENOMEM", libc::ENOMEM),
+  ("EACCES", libc::EACCES),
+  ("EFAULT", libc::EFAULT),
+  ("EBUSY", libc::EBUSY),
+  ("EEXIST", libc::EEXIST),
+  ("EINVAL", libc::EINVAL),
+  ("ENOSPC", libc::ENOSPC),
+  ("EROFS", libc::EROFS),
+  ("ENOSYS", libc::ENOSYS),
+  ("EOPNOTSUPP", libc::EOPNOTSUPP),
+  ("EAFNOSUPPORT", libc::EAFNOSUPPORT),
+];
+
+impl TryFrom<String> for SeccompAction {
+  type Error = String;
+
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    let raw = value.trim();
+    match raw.to_ascii_lowercase().as_str() {
+      "kill" | "kill-process" => return Ok(Self::Kill),
+      "log" => return Ok(Self::Log),
+      "errno" => return Ok(Self::default()),
+      _ => {}
+    }
+    let errno = raw.strip_prefix("errno:").unwrap_or(raw).trim();
+    let parsed = errno.parse::<i32>().ok().or_else(|| {
+      ERRNO_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(errno))
+        .map(|(_, code)| *code)
+    });
+    match parsed {
+      Some(code) if (1..=libc::SECCOMP_RET_DATA as i32).contains(&code) => Ok(Self::Errno(code)),
+      _ => Err(format!("unknown seccomp action '{value}'")),
+    }
+  }
+}
+
+impl From<SeccompAction> for String {
+  fn from(value: SeccompAction) -> Self {
+    match value {
+      SeccompAction::Kill => "kill".into(),
+      SeccompAction::Log => "log".into(),
+      SeccompAction::Errno(code) => ERRNO_NAMES
+        .iter()
+        .find(|(_, c)| *c == code)
+        .map(|(name, _)| name.to_string())
+        .unwrap_or_else(|| code.to_string()),
+    }
+  }
+}
+
+impl SeccompAction {
+  fn ret(self) -> u32 {
+    match self {
+      Self::Errno(code) => libc::SECCOMP_RET_ERRNO | (code as u32 & libc::SECCOMP_RET_DATA),
+      Self::Kill => libc::SECCOMP_RET_KILL_PROCESS,
+      Self::Log => libc::SECCOMP_RET_LOG,
+    }
+  }
+}
+
+/// Groups a `default` profile denies.
+pub const DEFAULT_DENY_GROUPS: &[&str] = &[
+  "@clock", "@debug", "@module", "@mount", "@raw-io", "@reboot", "@swap",
+];
+
+/// Expands a named group into syscall names and nested `@group`s. Names
+/// missing on the running architecture are skipped when the group is resolved.
+pub fn syscall_group(name: &str) -> Option<&'static [&'static str]> {
+  let group: &'static [&'static str] = match name {
+    "@default" => &[
+      "arch_prctl",
+      "brk",
+      "clock_getres",
+      "clock_gettime",
+      "clock_nanosleep",
+      "execve",
+      "exit",
+      "exit_group",
+      "futex",
+      "get_robust_list",
+      "getegid",
+      "geteuid",
+      "getgid",
+      "getpgid",
+      "getpgrp",
+      "getpid",
+      "getppid",
+      "getrandom",
+      "getrlimit",
+      "getsid",
+      "gettid",
+      "gettimeofday",
+      "getuid",
+      "membarrier",
+      "mmap",
+      "mprotect",
+      "munmap",
+      "nanosleep",
+      "prlimit64",
+      "restart_syscall",
+      "rseq",
+      "rt_sigreturn",
+      "sched_getaffinity",
+      "sched_yield",
+      "set_robust_list",
+      "set_tid_address",
+    ],
+    "@basic-io" => &[
+      "close",
+      "close_range",
+      "dup",
+      "dup2",
+      "dup3",
+      "lseek",
+      "pread64",
+      "preadv",
+      "preadv2",
+      "pwrite64",
+      "pwritev",
+      "pwritev2",
+      "read",
+      "readv",
+      "write",
+      "writev",
+    ],
+    "@file-system" => &[
+      "access",
+      "chdir",
+      "chmod",
+      "chown",
+      "creat",
+      "faccessat",
+      "faccessat2",
+      "fallocate",
+      "fchdir",
+      "fchmod",
+      "fchmodat",
+      "fchown",
+      "fchownat",
+      "fcntl",
+      "fdatasync",
+      "flock",
+      "fstat",
+      "fstatfs",
+      "fsync",
+      "ftruncate",
+      "futimesat",
+      "getcwd",
+      "getdents",
+      "getdents64",
+      "inotify_add_watch",
+      "inotify_init",
+      "inotify_init1",
+      "inotify_rm_watch",
+      "lchown",
+      "link",
+      "linkat",
+      "lstat",
+      "mkdir",
+      "mkdirat",
+      "newfstatat",
+      "open",
+      "openat",
+      "openat2",
+      "readlink",
+      "readlinkat",
+      "rename",
+      "renameat",
+      "renameat2",
+      "rmdir",
+      "stat",
+      "statfs",
+      "statx",
+      "symlink",
+      "symlinkat",
+      "truncate",
+      "umask",
+      "unlink",
+      "unlinkat",
+      "utimensat",
+      "utimes",
+    ],
+    "@io-event" => &[
+      "epoll_create",
+      "epoll_create1",
+      "epoll_ctl",
+      "epoll_pwait",
+      "epoll_pwait2",
+      "epoll_wait",
+      "eventfd",
+      "eventfd2",
+      "poll",
+      "ppoll",
+      "pselect6",
+      "select",
+    ],
+    "@ipc" => &[
+      "memfd_create",
+      "mq_getsetattr",
+      "mq_notify",
+      "mq_open",
+      "mq_timedreceive",
+      "mq_timedsend",
+      "mq_unlink",
+      "msgctl",
+      "msgget",
+      "msgrcv",
+      "msgsnd",
+      "pipe",
+      "pipe2",
+      "semctl",
+      "semget",
+      "semop",
+      "semtimedop",
+      "shmat",
+      "shmctl",
+      "shmdt",
+      "shmget",
+    ],
+    "@memory" => &[
+      "brk", "madvise", "mincore", "mlock", "mlock2", "mmap", "mprotect", "mremap", "msync",
+      "munlock", "munmap",
+    ],
+    "@network-io" => &[
+      "accept",
+      "accept4",
+      "bind",
+      "connect",
+      "getpeername",
+      "getsockname",
+      "getsockopt",
+      "listen",
+      "recvfrom",
+      "recvmmsg",
+      "recvmsg",
+      "sendmmsg",
+      "sendmsg",
+      "sendto",
+      "setsockopt",
+      "shutdown",
+      "socket",
+      "socketpair",
+    ],
+    "@process" => &[
+      "clone",
+      "clone3",
+      "execve",
+      "execveat",
+      "exit",
+      "exit_group",
+      "fork",
+      "getpgid",
+      "getpgrp",
+      "getpid",
+      "getppid",
+      "getsid",
+      "gettid",
+      "kill",
+      "pidfd_open",
+      "pidfd_send_signal",
+      "prctl",
+      "setpgid",
+      "setsid",
+      "tgkill",
+      "tkill",
+      "vfork",
+      "wait4",
+      "waitid",
+    ],
+    "@signal" => &[
+      "rt_sigaction",
+      "rt_sigpending",
+      "rt_sigprocmask",
+      "rt_sigqueueinfo",
+      "rt_sigreturn",
+      "rt_sigsuspend",
+      "rt_sigtimedwait",
+      "sigaltstack",
+      "signalfd",
+      "signalfd4",
+    ],
+    "@timer" => &[
+      "alarm",
+      "getitimer",
+      "setitimer",
+      "timer_create",
+      "timer_delete",
+      "timer_getoverrun",
+      "timer_gettime",
+      "timer_settime",
+      "timerfd_create",
+      "timerfd_gettime",
+      "timerfd_settime",
+    ],
+    "@credentials" => &[
+      "capget",
+      "getegid",
+      "geteuid",
+      "getgid",
+      "getgroups",
+      "getresgid",
+      "getresuid",
+      "getuid",
+    ],
+    "@setuid" => &[
+      "setfsgid",
+      "setfsuid",
+      "setgid",
+      "setgroups",
+      "setregid",
+      "setresgid",
+      "setresuid",
+      "setreuid",
+      "setuid",
+    ],
+    "@resources" => &[
+      "getpriority",
+      "getrlimit",
+      "getrusage",
+      "ioprio_get",
+      "ioprio_set",
+      "prlimit64",
+      "sched_getaffinity",
+      "sched_getparam",
+      "sched_getscheduler",
+      "sched_setaffinity",
+      "setpriority",
+      "setrlimit",
+    ],
+    "@sync" => &[
+      "fdatasync",
+      "fsync",
+      "msync",
+      "sync",
+      "sync_file_range",
+      "syncfs",
+    ],
+    "@file-io" => &[
+      "copy_file_range",
+      "ioctl",
+      "sendfile",
+      "splice",
+      "tee",
+      "vmsplice",
+    ],
+    "@system-info" => &["getcpu", "sysinfo", "uname"],
+    "@system-service" => &[
+      "@default",
+      "@basic-io",
+      "@credentials",
+      "@file-io",
+      "@file-system",
+      "@io-event",
+      "@ipc",
+      "@memory",
+      "@network-io",
+      "@process",
+      "@resources",
+      "@setuid",
+      "@signal",
+      "@sync",
+      "@system-info",
+      "@timer",
+    ],
+    "@clock" => &["adjtimex", "clock_adjtime", "clock_settime", "settimeofday"],
+    "@debug" => &[
+      "perf_event_open",
+      "process_vm_readv",
+      "process_vm_writev",
+      "ptrace",
+    ],
+    "@module" => &["delete_module", "finit_module", "init_module"],
+    "@mount" => &[
+      "chroot",
+      "fsconfig",
+      "fsmount",
+      "fsopen",
+      "mount",
+      "mount_setattr",
+      "move_mount",
+      "open_tree",
+      "pivot_root",
+      "umount2",
+    ],
+    "@raw-io" => &["ioperm", "iopl"],
+    "@reboot" => &["kexec_file_load", "kexec_load", "reboot"],
+    "@swap" => &["swapoff", "swapon"],
+    _ => return None,
+  };
+  Some(group)
+}
+
+#[cfg(target_arch = "x86_64")]
+const AUDIT_ARCH: u32 = 0xc000_003e;
+#[cfg(target_arch = "aarch64")]
+const AUDIT_ARCH: u32 = 0xc000_00b7;
+
+/// Syscall number on the running architecture.
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+pub fn syscall_number(name: &str) -> Option<i64> {
+  let nr = match name {
+    "accept" => libc::SYS_accept,
+    "accept4" => libc::SYS_accept4,
+    #[cfg(target_arch = "x86_64")]
+    "access" => libc::SYS_access,
+    "adjtimex" => libc::SYS_adjtimex,
+    #[cfg(target_arch = "x86_64")]
+    "alarm" => libc::SYS_alarm,
+    #[cfg(target_arch = "x86_64")]
+    "arch_prctl" => libc::SYS_arch_prctl,
+    "bind" => libc::SYS_bind,
+    "brk" => libc::SYS_brk,
+    "capget" => libc::SYS_capget,
+    "chdir" => libc::SYS_chdir,
+    #[cfg(target_arch = "x86_64")]
+    "chmod" => libc::SYS_chmod,
+    #[cfg(target_arch = "x86_64")]
+    "chown" => libc::SYS_chown,
+    "chroot" => libc::SYS_chroot,
+    "clock_adjtime" => libc::SYS_clock_adjtime,
+    "clock_getres" => libc::SYS_clock_getres,
+    "clock_gettime" => libc::SYS_clock_gettime,
+    "clock_nanosleep" => libc::SYS_clock_nanosleep,
+    "clock_settime" => libc::SYS_clock_settime,
+    "clone" => libc::SYS_clone,
+    "clone3" => libc::SYS_clone3,
+    "close" => libc::SYS_close,
+    "close_range" => libc::SYS_close_range,
+    "connect" => libc::SYS_connect,
+    "copy_file_range" => libc::SYS_copy_file_range,
+    #[cfg(target_arch = "x86_64")]
+    "creat" => libc::SYS_creat,
+    "delete_module" => libc::SYS_delete_module,
+    "dup" => libc::SYS_dup,
+    #[cfg(target_arch = "x86_64")]
+    "dup2" => libc::SYS_dup2,
+    "dup3" => libc::SYS_dup3,
+    #[cfg(target_arch = "x86_64")]
+    "epoll_create" => libc::SYS_epoll_create,
+    "epoll_create1" => libc::SYS_epoll_create1,
+    "epoll_ctl" => libc::SYS_epoll_ctl,
+    "epoll_pwait" => libc::SYS_epoll_pwait,
+    "epoll_pwait2" => libc::SYS_epoll_pwait2,
+    #[cfg(target_arch = "x86_64")]
+    "epoll_wait" => libc::SYS_epoll_wait,
+    #[cfg(target_arch = "x86_64")]
+    "eventfd" => libc::SYS_eventfd,
+    "eventfd2" => libc::SYS_eventfd2,
+    "execve" => libc::SYS_execve,
+    "execveat" => libc::SYS_execveat,
+    "exit" => libc::SYS_exit,
+    "exit_group" => libc::SYS_exit_group,
+    "faccessat" => libc::SYS_faccessat,
+    "faccessat2" => libc::SYS_faccessat2,
+    "fallocate" => libc::SYS_fallocate,
+    "fchdir" => libc::SYS_fchdir,
+    "fchmod" => libc::SYS_fchmod,
+    "fchmodat" => libc::SYS_fchmodat,
+    "fchown" => libc::SYS_fchown,
+    "fchownat" => libc::SYS_fchownat,
+    "fcntl" => libc::SYS_fcntl,
+    "fdatasync" => libc::SYS_fdatasync,
+    "finit_module" => libc::SYS_finit_module,
+    "flock" => libc::SYS_flock,
+    #[cfg(target_arch = "x86_64")]
+    "fork" => libc::SYS_fork,
+    "fsconfig" => libc::SYS_fsconfig,
+    "fsmount" => libc::SYS_fsmount,
+    "fsopen" => libc::SYS_fsopen,
+    "fstat" => libc::SYS_fstat,
+    "fstatfs" => libc::SYS_fstatfs,
+    "fsync" => libc::SYS_fsync,
+    "ftruncate" => libc::SYS_ftruncate,
+    "futex" => libc::SYS_futex,
+    #[cfg(target_arch = "x86_64")]
+    "futimesat" => libc::SYS_futimesat,
+    "get_robust_list" => libc::SYS_get_robust_list,
+    "getcpu" => libc::SYS_getcpu,
+    "getcwd" => libc::SYS_getcwd,
+    #[cfg(target_arch = "x86_64")]
+    "getdents" => libc::SYS_getdents,
+    "getdents64" => libc::SYS_getdents64,
+    "getegid" => libc::SYS_getegid,
+    "geteuid" => libc::SYS_geteuid,
+    "getgid" => libc::SYS_getgid,
+    "getgroups" => libc::SYS_getgroups,
+    "getitimer" => libc::SYS_getitimer,
+    "getpeername" => libc::SYS_getpeername,
+    "getpgid" => libc::SYS_getpgid,
+    #[cfg(target_arch = "x86_64")]
+    "getpgrp" => libc::SYS_getpgrp,
+    "getpid" => libc::SYS_getpid,
+    "getppid" => libc::SYS_getppid,
+    "getpriority" => libc::SYS_getpriority,
+    "getrandom" => libc::SYS_getrandom,
+    "getresgid" => libc::SYS_getresgid,
+    "getresuid" => libc::SYS_getresuid,
+    #[cfg(target_arch = "x86_64")]
+    "getrlimit" => libc::SYS_getrlimit,
+    "getrusage" => libc::SYS_getrusage,
+    "getsid" => libc::SYS_getsid,
+    "getsockname" => libc::SYS_getsockname,
+    "getsockopt" => libc::SYS_getsockopt,
+    "gettid" => libc::SYS_gettid,
+    "gettimeofday" => libc::SYS_gettimeofday,
+    "getuid" => libc::SYS_getuid,
+    "init_module" => libc::SYS_init_module,
+    "inotify_add_watch" => libc::SYS_inotify_add_watch,
+    #[cfg(target_arch = "x86_64")]
+    "inotify_init" => libc::SYS_inotify_init,
+    "inotify_init1" => libc::SYS_inotify_init1,
+    "inotify_rm_watch" => libc::SYS_inotify_rm_watch,
+    "ioctl" => libc::SYS_ioctl,
+    #[cfg(target_arch = "x86_64")]
+    "ioperm" => libc::SYS_ioperm,
+    #[cfg(target_arch = "x86_64")]
+    "iopl" => libc::SYS_iopl,
+    "ioprio_get" => libc::SYS_ioprio_get,
+    "ioprio_set" => libc::SYS_ioprio_set,
+    #[cfg(target_arch = "x86_64")]
+    "kexec_file_load" => libc::SYS_kexec_file_load,
+    "kexec_load" => libc::SYS_kexec_load,
+    "kill" => libc::SYS_kill,
+    #[cfg(target_arch = "x86_64")]
+    "lchown" => libc::SYS_lchown,
+    #[cfg(target_arch = "x86_64")]
+    "link" => libc::SYS_link,
+    "linkat" => libc::SYS_linkat,
+    "listen" => libc::SYS_listen,
+    "lseek" => libc::SYS_lseek,
+    #[cfg(target_arch = "x86_64")]
+    "lstat" => libc::SYS_lstat,
+    "madvise" => libc::SYS_madvise,
+    "membarrier" => libc::SYS_membarrier,
+    "memfd_create" => libc::SYS_memfd_create,
+    "mincore" => libc::SYS_mincore,
+    #[cfg(target_arch = "x86_64")]
+    "mkdir" => libc::SYS_mkdir,
+    "mkdirat" => libc::SYS_mkdirat,
+    "mlock" => libc::SYS_mlock,
+    "mlock2" => libc::SYS_mlock2,
+    "mmap" => libc::SYS_mmap,
+    "mount" => libc::SYS_mount,
+    "mount_setattr" => libc::SYS_mount_setattr,
+    "move_mount" => libc::SYS_move_mount,
+    "mprotect" => libc::SYS_mprotect,
+    "mq_getsetattr" => libc::SYS_mq_getsetattr,
+    "mq_notify" => libc::SYS_mq_notify,
+    "mq_open" => libc::SYS_mq_open,
+    "mq_timedreceive" => libc::SYS_mq_timedreceive,
+    "mq_timedsend" => libc::SYS_mq_timedsend,
+    "mq_unlink" => libc::SYS_mq_unlink,
+    "mremap" => libc::SYS_mremap,
+    "msgctl" => libc::SYS_msgctl,
+    "msgget" => libc::SYS_msgget,
+    "msgrcv" => libc::SYS_msgrcv,
+    "msgsnd" => libc::SYS_msgsnd,
+    "msync" => libc::SYS_msync,
+    "munlock" => libc::SYS_munlock,
+    "munmap" => libc::SYS_munmap,
+    "nanosleep" => libc::SYS_nanosleep,
+    "newfstatat" => libc::SYS_newfstatat,
+    #[cfg(target_arch = "x86_64")]
+    "open" => libc::SYS_open,
+    "open_tree" => libc::SYS_open_tree,
+    "openat" => libc::SYS_openat,
+    "openat2" => libc::SYS_openat2,
+    "perf_event_open" => libc::SYS_perf_event_open,
+    "pidfd_open" => libc::SYS_pidfd_open,
+    "pidfd_send_signal" => libc::SYS_pidfd_send_signal,
+    #[cfg(target_arch = "x86_64")]
+    "pipe" => libc::SYS_pipe,
+    "pipe2" => libc::SYS_pipe2,
+    "pivot_root" => libc::SYS_pivot_root,
+    #[cfg(target_arch = "x86_64")]
+    "poll" => libc::SYS_poll,
+    "ppoll" => libc::SYS_ppoll,
+    "prctl" => libc::SYS_prctl,
+    "pread64" => libc::SYS_pread64,
+    "preadv" => libc::SYS_preadv,
+    "preadv2" => libc::SYS_preadv2,
+    "prlimit64" => libc::SYS_prlimit64,
+    "process_vm_readv" => libc::SYS_process_vm_readv,
+    "process_vm_writev" => libc::SYS_process_vm_writev,
+    "pselect6" => libc::SYS_pselect6,
+    "ptrace" => libc::SYS_ptrace,
+    "pwrite64" => libc::SYS_pwrite64,
+    "pwritev" => libc::SYS_pwritev,
+    "pwritev2" => libc::SYS_pwritev2,
+    "read" => libc::SYS_read,
+    #[cfg(target_arch = "x86_64")]
+    "readlink" => libc::SYS_readlink,
+    "readlinkat" => libc::SYS_readlinkat,
+    "readv" => libc::SYS_readv,
+    "reboot" => libc::SYS_reboot,
+    "recvfrom" => libc::SYS_recvfrom,
+    "recvmmsg" => libc::SYS_recvmmsg,
+    "recvmsg" => libc::SYS_recvmsg,
+    #[cfg(target_arch = "x86_64")]
+    "rename" => libc::SYS_rename,
+    #[cfg(target_arch = "x86_64")]
+    "renameat" => libc::SYS_renameat,
+    "renameat2" => libc::SYS_renameat2,
+    "restart_syscall" => libc::SYS_restart_syscall,
+    #[cfg(target_arch = "x86_64")]
+    "rmdir" => libc::SYS_rmdir,
+    "rseq" => libc::SYS_rseq,
+    "rt_sigaction" => libc::SYS_rt_sigaction,
+    "rt_sigpending" => libc::SYS_rt_sigpending,
+    "rt_sigprocmask" => libc::SYS_rt_sigprocmask,
+    "rt_sigqueueinfo" => libc::SYS_rt_sigqueueinfo,
+    "rt_sigreturn" => libc::SYS_rt_sigreturn,
+    "rt_sigsuspend" => libc::SYS_rt_sigsuspend,
+    "rt_sigtimedwait" => libc::SYS_rt_sigtimedwait,
+    "sched_getaffinity" => libc::SYS_sched_getaffinity,
+    "sched_getparam" => libc::SYS_sched_getparam,
+    "sched_getscheduler" => libc::SYS_sched_getscheduler,
+    "sched_setaffinity" => libc::SYS_sched_setaffinity,
+    "sched_yield" => libc::SYS_sched_yield,
+    #[cfg(target_arch = "x86_64")]
+    "select" => libc::SYS_select,
+    "semctl" => libc::SYS_semctl,
+    "semget" => libc::SYS_semget,
+    "semop" => libc::SYS_semop,
+    "semtimedop" => libc::SYS_semtimedop,
+    #[cfg(target_arch = "x86_64")]
+    "sendfile" => libc::SYS_sendfile,
+    "sendmmsg" => libc::SYS_sendmmsg,
+    "sendmsg" => libc::SYS_sendmsg,
+    "sendto" => libc::SYS_sendto,
+    "set_robust_list" => libc::SYS_set_robust_list,
+    "set_tid_address" => libc::SYS_set_tid_address,
+    "setfsgid" => libc::SYS_setfsgid,
+    "setfsuid" => libc::SYS_setfsuid,
+    "setgid" => libc::SYS_setgid,
+    "setgroups" => libc::SYS_setgroups,
+    "setitimer" => libc::SYS_setitimer,
+    "setpgid" => libc::SYS_setpgid,
+    "setpriority" => libc::SYS_setpriority,
+    "setregid" => libc::SYS_setregid,
+    "setresgid" => libc::SYS_setresgid,
+    "setresuid" => libc::SYS_setresuid,
+    "setreuid" => libc::SYS_setreuid,
+    #[cfg(target_arch = "x86_64")]
+    "setrlimit" => libc::SYS_setrlimit,
+    "setsid" => libc::SYS_setsid,
+    "setsockopt" => libc::SYS_setsockopt,
+    "settimeofday" => libc::SYS_settimeofday,
+    "setuid" => libc::SYS_setuid,
+    "shmat" => libc::SYS_shmat,
+    "shmctl" => libc::SYS_shmctl,
+    "shmdt" => libc::SYS_shmdt,
+    "shmget" => libc::SYS_shmget,
+    "shutdown" => libc::SYS_shutdown,
+    "sigaltstack" => libc::SYS_sigaltstack,
+    #[cfg(target_arch = "x86_64")]
+    "signalfd" => libc::SYS_signalfd,
+    "signalfd4" => libc::SYS_signalfd4,
+    "socket" => libc::SYS_socket,
+    "socketpair" => libc::SYS_socketpair,
+    "splice" => libc::SYS_splice,
+    #[cfg(target_arch = "x86_64")]
+    "stat" => libc::SYS_stat,
+    "statfs" => libc::SYS_statfs,
+    "statx" => libc::SYS_statx,
+    "swapoff" => libc::SYS_swapoff,
+    "swapon" => libc::SYS_swapon,
+    #[cfg(target_arch = "x86_64")]
+    "symlink" => libc::SYS_symlink,
+    "symlinkat" => libc::SYS_symlinkat,
+    "sync" => libc::SYS_sync,
+    #[cfg(target_arch = "x86_64")]
+    "sync_file_range" => libc::SYS_sync_file_range,
+    "syncfs" => libc::SYS_syncfs,
+    "sysinfo" => libc::SYS_sysinfo,
+    "tee" => libc::SYS_tee,
+    "tgkill" => libc::SYS_tgkill,
+    "timer_create" => libc::SYS_timer_create,
+    "timer_delete" => libc::SYS_timer_delete,
+    "timer_getoverrun" => libc::SYS_timer_getoverrun,
+    "timer_gettime" => libc::SYS_timer_gettime,
+    "timer_settime" => libc::SYS_timer_settime,
+    "timerfd_create" => libc::SYS_timerfd_create,
+    "timerfd_gettime" => libc::SYS_timerfd_gettime,
+    "timerfd_settime" => libc::SYS_timerfd_settime,
+    "tkill" => libc::SYS_tkill,
+    "truncate" => libc::SYS_truncate,
+    "umask" => libc::SYS_umask,
+    "umount2" => libc::SYS_umount2,
+    "uname" => libc::SYS_uname,
+    #[cfg(target_arch = "x86_64")]
+    "unlink" => libc::SYS_unlink,
+    "unlinkat" => libc::SYS_unlinkat,
+    "utimensat" => libc::SYS_utimensat,
+    #[cfg(target_arch = "x86_64")]
+    "utimes" => libc::SYS_utimes,
+    #[cfg(target_arch = "x86_64")]
+    "vfork" => libc::SYS_vfork,
+    "vmsplice" => libc::SYS_vmsplice,
+    "wait4" => libc::SYS_wait4,
+    "waitid" => libc::SYS_waitid,
+    "write" => libc::SYS_write,
+    "writev" => libc::SYS_writev,
+    _ => return None,
+  };
+  Some(nr)
+}
+
+#[cfg(not(any(target_arch = "x86_64", target_arch = "aarch64")))]
+pub fn syscall_number(_name: &str) -> Option<i64> {
+  None
+}
+
+fn invalid(msg: String) -> Error {
+  Error::new(ErrorKind::InvalidInput, msg)
+}
+
+/// Resolves syscall names and `@group`s into numbers. Names given directly
+/// must exist on this architecture; group members that don't are skipped.
+pub fn resolve_syscalls(names: &[&str]) -> std::io::Result<BTreeSet<u32>> {
+  fn walk(name: &str, strict: bool, out: &mut BTreeSet<u32>, depth: usize) -> std::io::Result<()> {
+    if let Some(group) = name.starts_with('@').then(|| syscall_group(name)) {
+      let group = group.ok_or_else(|| invalid(format!("unknown syscall group '{name}'")))?;
+      if depth > 4 {
+        return Err(invalid(format!("syscall group '{name}' nests too deeply")));
+      }
+      for member in group {
+        walk(member, false, out, depth + 1)?;
+      }
+      return Ok(());
+    }
+    match syscall_number(name) {
+      Some(nr) => {
+        out.insert(nr as u32);
+      }
+      None if strict => {
+        return Err(invalid(format!(
+          "unknown syscall '{name}' for this architecture"
+        )));
+      }
+      None => {}
+    }
+    Ok(())
+  }
+
+  let mut out = BTreeSet::new();
+  for name in names {
+    walk(name.trim(), true, &mut out, 0)?;
+  }
+  Ok(out)
+}
+
+/// A compiled classic BPF program ready for `SECCOMP_SET_MODE_FILTER`.
+#[derive(Debug, Clone)]
+pub struct SeccompFilter {
+  pub program: Vec<libc::sock_filter>,
+}
+
+// Offsets into `struct seccomp_data`.
+const DATA_NR: u32 = 0;
+const DATA_ARCH: u32 = 4;
+#[cfg(target_arch = "x86_64")]
+const X32_SYSCALL_BIT: u32 = 0x4000_0000;
+
+fn stmt(code: u32, k: u32) -> libc::sock_filter {
+  jump(code, k, 0, 0)
+}
+
+fn jump(code: u32, k: u32, jt: u8, jf: u8) -> libc::sock_filter {
+  libc::sock_filter {
+    code: code as u16,
+    jt,
+    jf,
+    k,
+  }
+}
+
+impl SeccompFilter {
+  /// Compiles a resolved policy. With an `allow` list every other syscall
+  /// gets the policy action; otherwise only `deny`ed syscalls do. Returns
+  /// `None` for policies that install no filter (no lists, or `strict`).
+  pub fn compile(policy: &SeccompPolicy) -> std::io::Result<Option<Self>> {
+    let mut deny = policy.deny.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+    match policy.profile.as_ref().map(|p| p.as_str()) {
+      None => {}
+      Some("strict") => {
+        if !policy.allow.is_empty() || !policy.deny.is_empty() {
+          return Err(invalid(
+            "seccomp profile 'strict' cannot be combined with allow/deny lists".into(),
+          ));
+        }
+        return Ok(None);
+      }
+      Some("default") => deny.extend(DEFAULT_DENY_GROUPS),
+      Some(profile) => return Err(invalid(format!("unsupported seccomp profile '{profile}'"))),
+    }
+    if policy.allow.is_empty() && deny.is_empty() {
+      return Ok(None);
+    }
+    if !cfg!(any(target_arch = "x86_64", target_arch = "aarch64")) {
+      return Err(Error::new(
+        ErrorKind::Unsupported,
+        "seccomp filters are not supported on this architecture",
+      ));
+    }
+
+    let action = policy.action.unwrap_or_default().ret();
+    let denied = resolve_syscalls(&deny)?;
+    let (listed, on_match, otherwise) = if policy.allow.is_empty() {
+      (denied, action, libc::SECCOMP_RET_ALLOW)
+    } else {
+      let allow = policy.allow.iter().map(|n| n.as_str()).collect::<Vec<_>>();
+      let mut allowed = resolve_syscalls(&allow)?;
+      allowed.retain(|nr| !denied.contains(nr));
+      // the filter is installed before `execve`, so the service could never start
+      if syscall_number("execve").is_some_and(|nr| !allowed.contains(&(nr as u32))) {
+        return Err(invalid(
+          "seccomp allow list must permit 'execve' (it is part of '@default')".into(),
+        ));
+      }
+      (allowed, libc::SECCOMP_RET_ALLOW, action)
+    };
+
+    let ld = libc::BPF_LD | libc::BPF_W | libc::BPF_ABS;
+    let jeq = libc::BPF_JMP | libc::BPF_JEQ | libc::BPF_K;
+    let ret = libc::BPF_RET | libc::BPF_K;
+
+    let mut program = vec![
+      stmt(ld, DATA_ARCH),
+      jump(jeq, AUDIT_ARCH, 1, 0),
+      stmt(ret, libc::SECCOMP_RET_KILL_PROCESS),
+      stmt(ld, DATA_NR),
+    ];
+    // x32 syscalls share the x86_64 arch tag; treat them as unlisted-and-denied.
+    #[cfg(target_arch = "x86_64")]
+    program.extend([
+      jump(
+        libc::BPF_JMP | libc::BPF_JGE | libc::BPF_K,
+        X32_SYSCALL_BIT,
+        0,
+        1,
+      ),
+      stmt(ret, action),
+    ]);
+    for nr in listed {
+      program.push(jump(jeq, nr, 0, 1));
+      program.push(stmt(ret, on_match));
+    }
+    program.push(stmt(ret, otherwise));
+
+    if program.len() > u16::MAX as usize {
+      return Err(invalid("seccomp filter is too large".into()));
+    }
+    Ok(Some(Self { program }))
+  }
+
+  /// Installs the filter on the calling thread. Sets `no_new_privs` first so
+  /// unprivileged processes may load it. Only async-signal-safe calls are
+  /// made, so this is fine between fork and exec.
+  pub fn install(&self) -> std::io::Result<()> {
+    let prog = libc::sock_fprog {
+      len: self.program.len() as u16,
+      filter: self.program.as_ptr() as *mut libc::sock_filter,
+    };
+    unsafe {
+      if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) < 0 {
+        return Err(Error::last_os_error());
+      }
+      if libc::syscall(
+        libc::SYS_seccomp,
+        libc::SECCOMP_SET_MODE_FILTER,
+        0,
+        &prog as *const libc::sock_fprog,
+      ) < 0
+      {
+        return Err(Error::last_os_error());
+      }
+    }
+    Ok(())
+  }
+}
+
+/// A policy prepared in the parent. The forked child only installs it, since
+/// compiling allocates and isn't safe between fork and exec.
+#[derive(Debug, Clone)]
+pub enum SeccompProgram {
+  Strict,
+  Filter(SeccompFilter),
+}
+
+impl SeccompProgram {
+  /// Compiles a resolved policy, see `SeccompFilter::compile`. Returns
+  /// `None` when there is nothing to install.
+  pub fn prepare(policy: &SeccompPolicy) -> std::io::Result<Option<Self>> {
+    if policy.path.is_some() {
+      return Err(invalid(
+        "seccomp policy file must be resolved before spawning".into(),
+      ));
+    }
+    if policy.profile.as_ref().map(|p| p.as_str()) == Some("strict") {
+      SeccompFilter::compile(policy)?;
+      return Ok(Some(Self::Strict));
+    }
+    Ok(SeccompFilter::compile(policy)?.map(Self::Filter))
+  }
+
+  /// Installs the program on the calling thread. Only async-signal-safe
+  /// calls are made.
+  pub fn install(&self) -> std::io::Result<()> {
+    match self {
+      Self::Strict => unsafe {
+        if libc::prctl(libc::PR_SET_NO_NEW_PRIVS, 1, 0, 0, 0) < 0 {
+          return Err(Error::last_os_error());
+        }
+        if libc::prctl(libc::PR_SET_SECCOMP, libc::SECCOMP_MODE_STRICT, 0, 0, 0) < 0 {
+          return Err(Error::last_os_error());
+        }
+        Ok(())
+      },
+      Self::Filter(filter) => filter.install(),
+    }
+  }
+}
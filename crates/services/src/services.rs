@@ -1,3 +1,4 @@
+use nix::sys::epoll::EpollFlags;
 use nix::sys::signal::{Signal, kill};
 use nix::sys::time::TimeSpec;
 use nix::sys::timerfd::{ClockId, Expiration, TimerFd, TimerFlags, TimerSetTimeFlags};
@@ -6,11 +7,11 @@ use rind_ipc::payloads::SSPayload;
 use rind_ipc::ser::ser_to_vec;
 use rind_ipc::{Message, TransportMessageAction, TransportMessageType};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::io::{BufRead, BufReader};
 use std::ops::{Deref, DerefMut};
-use std::os::fd::{AsFd, AsRawFd, RawFd};
-use std::path::PathBuf;
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use std::sync::Mutex;
 use std::sync::atomic::{AtomicU64, Ordering};
@@ -20,6 +21,14 @@ use std::time::{Duration, Instant};
 use rind_core::reexports::*;
 use rind_core::{notifier::Notifier, prelude::*};
 
+use crate::cgroups::{
+  CgroupUsage, DEFAULT_MEMORY_PRESSURE, MemoryEvents, Pressure, apply_cgroup, open_memory_events,
+  open_psi_trigger,
+};
+use crate::history::{ExitHistory, ExitRecord, ExitStatus, ExitTrigger};
+use crate::namespaces::{cap_number, cap_numbers, securebit_flag};
+use crate::notify::{NotifyCommand, NotifySocket, drain_notify_fd, pid_in_instance};
+use crate::seccomp::{SeccompAction, SeccompFilter};
 use crate::sockets::get_all_sockets;
 use crate::{SocketRuntime, TimerRuntime};
 use rind_flow::transport::{TransportMethod, start_stdout_listener, transport_id};
@@ -61,6 +70,14 @@ pub struct RunOption {
   pub variable: Option<String>,
   #[serde(default)]
   pub executor: Option<Ustr>,
+  pub remote: Option<Ustr>,
+  /// File holding the pre-shared token of the agent at `remote`.
+  #[serde(rename = "remote-token")]
+  pub remote_token: Option<Ustr>,
+  /// Hook commands only: a non-zero exit doesn't fail the chain, like
+  /// systemd's `-` prefix.
+  #[serde(default, rename = "ignore-failure")]
+  pub ignore_failure: bool,
 
   pub files: Option<Vec<RunOptionFile>>,
 }
@@ -111,11 +128,174 @@ impl RunOptions {
   }
 }
 
-#[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, PartialEq)]
 #[serde(untagged, rename_all = "snake_case")]
 pub enum RestartPolicy {
   Bool(bool),
-  OnFailure { max_retries: u32 },
+  OnFailure(RetryLimit),
+  Detailed(RestartConfig),
+}
+
+/// The `{ max_retries = n }` form, kept apart from `RestartConfig` so it
+/// doesn't pick up a burst limit it never had.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RetryLimit {
+  pub max_retries: u32,
+}
+
+impl RestartPolicy {
+  /// `true` and `{ max_retries = n }` keep restarting however often the
+  /// service dies, so only the table form has a burst limit.
+  pub fn config(&self) -> Option<RestartConfig> {
+    match self {
+      RestartPolicy::Bool(false) => None,
+      RestartPolicy::Bool(true) => Some(RestartConfig {
+        on: RestartOn::Always,
+        burst: None,
+        ..Default::default()
+      }),
+      RestartPolicy::OnFailure(limit) => Some(RestartConfig {
+        max_retries: Some(limit.max_retries),
+        burst: None,
+        ..Default::default()
+      }),
+      RestartPolicy::Detailed(config) => Some(config.clone()),
+    }
+  }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RestartOn {
+  Always,
+  #[default]
+  OnFailure,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RestartConfig {
+  #[serde(default)]
+  pub on: RestartOn,
+  #[serde(rename = "max-retries")]
+  pub max_retries: Option<u32>,
+  #[serde(rename = "delay-ms", default = "default_restart_delay_ms")]
+  pub delay_ms: u64,
+  #[serde(default = "default_restart_multiplier")]
+  pub multiplier: f64,
+  #[serde(rename = "max-delay-ms", default = "default_restart_max_delay_ms")]
+  pub max_delay_ms: u64,
+  #[serde(default = "default_restart_burst")]
+  pub burst: Option<u32>,
+  #[serde(
+    rename = "burst-interval-ms",
+    default = "default_restart_burst_interval_ms"
+  )]
+  pub burst_interval_ms: u64,
+  #[serde(rename = "healthy-after-ms")]
+  pub healthy_after_ms: Option<u64>,
+}
+
+fn default_restart_delay_ms() -> u64 {
+  100
+}
+
+fn default_restart_burst() -> Option<u32> {
+  Some(5)
+}
+
+fn default_restart_multiplier() -> f64 {
+  2.0
+}
+
+fn default_restart_max_delay_ms() -> u64 {
+  60_000
+}
+
+fn default_restart_burst_interval_ms() -> u64 {
+  10_000
+}
+
+impl Default for RestartConfig {
+  fn default() -> Self {
+    Self {
+      on: RestartOn::default(),
+      max_retries: None,
+      delay_ms: default_restart_delay_ms(),
+      multiplier: default_restart_multiplier(),
+      max_delay_ms: default_restart_max_delay_ms(),
+      burst: default_restart_burst(),
+      burst_interval_ms: default_restart_burst_interval_ms(),
+      healthy_after_ms: None,
+    }
+  }
+}
+
+impl RestartConfig {
+  /// Backoff before restart number `attempt` (0-based).
+  pub fn delay_for(&self, attempt: u32) -> Duration {
+    let scaled = self.delay_ms as f64 * self.multiplier.max(1.0).powi(attempt as i32);
+    Duration::from_millis(scaled.min(self.max_delay_ms.max(self.delay_ms) as f64) as u64)
+  }
+}
+
+#[derive(Debug, Default)]
+pub struct RestartTracker {
+  pub attempts: u32,
+  pub recent: VecDeque<Instant>,
+  pub pending_fd: Option<RawFd>,
+}
+
+impl RestartTracker {
+  /// Decides what to do with an exit. `None` means the policy gives up,
+  /// otherwise the returned delay is how long to wait before restarting.
+  pub fn next(
+    &mut self,
+    config: &RestartConfig,
+    code: i32,
+    uptime: Duration,
+    now: Instant,
+  ) -> Result<Option<Duration>, String> {
+    if config.on == RestartOn::OnFailure && code == 0 {
+      return Ok(None);
+    }
+
+    if let Some(healthy_after) = config.healthy_after_ms
+      && uptime >= Duration::from_millis(healthy_after)
+    {
+      self.attempts = 0;
+      self.recent.clear();
+    }
+
+    if let Some(max_retries) = config.max_retries
+      && self.attempts >= max_retries
+    {
+      return Ok(None);
+    }
+
+    let window = Duration::from_millis(config.burst_interval_ms);
+    while self
+      .recent
+      .front()
+      .is_some_and(|at| now.duration_since(*at) > window)
+    {
+      self.recent.pop_front();
+    }
+    if let Some(burst) = config.burst
+      && self.recent.len() as u32 >= burst
+    {
+      return Err(format!(
+        "restart burst limit reached ({burst} in {}ms)",
+        config.burst_interval_ms
+      ));
+    }
+
+    let delay = config.delay_for(self.attempts);
+    self.attempts += 1;
+    self.recent.push_back(now);
+    Ok(Some(delay))
+  }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -149,12 +329,15 @@ pub enum ServiceState {
   Starting,
   Stopping,
   Exited(i32),
+  /// Exited with this code after the kernel OOM-killed a process in the
+  /// instance's cgroup.
+  OomKilled(i32),
   Error(String),
 }
 
 use crate::executors::{
   Executor, ExecutorContext, ImaExecutor, InstanceHandle, NamespaceNetworkConfig, NativeExecutor,
-  RemoteExecutor,
+  RemoteExecutor, is_virtual_pid,
 };
 
 pub struct ChildInstance {
@@ -164,7 +347,32 @@ pub struct ChildInstance {
   pub state: ServiceState,
   pub retry_count: u32,
   pub stop_time: Option<Instant>,
+  pub started_at: Instant,
   pub manually_stopped: bool,
+  pub main_pid: Option<u32>,
+  pub status: Option<String>,
+  pub launch: Option<LaunchContext>,
+  /// Why the instance is going down, set when rind stops or kills it.
+  pub exit_trigger: Option<ExitTrigger>,
+  /// Serves one accepted socket connection; never restarted.
+  pub accepted: bool,
+  /// The `pre-start` or `post-start` chain the instance waits on. It stays
+  /// `Starting`, without a process for `pre-start`, until the chain is done.
+  pub pending_hooks: Option<u64>,
+}
+
+/// What an instance was spawned with, so `pre-start`, `post-start`, `stop`
+/// and `reload` commands run as the same user, isolation and env.
+#[derive(Debug, Clone)]
+pub struct LaunchContext {
+  pub registry_key: Ustr,
+  pub branch_ctx: Option<ServiceBranchContext>,
+  pub resolved_user: Option<Ustr>,
+  pub envs: HashMap<Ustr, Ustr>,
+  pub isolation: ServiceIsolation,
+  pub cgroup_path: Option<PathBuf>,
+  pub namespace_mounts: Vec<NamespaceMountEntry>,
+  pub namespace_networks: Vec<NamespaceNetworkConfig>,
 }
 
 impl ChildInstance {
@@ -180,12 +388,28 @@ impl ChildInstance {
       state: ServiceState::Active,
       retry_count: 0,
       stop_time: None,
+      started_at: Instant::now(),
       manually_stopped: false,
+      main_pid: None,
+      status: None,
+      launch: None,
+      exit_trigger: None,
+      accepted: false,
+      pending_hooks: None,
     }
   }
 
   pub fn pid(&self) -> Option<u32> {
-    self.handle.as_ref().and_then(|h| h.pid())
+    self
+      .main_pid
+      .or_else(|| self.handle.as_ref().and_then(|h| h.pid()))
+  }
+
+  /// Reads what the instance's cgroup has consumed so far; `None` when it
+  /// runs without one.
+  pub fn cgroup_usage(&self) -> Option<CgroupUsage> {
+    let path = self.launch.as_ref()?.cgroup_path.as_ref()?;
+    CgroupUsage::read(path)
   }
 }
 
@@ -274,6 +498,24 @@ pub struct ServiceCgroup {
   pub cpu_max: Option<Ustr>,
   #[serde(rename = "pids-max")]
   pub pids_max: Option<Ustr>,
+  #[serde(rename = "memory-high")]
+  pub memory_high: Option<Ustr>,
+  #[serde(rename = "memory-swap-max")]
+  pub memory_swap_max: Option<Ustr>,
+  #[serde(rename = "cpu-weight")]
+  pub cpu_weight: Option<Ustr>,
+  /// One `io.max` line per device, e.g. `"8:0 rbps=1048576"`.
+  #[serde(rename = "io-max", default)]
+  pub io_max: Vec<Ustr>,
+  #[serde(rename = "io-weight")]
+  pub io_weight: Option<Ustr>,
+  #[serde(rename = "cpuset-cpus")]
+  pub cpuset_cpus: Option<Ustr>,
+  #[serde(rename = "cpuset-mems")]
+  pub cpuset_mems: Option<Ustr>,
+  /// PSI trigger for `memory.pressure`, or `"off"`.
+  #[serde(rename = "memory-pressure")]
+  pub memory_pressure: Option<Ustr>,
 }
 
 impl Default for ServiceCgroup {
@@ -283,16 +525,55 @@ impl Default for ServiceCgroup {
       memory_max: None,
       cpu_max: None,
       pids_max: None,
+      memory_high: None,
+      memory_swap_max: None,
+      cpu_weight: None,
+      io_max: Vec::new(),
+      io_weight: None,
+      cpuset_cpus: None,
+      cpuset_mems: None,
+      memory_pressure: None,
     }
   }
 }
 
 impl ServiceCgroup {
   fn is_empty(&self) -> bool {
-    self.path.is_none()
-      && self.memory_max.is_none()
-      && self.cpu_max.is_none()
-      && self.pids_max.is_none()
+    self.path.is_none() && self.memory_pressure.is_none() && self.settings().is_empty()
+  }
+
+  /// The PSI trigger to arm, unless pressure watching is turned off.
+  pub fn pressure_trigger(&self) -> Option<&str> {
+    match self.memory_pressure.as_ref().map(|p| p.as_str()) {
+      Some("off") => None,
+      Some(trigger) => Some(trigger),
+      None => Some(DEFAULT_MEMORY_PRESSURE),
+    }
+  }
+
+  /// Interface files to write, with the controller each one belongs to.
+  pub fn settings(&self) -> Vec<(&'static str, &'static str, &str)> {
+    let mut settings = Vec::new();
+    let scalars = [
+      ("memory", "memory.max", &self.memory_max),
+      ("memory", "memory.high", &self.memory_high),
+      ("memory", "memory.swap.max", &self.memory_swap_max),
+      ("cpu", "cpu.max", &self.cpu_max),
+      ("cpu", "cpu.weight", &self.cpu_weight),
+      ("io", "io.weight", &self.io_weight),
+      ("pids", "pids.max", &self.pids_max),
+      ("cpuset", "cpuset.cpus", &self.cpuset_cpus),
+      ("cpuset", "cpuset.mems", &self.cpuset_mems),
+    ];
+    for (controller, file, value) in scalars {
+      if let Some(value) = value {
+        settings.push((controller, file, value.as_str()));
+      }
+    }
+    for line in &self.io_max {
+      settings.push(("io", "io.max", line.as_str()));
+    }
+    settings
   }
 }
 
@@ -315,6 +596,8 @@ pub struct ServiceNamespaces {
   #[serde(default)]
   pub mount_private: bool,
   pub rootfs: Option<Ustr>,
+  pub overlay: Option<RootfsOverlay>,
+  pub propagation: Option<Ustr>,
   pub hostname: Option<Ustr>,
   #[serde(default)]
   pub persist: bool,
@@ -322,6 +605,14 @@ pub struct ServiceNamespaces {
   pub init: bool,
 }
 
+/// Writable layer over a read-only `rootfs`; `upper` and `work` must live on
+/// the same filesystem.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct RootfsOverlay {
+  pub upper: Ustr,
+  pub work: Ustr,
+}
+
 impl ServiceNamespaces {
   fn is_empty(&self) -> bool {
     !self.mount
@@ -333,6 +624,8 @@ impl ServiceNamespaces {
       && !self.cgroup
       && !self.mount_private
       && self.rootfs.is_none()
+      && self.overlay.is_none()
+      && self.propagation.is_none()
       && self.hostname.is_none()
       && !self.persist
       && !self.init
@@ -353,34 +646,194 @@ impl ServiceIsolation {
     self
       .namespaces
       .as_ref()
-      .map(|ns| ns.pid || ns.user || ns.persist || ns.init)
+      .map(|ns| ns.pid || ns.user || ns.persist || ns.init || ns.rootfs.is_some())
       .unwrap_or(false)
       || self.capabilities.is_some()
       || self.seccomp.is_some()
   }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "CapabilityPolicyToml")]
 pub struct CapabilityPolicy {
   pub drop: Vec<Ustr>,
   pub keep: Vec<Ustr>,
+  pub ambient: Vec<Ustr>,
+  pub inheritable: Vec<Ustr>,
+  #[serde(rename = "no-new-privileges")]
+  pub no_new_privileges: bool,
+  pub securebits: Vec<Ustr>,
+}
+
+#[derive(Deserialize)]
+struct CapabilityPolicyToml {
+  #[serde(default)]
+  drop: Vec<Ustr>,
+  #[serde(default)]
+  keep: Vec<Ustr>,
+  #[serde(default)]
+  ambient: Vec<Ustr>,
+  #[serde(default)]
+  inheritable: Vec<Ustr>,
+  #[serde(default, rename = "no-new-privileges")]
+  no_new_privileges: bool,
+  #[serde(default)]
+  securebits: Vec<Ustr>,
+}
+
+impl TryFrom<CapabilityPolicyToml> for CapabilityPolicy {
+  type Error = String;
+
+  fn try_from(raw: CapabilityPolicyToml) -> Result<Self, Self::Error> {
+    let policy = CapabilityPolicy {
+      drop: raw.drop,
+      keep: raw.keep,
+      ambient: raw.ambient,
+      inheritable: raw.inheritable,
+      no_new_privileges: raw.no_new_privileges,
+      securebits: raw.securebits,
+    };
+    policy.validate()?;
+    Ok(policy)
+  }
 }
 
 impl CapabilityPolicy {
   fn is_empty(&self) -> bool {
-    self.drop.is_empty() && self.keep.is_empty()
+    self.drop.is_empty()
+      && self.keep.is_empty()
+      && self.ambient.is_empty()
+      && self.inheritable.is_empty()
+      && !self.no_new_privileges
+      && self.securebits.is_empty()
+  }
+
+  pub fn drops_all(&self) -> bool {
+    self.drop.iter().any(|cap| cap.as_str() == "all")
+  }
+
+  /// Checks every name against the capability and securebits tables and
+  /// rejects contradictory settings.
+  pub fn validate(&self) -> Result<(), String> {
+    for (field, names) in [
+      ("drop", &self.drop),
+      ("keep", &self.keep),
+      ("ambient", &self.ambient),
+      ("inheritable", &self.inheritable),
+    ] {
+      for name in names {
+        let all_allowed = field == "drop";
+        if (all_allowed && name.as_str() == "all") || cap_number(name.as_str()).is_some() {
+          continue;
+        }
+        return Err(format!("unknown capability '{name}' in {field}"));
+      }
+    }
+    for name in &self.securebits {
+      if securebit_flag(name.as_str()).is_none() {
+        return Err(format!("unknown securebit '{name}'"));
+      }
+    }
+    let dropped = cap_numbers(&self.drop);
+    if let Some(cap) = self
+      .ambient
+      .iter()
+      .find(|cap| cap_number(cap.as_str()).is_some_and(|nr| dropped.contains(&nr)))
+    {
+      return Err(format!("ambient capability '{cap}' is also dropped"));
+    }
+    if !self.ambient.is_empty()
+      && self
+        .securebits
+        .iter()
+        .any(|bit| securebit_flag(bit.as_str()) == securebit_flag("no-cap-ambient-raise"))
+    {
+      return Err("ambient capabilities conflict with securebit 'no-cap-ambient-raise'".into());
+    }
+    Ok(())
+  }
+
+  /// Lists from both policies add up; `no-new-privileges` is sticky.
+  fn merge_over(self, base: CapabilityPolicy) -> CapabilityPolicy {
+    fn union(mut base: Vec<Ustr>, extra: Vec<Ustr>) -> Vec<Ustr> {
+      for name in extra {
+        if !base.contains(&name) {
+          base.push(name);
+        }
+      }
+      base
+    }
+    CapabilityPolicy {
+      drop: union(base.drop, self.drop),
+      keep: union(base.keep, self.keep),
+      ambient: union(base.ambient, self.ambient),
+      inheritable: union(base.inheritable, self.inheritable),
+      no_new_privileges: base.no_new_privileges || self.no_new_privileges,
+      securebits: union(base.securebits, self.securebits),
+    }
   }
 }
 
-#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct SeccompPolicy {
   pub profile: Option<Ustr>,
   pub path: Option<Ustr>,
+  #[serde(default)]
+  pub allow: Vec<Ustr>,
+  #[serde(default)]
+  pub deny: Vec<Ustr>,
+  pub action: Option<SeccompAction>,
 }
 
 impl SeccompPolicy {
   fn is_empty(&self) -> bool {
-    self.profile.is_none() && self.path.is_none()
+    self.profile.is_none()
+      && self.path.is_none()
+      && self.allow.is_empty()
+      && self.deny.is_empty()
+      && self.action.is_none()
+  }
+
+  /// Layers `self` over `base`: scalar settings override, a non-empty
+  /// `allow` list replaces the base one and `deny` lists add up.
+  fn merge_over(self, base: SeccompPolicy) -> SeccompPolicy {
+    let mut deny = base.deny;
+    for name in self.deny {
+      if !deny.contains(&name) {
+        deny.push(name);
+      }
+    }
+    SeccompPolicy {
+      profile: self.profile.or(base.profile),
+      path: self.path.or(base.path),
+      allow: if self.allow.is_empty() {
+        base.allow
+      } else {
+        self.allow
+      },
+      deny,
+      action: self.action.or(base.action),
+    }
+  }
+
+  /// Loads the policy file named by `path`, if any, and layers the inline
+  /// settings over it. The result no longer references a file, so it can be
+  /// compiled after fork without touching the filesystem.
+  pub fn resolve(self) -> CoreResult<SeccompPolicy> {
+    let Some(path) = self.path.clone() else {
+      return Ok(self);
+    };
+    let source = std::fs::read_to_string(path.as_str()).map_err(|err| {
+      CoreError::InvalidState(format!("failed to read seccomp policy '{path}': {err}"))
+    })?;
+    let file: SeccompPolicy = toml::from_str(&source)
+      .map_err(|err| CoreError::InvalidState(format!("invalid seccomp policy '{path}': {err}")))?;
+    if file.path.is_some() {
+      return Err(CoreError::InvalidState(format!(
+        "seccomp policy '{path}' may not reference another policy file"
+      )));
+    }
+    Ok(SeccompPolicy { path: None, ..self }.merge_over(file))
   }
 }
 
@@ -407,6 +860,68 @@ fn default_watchdog_grace_ms() -> u64 {
   15_000
 }
 
+/// Which processes receive the stop signal and the SIGKILL escalation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum KillMode {
+  #[default]
+  ProcessGroup,
+  MainProcess,
+  Cgroup,
+}
+
+/// A signal by name, `SIGINT` or `INT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct StopSignal(pub Signal);
+
+impl TryFrom<String> for StopSignal {
+  type Error = String;
+
+  fn try_from(value: String) -> Result<Self, Self::Error> {
+    let name = value.trim().to_ascii_uppercase();
+    let name = if name.starts_with("SIG") {
+      name
+    } else {
+      format!("SIG{name}")
+    };
+    name
+      .parse::<Signal>()
+      .map(StopSignal)
+      .map_err(|_| format!("unknown signal '{value}'"))
+  }
+}
+
+impl From<StopSignal> for String {
+  fn from(value: StopSignal) -> Self {
+    value.0.as_str().to_string()
+  }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StopOutcome {
+  /// Exited after the stop signal, within the timeout.
+  Clean,
+  /// Ignored the stop signal and was SIGKILLed after the timeout.
+  TimedOut,
+  /// Force-stopped with SIGKILL right away.
+  Killed,
+}
+
+/// How long a SIGKILLed instance may go unreported before it is torn down.
+const STOP_KILL_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+struct StopDeadline {
+  since: Instant,
+  timeout: Duration,
+  kill_mode: KillMode,
+  cgroup: Option<PathBuf>,
+  forced: bool,
+  /// When the stop timed out and SIGKILL was sent.
+  killed_at: Option<Instant>,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ServiceSpace {
@@ -418,13 +933,14 @@ pub enum ServiceSpace {
   },
 }
 
-#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum ServiceType {
   #[default]
   Fork,
   Wait,
   Job,
+  Notify,
 }
 
 #[model(
@@ -433,8 +949,10 @@ pub enum ServiceType {
     name, run, after, r#type, branching, restart, start_on, stop_on, on_start,
     on_stop, transport, working_dir, space, user_source, singleton, managed_by,
     cgroup, namespaces, watchdog, description, pre_exec, cleanup, options,
+    pre_start, post_start, stop, reload, stop_signal, stop_timeout_ms,
+    start_timeout_ms, hook_timeout_ms, kill_mode, seccomp, capabilities, network,
   ),
-  derive_metadata(Debug, Default)
+  derive_metadata(Debug, Clone, Default)
 )]
 pub struct Service {
   // Metadata
@@ -471,11 +989,31 @@ pub struct Service {
   pub cgroup: Option<ServiceCgroup>,
   pub namespaces: Option<ServiceNamespaces>,
   pub watchdog: Option<ServiceWatchdog>,
+  #[serde(rename = "pre-start")]
+  pub pre_start: Option<Vec<RunOption>>,
+  #[serde(rename = "post-start")]
+  pub post_start: Option<Vec<RunOption>>,
+  pub stop: Option<Vec<RunOption>>,
+  pub reload: Option<Vec<RunOption>>,
+  #[serde(rename = "stop-signal")]
+  pub stop_signal: Option<StopSignal>,
+  #[serde(rename = "stop-timeout-ms")]
+  pub stop_timeout_ms: Option<u64>,
+  #[serde(rename = "start-timeout-ms")]
+  pub start_timeout_ms: Option<u64>,
+  #[serde(rename = "hook-timeout-ms")]
+  pub hook_timeout_ms: Option<u64>,
+  #[serde(rename = "kill-mode", default)]
+  pub kill_mode: KillMode,
+  pub seccomp: Option<SeccompPolicy>,
+  pub capabilities: Option<CapabilityPolicy>,
+  pub network: Option<Vec<NamespaceNetworkConfig>>,
 
   // Instance data
   pub id: ServiceId,
   pub instances: ChildInstanceGroup,
   pub last_state: ServiceState,
+  pub last_stop: Option<StopOutcome>,
 }
 
 impl Service {
@@ -485,6 +1023,7 @@ impl Service {
       id: ServiceId::default(),
       instances: ChildInstanceGroup::default(),
       last_state: ServiceState::Inactive,
+      last_stop: None,
     }
   }
 }
@@ -494,13 +1033,90 @@ pub struct ServiceRuntime {
   stdio_rx: Receiver<(Ustr, TransportMessage, usize)>,
   stdio_writers: Mutex<HashMap<Ustr, Vec<Sender<TransportMessage>>>>,
   pid_map: HashMap<u32, Ustr>,
-  stopping_map: HashMap<u32, Instant>,
+  stopping_map: HashMap<u32, StopDeadline>,
   trigger_index: HashMap<Ustr, HashSet<Ustr>>,
   watchdog_fds: HashMap<RawFd, WatchdogBinding>,
   watchdog_pids: HashMap<u32, RawFd>,
+  restarts: HashMap<(Ustr, Ustr), RestartTracker>,
+  restart_fds: HashMap<RawFd, PendingRestart>,
+  notify_fds: HashMap<RawFd, NotifyBinding>,
+  notify_pids: HashMap<u32, RawFd>,
+  hooks: HashMap<u32, HookChain>,
+  hook_timers: HashMap<RawFd, u32>,
+  memory_watches: HashMap<u32, MemoryWatch>,
+  memory_watch_fds: HashMap<RawFd, u32>,
   executors: HashMap<Ustr, Box<dyn Executor>>,
 }
 
+static HOOK_CHAIN_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// What a hook chain leads to once its last command exited with 0.
+enum HookThen {
+  /// `pre-start`: spawns the instance waiting on the chain.
+  Spawn {
+    run: Box<RunOption>,
+    branch_ctx: Option<ServiceBranchContext>,
+    /// Copy of an accepted connection, handed to the instance.
+    connection: Option<OwnedFd>,
+  },
+  /// `post-start`: the instance waiting on the chain becomes active.
+  PostStart,
+  /// `stop`: if the chain fails, the instance gets `signal` after all.
+  Stop {
+    signal: Signal,
+  },
+  Reload,
+}
+
+/// `pre-start`, `post-start`, `stop` or `reload` commands of one instance,
+/// run one after another without blocking the main loop.
+struct HookChain {
+  id: u64,
+  hooks: Vec<RunOption>,
+  next: usize,
+  launch: LaunchContext,
+  main_pid: Option<u32>,
+  then: HookThen,
+  running: Option<RunningHook>,
+}
+
+impl HookChain {
+  fn new(
+    hooks: Vec<RunOption>,
+    launch: LaunchContext,
+    main_pid: Option<u32>,
+    then: HookThen,
+  ) -> Self {
+    Self {
+      id: HOOK_CHAIN_COUNTER.fetch_add(1, Ordering::Relaxed),
+      hooks,
+      next: 0,
+      launch,
+      main_pid,
+      then,
+      running: None,
+    }
+  }
+}
+
+struct RunningHook {
+  exec: Ustr,
+  handle: Box<dyn InstanceHandle>,
+  timer_fd: RawFd,
+  timeout: Duration,
+  timed_out: bool,
+  ignore_failure: bool,
+}
+
+/// A restart armed for one instance, keyed like `restarts` by the scoped
+/// service name and the instance's branch key.
+#[derive(Debug, Clone)]
+struct PendingRestart {
+  service_key: Ustr,
+  key: Ustr,
+  branch_ctx: Option<ServiceBranchContext>,
+}
+
 #[derive(Debug, Clone)]
 struct WatchdogBinding {
   service_key: Ustr,
@@ -509,6 +1125,35 @@ struct WatchdogBinding {
   pid: u32,
 }
 
+/// `memory.events` and `memory.pressure` fds of one instance cgroup.
+#[derive(Debug, Clone)]
+struct MemoryWatch {
+  service_key: Ustr,
+  branch: Option<Ustr>,
+  events_fd: Option<RawFd>,
+  pressure_fd: Option<RawFd>,
+  oom_kills_at_start: u64,
+  oom_kills_reported: u64,
+}
+
+#[derive(Debug, Clone)]
+struct NotifyBinding {
+  service_key: Ustr,
+  pid: u32,
+  /// The process rind spawned, `pid` moves on `MAINPID`.
+  root: u32,
+  cgroup: Option<PathBuf>,
+  path: PathBuf,
+  /// Start timeout, disarmed on `READY=1`.
+  ready_fd: Option<RawFd>,
+}
+
+impl NotifyBinding {
+  fn owns(&self, pid: u32) -> bool {
+    pid_in_instance(pid, &[self.pid, self.root], self.cgroup.as_deref())
+  }
+}
+
 impl Default for ServiceRuntime {
   fn default() -> Self {
     let (stdio_tx, stdio_rx) = mpsc::channel();
@@ -530,6 +1175,14 @@ impl Default for ServiceRuntime {
       trigger_index: HashMap::new(),
       watchdog_fds: HashMap::new(),
       watchdog_pids: HashMap::new(),
+      restarts: HashMap::new(),
+      restart_fds: HashMap::new(),
+      notify_fds: HashMap::new(),
+      notify_pids: HashMap::new(),
+      hooks: HashMap::new(),
+      hook_timers: HashMap::new(),
+      memory_watches: HashMap::new(),
+      memory_watch_fds: HashMap::new(),
       executors,
     }
   }
@@ -540,7 +1193,7 @@ impl ServiceRuntime {
     Ustr::from(key.split('@').next().unwrap_or(key))
   }
 
-  fn ensure_scoped_name(name: &str) -> Ustr {
+  pub(crate) fn ensure_scoped_name(name: &str) -> Ustr {
     if name.contains('@') {
       Ustr::from(name)
     } else {
@@ -613,6 +1266,27 @@ impl ServiceRuntime {
       memory_max: Self::attr(&attrs, &["cgroup.memory-max", "cgroup.memory_max"]).map(Ustr::from),
       cpu_max: Self::attr(&attrs, &["cgroup.cpu-max", "cgroup.cpu_max"]).map(Ustr::from),
       pids_max: Self::attr(&attrs, &["cgroup.pids-max", "cgroup.pids_max"]).map(Ustr::from),
+      memory_high: Self::attr(&attrs, &["cgroup.memory-high", "cgroup.memory_high"])
+        .map(Ustr::from),
+      memory_swap_max: Self::attr(
+        &attrs,
+        &["cgroup.memory-swap-max", "cgroup.memory_swap_max"],
+      )
+      .map(Ustr::from),
+      cpu_weight: Self::attr(&attrs, &["cgroup.cpu-weight", "cgroup.cpu_weight"]).map(Ustr::from),
+      io_max: Self::attr(&attrs, &["cgroup.io-max", "cgroup.io_max"])
+        .map(Self::split_attr_list)
+        .unwrap_or_default(),
+      io_weight: Self::attr(&attrs, &["cgroup.io-weight", "cgroup.io_weight"]).map(Ustr::from),
+      cpuset_cpus: Self::attr(&attrs, &["cgroup.cpuset-cpus", "cgroup.cpuset_cpus"])
+        .map(Ustr::from),
+      cpuset_mems: Self::attr(&attrs, &["cgroup.cpuset-mems", "cgroup.cpuset_mems"])
+        .map(Ustr::from),
+      memory_pressure: Self::attr(
+        &attrs,
+        &["cgroup.memory-pressure", "cgroup.memory_pressure"],
+      )
+      .map(Ustr::from),
     };
     (!cgroup.is_empty()).then_some(cgroup)
   }
@@ -650,6 +1324,27 @@ impl ServiceRuntime {
         &["namespace.rootfs", "namespaces.rootfs", "ns.rootfs"],
       )
       .map(Ustr::from),
+      overlay: Self::attr(
+        &attrs,
+        &["namespace.overlay.upper", "namespaces.overlay.upper"],
+      )
+      .zip(Self::attr(
+        &attrs,
+        &["namespace.overlay.work", "namespaces.overlay.work"],
+      ))
+      .map(|(upper, work)| RootfsOverlay {
+        upper: Ustr::from(upper),
+        work: Ustr::from(work),
+      }),
+      propagation: Self::attr(
+        &attrs,
+        &[
+          "namespace.propagation",
+          "namespaces.propagation",
+          "ns.propagation",
+        ],
+      )
+      .map(Ustr::from),
       hostname: Self::attr(
         &attrs,
         &["namespace.hostname", "namespaces.hostname", "ns.hostname"],
@@ -690,19 +1385,80 @@ impl ServiceRuntime {
       )
       .map(Self::split_attr_list)
       .unwrap_or_default(),
+      ambient: Self::attr(
+        &attrs,
+        &["capabilities.ambient", "capability.ambient", "caps.ambient"],
+      )
+      .map(Self::split_attr_list)
+      .unwrap_or_default(),
+      inheritable: Self::attr(
+        &attrs,
+        &[
+          "capabilities.inheritable",
+          "capability.inheritable",
+          "caps.inheritable",
+        ],
+      )
+      .map(Self::split_attr_list)
+      .unwrap_or_default(),
+      no_new_privileges: Self::attr_bool(
+        &attrs,
+        &["capabilities.no-new-privileges", "no-new-privileges"],
+      )
+      .unwrap_or(false),
+      securebits: Self::attr(&attrs, &["capabilities.securebits", "securebits"])
+        .map(Self::split_attr_list)
+        .unwrap_or_default(),
     };
     (!caps.is_empty()).then_some(caps)
   }
 
+  fn merge_capabilities(
+    scope: Option<CapabilityPolicy>,
+    service: Option<CapabilityPolicy>,
+  ) -> CoreResult<Option<CapabilityPolicy>> {
+    let merged = match (scope, service) {
+      (None, None) => return Ok(None),
+      (Some(p), None) | (None, Some(p)) => p,
+      (Some(scope), Some(service)) => service.merge_over(scope),
+    };
+    merged
+      .validate()
+      .map_err(|err| CoreError::InvalidState(format!("invalid capability policy: {err}")))?;
+    Ok(Some(merged))
+  }
+
   fn scope_seccomp(scope: Option<&str>) -> Option<SeccompPolicy> {
     let attrs = ScopeStore::attrs_for_scope(scope?)?;
     let seccomp = SeccompPolicy {
       profile: Self::attr(&attrs, &["seccomp.profile"]).map(Ustr::from),
       path: Self::attr(&attrs, &["seccomp.path"]).map(Ustr::from),
+      allow: Self::attr(&attrs, &["seccomp.allow"])
+        .map(Self::split_attr_list)
+        .unwrap_or_default(),
+      deny: Self::attr(&attrs, &["seccomp.deny"])
+        .map(Self::split_attr_list)
+        .unwrap_or_default(),
+      action: Self::attr(&attrs, &["seccomp.action"])
+        .and_then(|action| SeccompAction::try_from(action.to_string()).ok()),
     };
     (!seccomp.is_empty()).then_some(seccomp)
   }
 
+  fn merge_seccomp(
+    scope: Option<SeccompPolicy>,
+    service: Option<SeccompPolicy>,
+  ) -> CoreResult<Option<SeccompPolicy>> {
+    let merged = match (scope, service) {
+      (None, None) => return Ok(None),
+      (Some(p), None) | (None, Some(p)) => p.resolve()?,
+      (Some(scope), Some(service)) => service.resolve()?.merge_over(scope.resolve()?),
+    };
+    SeccompFilter::compile(&merged)
+      .map_err(|err| CoreError::InvalidState(format!("invalid seccomp policy: {err}")))?;
+    Ok(Some(merged))
+  }
+
   fn merge_cgroup(
     scope: Option<ServiceCgroup>,
     service: Option<ServiceCgroup>,
@@ -715,6 +1471,18 @@ impl ServiceRuntime {
         memory_max: service.memory_max.or(scope.memory_max),
         cpu_max: service.cpu_max.or(scope.cpu_max),
         pids_max: service.pids_max.or(scope.pids_max),
+        memory_high: service.memory_high.or(scope.memory_high),
+        memory_swap_max: service.memory_swap_max.or(scope.memory_swap_max),
+        cpu_weight: service.cpu_weight.or(scope.cpu_weight),
+        io_max: if service.io_max.is_empty() {
+          scope.io_max
+        } else {
+          service.io_max
+        },
+        io_weight: service.io_weight.or(scope.io_weight),
+        cpuset_cpus: service.cpuset_cpus.or(scope.cpuset_cpus),
+        cpuset_mems: service.cpuset_mems.or(scope.cpuset_mems),
+        memory_pressure: service.memory_pressure.or(scope.memory_pressure),
       }),
     }
   }
@@ -736,6 +1504,8 @@ impl ServiceRuntime {
         cgroup: scope.cgroup || service.cgroup,
         mount_private: scope.mount_private || service.mount_private,
         rootfs: service.rootfs.or(scope.rootfs),
+        overlay: service.overlay.or(scope.overlay),
+        propagation: service.propagation.or(scope.propagation),
         hostname: service.hostname.or(scope.hostname),
         persist: scope.persist || service.persist,
         init: scope.init || service.init,
@@ -771,6 +1541,44 @@ impl ServiceRuntime {
     )))
   }
 
+  /// The remote executor runs `run` as the agent's user, outside any local
+  /// cgroup or namespace, so it can't honour a user switch or isolation.
+  fn validate_remote_confinement(
+    service: &Service,
+    run: &RunOption,
+    user: Option<&Ustr>,
+    isolation: &ServiceIsolation,
+  ) -> CoreResult<Void> {
+    if run.executor.as_ref().map(|e| e.as_str()) != Some("remote") {
+      return Ok(Void);
+    }
+    let mut invalid = Vec::new();
+    if user.is_some() {
+      invalid.push("user");
+    }
+    if isolation.cgroup.is_some() {
+      invalid.push("cgroup");
+    }
+    if isolation.namespaces.is_some() {
+      invalid.push("namespaces");
+    }
+    if isolation.capabilities.is_some() {
+      invalid.push("capabilities");
+    }
+    if isolation.seccomp.is_some() {
+      invalid.push("seccomp");
+    }
+    if invalid.is_empty() {
+      return Ok(Void);
+    }
+
+    Err(CoreError::InvalidState(format!(
+      "service '{}' uses the remote executor, which can't apply: {}",
+      service.metadata.name,
+      invalid.join(", ")
+    )))
+  }
+
   fn isolation_for(service: &Service, scope: Option<&str>) -> CoreResult<ServiceIsolation> {
     Self::validate_service_inline_namespaces(service)?;
     Ok(ServiceIsolation {
@@ -780,8 +1588,11 @@ impl ServiceRuntime {
         Self::scope_namespaces(scope),
         service.metadata.namespaces.clone(),
       ),
-      capabilities: Self::scope_capabilities(scope),
-      seccomp: Self::scope_seccomp(scope),
+      capabilities: Self::merge_capabilities(
+        Self::scope_capabilities(scope),
+        service.metadata.capabilities.clone(),
+      )?,
+      seccomp: Self::merge_seccomp(Self::scope_seccomp(scope), service.metadata.seccomp.clone())?,
     })
   }
 
@@ -796,24 +1607,8 @@ impl ServiceRuntime {
     let Some(cgroup) = cgroup else {
       return Ok(Void);
     };
-    let Some(path) = Self::cgroup_path_for(service, Some(cgroup), branch_ctx, user) else {
-      return Ok(Void);
-    };
-
-    std::fs::create_dir_all(&path)?;
-
-    if let Some(mem) = &cgroup.memory_max {
-      let _ = std::fs::write(path.join("memory.max"), mem.as_str());
-    }
-    if let Some(cpu) = &cgroup.cpu_max {
-      let _ = std::fs::write(path.join("cpu.max"), cpu.as_str());
-    }
-    if let Some(pids) = &cgroup.pids_max {
-      let _ = std::fs::write(path.join("pids.max"), pids.as_str());
-    }
-
-    std::fs::write(path.join("cgroup.procs"), pid.to_string())?;
-    Ok(Void)
+    let path = Self::cgroup_path_for(service, Some(cgroup), branch_ctx, user);
+    apply_cgroup(path.as_deref(), Some(cgroup), pid)
   }
 
   fn arm_watchdog_timer(
@@ -875,16 +1670,673 @@ impl ServiceRuntime {
     }
   }
 
-  fn refresh_watchdog_fd(&self, fd: RawFd, watchdog: &ServiceWatchdog) -> Result<Void, CoreError> {
-    let grace = Duration::from_millis(watchdog.grace_ms.max(1));
-    let spec = libc::itimerspec {
-      it_interval: libc::timespec {
-        tv_sec: 0,
-        tv_nsec: 0,
-      },
-      it_value: libc::timespec {
-        tv_sec: grace.as_secs() as libc::time_t,
-        tv_nsec: grace.subsec_nanos() as libc::c_long,
+  fn stop_timeout(service: &ServiceMetadata) -> Duration {
+    service.stop_timeout_ms.map_or_else(
+      || {
+        Duration::from_secs(
+          std::env::var("RIND_SERVICE_TIMEOUT")
+            .ok()
+            .and_then(|x| x.parse::<u64>().ok())
+            .unwrap_or(5),
+        )
+      },
+      Duration::from_millis,
+    )
+  }
+
+  /// `hook-timeout-ms`, 30s by default.
+  fn hook_timeout(service: &Service) -> Duration {
+    Duration::from_millis(service.metadata.hook_timeout_ms.unwrap_or(30_000).max(1))
+  }
+
+  /// Spawns the next command of `chain` and tracks it in `pid_map`; its
+  /// exit comes back through `hook_exited`. Returns the chain with its
+  /// outcome once every command ran, or when one can't be spawned and
+  /// doesn't ignore failures.
+  fn advance_hooks(
+    &mut self,
+    service: &Service,
+    mut chain: HookChain,
+    log: &LogHandle,
+    resources: &mut Resources,
+  ) -> Option<(HookChain, CoreResult<Void>)> {
+    loop {
+      let Some(hook) = chain.hooks.get(chain.next).cloned() else {
+        return Some((chain, Ok(Void)));
+      };
+      chain.next += 1;
+      match self.spawn_hook(service, &hook, &chain, log, resources) {
+        Ok((pid, running)) => {
+          self.pid_map.insert(pid, chain.launch.registry_key.clone());
+          self.hook_timers.insert(running.timer_fd, pid);
+          chain.running = Some(running);
+          self.hooks.insert(pid, chain);
+          return None;
+        }
+        Err(e) if hook.ignore_failure => log.log(
+          LogLevel::Warn,
+          "service-runtime",
+          format!("ignoring failure of command '{}': {e}", hook.exec),
+          HashMap::from([("service".into(), chain.launch.registry_key.to_string())]),
+        ),
+        Err(e) => return Some((chain, Err(e))),
+      }
+    }
+  }
+
+  fn spawn_hook(
+    &self,
+    service: &Service,
+    hook: &RunOption,
+    chain: &HookChain,
+    log: &LogHandle,
+    resources: &mut Resources,
+  ) -> CoreResult<(u32, RunningHook)> {
+    let launch = &chain.launch;
+    let executor_name = hook
+      .executor
+      .clone()
+      .unwrap_or_else(|| Ustr::from("native"));
+    let executor = self
+      .executors
+      .get(&executor_name)
+      .ok_or_else(|| CoreError::Custom(format!("Executor {} not found", executor_name)))?;
+
+    let mut envs = launch.envs.clone();
+    if let Some(env) = &hook.env {
+      envs.extend(env.clone());
+    }
+    if let Some(pid) = chain.main_pid {
+      envs.insert(Ustr::from("MAINPID"), Ustr::from(pid.to_string()));
+    }
+
+    // executors wait for `wait` services in place, hooks must not block
+    let mut metadata = (*service.metadata).clone();
+    metadata.r#type = ServiceType::Fork;
+    let hook_service = Service::new(Arc::new(metadata));
+
+    let mut handle = executor.spawn(ExecutorContext {
+      service: &hook_service,
+      run: hook,
+      log,
+      branch_ctx: launch.branch_ctx.as_ref(),
+      sockets_map: &HashMap::new(),
+      sm: None,
+      variables: None,
+      registry_key: launch.registry_key.clone(),
+      notifier: None,
+      resources: &mut *resources,
+      resolved_user: launch.resolved_user.clone(),
+      args: hook
+        .args
+        .iter()
+        .map(|arg| expand_hook_arg(arg, &envs))
+        .collect(),
+      envs,
+      isolation: launch.isolation.clone(),
+      cgroup_path: launch.cgroup_path.clone(),
+      namespace_mounts: launch.namespace_mounts.clone(),
+      namespace_networks: launch.namespace_networks.clone(),
+    })?;
+
+    let Some(pid) = handle.pid().filter(|pid| !is_virtual_pid(*pid)) else {
+      let _ = handle.kill(Signal::SIGKILL);
+      return Err(CoreError::InvalidState(format!(
+        "command '{}' needs an executor that forks locally",
+        hook.exec
+      )));
+    };
+    drop(handle.take_stdin());
+    start_service_stream_logs(
+      launch.registry_key.clone(),
+      handle.take_stdout(),
+      handle.take_stderr(),
+      log.clone(),
+    );
+
+    let timeout = Self::hook_timeout(service);
+    let timer_fd = match Self::arm_hook_timer(pid, timeout, resources) {
+      Ok(fd) => fd,
+      Err(e) => {
+        let _ = handle.kill(Signal::SIGKILL);
+        return Err(e);
+      }
+    };
+    Ok((
+      pid,
+      RunningHook {
+        exec: hook.exec.clone(),
+        handle,
+        timer_fd,
+        timeout,
+        timed_out: false,
+        ignore_failure: hook.ignore_failure,
+      },
+    ))
+  }
+
+  fn arm_hook_timer(pid: u32, timeout: Duration, resources: &mut Resources) -> CoreResult<RawFd> {
+    let tfd = TimerFd::new(
+      ClockId::CLOCK_MONOTONIC,
+      TimerFlags::TFD_NONBLOCK | TimerFlags::TFD_CLOEXEC,
+    )
+    .map_err(CoreError::custom)?;
+    tfd
+      .set(
+        Expiration::OneShot(TimeSpec::from(timeout.max(Duration::from_millis(1)))),
+        TimerSetTimeFlags::empty(),
+      )
+      .map_err(CoreError::custom)?;
+
+    let fd = tfd.as_fd().as_raw_fd();
+    resources.own(fd, tfd);
+    resources.action(
+      fd,
+      ResourceAction::from(("services", "hook_expired")).payload(move |p| p.insert("pid", pid)),
+    );
+    Ok(fd)
+  }
+
+  /// Moves the chain of the hook command `pid` along once it exited with
+  /// `code`. A non-zero exit or a timeout ends the chain with an error,
+  /// unless the command ignores failures, which only covers its exit.
+  fn hook_exited(
+    &mut self,
+    service: Option<&Service>,
+    pid: u32,
+    code: i32,
+    log: &LogHandle,
+    resources: &mut Resources,
+  ) -> Option<(HookChain, CoreResult<Void>)> {
+    let mut chain = self.hooks.remove(&pid)?;
+    self.pid_map.remove(&pid);
+    let running = chain.running.take()?;
+    if self.hook_timers.remove(&running.timer_fd).is_some() {
+      resources.terminate(running.timer_fd);
+    }
+
+    let failure = if running.timed_out {
+      Some(format!(
+        "command '{}' timed out after {}s",
+        running.exec,
+        running.timeout.as_secs()
+      ))
+    } else if code != 0 && !running.ignore_failure {
+      Some(format!("command '{}' exited with {code}", running.exec))
+    } else {
+      if code != 0 {
+        log.log(
+          LogLevel::Warn,
+          "service-runtime",
+          format!(
+            "ignoring failure of command '{}' (exit {code})",
+            running.exec
+          ),
+          HashMap::from([
+            ("service".into(), chain.launch.registry_key.to_string()),
+            ("pid".into(), pid.to_string()),
+          ]),
+        );
+      }
+      None
+    };
+    if let Some(failure) = failure {
+      return Some((chain, Err(CoreError::InvalidState(failure))));
+    }
+
+    match service {
+      Some(service) => self.advance_hooks(service, chain, log, resources),
+      None => Some((
+        chain,
+        Err(CoreError::InvalidState(
+          "service is no longer loaded".into(),
+        )),
+      )),
+    }
+  }
+
+  /// Kills the running command of chain `id`, which then finishes with an
+  /// error.
+  fn abort_hooks(&mut self, id: u64) {
+    if let Some(running) = self
+      .hooks
+      .values_mut()
+      .find(|chain| chain.id == id)
+      .and_then(|chain| chain.running.as_mut())
+    {
+      let _ = running.handle.kill(Signal::SIGKILL);
+    }
+  }
+
+  /// Acts on a finished chain, see `HookThen`.
+  fn finish_hooks(
+    &mut self,
+    chain: HookChain,
+    result: CoreResult<Void>,
+    ctx: &mut RuntimeContext<'_>,
+    dispatch: &RuntimeDispatcher,
+    log: &LogHandle,
+  ) -> CoreResult<Void> {
+    let key = chain.launch.registry_key.clone();
+    let started = match chain.then {
+      HookThen::Spawn {
+        run,
+        mut branch_ctx,
+        connection,
+      } => {
+        if let Some(branch_ctx) = branch_ctx.as_mut() {
+          branch_ctx.connection_fd = connection.as_ref().map(|fd| fd.as_raw_fd());
+        }
+        let sockets_map = get_all_sockets(&ctx.registry);
+        let notifier = ctx.notifier.clone();
+        ctx
+          .registry
+          .singleton_handle::<(&mut FacetGraph, &mut VariableHeap), bool>(
+            (FacetGraph::KEY.into(), VariableHeap::KEY.into()),
+            |registry, (sm, vh)| {
+              let Some(service) = registry
+                .instances
+                .get_mut(&key)
+                .and_then(|x| x.iter_mut().find_map(|i| i.downcast_mut::<Service>()))
+              else {
+                return Ok(false);
+              };
+              // gone or stopped while the hooks ran
+              let Some(idx) = service.instances.0.iter().position(|inst| {
+                inst.pending_hooks == Some(chain.id) && inst.state == ServiceState::Starting
+              }) else {
+                return Ok(false);
+              };
+
+              let spawned = result.and_then(|_| {
+                self.spawn_process(
+                  service,
+                  &run,
+                  log,
+                  branch_ctx.as_ref(),
+                  &sockets_map,
+                  Some(sm),
+                  Some(vh),
+                  key.clone(),
+                  notifier.clone(),
+                  ctx.resources,
+                  chain.launch.namespace_mounts.clone(),
+                  chain.launch.namespace_networks.clone(),
+                  true,
+                )
+              });
+              match spawned {
+                Ok(instance) => {
+                  service.instances.0[idx] = instance;
+                  self.register_service_transport(service, dispatch, Some(key.clone()));
+                  let inst = &mut service.instances.0[idx];
+                  if inst.pending_hooks.is_some() || service.metadata.r#type == ServiceType::Notify
+                  {
+                    return Ok(false);
+                  }
+                  inst.state = ServiceState::Active;
+                  self.run_triggers(service.metadata.on_start.as_ref(), Some(sm), dispatch, log);
+                  Ok(true)
+                }
+                Err(e) => {
+                  service.instances.0.remove(idx);
+                  service.last_state =
+                    ServiceState::Error(format!("Failed to start service \"{key}\": {e}"));
+                  let mut fields = self.log_fields(service, "start", Some(&key));
+                  fields.insert("error".into(), e.to_string());
+                  log.log(
+                    LogLevel::Error,
+                    "service-runtime",
+                    "failed to start service",
+                    fields,
+                  );
+                  Self::announce_failed_start(dispatch, key.clone());
+                  Ok(false)
+                }
+              }
+            },
+          )?
+      }
+      HookThen::PostStart => ctx.registry.singleton_handle::<(&mut FacetGraph,), bool>(
+        (FacetGraph::KEY.into(),),
+        |registry, (sm,)| {
+          let Some(service) = registry
+            .instances
+            .get_mut(&key)
+            .and_then(|x| x.iter_mut().find_map(|i| i.downcast_mut::<Service>()))
+          else {
+            return Ok(false);
+          };
+          let Some(idx) = service
+            .instances
+            .0
+            .iter()
+            .position(|inst| inst.pending_hooks == Some(chain.id))
+          else {
+            return Ok(false);
+          };
+          let metadata = service.metadata.clone();
+          let inst = &mut service.instances.0[idx];
+          inst.pending_hooks = None;
+
+          if let Err(e) = result {
+            // the start failed, this is no crash to restart from
+            inst.manually_stopped = true;
+            let cgroup = inst.launch.as_ref().and_then(|l| l.cgroup_path.clone());
+            if let Some(handle) = inst.handle.as_mut() {
+              let _ = handle.kill(Signal::SIGTERM);
+            }
+            if let Some(pid) = inst.pid() {
+              self.stopping_map.insert(
+                pid,
+                StopDeadline {
+                  since: Instant::now(),
+                  timeout: Self::stop_timeout(&metadata),
+                  kill_mode: metadata.kill_mode,
+                  cgroup,
+                  forced: false,
+                  killed_at: None,
+                },
+              );
+            }
+            inst.state = ServiceState::Stopping;
+            service.last_state =
+              ServiceState::Error(format!("Failed to start service \"{key}\": {e}"));
+            let mut fields = self.log_fields(service, "start", Some(&key));
+            fields.insert("error".into(), e.to_string());
+            log.log(
+              LogLevel::Error,
+              "service-runtime",
+              "post-start failed, stopping service",
+              fields,
+            );
+            return Ok(false);
+          }
+
+          if inst.state != ServiceState::Starting || metadata.r#type == ServiceType::Notify {
+            return Ok(false);
+          }
+          inst.state = ServiceState::Active;
+          self.run_triggers(metadata.on_start.as_ref(), Some(sm), dispatch, log);
+          Ok(true)
+        },
+      )?,
+      HookThen::Stop { signal } => {
+        if let Err(e) = result {
+          let mut fields = HashMap::new();
+          fields.insert("service".to_string(), key.to_string());
+          fields.insert("error".to_string(), e.to_string());
+          log.log(
+            LogLevel::Warn,
+            "service-runtime",
+            "stop command failed, sending the stop signal",
+            fields,
+          );
+          if let Ok(service) = ctx.registry.as_one_mut::<Service>("*", key.as_str())
+            && let Some(pid) = chain.main_pid
+            && let Some(idx) = service.instances.find_by_pid(pid as i32)
+          {
+            let kill_mode = service.metadata.kill_mode;
+            let inst = &mut service.instances.0[idx];
+            let cgroup = inst.launch.as_ref().and_then(|l| l.cgroup_path.clone());
+            if let Some(handle) = inst.handle.as_mut() {
+              signal_instance(
+                handle.as_mut(),
+                inst.main_pid,
+                kill_mode,
+                cgroup.as_deref(),
+                signal,
+              );
+            }
+          }
+        }
+        false
+      }
+      HookThen::Reload => {
+        let mut fields = HashMap::new();
+        fields.insert("action".to_string(), "reload".to_string());
+        fields.insert("service".to_string(), key.to_string());
+        match result {
+          Ok(_) => log.log(
+            LogLevel::Info,
+            "service-runtime",
+            "service reloaded",
+            fields,
+          ),
+          Err(e) => {
+            fields.insert("error".to_string(), e.to_string());
+            log.log(LogLevel::Error, "service-runtime", "reload failed", fields);
+          }
+        }
+        false
+      }
+    };
+
+    if started {
+      self.reconcile(ctx, log, dispatch, key, ServiceEventKind::Started)?;
+    }
+    Ok(Void)
+  }
+
+  fn close_notify_pid(&mut self, pid: u32, resources: &mut Resources) {
+    if let Some(fd) = self.notify_pids.remove(&pid)
+      && let Some(binding) = self.notify_fds.remove(&fd)
+    {
+      resources.terminate(fd);
+      if let Some(ready_fd) = binding.ready_fd {
+        resources.terminate(ready_fd);
+      }
+      let _ = std::fs::remove_file(&binding.path);
+    }
+  }
+
+  /// Fails a notify instance that hasn't sent `READY=1` within
+  /// `start-timeout-ms` (90s by default), see `ready_timeout`.
+  fn arm_ready_timeout(
+    &mut self,
+    service: &ServiceMetadata,
+    notify_fd: RawFd,
+    resources: &mut Resources,
+  ) -> Result<RawFd, CoreError> {
+    let tfd = TimerFd::new(
+      ClockId::CLOCK_MONOTONIC,
+      TimerFlags::TFD_NONBLOCK | TimerFlags::TFD_CLOEXEC,
+    )
+    .map_err(CoreError::custom)?;
+    let timeout = Duration::from_millis(service.start_timeout_ms.unwrap_or(90_000).max(1));
+    tfd
+      .set(
+        Expiration::OneShot(TimeSpec::from(timeout)),
+        TimerSetTimeFlags::empty(),
+      )
+      .map_err(CoreError::custom)?;
+
+    let fd = tfd.as_fd().as_raw_fd();
+    resources.own(fd, tfd);
+    resources.action(
+      fd,
+      ResourceAction::from(("services", "ready_timeout"))
+        .payload(move |p| p.insert("notify_fd", notify_fd)),
+    );
+    Ok(fd)
+  }
+
+  /// Watches the instance cgroup for OOM kills and memory pressure. Missing
+  /// interface files (no memory controller) leave that part unwatched.
+  fn arm_memory_watch(
+    &mut self,
+    service_key: Ustr,
+    branch: Option<Ustr>,
+    pid: u32,
+    cgroup_path: &Path,
+    cgroup: &ServiceCgroup,
+    resources: &mut Resources,
+  ) {
+    let mut watch = MemoryWatch {
+      service_key,
+      branch,
+      events_fd: None,
+      pressure_fd: None,
+      oom_kills_at_start: 0,
+      oom_kills_reported: 0,
+    };
+
+    if let Ok(owned) = open_memory_events(cgroup_path) {
+      let fd = owned.as_raw_fd();
+      let events = MemoryEvents::read_fd(&owned).unwrap_or_default();
+      watch.oom_kills_at_start = events.oom_kill;
+      watch.oom_kills_reported = events.oom_kill;
+      watch.events_fd = Some(fd);
+      resources.own(fd, owned);
+    }
+    if let Some(trigger) = cgroup.pressure_trigger()
+      && let Ok(owned) = open_psi_trigger(&cgroup_path.join("memory.pressure"), trigger)
+    {
+      let fd = owned.as_raw_fd();
+      watch.pressure_fd = Some(fd);
+      resources.own(fd, owned);
+    }
+
+    for fd in [watch.events_fd, watch.pressure_fd].into_iter().flatten() {
+      resources.flag(fd, EpollFlags::EPOLLPRI);
+      resources.action(fd, ResourceAction::from(("services", "memory_event")));
+      self.memory_watch_fds.insert(fd, pid);
+    }
+    if watch.events_fd.is_some() || watch.pressure_fd.is_some() {
+      self.memory_watches.insert(pid, watch);
+    }
+  }
+
+  /// Stops watching `pid`'s cgroup. Returns how many OOM kills happened
+  /// since the instance started, after reporting any not yet seen.
+  fn release_memory_watch(
+    &mut self,
+    pid: u32,
+    resources: &mut Resources,
+    dispatch: &RuntimeDispatcher,
+  ) -> u64 {
+    let Some(mut watch) = self.memory_watches.remove(&pid) else {
+      return 0;
+    };
+    let mut oom_kills = watch.oom_kills_reported;
+    if let Some(fd) = watch.events_fd
+      && let Some(events) = MemoryEvents::read_fd(unsafe { BorrowedFd::borrow_raw(fd) })
+    {
+      oom_kills = events.oom_kill;
+      Self::report_oom_kills(&mut watch, oom_kills, dispatch);
+    }
+    for fd in [watch.events_fd, watch.pressure_fd].into_iter().flatten() {
+      self.memory_watch_fds.remove(&fd);
+      resources.terminate(fd);
+    }
+    oom_kills.saturating_sub(watch.oom_kills_at_start)
+  }
+
+  fn report_oom_kills(watch: &mut MemoryWatch, oom_kills: u64, dispatch: &RuntimeDispatcher) {
+    if oom_kills <= watch.oom_kills_reported {
+      return;
+    }
+    let payload = serde_json::json!({
+      "service": Self::instance_key_name(watch.service_key.as_str()).as_str(),
+      "branch": watch.branch.as_ref().map(|b| b.as_str()),
+      "count": oom_kills - watch.oom_kills_reported,
+    });
+    watch.oom_kills_reported = oom_kills;
+    Self::emit_impulse(dispatch, "rind:oom_killed", payload);
+  }
+
+  fn emit_impulse(dispatch: &RuntimeDispatcher, name: &str, payload: serde_json::Value) {
+    let _ = dispatch.dispatch(
+      "flow",
+      "impulse",
+      RuntimePayload::default()
+        .insert("name", Ustr::from(name))
+        .insert("payload", payload),
+    );
+  }
+
+  fn arm_restart_timer(
+    &mut self,
+    restart: PendingRestart,
+    delay: Duration,
+    resources: &mut Resources,
+  ) -> Result<Void, CoreError> {
+    let tracker_key = (
+      Self::ensure_scoped_name(restart.service_key.as_str()),
+      restart.key.clone(),
+    );
+    self.cancel_pending_restart(restart.service_key.as_str(), Some(&restart.key), resources);
+    // a zero expiration would disarm the timer instead of firing it
+    let delay = delay.max(Duration::from_millis(1));
+
+    let tfd = TimerFd::new(
+      ClockId::CLOCK_MONOTONIC,
+      TimerFlags::TFD_NONBLOCK | TimerFlags::TFD_CLOEXEC,
+    )
+    .map_err(CoreError::custom)?;
+    tfd
+      .set(
+        Expiration::OneShot(TimeSpec::from(delay)),
+        TimerSetTimeFlags::empty(),
+      )
+      .map_err(CoreError::custom)?;
+
+    let fd = tfd.as_fd().as_raw_fd();
+    resources.own(fd, tfd);
+    resources.action(fd, ResourceAction::from(("services", "restart_due")));
+
+    self.restart_fds.insert(fd, restart);
+    self.restarts.entry(tracker_key).or_default().pending_fd = Some(fd);
+    Ok(Void)
+  }
+
+  /// Cancels the pending restart of the instance with branch `key`, or of
+  /// every instance of the service without one.
+  fn cancel_pending_restart(
+    &mut self,
+    service_key: &str,
+    key: Option<&Ustr>,
+    resources: &mut Resources,
+  ) {
+    let scoped = Self::ensure_scoped_name(service_key);
+    for ((service, branch), tracker) in self.restarts.iter_mut() {
+      if *service != scoped || key.is_some_and(|key| key != branch) {
+        continue;
+      }
+      if let Some(fd) = tracker.pending_fd.take() {
+        self.restart_fds.remove(&fd);
+        resources.terminate(fd);
+      }
+    }
+  }
+
+  fn kill_virtual_pid(&self, registry: &mut InstanceRegistry, pid: u32, signal: Signal) {
+    let Some(service_key) = self.pid_map.get(&pid) else {
+      return;
+    };
+    let Some(instances) = registry.instances.get_mut(service_key) else {
+      return;
+    };
+    for instance in instances.iter_mut() {
+      if let Some(service) = instance.downcast_mut::<Service>()
+        && let Some(idx) = service.instances.find_by_pid(pid as i32)
+        && let Some(handle) = service.instances[idx].handle.as_mut()
+      {
+        let _ = handle.kill(signal);
+      }
+    }
+  }
+
+  fn refresh_watchdog_fd(&self, fd: RawFd, watchdog: &ServiceWatchdog) -> Result<Void, CoreError> {
+    let grace = Duration::from_millis(watchdog.grace_ms.max(1));
+    let spec = libc::itimerspec {
+      it_interval: libc::timespec {
+        tv_sec: 0,
+        tv_nsec: 0,
+      },
+      it_value: libc::timespec {
+        tv_sec: grace.as_secs() as libc::time_t,
+        tv_nsec: grace.subsec_nanos() as libc::c_long,
       },
     };
     let rc = unsafe { libc::timerfd_settime(fd, 0, &spec, std::ptr::null_mut()) };
@@ -937,7 +2389,7 @@ impl ServiceRuntime {
       return Ok(None);
     };
 
-    let Some(branches) = sm.facets.get(facet) else {
+    let Some(branches) = sm.get(facet) else {
       return Ok(None);
     };
 
@@ -1034,7 +2486,7 @@ impl ServiceRuntime {
         }
 
         if let Some(sm) = sm
-          && let Some(sessions) = sm.facets.get("rind:user_session")
+          && let Some(sessions) = sm.get("rind:user_session")
         {
           let mut users = HashSet::new();
           for sess in sessions {
@@ -1084,12 +2536,12 @@ impl ServiceRuntime {
         let mut interests = HashSet::new();
         if let Some(start_on) = &meta.start_on {
           for item in start_on {
-            interests.insert(item.name());
+            interests.extend(item.names());
           }
         }
         if let Some(stop_on) = &meta.stop_on {
           for item in stop_on {
-            interests.insert(item.name());
+            interests.extend(item.names());
           }
         }
 
@@ -1123,7 +2575,7 @@ impl ServiceRuntime {
     if let Some(sm) = sm {
       let key = Self::instance_key_name(registry_key.as_str());
 
-      if let Some(inst) = sm.facets.get("rind:inactive")
+      if let Some(inst) = sm.get("rind:inactive")
         && inst
           .iter()
           .any(|x| x.payload.to_string_payload() == key.as_str())
@@ -1148,6 +2600,7 @@ impl ServiceRuntime {
         resources,
         namespace_mounts.clone(),
         namespace_networks.clone(),
+        false,
       )?;
 
       instances.push(instance);
@@ -1171,6 +2624,14 @@ impl ServiceRuntime {
       .and_then(|v| v.as_str())
       .unwrap_or_default();
     let executor = table.get("executor").map(|v| v.to_string().to_ustr());
+    let remote = table
+      .get("remote")
+      .and_then(|v| v.as_str())
+      .map(|v| v.to_ustr());
+    let remote_token = table
+      .get("remote-token")
+      .and_then(|v| v.as_str())
+      .map(|v| v.to_ustr());
     let args = table
       .get("args")
       .and_then(|v| v.as_array())
@@ -1197,6 +2658,9 @@ impl ServiceRuntime {
       env,
       variable: None,
       executor,
+      remote,
+      remote_token,
+      ignore_failure: false,
       files,
     })
   }
@@ -1269,6 +2733,7 @@ impl ServiceRuntime {
     resources: &mut Resources,
     namespace_mounts: Vec<NamespaceMountEntry>,
     namespace_networks: Vec<NamespaceNetworkConfig>,
+    pre_started: bool,
   ) -> CoreResult<ChildInstance> {
     let (full_name, scope_name) = {
       let key = registry_key.as_str();
@@ -1287,6 +2752,7 @@ impl ServiceRuntime {
       self.resolve_service_user(service, branch_ctx, sm, scope_name)?
     };
     let isolation = Self::isolation_for(service, scope_name)?;
+    Self::validate_remote_confinement(service, run, resolved_user.as_ref(), &isolation)?;
     let watchdog_cfg = service.metadata.watchdog.clone();
 
     if let Some(transport) = &service.metadata.transport {
@@ -1302,8 +2768,7 @@ impl ServiceRuntime {
           {
             payload
           } else {
-            sm.facets
-              .get(state_name)
+            sm.get(state_name)
               .and_then(|v| v.first())
               .map(|x| &x.payload)?
           };
@@ -1420,15 +2885,13 @@ impl ServiceRuntime {
         Ustr::from("LISTEN_FDS"),
         Ustr::from(activation_fds.len().to_string()),
       );
-      if !activation_names.is_empty() {
-        let names = activation_names
-          .iter()
-          .map(|x| x.as_str())
-          .collect::<Vec<_>>()
-          .join(":");
-        envs.insert(Ustr::from("RIND_SOCKET_NAMES"), Ustr::from(names.clone()));
-        envs.insert(Ustr::from("LISTEN_FDNAMES"), Ustr::from(names));
-      }
+      let names = activation_names
+        .iter()
+        .map(|x| listen_fd_name(x.as_str()))
+        .collect::<Vec<_>>()
+        .join(":");
+      envs.insert(Ustr::from("RIND_SOCKET_NAMES"), Ustr::from(names.clone()));
+      envs.insert(Ustr::from("LISTEN_FDNAMES"), Ustr::from(names));
     }
 
     if let Some(watchdog) = &watchdog_cfg {
@@ -1444,7 +2907,80 @@ impl ServiceRuntime {
       }
     }
 
+    let cgroup_path = Self::cgroup_path_for(
+      service,
+      isolation.cgroup.as_ref(),
+      branch_ctx,
+      resolved_user.as_ref(),
+    );
+    let launch = LaunchContext {
+      registry_key: registry_key.clone(),
+      // Hooks never get the client connection.
+      branch_ctx: branch_ctx.cloned().map(|ctx| ServiceBranchContext {
+        connection_fd: None,
+        ..ctx
+      }),
+      resolved_user: resolved_user.clone(),
+      envs: envs.clone(),
+      isolation: isolation.clone(),
+      cgroup_path: cgroup_path.clone(),
+      namespace_mounts: namespace_mounts.clone(),
+      namespace_networks: namespace_networks.clone(),
+    };
+    if !pre_started
+      && let Some(hooks) = service.metadata.pre_start.clone().filter(|h| !h.is_empty())
+    {
+      let connection = branch_ctx
+        .and_then(|ctx| ctx.connection_fd)
+        .map(dup_fd)
+        .transpose()?;
+      let chain = HookChain::new(
+        hooks,
+        launch.clone(),
+        None,
+        HookThen::Spawn {
+          run: Box::new(run.clone()),
+          branch_ctx: branch_ctx.cloned(),
+          connection,
+        },
+      );
+      let id = chain.id;
+      if let Some((_, Err(e))) = self.advance_hooks(service, chain, log, resources) {
+        return Err(e);
+      }
+      let mut instance =
+        ChildInstance::new(branch_key.cloned().unwrap_or_default(), resolved_user, None);
+      instance.state = ServiceState::Starting;
+      instance.pending_hooks = Some(id);
+      instance.launch = Some(launch);
+      instance.accepted = branch_ctx.is_some_and(|ctx| ctx.connection_fd.is_some());
+      return Ok(instance);
+    }
+
     let executor_name = run.executor.clone().unwrap_or_else(|| Ustr::from("native"));
+    let notify_socket = if service.metadata.r#type == ServiceType::Notify {
+      if executor_name.as_str() != "native" {
+        return Err(CoreError::InvalidState(format!(
+          "notify service '{}' requires the native executor",
+          service.metadata.name
+        )));
+      }
+      let group = match &resolved_user {
+        Some(user) => rind_core::user::UserStore::load_system()?
+          .lookup_by_name(user.as_str())
+          .map(|user| user.gid),
+        None => None,
+      };
+      let socket = NotifySocket::bind(registry_key.as_str(), group)?;
+      envs.insert(
+        Ustr::from("NOTIFY_SOCKET"),
+        Ustr::from(socket.path.to_string_lossy().as_ref()),
+      );
+      Some(socket)
+    } else {
+      None
+    };
+
     let executor = self
       .executors
       .get(&executor_name)
@@ -1465,17 +3001,17 @@ impl ServiceRuntime {
       envs,
       args,
       isolation: isolation.clone(),
-      cgroup_path: Self::cgroup_path_for(
-        service,
-        isolation.cgroup.as_ref(),
-        branch_ctx,
-        resolved_user.as_ref(),
-      ),
+      cgroup_path,
       namespace_mounts,
       namespace_networks,
     })?;
 
-    if let Some(pid) = handle.pid() {
+    if let Some(pid) = handle.pid()
+      && is_virtual_pid(pid)
+    {
+      // Remote and in-process instances have no local process to confine.
+      self.pid_map.insert(pid, registry_key.clone());
+    } else if let Some(pid) = handle.pid() {
       if let Err(e) = self.setup_cgroup_for_pid(
         service,
         if isolation.needs_namespace_supervisor() {
@@ -1495,6 +3031,32 @@ impl ServiceRuntime {
       }
       self.pid_map.insert(pid, registry_key.clone());
 
+      if let Some(socket) = notify_socket {
+        let fd = socket.raw_fd();
+        let ready_fd = match self.arm_ready_timeout(&service.metadata, fd, resources) {
+          Ok(ready_fd) => ready_fd,
+          Err(e) => {
+            let _ = handle.kill(Signal::SIGKILL);
+            return Err(e);
+          }
+        };
+        let (path, owned) = socket.into_fd();
+        resources.own(fd, owned);
+        resources.action(fd, ResourceAction::from(("services", "notify_message")));
+        self.notify_fds.insert(
+          fd,
+          NotifyBinding {
+            service_key: registry_key.clone(),
+            pid,
+            root: pid,
+            cgroup: launch.cgroup_path.clone(),
+            path,
+            ready_fd: Some(ready_fd),
+          },
+        );
+        self.notify_pids.insert(pid, fd);
+      }
+
       if let Some(watchdog) = &watchdog_cfg {
         let _ = self.arm_watchdog_timer(
           registry_key.clone(),
@@ -1505,6 +3067,17 @@ impl ServiceRuntime {
           resources,
         );
       }
+
+      if let (Some(path), Some(cgroup)) = (&launch.cgroup_path, &isolation.cgroup) {
+        self.arm_memory_watch(
+          registry_key.clone(),
+          branch_key.cloned(),
+          pid,
+          path,
+          cgroup,
+          resources,
+        );
+      }
     }
 
     if service
@@ -1535,11 +3108,35 @@ impl ServiceRuntime {
       );
     }
 
-    Ok(ChildInstance::new(
+    let post_start = service
+      .metadata
+      .post_start
+      .clone()
+      .filter(|h| !h.is_empty())
+      .map(|hooks| HookChain::new(hooks, launch.clone(), handle.pid(), HookThen::PostStart));
+
+    let mut instance = ChildInstance::new(
       branch_key.cloned().unwrap_or_default(),
       resolved_user,
       Some(handle),
-    ))
+    );
+    instance.launch = Some(launch);
+    instance.accepted = branch_ctx.is_some_and(|ctx| ctx.connection_fd.is_some());
+    if service.metadata.r#type == ServiceType::Notify {
+      // Becomes active once the service sends READY=1.
+      instance.state = ServiceState::Starting;
+    }
+    if let Some(chain) = post_start {
+      instance.pending_hooks = Some(chain.id);
+      instance.state = ServiceState::Starting;
+      if let Some((_, Err(e))) = self.advance_hooks(service, chain, log, resources) {
+        if let Some(handle) = instance.handle.as_mut() {
+          let _ = handle.kill(Signal::SIGTERM);
+        }
+        return Err(e);
+      }
+    }
+    Ok(instance)
   }
 
   pub fn start_service(
@@ -1581,6 +3178,11 @@ impl ServiceRuntime {
     ) {
       Ok(_) => {
         self.register_service_transport(service, dispatch, Some(registry_key.clone()));
+        if service.metadata.r#type == ServiceType::Notify
+          || service.instances.iter().any(|i| i.pending_hooks.is_some())
+        {
+          return;
+        }
         if let Some(inst) = service.instances.as_one_mut() {
           inst.state = ServiceState::Active;
           self.run_triggers(service.metadata.on_start.as_ref(), sm, dispatch, log);
@@ -1620,15 +3222,38 @@ impl ServiceRuntime {
         if let Some(inst) = service.instances.as_one_mut() {
           inst.state = ServiceState::Error(err);
         }
+        Self::announce_failed_start(dispatch, registry_key);
       }
     }
   }
 
+  /// Lets dependents and timers waiting on `registry_key` know its start
+  /// failed, so they don't wait for an exit that never comes.
+  fn announce_failed_start(dispatch: &RuntimeDispatcher, registry_key: Ustr) {
+    let _ = dispatch.dispatch(
+      "services",
+      "reconcile_stacks",
+      rpayload!({
+        "service": registry_key.clone(),
+        "action": ServiceEventKind::Failed
+      }),
+    );
+    let _ = dispatch.dispatch(
+      "timer",
+      "reconcile_timers",
+      rpayload!({
+        "service": registry_key,
+        "action": ServiceEventKind::Failed
+      }),
+    );
+  }
+
   fn stop_service_instance(
     &mut self,
     inst: &mut ChildInstance,
     service: Arc<ServiceMetadata>,
     mode: StopMode,
+    trigger: ExitTrigger,
     dispatch: &RuntimeDispatcher,
     sm: Option<&FacetGraph>,
     key: Option<Ustr>,
@@ -1652,20 +3277,70 @@ impl ServiceRuntime {
         return;
       }
     }
+    if let Some(id) = inst.pending_hooks.take() {
+      self.abort_hooks(id);
+    }
+    let main_pid = inst.main_pid;
+    let cgroup = inst.launch.as_ref().and_then(|l| l.cgroup_path.clone());
     if let Some(handle) = inst.handle.as_mut() {
       let signal = if mode == StopMode::ForceKill {
         Signal::SIGKILL
       } else {
-        Signal::SIGTERM
+        service.stop_signal.map_or(Signal::SIGTERM, |s| s.0)
       };
-      let _ = handle.kill(signal);
-      if let Some(pid) = handle.pid() {
+      let stopped_by_hooks = match (&service.stop, &inst.launch) {
+        (Some(hooks), Some(launch)) if mode == StopMode::Graceful => {
+          let chain = HookChain::new(
+            hooks.clone(),
+            launch.clone(),
+            main_pid.or(handle.pid()),
+            HookThen::Stop { signal },
+          );
+          match self.advance_hooks(&Service::new(service.clone()), chain, log, resources) {
+            None | Some((_, Ok(_))) => true,
+            Some((_, Err(e))) => {
+              let mut fields = HashMap::new();
+              fields.insert("service".to_string(), service.name.to_string());
+              fields.insert("error".to_string(), e.to_string());
+              log.log(
+                LogLevel::Warn,
+                "service-runtime",
+                "stop command failed, sending the stop signal",
+                fields,
+              );
+              false
+            }
+          }
+        }
+        _ => false,
+      };
+      if !stopped_by_hooks {
+        signal_instance(
+          handle.as_mut(),
+          main_pid,
+          service.kill_mode,
+          cgroup.as_deref(),
+          signal,
+        );
+      }
+      if let Some(pid) = main_pid.or(handle.pid()) {
         self.disarm_watchdog_pid(pid, resources);
-        self.stopping_map.insert(pid, Instant::now());
+        self.stopping_map.insert(
+          pid,
+          StopDeadline {
+            since: Instant::now(),
+            timeout: Self::stop_timeout(&service),
+            kill_mode: service.kill_mode,
+            cgroup,
+            forced: mode == StopMode::ForceKill,
+            killed_at: None,
+          },
+        );
       }
       inst.state = ServiceState::Stopping;
       inst.stop_time = Some(Instant::now());
       inst.manually_stopped = true;
+      inst.exit_trigger.get_or_insert(trigger);
     } else {
       if inst.state == ServiceState::Active {
         self.run_triggers(service.on_stop.as_ref(), sm, dispatch, log);
@@ -1678,6 +3353,7 @@ impl ServiceRuntime {
     &mut self,
     service: &mut Service,
     mode: StopMode,
+    trigger: ExitTrigger,
     log: &LogHandle,
     dispatch: &RuntimeDispatcher,
     sm: Option<&FacetGraph>,
@@ -1694,6 +3370,7 @@ impl ServiceRuntime {
           inst,
           service.metadata.clone(),
           mode,
+          trigger,
           dispatch,
           sm,
           key.clone(),
@@ -1708,6 +3385,7 @@ impl ServiceRuntime {
           inst,
           service.metadata.clone(),
           mode,
+          trigger,
           dispatch,
           sm,
           key.clone(),
@@ -1797,7 +3475,9 @@ impl ServiceRuntime {
     &mut self,
     service: &mut Service,
     pid: i32,
-    code: i32,
+    status: ExitStatus,
+    stop: Option<StopOutcome>,
+    history: &mut ExitHistory,
     log: &LogHandle,
     dispatch: &RuntimeDispatcher,
     sm: Option<&FacetGraph>,
@@ -1805,44 +3485,92 @@ impl ServiceRuntime {
     resources: &mut Resources,
   ) -> Option<ServiceExitAction> {
     self.disarm_watchdog_pid(pid as u32, resources);
+    self.close_notify_pid(pid as u32, resources);
+    let oom_kills = self.release_memory_watch(pid as u32, resources, dispatch);
+    let code = status.code();
+    let exit_state = || {
+      if oom_kills > 0 {
+        ServiceState::OomKilled(code)
+      } else {
+        ServiceState::Exited(code)
+      }
+    };
     let idx = service.instances.find_by_pid(pid)?;
-    let (manually_stopped, retry_count) = {
+    let (skip_restart, uptime, restart) = {
       let inst = &mut service.instances.0[idx];
+      let restart = PendingRestart {
+        service_key: service_key.clone(),
+        key: inst.key.clone(),
+        branch_ctx: inst
+          .launch
+          .as_ref()
+          .and_then(|launch| launch.branch_ctx.clone()),
+      };
 
       if matches!(inst.state, ServiceState::Active | ServiceState::Stopping) {
         self.run_triggers(service.metadata.on_stop.as_ref(), sm, dispatch, log);
       }
 
-      inst.state = ServiceState::Exited(code);
+      inst.state = exit_state();
       inst.handle = None;
-      (inst.manually_stopped, inst.retry_count)
+      let uptime = inst.started_at.elapsed();
+      let trigger = if oom_kills > 0 {
+        ExitTrigger::Oom
+      } else {
+        inst.exit_trigger.take().unwrap_or_default()
+      };
+      history.push(
+        service_key.as_str(),
+        ExitRecord::new(inst.key.clone(), status, trigger, stop, uptime),
+      );
+      (inst.manually_stopped || inst.accepted, uptime, restart)
     };
 
-    service.last_state = ServiceState::Exited(code);
+    service.last_state = exit_state();
 
     self.maybe_unregister_service_transport(service, dispatch, Some(&service_key));
 
-    let restart_policy = service.metadata.restart.as_ref();
-    let action = if manually_stopped {
-      ServiceExitAction::StopDependents
-    } else {
-      match restart_policy {
-        Some(RestartPolicy::Bool(true)) => ServiceExitAction::Restart,
-        Some(RestartPolicy::OnFailure { max_retries }) => {
-          if code != 0 && *max_retries > 0 && retry_count < *max_retries {
-            if let Some(inst) = service.instances.0.get_mut(idx) {
-              inst.retry_count += 1;
-            }
-            ServiceExitAction::Restart
-          } else {
+    let restart_config = service
+      .metadata
+      .restart
+      .as_ref()
+      .and_then(RestartPolicy::config);
+    let action = match restart_config {
+      Some(config) if !skip_restart => {
+        let tracker = self
+          .restarts
+          .entry((
+            Self::ensure_scoped_name(service_key.as_str()),
+            restart.key.clone(),
+          ))
+          .or_default();
+        let next = tracker.next(&config, code, uptime, Instant::now());
+        service.instances.0[idx].retry_count = tracker.attempts;
+        match next {
+          Ok(Some(delay)) => ServiceExitAction::RestartAfter(restart, delay),
+          Ok(None) => ServiceExitAction::StopDependents,
+          Err(reason) => {
+            let mut fields = self.log_fields(service, "restart", Some(&service_key));
+            fields.insert("reason".to_string(), reason.clone());
+            log.log(
+              LogLevel::Error,
+              "service-runtime",
+              "giving up on restarts",
+              fields,
+            );
+            service.last_state = ServiceState::Error(reason);
             ServiceExitAction::StopDependents
           }
         }
-        _ => ServiceExitAction::StopDependents,
       }
+      _ => ServiceExitAction::StopDependents,
     };
 
-    if !matches!(action, ServiceExitAction::Restart) {
+    if matches!(action, ServiceExitAction::RestartAfter(..)) {
+      service.instances.0.remove(idx);
+    }
+
+    if matches!(action, ServiceExitAction::StopDependents) {
       service.instances.0.retain(|inst| {
         inst.state == ServiceState::Active
           || inst.state == ServiceState::Starting
@@ -2094,7 +3822,7 @@ impl ServiceRuntime {
       } else {
         (state_name, None)
       };
-      if let Some(instances) = sm.facets.get(&Ustr::from(state_name)) {
+      if let Some(instances) = sm.get(&Ustr::from(state_name)) {
         for inst in instances {
           if subset_match(
             &key_val,
@@ -2121,6 +3849,25 @@ impl ServiceRuntime {
     return subset_match(&key_val, &serde_json::json!(spec));
   }
 
+  fn awaits_ready(&self, ctx: &RuntimeContext<'_>, name: &str) -> bool {
+    ctx
+      .registry
+      .metadata
+      .find::<Service>("*", Self::instance_key_name(name).as_str())
+      .is_some_and(|svc| svc.r#type == ServiceType::Notify)
+      || ctx
+        .registry
+        .as_one::<Service>("*", Self::ensure_scoped_name(name))
+        .is_ok_and(|svc| svc.instances.iter().any(|i| i.pending_hooks.is_some()))
+  }
+
+  fn is_ready(&self, ctx: &RuntimeContext<'_>, name: &str) -> bool {
+    ctx
+      .registry
+      .as_one::<Service>("*", Self::ensure_scoped_name(name))
+      .is_ok_and(|svc| svc.instances.is_active())
+  }
+
   fn reconcile(
     &mut self,
     ctx: &mut RuntimeContext<'_>,
@@ -2149,10 +3896,53 @@ impl ServiceRuntime {
 
 #[derive(Debug)]
 enum ServiceExitAction {
-  Restart,
+  RestartAfter(PendingRestart, Duration),
   StopDependents,
 }
 
+/// Sends `signal` to the processes `kill_mode` selects. Falls back to the
+/// process group when the instance has no cgroup.
+fn signal_instance(
+  handle: &mut dyn InstanceHandle,
+  main_pid: Option<u32>,
+  kill_mode: KillMode,
+  cgroup: Option<&std::path::Path>,
+  signal: Signal,
+) {
+  let pid = main_pid.or(handle.pid());
+  match (kill_mode, cgroup) {
+    (KillMode::Cgroup, Some(cgroup)) => {
+      if signal == Signal::SIGKILL && std::fs::write(cgroup.join("cgroup.kill"), "1").is_ok() {
+        return;
+      }
+      let procs = std::fs::read_to_string(cgroup.join("cgroup.procs")).unwrap_or_default();
+      for pid in procs.lines().filter_map(|x| x.trim().parse::<i32>().ok()) {
+        let _ = kill(Pid::from_raw(pid), signal);
+      }
+    }
+    (KillMode::MainProcess, _) if pid.is_some_and(|pid| !is_virtual_pid(pid)) => {
+      let _ = kill(Pid::from_raw(pid.unwrap_or_default() as i32), signal);
+    }
+    _ => {
+      let _ = handle.kill(signal);
+      if let Some(main_pid) = main_pid
+        && Some(main_pid) != handle.pid()
+      {
+        let _ = kill(Pid::from_raw(main_pid as i32), signal);
+      }
+    }
+  }
+}
+
+/// Copies an fd rind has to keep handing out after closing its own.
+fn dup_fd(fd: RawFd) -> CoreResult<OwnedFd> {
+  let dup = unsafe { libc::fcntl(fd, libc::F_DUPFD_CLOEXEC, 3) };
+  if dup < 0 {
+    return Err(std::io::Error::last_os_error().into());
+  }
+  Ok(unsafe { OwnedFd::from_raw_fd(dup) })
+}
+
 fn is_stdio_transport(method: &TransportMethod) -> bool {
   match method {
     TransportMethod::Type(id) => id.0.as_str() == "stdio",
@@ -2240,35 +4030,88 @@ pub struct ServiceBranchContext {
   pub key: Option<Ustr>,
   pub payload: Option<FlowPayload>,
   pub forced_user: Option<Ustr>,
+  /// Connection accepted by an `accept = true` socket, put on the instance's
+  /// stdin and stdout. Owned by `Resources` until the start action closes it.
+  #[serde(default)]
+  pub connection_fd: Option<i32>,
 }
 
+/// Sockets handed to a service on start, in `LISTEN_FDS` order. `names[i]`
+/// is the socket behind `fds[i]`.
 #[derive(Debug, Clone, Default)]
 pub struct SocketActivation {
-  pub fds: HashSet<RawFd>,
-  pub names: HashSet<Ustr>,
+  pub fds: Vec<RawFd>,
+  pub names: Vec<Ustr>,
 }
 
 impl SocketActivation {
   pub fn is_empty(&self) -> bool {
     self.fds.is_empty()
   }
+
+  /// Appends `fd` unless it is already part of the handoff.
+  pub fn push(&mut self, fd: RawFd, name: Ustr) {
+    if !self.fds.contains(&fd) {
+      self.fds.push(fd);
+      self.names.push(name);
+    }
+  }
 }
 
 impl From<Vec<(Ustr, RawFd)>> for SocketActivation {
   fn from(value: Vec<(Ustr, RawFd)>) -> Self {
-    SocketActivation {
-      fds: value.iter().map(|(_, x)| *x).collect(),
-      names: value.into_iter().map(|(x, _)| x).collect(),
+    let mut activation = SocketActivation::default();
+    for (name, fd) in value {
+      activation.push(fd, name);
     }
+    activation
   }
 }
 
-pub fn handle_ipc_start(
-  msg: Message,
-  ctx: &mut RuntimeContext<'_>,
-  dispatch: &RuntimeDispatcher,
-  _log: &LogHandle,
-) -> Result<Message, CoreError> {
+/// `LISTEN_FDNAMES` entry for a socket: its bare name, since the variable is
+/// colon-separated.
+fn listen_fd_name(name: &str) -> &str {
+  let (_, child, _) = rslvns!(res name);
+  if child.is_empty() { "unknown" } else { child }
+}
+
+/// Expands `$NAME` and `${NAME}` in a hook argument from `envs`, the way
+/// systemd does for `$MAINPID`. Unknown names and a bare `$` stay as they are.
+fn expand_hook_arg(arg: &str, envs: &HashMap<Ustr, Ustr>) -> Ustr {
+  let mut out = String::with_capacity(arg.len());
+  let mut rest = arg;
+  while let Some(at) = rest.find('$') {
+    out.push_str(&rest[..at]);
+    let tail = &rest[at + 1..];
+    let (name, len) = match tail.strip_prefix('{') {
+      Some(braced) => match braced.find('}') {
+        Some(end) => (&braced[..end], end + 2),
+        None => ("", 0),
+      },
+      None => {
+        let end = tail
+          .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+          .unwrap_or(tail.len());
+        (&tail[..end], end)
+      }
+    };
+    match envs.get(name).filter(|_| !name.is_empty()) {
+      Some(value) => out.push_str(value),
+      None => out.push_str(&rest[at..at + 1 + len]),
+    }
+    rest = &tail[len..];
+  }
+  out.push_str(rest);
+  Ustr::from(out)
+}
+
+/// Checks whether the sender of `msg` may manage the service it names.
+/// Returns the payload and, for callers without system-wide rights, the
+/// user whose instances the command is limited to.
+fn authorize_service_ipc(
+  msg: &Message,
+  ctx: &RuntimeContext<'_>,
+) -> Result<(SSPayload, Option<Ustr>), CoreError> {
   let pm = ctx
     .registry
     .singleton::<PermissionStore>(PermissionStore::KEY)
@@ -2283,15 +4126,17 @@ pub fn handle_ipc_start(
     return Err(CoreError::PermissionDenied);
   };
 
+  if uid == 0 || pm.user_has(uid, PERM_SYSTEM_SERVICES) {
+    return Ok((payload, None));
+  }
+
   let svc = ctx.registry.metadata.find::<Service>("*", &payload.name);
   let caller = pm.users.lookup_by_uid(uid);
-  let can_manage = if uid == 0 || pm.user_has(uid, PERM_SYSTEM_SERVICES) {
-    true
-  } else if let (Some(caller), Some(svc)) = (caller, svc.as_ref()) {
+  let can_manage = if let (Some(caller), Some(svc)) = (caller, svc.as_ref()) {
     if let Some(ref perms) = svc.managed_by {
       perms
         .iter()
-        .any(|x| pm.from_name(x).map_or(false, |x| pm.user_has(uid, x)))
+        .any(|x| pm.from_name(x).is_some_and(|x| pm.user_has(uid, x)))
     } else {
       match &svc.space {
         ServiceSpace::User => true,
@@ -2307,13 +4152,19 @@ pub fn handle_ipc_start(
     return Err(CoreError::PermissionDenied);
   }
 
-  let mut dispatch_payload = ServiceRuntime::actions.start(payload.name.to_ustr());
+  Ok((payload, caller.map(|u| u.username.clone())))
+}
 
-  if let (false, false, Some(username)) = (
-    uid == 0,
-    pm.user_has(uid, PERM_SYSTEM_SERVICES),
-    caller.map(|u| u.username.clone()),
-  ) {
+pub fn handle_ipc_start(
+  msg: Message,
+  ctx: &mut RuntimeContext<'_>,
+  dispatch: &RuntimeDispatcher,
+  _log: &LogHandle,
+) -> Result<Message, CoreError> {
+  let (payload, only_user) = authorize_service_ipc(&msg, ctx)?;
+
+  let mut dispatch_payload = ServiceRuntime::actions.start(payload.name.to_ustr());
+  if let Some(username) = only_user {
     dispatch_payload = dispatch_payload.only_user(username.to_string());
   }
 
@@ -2335,53 +4186,12 @@ pub fn handle_ipc_stop(
   dispatch: &RuntimeDispatcher,
   _log: &LogHandle,
 ) -> Result<Message, CoreError> {
-  let pm = ctx
-    .registry
-    .singleton::<PermissionStore>(PermissionStore::KEY)
-    .cloned()
-    .unwrap_or_default();
-
-  let payload = msg
-    .parse_payload::<SSPayload>()
-    .map_err(CoreError::Custom)?;
-
-  let Some(uid) = msg.from_uid else {
-    return Err(CoreError::PermissionDenied);
-  };
-
-  let svc = ctx.registry.metadata.find::<Service>("*", &payload.name);
-  let caller = pm.users.lookup_by_uid(uid);
-  let can_manage = if uid == 0 || pm.user_has(uid, PERM_SYSTEM_SERVICES) {
-    true
-  } else if let (Some(caller), Some(svc)) = (caller, svc.as_ref()) {
-    if let Some(ref perms) = svc.managed_by {
-      perms
-        .iter()
-        .any(|x| pm.from_name(x).map_or(false, |x| pm.user_has(uid, x)))
-    } else {
-      match &svc.space {
-        ServiceSpace::User => true,
-        ServiceSpace::UserSelective { user } => user.as_str() == caller.username.as_str(),
-        ServiceSpace::System => false,
-      }
-    }
-  } else {
-    false
-  };
-
-  if !can_manage {
-    return Err(CoreError::PermissionDenied);
-  }
+  let (payload, only_user) = authorize_service_ipc(&msg, ctx)?;
 
   let mut dispatch_payload = ServiceRuntime::actions
     .stop(payload.name.to_ustr())
     .force(payload.force);
-
-  if let (false, false, Some(username)) = (
-    uid == 0,
-    pm.user_has(uid, PERM_SYSTEM_SERVICES),
-    caller.map(|u| u.username.clone()),
-  ) {
+  if let Some(username) = only_user {
     dispatch_payload = dispatch_payload.only_user(username);
   }
 
@@ -2397,9 +4207,30 @@ pub fn handle_ipc_stop(
   Ok(Message::ok(format!("stopped {}", payload.name)))
 }
 
+pub fn handle_ipc_reload(
+  msg: Message,
+  ctx: &mut RuntimeContext<'_>,
+  dispatch: &RuntimeDispatcher,
+  _log: &LogHandle,
+) -> Result<Message, CoreError> {
+  let (payload, only_user) = authorize_service_ipc(&msg, ctx)?;
+
+  let mut dispatch_payload = ServiceRuntime::actions.reload(payload.name.to_ustr());
+  if let Some(username) = only_user {
+    dispatch_payload = dispatch_payload.only_user(username);
+  }
+
+  dispatch_payload.dispatch(dispatch)?;
+
+  Ok(Message::ok(format!("reloading {}", payload.name)))
+}
+
 #[runtime("services")]
 impl ServiceRuntime {
   fn bootstrap(&mut self) {
+    ctx
+      .registry
+      .singleton_or_insert_with(ExitHistory::KEY, ExitHistory::default);
     self.rebuild_trigger_index(ctx.registry.metadata);
   }
 
@@ -2473,6 +4304,34 @@ impl ServiceRuntime {
     }
   }
 
+  fn memory_event(&mut self, fd: i32) {
+    let Some(pid) = self.memory_watch_fds.get(&(fd as RawFd)).copied() else {
+      return Ok(None);
+    };
+    let Some(watch) = self.memory_watches.get_mut(&pid) else {
+      return Ok(None);
+    };
+    let borrowed = unsafe { BorrowedFd::borrow_raw(fd as RawFd) };
+
+    if watch.events_fd == Some(fd as RawFd) {
+      if let Some(events) = MemoryEvents::read_fd(borrowed) {
+        Self::report_oom_kills(watch, events.oom_kill, dispatch);
+      }
+    } else if let Some(pressure) = Pressure::read_fd(borrowed) {
+      let payload = serde_json::json!({
+        "service": Self::instance_key_name(watch.service_key.as_str()).as_str(),
+        "branch": watch.branch.as_ref().map(|b| b.as_str()),
+        "some_avg10": pressure.some_avg10,
+        "full_avg10": pressure.full_avg10,
+      });
+      let mut fields = HashMap::new();
+      fields.insert("service".to_string(), watch.service_key.to_string());
+      fields.insert("some_avg10".to_string(), pressure.some_avg10.to_string());
+      log.log(LogLevel::Warn, "service-runtime", "memory pressure", fields);
+      Self::emit_impulse(dispatch, "rind:memory_pressure", payload);
+    }
+  }
+
   fn watchdog_expired(&mut self, fd: i32) {
     let Some(binding) = self.watchdog_fds.get(&(fd as RawFd)).cloned() else {
       return Ok(None);
@@ -2493,6 +4352,13 @@ impl ServiceRuntime {
 
     match action {
       WatchdogAction::Signal => {
+        if let Ok(service) = ctx
+          .registry
+          .as_one_mut::<Service>("*", binding.service_key.as_str())
+          && let Some(idx) = service.instances.find_by_pid(binding.pid as i32)
+        {
+          service.instances.0[idx].exit_trigger = Some(ExitTrigger::Watchdog);
+        }
         let _ = kill(Pid::from_raw(-(binding.pid as i32)), Signal::SIGABRT);
       }
       WatchdogAction::Stop => {
@@ -2500,7 +4366,8 @@ impl ServiceRuntime {
           rpayload!({
             "name": Self::instance_key_name(binding.service_key.as_str()),
             "force": true,
-            "only_user": binding.user.clone()
+            "only_user": binding.user.clone(),
+            "trigger": ExitTrigger::Watchdog
           }),
           ctx,
           dispatch,
@@ -2512,7 +4379,8 @@ impl ServiceRuntime {
           rpayload!({
             "name": Self::instance_key_name(binding.service_key.as_str()),
             "force": true,
-            "only_user": binding.user.clone()
+            "only_user": binding.user.clone(),
+            "trigger": ExitTrigger::Watchdog
           }),
           ctx,
           dispatch,
@@ -2592,6 +4460,9 @@ impl ServiceRuntime {
             else {
               continue;
             };
+            for conds in [&meta.start_on, &meta.stop_on].into_iter().flatten() {
+              sm.observe_conditions(conds, emit_event.as_ref());
+            }
 
             let Some((_unit, _)) = service_name.split_once(':') else {
               continue;
@@ -2633,6 +4504,7 @@ impl ServiceRuntime {
                           self.stop_service(
                             service,
                             StopMode::Graceful,
+                            ExitTrigger::Flow,
                             log,
                             dispatch,
                             Some(sm),
@@ -2680,6 +4552,7 @@ impl ServiceRuntime {
                       self.stop_service(
                         service,
                         StopMode::Graceful,
+                        ExitTrigger::Flow,
                         log,
                         dispatch,
                         Some(sm),
@@ -2711,12 +4584,13 @@ impl ServiceRuntime {
             }
 
             if let Some(ref afters) = meta.after {
+              // `after` waits for readiness, so notify services still starting don't count.
               let all_active = afters.iter().all(|a| {
                 if let Ok(svc) = registry.as_one::<Service>("*", a.as_str()) {
-                  svc.instances.iter().any(|inst| {
-                    inst.state == ServiceState::Active
-                      || inst.state == ServiceState::Starting
-                  })
+                  svc
+                    .instances
+                    .iter()
+                    .any(|inst| inst.state == ServiceState::Active)
                 } else {
                   false
                 }
@@ -2741,7 +4615,6 @@ impl ServiceRuntime {
 
             if let Some(branching) = &ser.metadata.branching {
               let mut branches = sm
-                .facets
                 .get(&branching.source)
                 .cloned()
                 .unwrap_or_default();
@@ -2802,6 +4675,7 @@ impl ServiceRuntime {
                   key: Some(key.clone()),
                   payload: Some(branch.payload.clone()),
                   forced_user: None,
+                  connection_fd: None,
                 };
                 to_start.push((service_name.clone(), Some(branch_ctx)));
                 started += 1;
@@ -2811,6 +4685,10 @@ impl ServiceRuntime {
 
             to_start.push((service_name.clone(), None));
           }
+          // debounce and hold windows left pending come due on the flow timer
+          if sm.next_wakeup().is_some() {
+            FlowRuntime::actions.arm_timer().dispatch(dispatch)?;
+          }
           Ok(if to_start.is_empty() {
             None
           } else {
@@ -2841,20 +4719,22 @@ impl ServiceRuntime {
     #[optional] branch_ctx: ServiceBranchContext,
     #[default] deferred: bool,
   ) {
+    self.cancel_pending_restart(
+      name.as_str(),
+      branch_ctx.as_ref().and_then(|c| c.key.as_ref()),
+      ctx.resources,
+    );
     let socket_fds_raw = socket_fds.iter().map(|fd| *fd as RawFd).collect::<Vec<_>>();
     let mut sockets_map = get_all_sockets(&ctx.registry);
     if !socket_fds_raw.is_empty() {
-      let entry = sockets_map
-        .entry(name.clone())
-        .or_insert_with(|| SocketActivation {
-          fds: HashSet::new(),
-          names: HashSet::new(),
-        });
-      entry.fds.extend(socket_fds_raw.clone());
-      entry.names.extend(socket_fd_names.clone());
+      let entry = sockets_map.entry(name.clone()).or_default();
+      for (idx, fd) in socket_fds_raw.iter().enumerate() {
+        entry.push(*fd, socket_fd_names.get(idx).cloned().unwrap_or_default());
+      }
     }
 
-    ctx
+    let connection_fd = branch_ctx.as_ref().and_then(|c| c.connection_fd);
+    let started = ctx
       .registry
       .singleton_handle::<(&mut FacetGraph, &mut VariableHeap), Option<(
         Ustr,
@@ -2883,9 +4763,11 @@ impl ServiceRuntime {
               flags: m.flags.as_ref().map(|f| f.clone()),
               data: m.data.clone(),
               create: m.create,
+              propagation: m.propagation.as_ref().map(|p| p.to_string()),
             })
             .collect();
-          let ns_networks: Vec<NamespaceNetworkConfig> = Vec::new();
+          let ns_networks: Vec<NamespaceNetworkConfig> =
+            service.metadata.network.clone().unwrap_or_default();
 
           if !deferred
             && self.register_service_transport(service, dispatch, Some(service_key.clone()))
@@ -2937,11 +4819,14 @@ impl ServiceRuntime {
               ns_networks.clone(),
             ) {
               Ok(instances) => {
+                let spawned = instances.iter().any(|i| i.pending_hooks.is_none());
                 service.instances.extend(instances);
                 self.register_service_transport(service, dispatch, Some(service_key.clone()));
-                if !service.instances.is_empty() {
+                if spawned && service.metadata.r#type != ServiceType::Notify {
                   for inst in service.instances.iter_mut() {
-                    inst.state = ServiceState::Active;
+                    if inst.pending_hooks.is_none() {
+                      inst.state = ServiceState::Active;
+                    }
                   }
                   self.run_triggers(service.metadata.on_start.as_ref(), Some(sm), dispatch, log);
                 }
@@ -2957,6 +4842,7 @@ impl ServiceRuntime {
               }
               Err(e) => {
                 let err = format!("Failed to start service \"{}\": {e}", service_key);
+                service.last_state = ServiceState::Error(err.clone());
                 let mut fields = self.log_fields(service, "start", Some(&service_key));
                 fields.insert("error".into(), e.to_string());
                 log.log(
@@ -2970,6 +4856,7 @@ impl ServiceRuntime {
                     inst.state = ServiceState::Error(err.clone());
                   }
                 }
+                Self::announce_failed_start(dispatch, service_key);
                 Ok(None)
               }
             }
@@ -2978,6 +4865,7 @@ impl ServiceRuntime {
               key: None,
               payload: None,
               forced_user: Some(user.into()),
+              connection_fd: None,
             };
 
             match self.spawn_all(
@@ -2996,7 +4884,10 @@ impl ServiceRuntime {
               Ok(instances) => {
                 service.instances.extend(instances);
                 self.register_service_transport(service, dispatch, Some(service_key.clone()));
-                if let Some(inst) = service.instances.as_one_mut() {
+                if service.metadata.r#type != ServiceType::Notify
+                  && let Some(inst) = service.instances.as_one_mut()
+                  && inst.pending_hooks.is_none()
+                {
                   inst.state = ServiceState::Active;
                   self.run_triggers(service.metadata.on_start.as_ref(), Some(sm), dispatch, log);
                 }
@@ -3012,6 +4903,7 @@ impl ServiceRuntime {
               }
               Err(e) => {
                 let err = format!("Failed to start service \"{}\": {e}", service_key);
+                service.last_state = ServiceState::Error(err.clone());
                 let mut fields = self.log_fields(service, "start", Some(&service_key));
                 fields.insert("error".into(), e.to_string());
                 log.log(
@@ -3023,6 +4915,7 @@ impl ServiceRuntime {
                 if let Some(inst) = service.instances.as_one_mut() {
                   inst.state = ServiceState::Error(err);
                 }
+                Self::announce_failed_start(dispatch, service_key);
                 Ok(None)
               }
             }
@@ -3051,7 +4944,17 @@ impl ServiceRuntime {
             )))
           }
         },
-      )?
+      );
+
+    // Spawned instances hold their own copy of an accepted connection; drop
+    // rind's unless the start was deferred and will run again.
+    if let Some(fd) = connection_fd
+      && !matches!(started, Ok(Some((_, _, _, true, _, _))))
+    {
+      ctx.resources.remove_full(fd);
+    }
+
+    started?
       .map(
         |(name, socket_fds, socket_fd_names, deferred, only_user, branch_ctx)| {
           if deferred {
@@ -3070,7 +4973,7 @@ impl ServiceRuntime {
 
             // self.__runtime_start(payload, ctx, dispatch, log)?;
             dispatch.dispatch("services", "start", payload)?;
-          } else {
+          } else if !self.awaits_ready(ctx, name.as_str()) {
             self.reconcile(ctx, log, dispatch, name, ServiceEventKind::Started)?;
           }
 
@@ -3086,12 +4989,17 @@ impl ServiceRuntime {
     #[default] force: bool,
     #[optional] index: usize,
     #[optional] only_user: Ustr,
+    #[optional] trigger: ExitTrigger,
   ) {
+    self.cancel_pending_restart(name.as_str(), None, ctx.resources);
+    let scoped = Self::ensure_scoped_name(name.as_str());
+    self.restarts.retain(|(service, _), _| *service != scoped);
     let mode = if force {
       StopMode::ForceKill
     } else {
       StopMode::Graceful
     };
+    let trigger = trigger.unwrap_or(ExitTrigger::Manual);
     let notifier = ctx.notifier.clone();
 
     ctx.registry.singleton_handle::<(&mut FacetGraph,), _>(
@@ -3103,6 +5011,7 @@ impl ServiceRuntime {
         self.stop_service(
           service,
           mode,
+          trigger,
           log,
           dispatch,
           Some(sm),
@@ -3143,6 +5052,7 @@ impl ServiceRuntime {
                 self.stop_service(
                   service,
                   mode,
+                  ExitTrigger::Manual,
                   log,
                   dispatch,
                   Some(sm),
@@ -3165,6 +5075,9 @@ impl ServiceRuntime {
 
   fn start_all(&mut self) {
     let mut started: HashSet<Ustr> = HashSet::new();
+    // Notify services that haven't reported READY yet; their dependents are
+    // started by `reconcile_stacks` instead.
+    let mut awaiting: HashSet<Ustr> = HashSet::new();
     let mut pending: Vec<(Ustr, Vec<Ustr>, Arc<ServiceMetadata>)> = Vec::new();
 
     for (full_name, svc_meta) in &ctx
@@ -3174,7 +5087,7 @@ impl ServiceRuntime {
         |_, (sm,)| {
           let mut all_services: Vec<(Ustr, Arc<ServiceMetadata>)> = Vec::new();
 
-          let Some(active) = sm.facets.get("rind:active") else {
+          let Some(active) = sm.get("rind:active") else {
             return Ok(all_services);
           };
 
@@ -3226,16 +5139,29 @@ impl ServiceRuntime {
         if !already_running {
           self.__runtime_start(rpayload!({ "name": full_name.clone() }), ctx, dispatch, log)?;
         }
-        started.insert(full_name.clone());
+        if self.awaits_ready(ctx, full_name.as_str()) && !self.is_ready(ctx, full_name.as_str()) {
+          awaiting.insert(full_name.clone());
+        } else {
+          started.insert(full_name.clone());
+        }
       }
     }
 
     loop {
       let mut progress = false;
       pending.retain(|(name, afters, meta)| {
-        if meta.start_on.is_some() || !afters.iter().all(|a| started.contains(a)) {
+        if meta.start_on.is_some()
+          || !afters
+            .iter()
+            .all(|a| started.contains(a) || awaiting.contains(a))
+        {
           return true;
         }
+        if afters.iter().any(|a| awaiting.contains(a)) {
+          awaiting.insert(name.clone());
+          progress = true;
+          return false;
+        }
         let key = Self::ensure_scoped_name(name.as_str());
         let already_running = ctx
           .registry
@@ -3251,7 +5177,11 @@ impl ServiceRuntime {
         if !already_running {
           let _ = self.__runtime_start(rpayload!({ "name": name.clone() }), ctx, dispatch, log);
         }
-        started.insert(name.clone());
+        if self.awaits_ready(ctx, name.as_str()) && !self.is_ready(ctx, name.as_str()) {
+          awaiting.insert(name.clone());
+        } else {
+          started.insert(name.clone());
+        }
         progress = true;
         false
       });
@@ -3322,6 +5252,7 @@ impl ServiceRuntime {
                     self.stop_service(
                       service,
                       StopMode::Graceful,
+                      ExitTrigger::Dependency,
                       log,
                       dispatch,
                       Some(sm),
@@ -3361,8 +5292,10 @@ impl ServiceRuntime {
                   !svc.instances.is_empty()
                     && !svc.instances.iter().any(|x| {
                       x.state == ServiceState::Inactive
+                        || x.state == ServiceState::Starting
                         || x.state == ServiceState::Stopping
                         || matches!(x.state, ServiceState::Exited(_))
+                        || matches!(x.state, ServiceState::OomKilled(_))
                         || matches!(x.state, ServiceState::Error(_))
                     })
                 } else {
@@ -3372,77 +5305,389 @@ impl ServiceRuntime {
             }
           };
 
-          if should_start {
-            self.__runtime_start(rpayload!({ "name": dependent.clone() }), ctx, dispatch, log)?;
-          }
-        }
-      }
+          if should_start {
+            self.__runtime_start(rpayload!({ "name": dependent.clone() }), ctx, dispatch, log)?;
+          }
+        }
+      }
+    }
+  }
+
+  fn stop_for_scope(&mut self, scope: Ustr) {
+    let notifier = ctx.notifier.clone();
+
+    ctx.registry.singleton_handle::<(&mut FacetGraph,), _>(
+      (FacetGraph::KEY.into(),),
+      |registry, (sm,)| {
+        for (group, svc) in registry
+          .metadata
+          .items::<Service>(scope.clone())
+          .unwrap_or_default()
+        {
+          let full_name = rslvns!(u group, svc.name);
+          self.stop_service(
+            registry.as_one_mut::<Service>(scope.clone(), full_name.clone())?,
+            StopMode::ForceKill,
+            ExitTrigger::Manual,
+            log,
+            dispatch,
+            Some(sm),
+            None,
+            None,
+            None,
+            Some(&full_name),
+            notifier.clone(),
+            ctx.resources,
+          );
+        }
+        Ok(Void)
+      },
+    )?;
+  }
+
+  fn reload(&mut self, name: Ustr, #[optional] only_user: Ustr) {
+    let key = Self::ensure_scoped_name(name.as_str());
+    let Ok(service) = ctx.registry.as_one_mut::<Service>("*", key.clone()) else {
+      return Err(CoreError::not_found("service", &name));
+    };
+
+    let targets: Vec<(usize, Option<u32>, Option<LaunchContext>)> = service
+      .instances
+      .iter()
+      .enumerate()
+      .filter(|(_, inst)| inst.handle.is_some())
+      .filter(|(_, inst)| only_user.is_none() || inst.user == only_user)
+      .map(|(idx, inst)| (idx, inst.pid(), inst.launch.clone()))
+      .collect();
+
+    for (idx, pid, launch) in targets {
+      let result = match (&service.metadata.reload, &launch) {
+        (Some(hooks), Some(launch)) => {
+          let chain = HookChain::new(hooks.clone(), launch.clone(), pid, HookThen::Reload);
+          match self.advance_hooks(service, chain, log, ctx.resources) {
+            // logged by `finish_hooks` once the commands exited
+            None => continue,
+            Some((_, result)) => result,
+          }
+        }
+        _ => match service.instances.0[idx].handle.as_mut() {
+          Some(handle) if pid != handle.pid() => kill(
+            Pid::from_raw(pid.unwrap_or_default() as i32),
+            Signal::SIGHUP,
+          )
+          .map_err(CoreError::System),
+          Some(handle) => handle.kill(Signal::SIGHUP),
+          None => continue,
+        },
+      };
+
+      let mut fields = self.log_fields(service, "reload", Some(&key));
+      match result {
+        Ok(_) => log.log(
+          LogLevel::Info,
+          "service-runtime",
+          "service reloaded",
+          fields,
+        ),
+        Err(e) => {
+          fields.insert("error".to_string(), e.to_string());
+          log.log(LogLevel::Error, "service-runtime", "reload failed", fields);
+        }
+      }
+    }
+  }
+
+  fn notify_message(&mut self, fd: i32) {
+    let binding = self.notify_fds.get(&(fd as RawFd)).cloned();
+    // only processes of the instance may notify, or anyone could point
+    // MAINPID at a process for rind to signal
+    let commands = drain_notify_fd(fd as RawFd, |sender| {
+      binding.as_ref().is_some_and(|b| b.owns(sender))
+    });
+    let Some(mut binding) = binding else {
+      return Ok(None);
+    };
+
+    let mut became_ready = false;
+    for command in commands {
+      if let NotifyCommand::Watchdog = command {
+        if let Some(wfd) = self.watchdog_pids.get(&binding.pid).copied()
+          && let Some(watchdog) = ctx
+            .registry
+            .metadata
+            .find::<Service>(
+              "*",
+              Self::instance_key_name(binding.service_key.as_str()).as_str(),
+            )
+            .and_then(|svc| svc.watchdog.clone())
+        {
+          let _ = self.refresh_watchdog_fd(wfd, &watchdog);
+        }
+        continue;
+      }
+
+      if let NotifyCommand::MainPid(main_pid) = command {
+        if main_pid == binding.pid {
+          continue;
+        }
+        if !binding.owns(main_pid) {
+          let mut fields = HashMap::new();
+          fields.insert("service".to_string(), binding.service_key.to_string());
+          fields.insert("pid".to_string(), main_pid.to_string());
+          log.log(
+            LogLevel::Warn,
+            "service-runtime",
+            "ignoring MAINPID outside the instance",
+            fields,
+          );
+          continue;
+        }
+        self.pid_map.insert(main_pid, binding.service_key.clone());
+        if let Some(wfd) = self.watchdog_pids.remove(&binding.pid) {
+          self.watchdog_pids.insert(main_pid, wfd);
+          if let Some(watchdog) = self.watchdog_fds.get_mut(&wfd) {
+            watchdog.pid = main_pid;
+          }
+        }
+        if let Some(watch) = self.memory_watches.remove(&binding.pid) {
+          for wfd in [watch.events_fd, watch.pressure_fd].into_iter().flatten() {
+            self.memory_watch_fds.insert(wfd, main_pid);
+          }
+          self.memory_watches.insert(main_pid, watch);
+        }
+        self.notify_pids.remove(&binding.pid);
+        self.notify_pids.insert(main_pid, fd as RawFd);
+      }
+
+      became_ready |= ctx.registry.singleton_handle::<(&mut FacetGraph,), bool>(
+        (FacetGraph::KEY.into(),),
+        |registry, (sm,)| {
+          let Some(service) = registry
+            .instances
+            .get_mut(&binding.service_key)
+            .and_then(|x| x.iter_mut().find_map(|i| i.downcast_mut::<Service>()))
+          else {
+            return Ok(false);
+          };
+          let Some(idx) = service.instances.find_by_pid(binding.pid as i32) else {
+            return Ok(false);
+          };
+          let mut fields = self.log_fields(service, "notify", Some(&binding.service_key));
+          let inst = &mut service.instances.0[idx];
+
+          match &command {
+            NotifyCommand::Ready if inst.state == ServiceState::Starting => {
+              inst.state = ServiceState::Active;
+              log.log(LogLevel::Info, "service-runtime", "service ready", fields);
+              self.run_triggers(service.metadata.on_start.as_ref(), Some(sm), dispatch, log);
+              return Ok(true);
+            }
+            NotifyCommand::Stopping => {
+              inst.state = ServiceState::Stopping;
+            }
+            NotifyCommand::Status(status) => {
+              fields.insert("status".to_string(), status.clone());
+              log.log(LogLevel::Debug, "service-runtime", "service status", fields);
+              inst.status = Some(status.clone());
+            }
+            NotifyCommand::MainPid(main_pid) => {
+              inst.main_pid = Some(*main_pid);
+            }
+            _ => {}
+          }
+          Ok(false)
+        },
+      )?;
+
+      if let NotifyCommand::MainPid(main_pid) = command {
+        binding.pid = main_pid;
+        self.notify_fds.insert(fd as RawFd, binding.clone());
+      }
+    }
+
+    if became_ready {
+      if let Some(ready_fd) = self
+        .notify_fds
+        .get_mut(&(fd as RawFd))
+        .and_then(|binding| binding.ready_fd.take())
+      {
+        ctx.resources.terminate(ready_fd);
+      }
+      self.reconcile(
+        ctx,
+        log,
+        dispatch,
+        binding.service_key,
+        ServiceEventKind::Started,
+      )?;
+    }
+  }
+
+  fn ready_timeout(&mut self, fd: i32, notify_fd: i32) {
+    ctx.resources.terminate(fd);
+    let Some(binding) = self.notify_fds.get_mut(&(notify_fd as RawFd)) else {
+      return Ok(None);
+    };
+    if binding.ready_fd != Some(fd as RawFd) {
+      return Ok(None);
+    }
+    binding.ready_fd = None;
+    let binding = binding.clone();
+
+    let Ok(service) = ctx
+      .registry
+      .as_one_mut::<Service>("*", binding.service_key.as_str())
+    else {
+      return Ok(None);
+    };
+    let Some(idx) = service.instances.find_by_pid(binding.pid as i32) else {
+      return Ok(None);
+    };
+    let kill_mode = service.metadata.kill_mode;
+    let mut fields = self.log_fields(service, "start", Some(&binding.service_key));
+    let inst = &mut service.instances.0[idx];
+    if inst.state != ServiceState::Starting {
+      return Ok(None);
+    }
+
+    // a failure, not a stop: the restart policy still applies
+    inst.exit_trigger = Some(ExitTrigger::StartTimeout);
+    let cgroup = inst.launch.as_ref().and_then(|l| l.cgroup_path.clone());
+    if let Some(handle) = inst.handle.as_mut() {
+      signal_instance(
+        handle.as_mut(),
+        inst.main_pid,
+        kill_mode,
+        cgroup.as_deref(),
+        Signal::SIGKILL,
+      );
     }
+    fields.insert("pid".to_string(), binding.pid.to_string());
+    log.log(
+      LogLevel::Error,
+      "service-runtime",
+      "service did not become ready, killing it",
+      fields,
+    );
   }
 
-  fn stop_for_scope(&mut self, scope: Ustr) {
-    let notifier = ctx.notifier.clone();
+  fn hook_expired(&mut self, fd: i32, pid: i32) {
+    ctx.resources.terminate(fd);
+    self.hook_timers.remove(&(fd as RawFd));
+    if let Some(running) = self
+      .hooks
+      .get_mut(&(pid as u32))
+      .and_then(|chain| chain.running.as_mut())
+      .filter(|running| running.timer_fd == fd as RawFd)
+    {
+      running.timed_out = true;
+      let _ = running.handle.kill(Signal::SIGKILL);
+    }
+  }
 
-    ctx.registry.singleton_handle::<(&mut FacetGraph,), _>(
-      (FacetGraph::KEY.into(),),
-      |registry, (sm,)| {
-        for (group, svc) in registry
-          .metadata
-          .items::<Service>(scope.clone())
-          .unwrap_or_default()
-        {
-          let full_name = rslvns!(u group, svc.name);
-          self.stop_service(
-            registry.as_one_mut::<Service>(scope.clone(), full_name.clone())?,
-            StopMode::ForceKill,
-            log,
-            dispatch,
-            Some(sm),
-            None,
-            None,
-            None,
-            Some(&full_name),
-            notifier.clone(),
-            ctx.resources,
-          );
-        }
-        Ok(Void)
-      },
-    )?;
+  fn restart_due(&mut self, fd: i32) {
+    ctx.resources.terminate(fd);
+    let Some(restart) = self.restart_fds.remove(&(fd as RawFd)) else {
+      return Ok(None);
+    };
+    if let Some(tracker) = self.restarts.get_mut(&(
+      Self::ensure_scoped_name(restart.service_key.as_str()),
+      restart.key.clone(),
+    )) {
+      tracker.pending_fd = None;
+    }
+
+    let mut payload = rpayload!({ "name": restart.service_key });
+    if let Some(branch_ctx) = restart.branch_ctx {
+      payload = payload.insert("branch_ctx", branch_ctx);
+    }
+    self.__runtime_start(payload, ctx, dispatch, log)?;
+  }
+
+  fn executor_exited(&mut self, fd: i32, pid: i32) {
+    let mut buf = [0u8; 4];
+    let n = unsafe { libc::read(fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+    ctx.resources.terminate(fd);
+    let code = if n == 4 {
+      i32::from_be_bytes(buf)
+    } else {
+      crate::executors::LOST_EXIT_CODE
+    };
+
+    self.__runtime_child_exited(rpayload!({ "pid": pid, "code": code }), ctx, dispatch, log)?;
   }
 
-  fn child_exited(&mut self, pid: i32, code: i32) {
+  fn child_exited(
+    &mut self,
+    pid: i32,
+    code: i32,
+    #[optional] signal: i32,
+    #[default] core_dumped: bool,
+  ) {
     let pid_u = pid as u32;
+    if let Some(chain) = self.hooks.get(&pid_u) {
+      let key = chain.launch.registry_key.clone();
+      let service = ctx.registry.as_one::<Service>("*", key).ok();
+      if let Some((chain, result)) = self.hook_exited(service, pid_u, code, log, ctx.resources) {
+        self.finish_hooks(chain, result, ctx, dispatch, log)?;
+      }
+      return Ok(None);
+    }
+    let status = match signal {
+      Some(signal) => ExitStatus::Signal {
+        signal,
+        core_dumped,
+      },
+      None => ExitStatus::Code(code),
+    };
     if let Some(service_key) = self.pid_map.remove(&pid_u) {
-      self.stopping_map.remove(&pid_u);
+      let stop_outcome = self.stopping_map.remove(&pid_u).map(|deadline| {
+        let outcome = if deadline.forced {
+          StopOutcome::Killed
+        } else if deadline.killed_at.is_some() {
+          StopOutcome::TimedOut
+        } else {
+          StopOutcome::Clean
+        };
+        let mut fields = HashMap::new();
+        fields.insert("service".to_string(), service_key.to_string());
+        fields.insert("outcome".to_string(), format!("{outcome:?}"));
+        fields.insert(
+          "elapsed_ms".to_string(),
+          deadline.since.elapsed().as_millis().to_string(),
+        );
+        log.log(LogLevel::Info, "service-runtime", "service stopped", fields);
+        outcome
+      });
 
+      ctx
+        .registry
+        .singleton_or_insert_with(ExitHistory::KEY, ExitHistory::default);
       match ctx
         .registry
-        .singleton_handle::<(&mut FacetGraph,), Option<(Ustr, ServiceEventKind)>>(
-          (FacetGraph::KEY.into(),),
-          |registry, (sm,)| {
+        .singleton_handle::<(&mut FacetGraph, &mut ExitHistory), Option<(Ustr, ServiceExitAction)>>(
+          (FacetGraph::KEY.into(), ExitHistory::KEY.into()),
+          |registry, (sm, history)| {
             let mut action = None;
             if let Some(instances) = registry.instances.get_mut(&service_key) {
               for instance in instances.iter_mut() {
                 if let Some(service) = instance.downcast_mut::<Service>() {
+                  if stop_outcome.is_some() && service.instances.find_by_pid(pid).is_some() {
+                    service.last_stop = stop_outcome;
+                  }
                   if let Some(exit_action) = self.handle_child_exit(
                     service,
                     pid,
-                    code,
+                    status,
+                    stop_outcome,
+                    history,
                     log,
                     dispatch,
                     Some(sm),
                     service_key.clone(),
                     ctx.resources,
                   ) {
-                    match exit_action {
-                      ServiceExitAction::Restart => {
-                        action = Some((service_key.clone(), ServiceEventKind::Started));
-                      }
-                      ServiceExitAction::StopDependents => {
-                        action = Some((service_key.clone(), ServiceEventKind::Exited { code }));
-                      }
-                    }
+                    action = Some((service_key.clone(), exit_action));
                   }
                 }
               }
@@ -3450,19 +5695,29 @@ impl ServiceRuntime {
             Ok(action)
           },
         )? {
-        Some((service, action)) => {
-          if action == ServiceEventKind::Started {
-            self.__runtime_start(
-              rpayload!({
-                "name": service
-              }),
-              ctx,
-              dispatch,
-              log,
-            )?;
-          } else {
-            self.reconcile(ctx, log, dispatch, service, action)?;
+        Some((service, ServiceExitAction::RestartAfter(restart, delay))) => {
+          let mut fields = HashMap::new();
+          fields.insert("service".to_string(), service.to_string());
+          if !restart.key.is_empty() {
+            fields.insert("branch".to_string(), restart.key.to_string());
           }
+          self.arm_restart_timer(restart, delay, ctx.resources)?;
+          fields.insert("delay_ms".to_string(), delay.as_millis().to_string());
+          log.log(
+            LogLevel::Info,
+            "service-runtime",
+            "restart scheduled",
+            fields,
+          );
+        }
+        Some((service, ServiceExitAction::StopDependents)) => {
+          self.reconcile(
+            ctx,
+            log,
+            dispatch,
+            service,
+            ServiceEventKind::Exited { code },
+          )?;
         }
         None => {}
       }
@@ -3470,23 +5725,86 @@ impl ServiceRuntime {
   }
 
   fn timeout_sweep(&mut self) {
-    let now = Instant::now();
-    let timeout = Duration::from_secs(
-      std::env::var("RIND_SERVICE_TIMEOUT")
-        .ok()
-        .and_then(|x| x.parse::<u64>().ok())
-        .unwrap_or(5),
-    );
-    let expired_pids: Vec<u32> = self
+    let expired: Vec<(u32, StopDeadline)> = self
+      .stopping_map
+      .iter()
+      .filter(|(_, deadline)| {
+        deadline.killed_at.is_none() && deadline.since.elapsed() > deadline.timeout
+      })
+      .map(|(&pid, deadline)| (pid, deadline.clone()))
+      .collect();
+
+    for (pid, deadline) in expired {
+      if is_virtual_pid(pid) {
+        self.kill_virtual_pid(&mut ctx.registry, pid, Signal::SIGKILL);
+      } else {
+        match (deadline.kill_mode, deadline.cgroup.as_ref()) {
+          (KillMode::Cgroup, Some(cgroup))
+            if std::fs::write(cgroup.join("cgroup.kill"), "1").is_ok() => {}
+          (KillMode::MainProcess, _) => {
+            let _ = kill(Pid::from_raw(pid as i32), Signal::SIGKILL);
+          }
+          _ => {
+            let _ = kill(Pid::from_raw(-(pid as i32)), Signal::SIGKILL);
+          }
+        }
+      }
+
+      let mut fields = HashMap::new();
+      fields.insert("pid".to_string(), pid.to_string());
+      if let Some(service) = self.pid_map.get(&pid) {
+        fields.insert("service".to_string(), service.to_string());
+      }
+      fields.insert(
+        "timeout_ms".to_string(),
+        deadline.timeout.as_millis().to_string(),
+      );
+      log.log(
+        LogLevel::Warn,
+        "service-runtime",
+        "stop timed out, sending SIGKILL",
+        fields,
+      );
+
+      if let Some(deadline) = self.stopping_map.get_mut(&pid) {
+        deadline.killed_at = Some(Instant::now());
+      }
+    }
+
+    // Nothing reaps a MAINPID that isn't rind's child, and a virtual pid's
+    // executor may never report back; tear those down here.
+    let lost: Vec<u32> = self
       .stopping_map
       .iter()
-      .filter(|(_, stop_time)| now.duration_since(**stop_time) > timeout)
+      .filter(|&(&pid, deadline)| {
+        deadline.killed_at.is_some_and(|at| {
+          at.elapsed() > STOP_KILL_GRACE
+            || (!is_virtual_pid(pid)
+              && kill(Pid::from_raw(pid as i32), None) == Err(nix::errno::Errno::ESRCH))
+        })
+      })
       .map(|(&pid, _)| pid)
       .collect();
 
-    for pid in expired_pids {
-      let pgid = Pid::from_raw(-(pid as i32));
-      let _ = kill(pgid, Signal::SIGKILL);
+    for pid in lost {
+      let mut fields = HashMap::new();
+      fields.insert("pid".to_string(), pid.to_string());
+      log.log(
+        LogLevel::Warn,
+        "service-runtime",
+        "killed process was not reaped, tearing it down",
+        fields,
+      );
+      self.__runtime_child_exited(
+        rpayload!({
+          "pid": pid as i32,
+          "code": 128 + Signal::SIGKILL as i32,
+          "signal": Signal::SIGKILL as i32
+        }),
+        ctx,
+        dispatch,
+        log,
+      )?;
       self.stopping_map.remove(&pid);
     }
   }
@@ -3495,6 +5813,7 @@ impl ServiceRuntime {
 #[cfg(test)]
 mod tests {
   use super::*;
+  use nix::sys::wait::{WaitStatus, waitpid};
   use rind_core::prelude::Metadata;
   use rind_ipc::FlowJson;
 
@@ -3516,6 +5835,11 @@ mod tests {
     attrs.insert(Ustr::from("cgroup.path"), "rind/scope-default".to_string());
     attrs.insert(Ustr::from("cgroup.memory-max"), "128M".to_string());
     attrs.insert(Ustr::from("cgroup.pids-max"), "64".to_string());
+    attrs.insert(Ustr::from("cgroup.memory-high"), "96M".to_string());
+    attrs.insert(
+      Ustr::from("cgroup.io-max"),
+      "8:0 rbps=1048576, 8:16 wiops=100".to_string(),
+    );
     ScopeStore::upsert_global(scope, attrs, None);
 
     let service = service_from_toml(
@@ -3523,17 +5847,24 @@ mod tests {
 [[service]]
 name = "demo"
 run.exec = "/bin/true"
-cgroup = { path = "rind/service", cpu-max = "50000 100000" }
+cgroup = { path = "rind/service", cpu-max = "50000 100000", cpu-weight = "200", cpuset-cpus = "0-1" }
 "#,
     );
 
     let isolation = ServiceRuntime::isolation_for(&service, Some(scope)).unwrap();
     let cgroup = isolation.cgroup.expect("cgroup should merge");
 
-    assert_eq!(cgroup.path.unwrap().as_str(), "rind/service");
-    assert_eq!(cgroup.memory_max.unwrap().as_str(), "128M");
-    assert_eq!(cgroup.cpu_max.unwrap().as_str(), "50000 100000");
-    assert_eq!(cgroup.pids_max.unwrap().as_str(), "64");
+    assert_eq!(cgroup.path.as_ref().unwrap().as_str(), "rind/service");
+    assert_eq!(cgroup.memory_max.as_ref().unwrap().as_str(), "128M");
+    assert_eq!(cgroup.cpu_max.as_ref().unwrap().as_str(), "50000 100000");
+    assert_eq!(cgroup.pids_max.as_ref().unwrap().as_str(), "64");
+    assert_eq!(cgroup.io_max.len(), 2);
+
+    let settings = cgroup.settings();
+    assert!(settings.contains(&("memory", "memory.high", "96M")));
+    assert!(settings.contains(&("cpu", "cpu.weight", "200")));
+    assert!(settings.contains(&("cpuset", "cpuset.cpus", "0-1")));
+    assert!(settings.contains(&("io", "io.max", "8:16 wiops=100")));
 
     ScopeStore::remove_scope_global(scope);
   }
@@ -3545,6 +5876,9 @@ cgroup = { path = "rind/service", cpu-max = "50000 100000" }
     attrs.insert(Ustr::from("namespace.mount"), "true".to_string());
     attrs.insert(Ustr::from("namespace.rootfs"), "/scope-root".to_string());
     attrs.insert(Ustr::from("namespace.hostname"), "scope-host".to_string());
+    attrs.insert(Ustr::from("namespace.propagation"), "slave".to_string());
+    attrs.insert(Ustr::from("namespace.overlay.upper"), "/upper".to_string());
+    attrs.insert(Ustr::from("namespace.overlay.work"), "/work".to_string());
     ScopeStore::upsert_global(scope, attrs, None);
 
     let service = service_from_toml(
@@ -3552,21 +5886,58 @@ cgroup = { path = "rind/service", cpu-max = "50000 100000" }
 [[service]]
 name = "demo"
 run.exec = "/bin/true"
-namespaces = { ipc = true, hostname = "service-host" }
+namespaces = { ipc = true, hostname = "service-host", propagation = "private" }
 "#,
     );
 
     let isolation = ServiceRuntime::isolation_for(&service, Some(scope)).unwrap();
+    assert!(isolation.needs_namespace_supervisor());
     let namespaces = isolation.namespaces.expect("namespaces should merge");
 
     assert!(namespaces.mount);
     assert!(namespaces.ipc);
     assert_eq!(namespaces.rootfs.unwrap().as_str(), "/scope-root");
     assert_eq!(namespaces.hostname.unwrap().as_str(), "service-host");
+    assert_eq!(namespaces.propagation.unwrap().as_str(), "private");
+    assert_eq!(
+      namespaces.overlay,
+      Some(RootfsOverlay {
+        upper: Ustr::from("/upper"),
+        work: Ustr::from("/work"),
+      })
+    );
 
     ScopeStore::remove_scope_global(scope);
   }
 
+  #[test]
+  fn remote_services_reject_local_confinement() {
+    let service = service_from_toml(
+      r#"
+[[service]]
+name = "far"
+run = { exec = "/bin/true", executor = "remote", remote = "127.0.0.1:7000" }
+cgroup = { memory-max = "64M" }
+"#,
+    );
+    let run = service.metadata.run.as_one().clone();
+    let isolation = ServiceRuntime::isolation_for(&service, Some("static")).unwrap();
+
+    let user = Ustr::from("nobody");
+    let err = ServiceRuntime::validate_remote_confinement(&service, &run, Some(&user), &isolation)
+      .unwrap_err()
+      .to_string();
+    assert!(err.contains("remote executor") && err.contains("user, cgroup"));
+
+    let plain = ServiceIsolation::default();
+    assert!(ServiceRuntime::validate_remote_confinement(&service, &run, None, &plain).is_ok());
+    let native = RunOption::default();
+    assert!(
+      ServiceRuntime::validate_remote_confinement(&service, &native, Some(&user), &isolation)
+        .is_ok()
+    );
+  }
+
   #[test]
   fn inline_service_namespace_rejects_scope_only_features() {
     let service = service_from_toml(
@@ -3657,6 +6028,278 @@ run.exec = "/bin/true"
     (VariableHeap::new(&path), path)
   }
 
+  #[test]
+  fn restart_table_parses_and_legacy_forms_normalize() {
+    let service = service_from_toml(
+      r#"
+[[service]]
+name = "demo"
+run.exec = "/bin/true"
+restart = { on = "always", delay-ms = 100, multiplier = 3.0, max-delay-ms = 500, burst = 4, healthy-after-ms = 2000 }
+"#,
+    );
+    let config = service.metadata.restart.as_ref().unwrap().config().unwrap();
+    assert_eq!(config.on, RestartOn::Always);
+    assert_eq!(config.burst, Some(4));
+    assert_eq!(config.delay_for(0), Duration::from_millis(100));
+    assert_eq!(config.delay_for(1), Duration::from_millis(300));
+    assert_eq!(config.delay_for(5), Duration::from_millis(500));
+
+    let legacy = service_from_toml(
+      r#"
+[[service]]
+name = "demo"
+run.exec = "/bin/true"
+restart = { max_retries = 2 }
+"#,
+    );
+    let config = legacy.metadata.restart.as_ref().unwrap().config().unwrap();
+    assert_eq!(config.on, RestartOn::OnFailure);
+    assert_eq!(config.max_retries, Some(2));
+    assert_eq!(config.burst, None);
+    assert_eq!(RestartPolicy::Bool(true).config().unwrap().burst, None);
+    assert!(RestartPolicy::Bool(false).config().is_none());
+  }
+
+  #[test]
+  fn stop_settings_parse() {
+    let service = service_from_toml(
+      r#"
+[[service]]
+name = "db"
+run.exec = "/bin/true"
+stop-signal = "INT"
+stop-timeout-ms = 60000
+kill-mode = "main-process"
+"#,
+    );
+    assert_eq!(
+      service.metadata.stop_signal,
+      Some(StopSignal(Signal::SIGINT))
+    );
+    assert_eq!(
+      ServiceRuntime::stop_timeout(&service.metadata),
+      Duration::from_secs(60)
+    );
+    assert_eq!(service.metadata.kill_mode, KillMode::MainProcess);
+
+    assert_eq!(
+      StopSignal::try_from("sighup".to_string()),
+      Ok(StopSignal(Signal::SIGHUP))
+    );
+    assert!(StopSignal::try_from("NOPE".to_string()).is_err());
+  }
+
+  #[test]
+  fn capability_policy_is_validated_at_load_and_merged_with_scope() {
+    let service = service_from_toml(
+      r#"
+[[service]]
+name = "web"
+run.exec = "/bin/true"
+capabilities = { drop = ["all"], ambient = ["CAP_NET_BIND_SERVICE"], no-new-privileges = true }
+"#,
+    );
+    let caps = service
+      .metadata
+      .capabilities
+      .clone()
+      .expect("inline policy");
+    assert!(caps.drops_all());
+    assert!(caps.no_new_privileges);
+
+    let mut metadata = Metadata::new("test").of::<Service>("service");
+    let bad = metadata.from_toml(
+      r#"
+[[service]]
+name = "web"
+run.exec = "/bin/true"
+capabilities = { ambient = ["net_bind_everything"] }
+"#,
+      "svc",
+    );
+    assert!(bad.is_err());
+
+    let conflicting = CapabilityPolicy {
+      drop: vec![Ustr::from("net_bind_service")],
+      ambient: vec![Ustr::from("net_bind_service")],
+      ..Default::default()
+    };
+    assert!(conflicting.validate().is_err());
+
+    let scope = "caps_merge_test";
+    let mut attrs = HashMap::new();
+    attrs.insert(Ustr::from("capabilities.keep"), "sys_chroot".to_string());
+    attrs.insert(
+      Ustr::from("capabilities.securebits"),
+      "noroot-locked".to_string(),
+    );
+    ScopeStore::upsert_global(scope, attrs, None);
+
+    let isolation = ServiceRuntime::isolation_for(&service, Some(scope)).unwrap();
+    let merged = isolation.capabilities.expect("merged policy");
+    assert_eq!(merged.keep, vec![Ustr::from("sys_chroot")]);
+    assert_eq!(merged.ambient, vec![Ustr::from("CAP_NET_BIND_SERVICE")]);
+    assert_eq!(merged.securebits, vec![Ustr::from("noroot-locked")]);
+
+    let mut attrs = HashMap::new();
+    attrs.insert(Ustr::from("capabilities.drop"), "not_a_cap".to_string());
+    ScopeStore::upsert_global(scope, attrs, None);
+    assert!(ServiceRuntime::isolation_for(&service, Some(scope)).is_err());
+
+    ScopeStore::remove_scope_global(scope);
+  }
+
+  #[test]
+  fn main_process_kill_mode_signals_only_the_main_pid() {
+    let child = std::process::Command::new("sleep")
+      .arg("5")
+      .spawn()
+      .expect("spawn sleep");
+    let pid = child.id();
+    let mut handle = crate::executors::ProcessHandle(child);
+
+    signal_instance(
+      &mut handle,
+      None,
+      KillMode::MainProcess,
+      None,
+      Signal::SIGINT,
+    );
+    assert_eq!(
+      waitpid(Pid::from_raw(pid as i32), None),
+      Ok(WaitStatus::Signaled(
+        Pid::from_raw(pid as i32),
+        Signal::SIGINT,
+        false
+      ))
+    );
+  }
+
+  #[test]
+  fn hooks_run_in_order_and_stop_on_failure() {
+    let service = service_from_toml(
+      r#"
+[[service]]
+name = "hooked"
+run.exec = "/bin/true"
+pre-start = [
+  { exec = "/bin/sh", args = ["-c", "test \"$MAINPID\" = 42 && test \"$HOOK\" = yes"] },
+]
+post-start = [
+  { exec = "/bin/false", ignore-failure = true },
+  { exec = "/nonexistent/hook", ignore-failure = true },
+  { exec = "/bin/true" },
+]
+stop = [{ exec = "/bin/sh", args = ["-c", "exit 3"] }]
+reload = [{ exec = "/bin/test", args = ["$MAINPID:${HOOK}", "=", "42:yes"] }]
+"#,
+    );
+    let log = LogHandle::mock();
+    let mut resources = Resources::default();
+    let mut envs = HashMap::new();
+    envs.insert(Ustr::from("HOOK"), Ustr::from("yes"));
+    let launch = LaunchContext {
+      registry_key: Ustr::from("hooked@static"),
+      branch_ctx: None,
+      resolved_user: None,
+      envs,
+      isolation: ServiceIsolation::default(),
+      cgroup_path: None,
+      namespace_mounts: Vec::new(),
+      namespace_networks: Vec::new(),
+    };
+
+    // drives a chain the way `child_exited` does, minus the reaper
+    let mut run = |rt: &mut ServiceRuntime, hooks: &Vec<RunOption>, main_pid: Option<u32>| {
+      let chain = HookChain::new(hooks.clone(), launch.clone(), main_pid, HookThen::Reload);
+      let mut step = rt.advance_hooks(&service, chain, &log, &mut resources);
+      loop {
+        if let Some((_, result)) = step {
+          return result;
+        }
+        let (&pid, _) = rt.hooks.iter().next().expect("a hook is running");
+        assert_eq!(rt.pid_map.get(&pid), Some(&Ustr::from("hooked@static")));
+        let code = match waitpid(Pid::from_raw(pid as i32), None) {
+          Ok(WaitStatus::Exited(_, code)) => code,
+          other => panic!("unexpected hook status {other:?}"),
+        };
+        step = rt.hook_exited(Some(&service), pid, code, &log, &mut resources);
+      }
+    };
+
+    let mut rt = ServiceRuntime::default();
+    let pre = service.metadata.pre_start.clone().unwrap();
+    run(&mut rt, &pre, Some(42)).expect("pre-start should pass");
+    assert!(run(&mut rt, &pre, None).is_err());
+
+    // no shell in between: the args themselves carry the variables
+    let reload = service.metadata.reload.clone().unwrap();
+    run(&mut rt, &reload, Some(42)).expect("reload should see $MAINPID");
+    assert!(run(&mut rt, &reload, Some(7)).is_err());
+
+    let post = service.metadata.post_start.clone().unwrap();
+    run(&mut rt, &post, Some(42)).expect("ignored failures go on");
+
+    let stop = service.metadata.stop.clone().unwrap();
+    let err = run(&mut rt, &stop, None).unwrap_err();
+    assert!(err.to_string().contains("exited with 3"));
+    assert!(rt.hooks.is_empty() && rt.pid_map.is_empty());
+  }
+
+  #[test]
+  fn restart_tracker_enforces_limits_and_healthy_reset() {
+    let config = RestartConfig {
+      max_retries: Some(3),
+      delay_ms: 10,
+      burst: Some(2),
+      burst_interval_ms: 1_000,
+      healthy_after_ms: Some(5_000),
+      ..Default::default()
+    };
+    let mut tracker = RestartTracker::default();
+    let now = Instant::now();
+    let short = Duration::from_millis(10);
+
+    assert_eq!(tracker.next(&config, 0, short, now), Ok(None));
+    assert_eq!(
+      tracker.next(&config, 1, short, now),
+      Ok(Some(Duration::from_millis(10)))
+    );
+    assert_eq!(
+      tracker.next(&config, 1, short, now),
+      Ok(Some(Duration::from_millis(20)))
+    );
+    assert!(tracker.next(&config, 1, short, now).is_err());
+
+    let later = now + Duration::from_secs(2);
+    assert_eq!(
+      tracker.next(&config, 1, short, later),
+      Ok(Some(Duration::from_millis(40)))
+    );
+    assert_eq!(tracker.next(&config, 1, short, later), Ok(None));
+
+    assert_eq!(
+      tracker.next(&config, 1, Duration::from_secs(6), later),
+      Ok(Some(Duration::from_millis(10)))
+    );
+    assert_eq!(tracker.attempts, 1);
+  }
+
+  #[test]
+  fn restart_defaults_back_off_and_limit_bursts() {
+    let config = RestartConfig::default();
+    let mut tracker = RestartTracker::default();
+    let now = Instant::now();
+    let short = Duration::from_millis(10);
+
+    for _ in 0..5 {
+      let delay = tracker.next(&config, 1, short, now).unwrap().unwrap();
+      assert!(!delay.is_zero());
+    }
+    assert!(tracker.next(&config, 1, short, now).is_err());
+  }
+
   #[test]
   fn branch_match_plain_string_exact() {
     let rt = ServiceRuntime::default();
@@ -3725,7 +6368,7 @@ run.exec = "/bin/true"
   fn branch_match_facet_spec_string_payload() {
     let rt = ServiceRuntime::default();
     let mut sm = test_facet_graph();
-    sm.facets.insert(
+    sm.insert(
       Ustr::from("test:state"),
       vec![FlowInstance {
         name: Ustr::from("test:state"),
@@ -3741,7 +6384,7 @@ run.exec = "/bin/true"
   fn branch_match_facet_spec_json_payload_with_key() {
     let rt = ServiceRuntime::default();
     let mut sm = test_facet_graph();
-    sm.facets.insert(
+    sm.insert(
       Ustr::from("test:session"),
       vec![FlowInstance {
         name: Ustr::from("test:session"),
@@ -3888,7 +6531,7 @@ run.exec = "/bin/true"
   fn branch_except_with_facet_spec() {
     let rt = ServiceRuntime::default();
     let mut sm = test_facet_graph();
-    sm.facets.insert(
+    sm.insert(
       Ustr::from("test:tty"),
       vec![FlowInstance {
         name: Ustr::from("test:tty"),
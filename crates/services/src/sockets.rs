@@ -1,22 +1,32 @@
 use rind_core::reexports::serde_json;
 use rind_flow::triggers::trigger_events;
 use rind_ipc::payloads::SSPayload;
-use std::collections::{HashMap, HashSet};
-use std::os::fd::{AsRawFd, RawFd};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::ffi::OsString;
+use std::net::{Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::fd::{AsFd, AsRawFd, BorrowedFd, FromRawFd, OwnedFd, RawFd};
 use std::os::unix::fs::PermissionsExt;
 use std::path::PathBuf;
+use std::time::{Duration, Instant};
 
+use nix::errno::Errno;
+use nix::fcntl::{OFlag, open};
 use nix::sys::socket::{
-  AddressFamily, Backlog, SockFlag, SockType, SockaddrIn, SockaddrIn6, UnixAddr, bind, listen,
-  setsockopt, socket, sockopt,
+  AddressFamily, Backlog, NetlinkAddr, SockFlag, SockType, SockaddrIn, SockaddrIn6,
+  SockaddrStorage, UnixAddr, accept4, bind, getpeername, listen, setsockopt, socket, sockopt,
 };
+use nix::sys::stat::Mode;
+use nix::sys::time::TimeSpec;
+use nix::sys::timerfd::{ClockId, Expiration, TimerFd, TimerFlags, TimerSetTimeFlags};
+use nix::unistd::mkfifo;
 use rind_core::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use crate::ServiceRuntime;
-use crate::services::SocketActivation;
+use crate::services::{Service, ServiceBranchContext, ServiceState, SocketActivation};
 use rind_flow::{
-  EmitTrigger, FacetGraph, FlowInstance, FlowItem, FlowRuntime, Trigger, condition_matches,
+  EmitTrigger, FacetGraph, FlowInstance, FlowItem, FlowPayload, FlowRuntime, Trigger,
+  condition_matches,
 };
 use rind_ipc::Message;
 use rind_primitives::permissions::PERM_SYSTEM_SERVICES;
@@ -29,6 +39,25 @@ pub enum SocketType {
   Tcp,
   Udp,
   Uds,
+  /// Unix datagram socket.
+  UdsDgram,
+  /// Unix `SOCK_SEQPACKET` socket.
+  Seqpacket,
+  /// Named pipe at `listen`.
+  Fifo,
+  /// Netlink socket; `listen` is `"<family> [groups]"`.
+  Netlink,
+}
+
+impl SocketType {
+  /// Types that listen for connections rather than receive data directly.
+  pub fn is_connection_based(&self) -> bool {
+    matches!(self, Self::Tcp | Self::Uds | Self::Seqpacket)
+  }
+
+  fn is_unix(&self) -> bool {
+    matches!(self, Self::Uds | Self::UdsDgram | Self::Seqpacket)
+  }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq, Default)]
@@ -39,11 +68,69 @@ pub enum SocketServiceLifecycle {
   Owned,
 }
 
+/// At most `count` events per `interval-ms`. Past that the socket is paused
+/// until the window has room again.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct SocketRateLimit {
+  pub count: u32,
+  #[serde(rename = "interval-ms", default = "default_rate_interval_ms")]
+  pub interval_ms: u64,
+}
+
+fn default_rate_interval_ms() -> u64 {
+  1_000
+}
+
+/// Sliding window of recent events checked against a [`SocketRateLimit`].
+#[derive(Debug, Default)]
+pub struct RateWindow {
+  recent: VecDeque<Instant>,
+}
+
+impl RateWindow {
+  /// `None` when another event fits in the window at `now`, otherwise how
+  /// long until it does.
+  pub fn throttled(&mut self, limit: &SocketRateLimit, now: Instant) -> Option<Duration> {
+    let interval = Duration::from_millis(limit.interval_ms);
+    while self
+      .recent
+      .front()
+      .is_some_and(|at| now.duration_since(*at) >= interval)
+    {
+      self.recent.pop_front();
+    }
+    if (self.recent.len() as u32) < limit.count {
+      return None;
+    }
+    Some(
+      self
+        .recent
+        .front()
+        .map_or(interval, |at| interval - now.duration_since(*at)),
+    )
+  }
+
+  pub fn record(&mut self, now: Instant) {
+    self.recent.push_back(now);
+  }
+}
+
+#[derive(Debug, Default)]
+struct SocketThrottle {
+  triggers: RateWindow,
+  accepts: RateWindow,
+  /// Timer that resumes the socket, while it is throttled.
+  timer: Option<RawFd>,
+}
+
 #[model(
   meta_name = name,
   meta_fields(
     name, listen, r#type, owner, start_on, lifecycle, trigger, stop_on,
-    managed_by, on_start, on_stop, on_data, permissions
+    managed_by, on_start, on_stop, on_data, permissions, accept, max_instances,
+    backlog, reuse_port, mode, user, group, bind_to_device, keepalive, freebind,
+    ipv6_only, trigger_limit, accept_limit
   ),
   derive_metadata(Debug, Clone, Default)
 )]
@@ -64,6 +151,38 @@ pub struct Socket {
   pub on_stop: Option<Vec<Trigger>>,
   #[serde(default)]
   pub lifecycle: SocketServiceLifecycle,
+  /// Accept connections in rind and start one `owner` instance per client,
+  /// with the connection on its stdin and stdout.
+  #[serde(default)]
+  pub accept: bool,
+  /// Caps concurrent per-connection instances; extra clients are dropped.
+  #[serde(rename = "max-instances")]
+  pub max_instances: Option<usize>,
+
+  /// Pending connection queue for connection-based sockets, 128 by default.
+  pub backlog: Option<u32>,
+  #[serde(rename = "reuse-port", default)]
+  pub reuse_port: bool,
+  /// Octal permissions of the socket or FIFO path, `0666` by default.
+  pub mode: Option<Ustr>,
+  /// Owner and group of the socket or FIFO path.
+  pub user: Option<Ustr>,
+  pub group: Option<Ustr>,
+  #[serde(rename = "bind-to-device")]
+  pub bind_to_device: Option<Ustr>,
+  #[serde(default)]
+  pub keepalive: bool,
+  #[serde(default)]
+  pub freebind: bool,
+  /// Keep IPv6 listeners off IPv4; they are dual-stack by default.
+  #[serde(rename = "ipv6-only", default)]
+  pub ipv6_only: bool,
+  /// Caps wakeups that fire `trigger` actions or start the owner.
+  #[serde(rename = "trigger-limit")]
+  pub trigger_limit: Option<SocketRateLimit>,
+  /// Caps connections accepted by an `accept = true` socket.
+  #[serde(rename = "accept-limit")]
+  pub accept_limit: Option<SocketRateLimit>,
 
   #[serde(rename = "managed-by")]
   pub managed_by: Option<Vec<Ustr>>,
@@ -73,11 +192,234 @@ pub struct Socket {
   pub active: bool,
 }
 
+/// Pending connections queued when a socket sets no `backlog`.
+const DEFAULT_BACKLOG: u32 = 128;
+
+const NETLINK_FAMILIES: [(&str, i32); 19] = [
+  ("route", libc::NETLINK_ROUTE),
+  ("usersock", libc::NETLINK_USERSOCK),
+  ("firewall", libc::NETLINK_FIREWALL),
+  ("sock-diag", libc::NETLINK_SOCK_DIAG),
+  ("nflog", libc::NETLINK_NFLOG),
+  ("xfrm", libc::NETLINK_XFRM),
+  ("selinux", libc::NETLINK_SELINUX),
+  ("iscsi", libc::NETLINK_ISCSI),
+  ("audit", libc::NETLINK_AUDIT),
+  ("fib-lookup", libc::NETLINK_FIB_LOOKUP),
+  ("connector", libc::NETLINK_CONNECTOR),
+  ("netfilter", libc::NETLINK_NETFILTER),
+  ("ip6-fw", libc::NETLINK_IP6_FW),
+  ("dnrtmsg", libc::NETLINK_DNRTMSG),
+  ("kobject-uevent", libc::NETLINK_KOBJECT_UEVENT),
+  ("generic", libc::NETLINK_GENERIC),
+  ("scsitransport", libc::NETLINK_SCSITRANSPORT),
+  ("ecryptfs", libc::NETLINK_ECRYPTFS),
+  ("rdma", libc::NETLINK_RDMA),
+];
+
+/// Parses a netlink `listen` value such as `"kobject-uevent 1"` into the
+/// protocol number and multicast group mask. Families may also be numeric.
+pub fn parse_netlink_listen(listen: &str) -> CoreResult<(i32, u32)> {
+  let mut parts = listen.split_whitespace();
+  let family = parts.next().unwrap_or_default();
+  let normalized = family.replace('_', "-");
+  let protocol = NETLINK_FAMILIES
+    .iter()
+    .find(|(name, _)| *name == normalized)
+    .map(|(_, protocol)| *protocol)
+    .or_else(|| family.parse().ok())
+    .ok_or_else(|| CoreError::InvalidState(format!("unknown netlink family '{family}'")))?;
+  let groups = match parts.next() {
+    Some(groups) => groups
+      .parse()
+      .map_err(|_| CoreError::InvalidState(format!("invalid netlink groups '{groups}'")))?,
+    None => 0,
+  };
+  Ok((protocol, groups))
+}
+
+/// Parses a tcp/udp `listen` value. A bare port listens on `[::]`, which
+/// takes IPv4 as well unless `ipv6-only` is set.
+pub fn parse_inet_listen(listen: &str) -> CoreResult<SocketAddr> {
+  if let Ok(port) = listen.parse::<u16>() {
+    return Ok(SocketAddr::from((Ipv6Addr::UNSPECIFIED, port)));
+  }
+  Ok(listen.parse()?)
+}
+
+/// Filesystem path of a unix socket or FIFO. Relative paths live under
+/// `/var/sock`; a stale file from a previous run is removed.
+fn listen_path(listen: &str, create: bool) -> CoreResult<PathBuf> {
+  let path = PathBuf::from("/var/sock").join(listen);
+  if let (Some(p), true) = (path.parent(), create) {
+    std::fs::create_dir_all(p)?;
+  }
+  if path.exists() {
+    let _ = std::fs::remove_file(&path);
+  }
+  Ok(path)
+}
+
+/// Whether the socket lives at a filesystem path that has to be cleaned up.
+fn has_listen_path(meta: &SocketMetadata) -> bool {
+  match &meta.r#type {
+    SocketType::Fifo => true,
+    ty if ty.is_unix() => !meta.listen.starts_with('@'),
+    _ => false,
+  }
+}
+
+fn apply_path_access(path: &std::path::Path, meta: &SocketMetadata) -> CoreResult<Void> {
+  let mode = match &meta.mode {
+    Some(mode) => u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+      .map_err(|_| CoreError::InvalidState(format!("invalid socket mode '{mode}'")))?,
+    None => 0o666,
+  };
+  std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+
+  if meta.user.is_none() && meta.group.is_none() {
+    return Ok(Void);
+  }
+  let store = rind_core::user::UserStore::load_system()?;
+  let uid = match &meta.user {
+    Some(user) => Some(
+      store
+        .lookup_by_name(user.as_str())
+        .ok_or_else(|| CoreError::InvalidState(format!("user '{user}' not found")))?
+        .uid,
+    ),
+    None => None,
+  };
+  let gid = match &meta.group {
+    Some(group) => Some(
+      store
+        .group_by_name(group.as_str())
+        .ok_or_else(|| CoreError::InvalidState(format!("group '{group}' not found")))?
+        .gid,
+    ),
+    None => None,
+  };
+  std::os::unix::fs::chown(path, uid, gid)?;
+  Ok(Void)
+}
+
+fn apply_inet_options(fd: &OwnedFd, meta: &SocketMetadata, addr: &SocketAddr) -> CoreResult<Void> {
+  if meta.r#type == SocketType::Tcp {
+    setsockopt(fd, sockopt::ReuseAddr, &true)?;
+  }
+  if addr.is_ipv6() {
+    setsockopt(fd, sockopt::Ipv6V6Only, &meta.ipv6_only)?;
+  }
+  if meta.reuse_port {
+    setsockopt(fd, sockopt::ReusePort, &true)?;
+  }
+  if let Some(device) = &meta.bind_to_device {
+    setsockopt(fd, sockopt::BindToDevice, &OsString::from(device.as_str()))?;
+  }
+  if meta.keepalive {
+    setsockopt(fd, sockopt::KeepAlive, &true)?;
+  }
+  if meta.freebind {
+    // Honoured by IPv6 sockets as well.
+    setsockopt(fd, sockopt::IpFreebind, &true)?;
+  }
+  Ok(Void)
+}
+
+/// Opens and binds the endpoint described by `meta`, non-blocking and
+/// close-on-exec, listening if it is connection-based.
+pub fn create_socket(meta: &SocketMetadata) -> CoreResult<OwnedFd> {
+  let flags = SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC;
+  let backlog = Backlog::new(
+    meta
+      .backlog
+      .unwrap_or(DEFAULT_BACKLOG)
+      .min(libc::SOMAXCONN as u32) as i32,
+  )?;
+
+  let fd = match meta.r#type {
+    SocketType::Tcp | SocketType::Udp => {
+      let addr = parse_inet_listen(&meta.listen)?;
+      let family = match addr {
+        SocketAddr::V4(_) => AddressFamily::Inet,
+        SocketAddr::V6(_) => AddressFamily::Inet6,
+      };
+      let ty = if meta.r#type == SocketType::Tcp {
+        SockType::Stream
+      } else {
+        SockType::Datagram
+      };
+
+      let fd = socket(family, ty, flags, None)?;
+      apply_inet_options(&fd, meta, &addr)?;
+      match addr {
+        SocketAddr::V4(a) => bind(fd.as_raw_fd(), &SockaddrIn::from(a))?,
+        SocketAddr::V6(a) => bind(fd.as_raw_fd(), &SockaddrIn6::from(a))?,
+      };
+      if meta.r#type == SocketType::Tcp {
+        listen(&fd, backlog)?;
+      }
+      fd
+    }
+    SocketType::Uds | SocketType::UdsDgram | SocketType::Seqpacket => {
+      let ty = match meta.r#type {
+        SocketType::Uds => SockType::Stream,
+        SocketType::UdsDgram => SockType::Datagram,
+        _ => SockType::SeqPacket,
+      };
+      let fd = socket(AddressFamily::Unix, ty, flags, None)?;
+
+      if let Some(name) = meta.listen.strip_prefix('@') {
+        bind(fd.as_raw_fd(), &UnixAddr::new_abstract(name.as_bytes())?)?;
+      } else {
+        let path = listen_path(&meta.listen, true)?;
+        bind(fd.as_raw_fd(), &UnixAddr::new(&path)?)?;
+        apply_path_access(&path, meta)?;
+      }
+      if meta.r#type.is_connection_based() {
+        listen(&fd, backlog)?;
+      }
+      fd
+    }
+    SocketType::Fifo => {
+      let path = listen_path(&meta.listen, true)?;
+      mkfifo(&path, Mode::from_bits_truncate(0o600))?;
+      apply_path_access(&path, meta)?;
+      // Opened read-write so the FIFO never reports EOF between writers.
+      open(
+        &path,
+        OFlag::O_RDWR | OFlag::O_NONBLOCK | OFlag::O_CLOEXEC,
+        Mode::empty(),
+      )?
+    }
+    SocketType::Netlink => {
+      let (protocol, groups) = parse_netlink_listen(&meta.listen)?;
+      let raw = unsafe {
+        libc::socket(
+          libc::AF_NETLINK,
+          libc::SOCK_RAW | libc::SOCK_NONBLOCK | libc::SOCK_CLOEXEC,
+          protocol,
+        )
+      };
+      if raw < 0 {
+        return Err(std::io::Error::last_os_error().into());
+      }
+      let fd = unsafe { OwnedFd::from_raw_fd(raw) };
+      bind(fd.as_raw_fd(), &NetlinkAddr::new(0, groups))?;
+      fd
+    }
+  };
+  Ok(fd)
+}
+
 pub struct SocketRuntime {
   instances: HashMap<RawFd, Ustr>,
   owner: HashMap<RawFd, Ustr>,
   paused: HashMap<Ustr, Vec<RawFd>>,
   trigger_index: HashMap<Ustr, std::collections::HashSet<Ustr>>,
+  accepted: u64,
+  throttles: HashMap<Ustr, SocketThrottle>,
+  throttle_timers: HashMap<RawFd, Ustr>,
 }
 
 impl Default for SocketRuntime {
@@ -87,86 +429,14 @@ impl Default for SocketRuntime {
       owner: HashMap::new(),
       paused: HashMap::new(),
       trigger_index: HashMap::new(),
+      accepted: 0,
+      throttles: HashMap::new(),
+      throttle_timers: HashMap::new(),
     }
   }
 }
 
 impl SocketRuntime {
-  fn get_socket_path(&self, listen: &str, create: bool) -> CoreResult<PathBuf> {
-    let path = PathBuf::from("/var/sock").join(listen);
-    if let (Some(p), true) = (path.parent(), create) {
-      std::fs::create_dir_all(p)?;
-    }
-    if path.exists() {
-      let _ = std::fs::remove_file(&path);
-    }
-    Ok(path)
-  }
-
-  fn create_socket(&self, meta: &SocketMetadata) -> CoreResult<std::os::fd::OwnedFd> {
-    let fd = match meta.r#type {
-      SocketType::Tcp => {
-        let addr: std::net::SocketAddr = meta.listen.parse()?;
-        let family = match addr {
-          std::net::SocketAddr::V4(_) => AddressFamily::Inet,
-          std::net::SocketAddr::V6(_) => AddressFamily::Inet6,
-        };
-
-        let fd = socket(
-          family,
-          SockType::Stream,
-          SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC,
-          None,
-        )?;
-        setsockopt(&fd, sockopt::ReuseAddr, &true)?;
-
-        match addr {
-          std::net::SocketAddr::V4(a) => bind(fd.as_raw_fd(), &SockaddrIn::from(a))?,
-          std::net::SocketAddr::V6(a) => bind(fd.as_raw_fd(), &SockaddrIn6::from(a))?,
-        };
-
-        listen(&fd, Backlog::new(128)?)?;
-        fd
-      }
-      SocketType::Uds => {
-        let path = self.get_socket_path(&meta.listen, true)?;
-
-        let fd = socket(
-          AddressFamily::Unix,
-          SockType::Stream,
-          SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC,
-          None,
-        )?;
-        let sockaddr = UnixAddr::new(&path)?;
-        bind(fd.as_raw_fd(), &sockaddr)?;
-        listen(&fd, Backlog::new(128)?)?;
-
-        std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o666))?;
-        fd
-      }
-      SocketType::Udp => {
-        let addr: std::net::SocketAddr = meta.listen.parse()?;
-        let family = match addr {
-          std::net::SocketAddr::V4(_) => AddressFamily::Inet,
-          std::net::SocketAddr::V6(_) => AddressFamily::Inet6,
-        };
-        let fd = socket(
-          family,
-          SockType::Datagram,
-          SockFlag::SOCK_NONBLOCK | SockFlag::SOCK_CLOEXEC,
-          None,
-        )?;
-
-        match addr {
-          std::net::SocketAddr::V4(a) => bind(fd.as_raw_fd(), &SockaddrIn::from(a))?,
-          std::net::SocketAddr::V6(a) => bind(fd.as_raw_fd(), &SockaddrIn6::from(a))?,
-        };
-        fd
-      }
-    };
-    Ok(fd)
-  }
-
   fn owner_fds(&self, owner: &Ustr) -> Vec<(RawFd, Ustr)> {
     let mut fds: Vec<(RawFd, Ustr)> = self
       .owner
@@ -196,12 +466,12 @@ impl SocketRuntime {
 
       if let Some(start_on) = &meta.start_on {
         for item in start_on {
-          interests.insert(item.name());
+          interests.extend(item.names());
         }
       }
       if let Some(stop_on) = &meta.stop_on {
         for item in stop_on {
-          interests.insert(item.name());
+          interests.extend(item.names());
         }
       }
 
@@ -226,9 +496,13 @@ impl SocketRuntime {
     notifier: Option<&Notifier>,
     log: Option<&LogHandle>,
   ) -> CoreResult<Void> {
-    let sock = registry.instantiate_one("*", name.clone(), |metadata| {
-      let owned_fd = self
-        .create_socket(&metadata)
+    let sock = registry.instantiate_one::<Socket>("*", name.clone(), |metadata| {
+      if metadata.accept && (metadata.owner.is_none() || !metadata.r#type.is_connection_based()) {
+        return Err(CoreError::InvalidState(format!(
+          "socket {name}: accept needs an owner and a connection-based socket"
+        )));
+      }
+      let owned_fd = create_socket(&metadata)
         .map_err(|e| CoreError::Custom(format!("failed to create socket {name}: {e}")))?;
       let fd = owned_fd.as_raw_fd();
 
@@ -245,7 +519,11 @@ impl SocketRuntime {
     resources.resume(sock.fd);
     self.instances.insert(sock.fd, name);
 
-    if let Some(owner) = sock.metadata.owner.as_ref() {
+    // Accepting sockets keep the listening fd to themselves; owners only
+    // ever see the connections.
+    if let Some(owner) = sock.metadata.owner.as_ref()
+      && !sock.metadata.accept
+    {
       self.owner.insert(sock.fd, owner.clone());
       sr.owners
         .entry(owner.clone())
@@ -281,16 +559,19 @@ impl SocketRuntime {
     resources.terminate(fd);
     self.instances.remove(&fd);
     self.owner.remove(&fd);
+    self.cancel_throttle(&name, resources);
 
-    if let Some(owner) = &socket.metadata.owner {
+    if let Some(owner) = &socket.metadata.owner
+      && !socket.metadata.accept
+    {
       if let Some(paused) = self.paused.get_mut(owner) {
         paused.retain(|&f| f != fd);
       }
       sr.owners.remove(owner);
     }
 
-    if socket.metadata.r#type == SocketType::Uds {
-      self.get_socket_path(&socket.metadata.listen, false)?;
+    if has_listen_path(&socket.metadata) {
+      listen_path(&socket.metadata.listen, false)?;
     }
 
     if let Some(triggers) = socket.metadata.on_stop.clone() {
@@ -304,11 +585,266 @@ impl SocketRuntime {
     Ok(Void)
   }
 
+  /// Checks the peer behind `fd` against the socket's `permissions`.
+  fn peer_permitted(
+    &self,
+    name: &Ustr,
+    socket: &Socket,
+    pm: Option<&PermissionStore>,
+    fd: RawFd,
+    log: &LogHandle,
+  ) -> bool {
+    let (Some(permissions), Some(pm)) = (&socket.metadata.permissions, pm) else {
+      return true;
+    };
+
+    let Ok(cred) = get_peer_cred(fd) else {
+      log.log(
+        LogLevel::Debug,
+        "sockets",
+        "permission denied: failed to get peer credentials",
+        [("name".to_string(), name.to_string())].into(),
+      );
+      return false;
+    };
+
+    if permissions
+      .iter()
+      .any(|x| pm.from_name(x).is_some_and(|x| pm.user_has(cred.uid, x)))
+    {
+      return true;
+    }
+
+    log.log(
+      LogLevel::Debug,
+      "sockets",
+      "permission denied",
+      [
+        ("name".to_string(), name.to_string()),
+        ("uid".to_string(), cred.uid.to_string()),
+      ]
+      .into(),
+    );
+    false
+  }
+
+  /// Branch key and payload for an accepted connection. TCP peers are keyed
+  /// by address; Unix peers by pid plus a sequence number, since one process
+  /// may hold several connections.
+  fn connection_peer(&mut self, name: &Ustr, socket: &Socket, fd: RawFd) -> (Ustr, FlowPayload) {
+    self.accepted += 1;
+    let (key, mut payload) = match socket.metadata.r#type {
+      SocketType::Tcp => {
+        let peer = getpeername::<SockaddrStorage>(fd).ok().and_then(|addr| {
+          addr
+            .as_sockaddr_in()
+            .map(|a| SocketAddrV4::from(*a).to_string())
+            .or_else(|| {
+              addr
+                .as_sockaddr_in6()
+                .map(|a| SocketAddrV6::from(*a).to_string())
+            })
+        });
+        let key = peer.unwrap_or_else(|| format!("tcp:{}", self.accepted));
+        (key.clone(), serde_json::json!({ "peer": key }))
+      }
+      _ => {
+        let cred = get_peer_cred(fd).ok();
+        let pid = cred.map_or(0, |c| c.pid);
+        (
+          format!("unix:{pid}:{}", self.accepted),
+          serde_json::json!({ "pid": pid, "uid": cred.map(|c| c.uid) }),
+        )
+      }
+    };
+    payload["socket"] = serde_json::Value::String(name.to_string());
+    (Ustr::from(key), FlowPayload::from_json(Some(payload)))
+  }
+
+  /// Pauses a socket that went over a rate limit and arms a timer that
+  /// resumes it once the window has room again.
+  fn throttle(
+    &mut self,
+    name: &Ustr,
+    limit: &str,
+    delay: Duration,
+    resources: &mut Resources,
+    dispatch: &RuntimeDispatcher,
+    log: &LogHandle,
+  ) -> CoreResult<Void> {
+    if let Some(fd) = self.socket_fd(name) {
+      resources.pause(fd);
+    }
+    let throttle = self.throttles.entry(name.clone()).or_default();
+    if throttle.timer.is_some() {
+      return Ok(Void);
+    }
+
+    let tfd = TimerFd::new(
+      ClockId::CLOCK_MONOTONIC,
+      TimerFlags::TFD_NONBLOCK | TimerFlags::TFD_CLOEXEC,
+    )
+    .map_err(CoreError::custom)?;
+    // A zero expiration would disarm the timer instead of firing it.
+    let delay = delay.max(Duration::from_millis(1));
+    tfd
+      .set(
+        Expiration::OneShot(TimeSpec::from(delay)),
+        TimerSetTimeFlags::empty(),
+      )
+      .map_err(CoreError::custom)?;
+
+    let timer = tfd.as_fd().as_raw_fd();
+    resources.own(timer, tfd);
+    resources.action(timer, ("sockets", "throttle_due"));
+    throttle.timer = Some(timer);
+    self.throttle_timers.insert(timer, name.clone());
+
+    let resume_ms = delay.as_millis() as u64;
+    log.log(
+      LogLevel::Warn,
+      "sockets",
+      "socket throttled",
+      [
+        ("name".to_string(), name.to_string()),
+        ("limit".to_string(), limit.to_string()),
+        ("resume_ms".to_string(), resume_ms.to_string()),
+      ]
+      .into(),
+    );
+    FlowRuntime::actions
+      .impulse("rind:socket_throttled".into())
+      .payload(serde_json::json!({
+        "socket": name.as_str(),
+        "limit": limit,
+        "resume_ms": resume_ms,
+      }))
+      .dispatch(dispatch)?;
+    Ok(Void)
+  }
+
+  fn socket_fd(&self, name: &Ustr) -> Option<RawFd> {
+    self
+      .instances
+      .iter()
+      .find_map(|(fd, socket)| (socket == name).then_some(*fd))
+  }
+
+  fn cancel_throttle(&mut self, name: &Ustr, resources: &mut Resources) {
+    if let Some(throttle) = self.throttles.remove(name)
+      && let Some(timer) = throttle.timer
+    {
+      self.throttle_timers.remove(&timer);
+      resources.terminate(timer);
+    }
+  }
+
+  /// Accepts every pending connection on an `accept = true` socket and starts
+  /// a branched `owner` instance per client. The start action closes rind's
+  /// copy of the connection once the instance holds its own.
+  fn accept_connections(
+    &mut self,
+    name: &Ustr,
+    socket: &Socket,
+    registry: &InstanceRegistry,
+    resources: &mut Resources,
+    dispatch: &RuntimeDispatcher,
+    log: &LogHandle,
+  ) -> CoreResult<Void> {
+    let Some(owner) = socket.metadata.owner.as_ref() else {
+      return Ok(Void);
+    };
+    let pm = registry.singleton::<PermissionStore>(PermissionStore::KEY);
+    let mut running = registry
+      .as_one::<Service>("*", owner.clone())
+      .map(|service| {
+        service
+          .instances
+          .iter()
+          .filter(|i| matches!(i.state, ServiceState::Active | ServiceState::Starting))
+          .count()
+      })
+      .unwrap_or(0);
+
+    loop {
+      let now = Instant::now();
+      if let Some(limit) = &socket.metadata.accept_limit
+        && let Some(delay) = self
+          .throttles
+          .entry(name.clone())
+          .or_default()
+          .accepts
+          .throttled(limit, now)
+      {
+        // Leave the rest in the backlog until the window has room.
+        self.throttle(name, "accept", delay, resources, dispatch, log)?;
+        break;
+      }
+
+      let client = match accept4(socket.fd, SockFlag::SOCK_CLOEXEC) {
+        Ok(fd) => unsafe { OwnedFd::from_raw_fd(fd) },
+        Err(Errno::EINTR | Errno::ECONNABORTED) => continue,
+        Err(Errno::EAGAIN) => break,
+        Err(e) => return Err(e.into()),
+      };
+      let fd = client.as_raw_fd();
+      if socket.metadata.accept_limit.is_some() {
+        self
+          .throttles
+          .entry(name.clone())
+          .or_default()
+          .accepts
+          .record(now);
+      }
+
+      if !self.peer_permitted(name, socket, pm, fd, log) {
+        continue;
+      }
+
+      if let Some(max) = socket.metadata.max_instances
+        && running >= max
+      {
+        log.log(
+          LogLevel::Warn,
+          "sockets",
+          "connection dropped: max-instances reached",
+          [
+            ("name".to_string(), name.to_string()),
+            ("owner".to_string(), owner.to_string()),
+          ]
+          .into(),
+        );
+        continue;
+      }
+
+      let (key, payload) = self.connection_peer(name, socket, fd);
+      resources.own(fd, client);
+
+      let branch_ctx = ServiceBranchContext {
+        key: Some(key),
+        payload: Some(payload),
+        forced_user: None,
+        connection_fd: Some(fd),
+      };
+      if let Err(e) = ServiceRuntime::actions
+        .start(owner.clone())
+        .branch_ctx(branch_ctx)
+        .dispatch(dispatch)
+      {
+        resources.remove_full(fd);
+        return Err(e);
+      }
+      running += 1;
+    }
+
+    Ok(Void)
+  }
+
   fn clear_socket(&mut self, socket: &Socket) {
     let fd = socket.fd;
 
     match socket.metadata.r#type {
-      SocketType::Tcp | SocketType::Uds => {
+      SocketType::Tcp | SocketType::Uds | SocketType::Seqpacket => {
         use nix::sys::socket::accept;
         use nix::unistd::close;
         loop {
@@ -320,7 +856,12 @@ impl SocketRuntime {
           }
         }
       }
-      SocketType::Udp => {
+      SocketType::Fifo => {
+        let fifo = unsafe { BorrowedFd::borrow_raw(fd) };
+        let mut buf = [0u8; 2048];
+        while matches!(nix::unistd::read(fifo, &mut buf), Ok(n) if n > 0) {}
+      }
+      SocketType::Udp | SocketType::UdsDgram | SocketType::Netlink => {
         use nix::sys::socket::{MsgFlags, recv};
         let mut buf = [0u8; 2048];
         loop {
@@ -404,6 +945,9 @@ impl SocketRuntime {
             let Some(meta) = registry.metadata.find::<Socket>("*", socket_name.as_str()) else {
               continue;
             };
+            for conds in [&meta.start_on, &meta.stop_on].into_iter().flatten() {
+              sm.observe_conditions(conds, emit_event.as_ref());
+            }
 
             let is_active = if let Ok(sock) = registry.as_one::<Socket>("*", socket_name.as_str()) {
               sock.active
@@ -455,6 +999,9 @@ impl SocketRuntime {
               );
             }
           }
+          if sm.next_wakeup().is_some() {
+            FlowRuntime::actions.arm_timer().dispatch(dispatch)?;
+          }
           Ok(Void)
         },
       )?;
@@ -470,7 +1017,7 @@ impl SocketRuntime {
           SocketRegistry::KEY.into(),
         ),
         |registry, (sm, _vh, sr)| {
-          let Some(active) = sm.facets.get("rind:active") else {
+          let Some(active) = sm.get("rind:active") else {
             return Ok(Void);
           };
 
@@ -585,6 +1132,28 @@ impl SocketRuntime {
       )?;
   }
 
+  fn throttle_due(&mut self, fd: i32) {
+    ctx.resources.terminate(fd);
+    let Some(name) = self.throttle_timers.remove(&(fd as RawFd)) else {
+      return Ok(None);
+    };
+    if let Some(throttle) = self.throttles.get_mut(&name) {
+      throttle.timer = None;
+    }
+
+    let Some(socket_fd) = self.socket_fd(&name) else {
+      return Ok(None);
+    };
+    // Sockets handed to a running owner are resumed by `reset_fds`.
+    if self.paused.values().any(|fds| fds.contains(&socket_fd)) {
+      return Ok(None);
+    }
+    ctx.resources.resume(socket_fd);
+    if let Some(n) = &ctx.notifier {
+      n.notify()?;
+    }
+  }
+
   #[action(rename = "reset_fds")]
   fn reset_fds_action(&mut self, name: Ustr) {
     if let Some(fds) = self.paused.remove(&name) {
@@ -638,15 +1207,14 @@ impl SocketRuntime {
 
   fn drain_incoming(&mut self, fd: i32) {
     let fd_raw = fd as RawFd;
-    let name = self.instances.get(&fd_raw).ok_or(CoreError::InvalidState(
-      "Socket for fd was not found".into(),
-    ))?;
+    let name = self
+      .instances
+      .get(&fd_raw)
+      .cloned()
+      .ok_or(CoreError::InvalidState(
+        "Socket for fd was not found".into(),
+      ))?;
     let socket = ctx.registry.as_one::<Socket>("*", name.clone())?;
-    ctx.resources.pause(fd_raw);
-
-    let pm = ctx
-      .registry
-      .singleton::<PermissionStore>(PermissionStore::KEY);
 
     log.log(
       LogLevel::Trace,
@@ -655,53 +1223,43 @@ impl SocketRuntime {
       [("name".to_string(), name.to_string())].into(),
     );
 
-    if let Some(ref permissions) = socket.metadata.permissions
-      && let Some(pm) = pm
-    {
-      let Ok(cred) = get_peer_cred(fd_raw) else {
-        log.log(
-          LogLevel::Debug,
-          "sockets",
-          "permission denied: failed to get peer credentials",
-          [("name".to_string(), name.to_string())].into(),
-        );
-
-        self.clear_socket(socket);
+    if let Some(limit) = &socket.metadata.trigger_limit {
+      let now = Instant::now();
+      let window = &mut self.throttles.entry(name.clone()).or_default().triggers;
+      if let Some(delay) = window.throttled(limit, now) {
+        self.throttle(&name, "trigger", delay, ctx.resources, dispatch, log)?;
         return Ok(None);
-      };
+      }
+      window.record(now);
+    }
 
-      if !permissions
-        .iter()
-        .any(|x| pm.from_name(x).map_or(false, |x| pm.user_has(cred.uid, x)))
-      {
-        log.log(
-          LogLevel::Debug,
-          "sockets",
-          "permission denied",
-          [
-            ("name".to_string(), name.to_string()),
-            ("uid".to_string(), cred.uid.to_string()),
-          ]
-          .into(),
-        );
+    if socket.metadata.accept {
+      self.accept_connections(&name, socket, &ctx.registry, ctx.resources, dispatch, log)?;
+    } else {
+      ctx.resources.pause(fd_raw);
+
+      let pm = ctx
+        .registry
+        .singleton::<PermissionStore>(PermissionStore::KEY);
+      if !self.peer_permitted(&name, socket, pm, fd_raw, log) {
         self.clear_socket(socket);
         return Ok(None);
       }
-    }
 
-    if let Some(owner) = socket.metadata.owner.clone() {
-      self.paused.entry(owner.clone()).or_default().push(fd_raw);
+      if let Some(owner) = socket.metadata.owner.clone() {
+        self.paused.entry(owner.clone()).or_default().push(fd_raw);
 
-      if let SocketServiceLifecycle::Owned = &socket.metadata.lifecycle {
-        let owner_fds = self.owner_fds(&owner);
-        let socket_fds: Vec<i32> = owner_fds.iter().map(|(fd, _)| *fd).collect();
-        let socket_fd_names: Vec<Ustr> = owner_fds.into_iter().map(|(_, name)| name).collect();
+        if let SocketServiceLifecycle::Owned = &socket.metadata.lifecycle {
+          let owner_fds = self.owner_fds(&owner);
+          let socket_fds: Vec<i32> = owner_fds.iter().map(|(fd, _)| *fd).collect();
+          let socket_fd_names: Vec<Ustr> = owner_fds.into_iter().map(|(_, name)| name).collect();
 
-        ServiceRuntime::actions
-          .start(owner)
-          .socket_fds(socket_fds)
-          .socket_fd_names(socket_fd_names)
-          .dispatch(dispatch)?;
+          ServiceRuntime::actions
+            .start(owner)
+            .socket_fds(socket_fds)
+            .socket_fd_names(socket_fd_names)
+            .dispatch(dispatch)?;
+        }
       }
     }
 
@@ -1,48 +1,428 @@
-use std::time::{Duration, Instant};
+use std::collections::HashMap;
+use std::os::fd::{AsFd, AsRawFd};
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+use nix::errno::Errno;
 use nix::sys::time::TimeSpec;
 use nix::sys::timerfd::{ClockId, Expiration, TimerFd, TimerFlags, TimerSetTimeFlags};
+use nix::time::clock_gettime;
 pub use rind_core::events::ServiceEventKind;
 use rind_core::prelude::*;
+pub use rind_core::utils::parse_duration;
 use rind_primitives::variables::VariableHeap;
-use std::os::fd::{AsFd, AsRawFd};
+use serde::{Deserialize, Serialize};
 
 pub use rind_flow::FacetGraph;
-use rind_flow::Trigger;
 use rind_flow::triggers::trigger_events;
+use rind_flow::{Trigger, state_root_path};
+
+use crate::calendar::{CalendarSpec, parse_calendar};
+use crate::services::{Service, ServiceRuntime, ServiceState};
 
 #[model(
   meta_name = name,
-  meta_fields(name, duration, after, finish),
+  meta_fields(
+    name, duration, on_calendar, repeat, randomized_delay, accuracy, persistent, unit, overlap,
+    after, finish
+  ),
   derive_metadata(Debug, Default)
 )]
 pub struct Timer {
   pub name: Ustr,
+  #[serde(default)]
   pub duration: Ustr,
+  #[serde(rename = "on-calendar")]
+  pub on_calendar: Option<Ustr>,
+  /// Re-arm a `duration` timer after every elapse instead of firing once.
+  #[serde(default)]
+  pub repeat: bool,
+  /// Random delay of up to this duration added to every elapse.
+  #[serde(rename = "randomized-delay")]
+  pub randomized_delay: Option<Ustr>,
+  /// Elapses are rounded up to a multiple of this window so nearby timers
+  /// wake together.
+  pub accuracy: Option<Ustr>,
+  /// Remember the last elapse across restarts and fire once on start if an
+  /// elapse was missed in between.
+  #[serde(default)]
+  pub persistent: bool,
+  /// Service started on every elapse, usually a `type = "job"` service.
+  pub unit: Option<Ustr>,
+  /// What to do when `unit` is still running at the next elapse.
+  #[serde(default)]
+  pub overlap: TimerOverlap,
   pub after: Option<Vec<Ustr>>,
   pub finish: Option<Vec<Trigger>>,
 
   pub deadline: Instant,
   pub fd: i32,
+  pub mode: TimerMode,
+  pub next_elapse: Option<SystemTime>,
+  pub last_elapse: Option<SystemTime>,
+  /// `unit` was started by this timer and has not exited yet.
+  pub running: bool,
+  /// A run was held back by `overlap = "queue"`.
+  pub queued: bool,
+  /// Waiting for `unit` to exit before re-arming (or finishing, for
+  /// one-shot timers).
+  pub awaiting_job: bool,
+  /// Exit code of the last `unit` run, `None` if it failed or was stopped.
+  pub last_exit: Option<i32>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TimerOverlap {
+  /// Drop the run.
+  #[default]
+  Skip,
+  /// Start one more run as soon as the current one exits.
+  Queue,
+}
+
+impl TimerMetadata {
+  /// The calendar expression for wall-clock timers, otherwise the duration.
+  pub fn schedule(&self) -> Ustr {
+    self
+      .on_calendar
+      .clone()
+      .unwrap_or_else(|| self.duration.clone())
+  }
+}
+
+/// When a timer elapses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TimerMode {
+  /// Once, `duration` after the timer starts.
+  Once(Duration),
+  /// Every `duration`, measured from the previous elapse.
+  Interval(Duration),
+  /// On every match of an `on-calendar` expression.
+  Calendar(CalendarSpec),
+}
+
+impl TimerMode {
+  pub fn from_metadata(metadata: &TimerMetadata) -> CoreResult<Self> {
+    if let Some(expr) = &metadata.on_calendar {
+      return Ok(Self::Calendar(parse_calendar(expr)?));
+    }
+    let duration = parse_duration(&metadata.duration)
+      .ok_or_else(|| CoreError::Custom(format!("invalid duration: {}", metadata.duration)))?;
+    Ok(if metadata.repeat {
+      Self::Interval(duration)
+    } else {
+      Self::Once(duration)
+    })
+  }
+
+  /// First elapse after `last`; `None` for one-shot timers and calendars
+  /// that never match again.
+  pub fn next_after(&self, last: SystemTime) -> Option<SystemTime> {
+    match self {
+      Self::Once(_) => None,
+      Self::Interval(interval) => Some(last + *interval),
+      Self::Calendar(spec) => spec.next_elapse(last),
+    }
+  }
+
+  /// Whether a timer that last elapsed at `last` should have elapsed again
+  /// by `now`.
+  pub fn missed(&self, last: SystemTime, now: SystemTime) -> bool {
+    self.next_after(last).is_some_and(|next| next <= now)
+  }
+
+  fn clock(&self) -> ClockId {
+    match self {
+      Self::Calendar(_) => ClockId::CLOCK_REALTIME,
+      _ => ClockId::CLOCK_MONOTONIC,
+    }
+  }
+}
+
+/// Rounds `at` up to the next multiple of `accuracy`.
+pub fn coalesce(at: Duration, accuracy: Duration) -> Duration {
+  let window = accuracy.as_nanos();
+  if window == 0 {
+    return at;
+  }
+  Duration::from_nanos((at.as_nanos().div_ceil(window) * window) as u64)
+}
+
+fn random_delay(max: Duration) -> Duration {
+  let max = max.as_nanos() as u64;
+  if max == 0 {
+    return Duration::ZERO;
+  }
+  let mut buf = [0u8; 8];
+  unsafe { libc::getrandom(buf.as_mut_ptr().cast(), buf.len(), 0) };
+  Duration::from_nanos(u64::from_ne_bytes(buf) % max)
+}
+
+/// Current time on the clock backing `mode`'s timerfd.
+fn clock_now(mode: &TimerMode) -> CoreResult<Duration> {
+  let clock = match mode {
+    TimerMode::Calendar(_) => nix::time::ClockId::CLOCK_REALTIME,
+    _ => nix::time::ClockId::CLOCK_MONOTONIC,
+  };
+  clock_gettime(clock)
+    .map(Duration::from)
+    .map_err(CoreError::custom)
+}
+
+impl Timer {
+  /// Arms `tfd` for the next elapse and updates `deadline` and
+  /// `next_elapse`. Returns `false` once the timer can no longer elapse.
+  fn arm(&mut self, tfd: &TimerFd) -> CoreResult<bool> {
+    let calendar = matches!(self.mode, TimerMode::Calendar(_));
+    let now = clock_now(&self.mode)?;
+    let wall_now = SystemTime::now();
+
+    let mut at = match &self.mode {
+      TimerMode::Once(duration) | TimerMode::Interval(duration) => now + *duration,
+      TimerMode::Calendar(spec) => {
+        let Some(next) = spec.next_elapse(wall_now) else {
+          return Ok(false);
+        };
+        next.duration_since(UNIX_EPOCH).map_err(CoreError::custom)?
+      }
+    };
+    if let Some(delay) = self.metadata.randomized_delay.as_ref() {
+      at += random_delay(parse_duration(delay.as_str()).unwrap_or_default());
+    }
+    if let Some(accuracy) = self.metadata.accuracy.as_ref() {
+      at = coalesce(at, parse_duration(accuracy.as_str()).unwrap_or_default());
+    }
+
+    let mut flags = TimerSetTimeFlags::TFD_TIMER_ABSTIME;
+    if calendar {
+      flags |= TimerSetTimeFlags::TFD_TIMER_CANCEL_ON_SET;
+    }
+    tfd
+      .set(Expiration::OneShot(TimeSpec::from(at)), flags)
+      .map_err(CoreError::custom)?;
+
+    let remaining = at.saturating_sub(now);
+    self.deadline = Instant::now() + remaining;
+    self.next_elapse = Some(wall_now + remaining);
+    Ok(true)
+  }
+
+  /// Arms `tfd` to elapse right away, for a persistent timer that missed an
+  /// elapse while rind was down.
+  fn arm_now(&mut self, tfd: &TimerFd) -> CoreResult<Void> {
+    tfd
+      .set(
+        Expiration::OneShot(TimeSpec::new(0, 1)),
+        TimerSetTimeFlags::empty(),
+      )
+      .map_err(CoreError::custom)?;
+    self.deadline = Instant::now();
+    self.next_elapse = Some(SystemTime::now());
+    Ok(Void)
+  }
+}
+
+/// Starts `timer`'s unit, unless its previous run is still going, in which
+/// case the run is skipped or queued per `overlap`. A start that can't be
+/// dispatched ends the run right away.
+fn start_unit(
+  ctx: &mut RuntimeContext<'_>,
+  dispatch: &RuntimeDispatcher,
+  log: &LogHandle,
+  timer: &Ustr,
+) -> CoreResult<Void> {
+  let Ok(state) = ctx.registry.as_one::<Timer>("*", timer.clone()) else {
+    return Ok(Void);
+  };
+  let Some(unit) = state.metadata.unit.clone() else {
+    return Ok(Void);
+  };
+  // instances left behind by a failed start don't count as a run
+  let busy = ctx
+    .registry
+    .as_one::<Service>("*", ServiceRuntime::ensure_scoped_name(&unit))
+    .is_ok_and(|svc| {
+      svc.instances.iter().any(|inst| {
+        matches!(
+          inst.state,
+          ServiceState::Active | ServiceState::Starting | ServiceState::Stopping
+        )
+      })
+    });
+
+  let Ok(state) = ctx.registry.as_one_mut::<Timer>("*", timer.clone()) else {
+    return Ok(Void);
+  };
+  let fields = [
+    ("timer".to_string(), timer.to_string()),
+    ("unit".to_string(), unit.to_string()),
+  ];
+  if !busy {
+    state.running = true;
+    if let Err(e) = dispatch.dispatch("services", "start", rpayload!({ "name": unit })) {
+      let mut fields: HashMap<String, String> = fields.into();
+      fields.insert("error".to_string(), e.to_string());
+      log.log(
+        LogLevel::Error,
+        "timer",
+        "failed to start timer unit",
+        fields,
+      );
+      return unit_finished(ctx, dispatch, log, timer, None);
+    }
+    return Ok(Void);
+  }
+  match state.metadata.overlap {
+    TimerOverlap::Skip => {
+      log.log(
+        LogLevel::Warn,
+        "timer",
+        "skipped timer run, previous run still active",
+        fields.into(),
+      );
+    }
+    TimerOverlap::Queue => {
+      state.queued = true;
+      log.log(
+        LogLevel::Info,
+        "timer",
+        "queued timer run behind active run",
+        fields.into(),
+      );
+    }
+  }
+  Ok(Void)
+}
+
+/// Handles `timer`'s unit going inactive: records the exit, starts a queued
+/// run and re-arms timers that wait for their job.
+fn unit_finished(
+  ctx: &mut RuntimeContext<'_>,
+  dispatch: &RuntimeDispatcher,
+  log: &LogHandle,
+  timer: &Ustr,
+  code: Option<i32>,
+) -> CoreResult<Void> {
+  let Ok(state) = ctx.registry.as_one_mut::<Timer>("*", timer.clone()) else {
+    return Ok(Void);
+  };
+  if !state.running && !state.awaiting_job {
+    return Ok(Void);
+  }
+
+  let mut fields: HashMap<String, String> = [
+    ("timer".to_string(), timer.to_string()),
+    (
+      "unit".to_string(),
+      state.metadata.unit.clone().unwrap_or_default().to_string(),
+    ),
+  ]
+  .into();
+  if state.running {
+    state.running = false;
+    state.last_exit = code;
+    fields.insert(
+      "code".to_string(),
+      code.map_or("none".to_string(), |code| code.to_string()),
+    );
+    log.log(LogLevel::Info, "timer", "timer run finished", fields);
+  }
+
+  if state.queued {
+    state.queued = false;
+    return start_unit(ctx, dispatch, log, timer);
+  }
+  if !state.awaiting_job {
+    return Ok(Void);
+  }
+
+  state.awaiting_job = false;
+  let fd = state.fd;
+  let rearmed = match (
+    matches!(state.mode, TimerMode::Once(_)),
+    ctx.resources.timer(fd),
+  ) {
+    (false, Some(tfd)) => state.arm(tfd)?,
+    _ => false,
+  };
+  if !rearmed {
+    ctx
+      .registry
+      .uninstantiate_one::<Timer>("*", timer.clone())?;
+    ctx.resources.terminate(fd);
+  }
+  Ok(Void)
+}
+
+/// Last elapse of every timer, kept as a registry singleton so it survives
+/// timers being stopped. Stamps of `persistent` timers are also written to
+/// disk and reloaded on boot.
+pub struct TimerStamps {
+  persistence: StatePersistence,
+  stamps: HashMap<Ustr, (SystemTime, bool)>,
 }
 
-pub fn parse_duration(s: &str) -> Option<Duration> {
-  let s = s.trim();
-  if s.is_empty() {
-    return None;
+impl TimerStamps {
+  pub const KEY: &str = "runtime:timer_stamps";
+
+  pub fn new(persistence: StatePersistence) -> Self {
+    let stamps = persistence
+      .load()
+      .unwrap_or_default()
+      .into_iter()
+      .filter_map(|(name, entries)| {
+        let data = entries.first()?.data.as_slice().try_into().ok()?;
+        let at = UNIX_EPOCH + Duration::from_secs(u64::from_le_bytes(data));
+        Some((Ustr::from(name), (at, true)))
+      })
+      .collect();
+    Self {
+      persistence,
+      stamps,
+    }
+  }
+
+  pub fn path() -> PathBuf {
+    state_root_path().join("timers.bin")
+  }
+
+  pub fn last(&self, timer: &str) -> Option<SystemTime> {
+    self
+      .stamps
+      .get(&ServiceRuntime::ensure_scoped_name(timer))
+      .map(|(at, _)| *at)
   }
 
-  let (num_str, unit) = s.split_at(s.len() - 1);
-  if let Ok(num) = num_str.parse::<u64>() {
-    match unit {
-      "s" => Some(Duration::from_secs(num)),
-      "m" => Some(Duration::from_secs(num * 60)),
-      "h" => Some(Duration::from_secs(num * 3600)),
-      "d" => Some(Duration::from_secs(num * 86400)),
-      _ => s.parse().ok().map(Duration::from_secs),
+  /// Records an elapse of `timer`; `persistent` stamps are saved to disk.
+  pub fn record(&mut self, timer: &str, at: SystemTime, persistent: bool) {
+    self
+      .stamps
+      .insert(ServiceRuntime::ensure_scoped_name(timer), (at, persistent));
+    if persistent {
+      self.persistence.save(self.snapshot());
     }
-  } else {
-    s.parse().ok().map(Duration::from_secs)
+  }
+
+  fn snapshot(&self) -> StateSnapshot {
+    self
+      .stamps
+      .iter()
+      .filter(|(_, (_, persistent))| *persistent)
+      .map(|(name, (at, _))| {
+        let secs = at.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+        (
+          name.to_string(),
+          vec![StateEntry {
+            data: secs.to_le_bytes().to_vec(),
+          }],
+        )
+      })
+      .collect()
+  }
+
+  pub fn flush(&self) -> CoreResult<Void> {
+    self.persistence.save_sync(&self.snapshot())
   }
 }
 
@@ -56,34 +436,49 @@ impl TimerRuntime {
       return Ok(None);
     }
 
+    let last = ctx
+      .registry
+      .singleton_or_insert_with(TimerStamps::KEY, || {
+        TimerStamps::new(StatePersistence::new(TimerStamps::path()))
+      })
+      .last(name.as_str());
+
     let timer = ctx
       .registry
       .instantiate_one::<Timer>("*", name.clone(), |metadata| {
-        let duration_str = metadata.duration.as_str();
-        let duration = parse_duration(duration_str)
-          .ok_or_else(|| CoreError::Custom(format!("invalid duration: {}", duration_str)))?;
-
+        let mode = TimerMode::from_metadata(&metadata)?;
         let tfd = TimerFd::new(
-          ClockId::CLOCK_MONOTONIC,
+          mode.clock(),
           TimerFlags::TFD_NONBLOCK | TimerFlags::TFD_CLOEXEC,
         )
         .map_err(CoreError::custom)?;
 
-        tfd
-          .set(
-            Expiration::OneShot(TimeSpec::from(duration)),
-            TimerSetTimeFlags::empty(),
-          )
-          .map_err(CoreError::custom)?;
+        let catch_up =
+          metadata.persistent && last.is_some_and(|last| mode.missed(last, SystemTime::now()));
+        let mut timer = Timer {
+          metadata,
+          deadline: Instant::now(),
+          fd: tfd.as_fd().as_raw_fd(),
+          mode,
+          next_elapse: None,
+          last_elapse: last,
+          running: false,
+          queued: false,
+          awaiting_job: false,
+          last_exit: None,
+        };
 
-        let fd = tfd.as_fd().as_raw_fd();
-        ctx.resources.own(fd, tfd);
+        if catch_up {
+          timer.arm_now(&tfd)?;
+        } else if !timer.arm(&tfd)? {
+          return Err(CoreError::Custom(format!(
+            "timer never elapses: {}",
+            timer.metadata.schedule()
+          )));
+        }
 
-        Ok(Timer {
-          metadata,
-          deadline: Instant::now() + duration,
-          fd,
-        })
+        ctx.resources.own(timer.fd, tfd);
+        Ok(timer)
       })?;
 
     let res_action: ResourceAction = ("timer", "finish_timer").into();
@@ -98,7 +493,10 @@ impl TimerRuntime {
       "started timer",
       [
         ("timer".to_string(), timer.metadata.name.to_string()),
-        ("duration".to_string(), timer.metadata.duration.to_string()),
+        (
+          "duration".to_string(),
+          timer.metadata.schedule().to_string(),
+        ),
       ]
       .into(),
     );
@@ -113,7 +511,10 @@ impl TimerRuntime {
       "stopped timer",
       [
         ("timer".to_string(), timer.metadata.name.to_string()),
-        ("duration".to_string(), timer.metadata.duration.to_string()),
+        (
+          "duration".to_string(),
+          timer.metadata.schedule().to_string(),
+        ),
       ]
       .into(),
     );
@@ -124,6 +525,7 @@ impl TimerRuntime {
     let service_name = Ustr::from(service_name.as_str().split('@').next().unwrap_or(""));
 
     let mut dependents = Vec::new();
+    let mut linked = Vec::new();
     for meta_name in ctx.registry.metadata.metadata_names() {
       let Some(meta) = ctx.registry.metadata.metadata(meta_name.clone()) else {
         continue;
@@ -140,6 +542,13 @@ impl TimerRuntime {
             {
               dependents.push(Ustr::from(format!("{}:{}", group, timer.name)));
             }
+            if timer
+              .unit
+              .as_ref()
+              .is_some_and(|unit| unit.as_str().split('@').next() == Some(service_name.as_str()))
+            {
+              linked.push(Ustr::from(format!("{}:{}", group, timer.name)));
+            }
           }
         }
       }
@@ -152,6 +561,13 @@ impl TimerRuntime {
         }
       }
       ServiceEventKind::Stopped | ServiceEventKind::Failed | ServiceEventKind::Exited { .. } => {
+        let code = match action {
+          ServiceEventKind::Exited { code } => Some(code),
+          _ => None,
+        };
+        for timer in linked {
+          unit_finished(ctx, dispatch, log, &timer, code)?;
+        }
         for dependent in dependents {
           let _ = dispatch.dispatch("timer", "stop", rpayload!({ "name": dependent }));
         }
@@ -160,16 +576,73 @@ impl TimerRuntime {
   }
 
   fn finish_timer(name: Ustr) {
+    let Ok(timer) = ctx.registry.as_one_mut::<Timer>("*", name.clone()) else {
+      return Ok(None);
+    };
+    let Some(tfd) = ctx.resources.timer(timer.fd) else {
+      return Ok(None);
+    };
+
+    // `EAGAIN` means `finish_timer` was dispatched before the timer expired;
+    // that finishes it early.
+    let elapsed = match nix::unistd::read(tfd, &mut [0u8; 8]) {
+      Ok(_) | Err(Errno::EAGAIN) => true,
+      Err(Errno::ECANCELED) => false,
+      Err(_) => return Ok(None),
+    };
+
+    let now = SystemTime::now();
+    if elapsed {
+      timer.last_elapse = Some(now);
+    }
+    // Duration timers with a unit re-arm once the job exits rather than
+    // from this elapse.
+    let rearmed = if elapsed
+      && timer.metadata.unit.is_some()
+      && !matches!(timer.mode, TimerMode::Calendar(_))
+    {
+      timer.awaiting_job = true;
+      true
+    } else {
+      !matches!(timer.mode, TimerMode::Once(_)) && timer.arm(tfd)?
+    };
+    let (fd, persistent, finish) = (
+      timer.fd,
+      timer.metadata.persistent,
+      timer.metadata.finish.clone(),
+    );
+
+    if !rearmed {
+      ctx.registry.uninstantiate_one::<Timer>("*", name.clone())?;
+      ctx.resources.terminate(fd);
+    }
+
+    if !elapsed {
+      log.log(
+        LogLevel::Info,
+        "timer",
+        "wall clock changed, re-armed calendar timer",
+        [("timer".to_string(), name.to_string())].into(),
+      );
+      return Ok(None);
+    }
+
+    ctx
+      .registry
+      .singleton_or_insert_with(TimerStamps::KEY, || {
+        TimerStamps::new(StatePersistence::new(TimerStamps::path()))
+      })
+      .record(name.as_str(), now, persistent);
+
+    start_unit(ctx, dispatch, log, &name)?;
+
     ctx
       .registry
       .singleton_handle::<(&mut FacetGraph, &mut VariableHeap), _>(
         (FacetGraph::KEY.into(), VariableHeap::KEY.into()),
-        |registry, (sm, _)| {
-          if let Ok(timer) = registry.uninstantiate_one::<Timer>("*", name) {
-            if let Some(triggers) = &timer.metadata.finish {
-              trigger_events(triggers.clone(), Some(sm), dispatch, Some(log));
-            }
-            ctx.resources.terminate(timer.fd);
+        |_, (sm, _)| {
+          if let Some(triggers) = finish {
+            trigger_events(triggers, Some(sm), dispatch, Some(log));
           }
           Ok(Void)
         },
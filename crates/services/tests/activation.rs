@@ -0,0 +1,107 @@
+use rind_core::prelude::*;
+use rind_services::namespaces::spawn_supervised;
+use rind_services::services::ServiceIsolation;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::fd::AsRawFd;
+use std::os::unix::net::{UnixListener, UnixStream};
+
+fn socket_inode(fd: i32) -> String {
+  std::fs::read_link(format!("/proc/self/fd/{fd}"))
+    .unwrap()
+    .to_string_lossy()
+    .into_owned()
+}
+
+#[test]
+fn activation_fds_follow_the_listen_fds_layout() {
+  let dir = std::env::temp_dir().join(format!("rind-activation-{}", std::process::id()));
+  let _ = std::fs::remove_dir_all(&dir);
+  std::fs::create_dir_all(&dir).unwrap();
+  let first = UnixListener::bind(dir.join("first.sock")).unwrap();
+  let second = UnixListener::bind(dir.join("second.sock")).unwrap();
+
+  let mut envs = HashMap::new();
+  envs.insert(Ustr::from("LISTEN_FDS"), Ustr::from("2"));
+  envs.insert(Ustr::from("LISTEN_FDNAMES"), Ustr::from("second:first"));
+  let script = r#"
+echo "fd3=$(readlink /proc/$$/fd/3)"
+echo "fd4=$(readlink /proc/$$/fd/4)"
+test "$LISTEN_PID" = "$$" && echo pid-ok
+echo "names=$LISTEN_FDNAMES"
+"#;
+
+  let mut handle = spawn_supervised(
+    "/bin/sh".into(),
+    vec![Ustr::from("-c"), Ustr::from(script)],
+    envs,
+    None,
+    None,
+    vec![second.as_raw_fd(), first.as_raw_fd()],
+    None,
+    ServiceIsolation::default(),
+    None,
+    Vec::new(),
+    Vec::new(),
+    Vec::new(),
+  )
+  .expect("spawn supervised service");
+  let pid = handle.pid().unwrap();
+  let mut output = String::new();
+  handle
+    .take_stdout()
+    .unwrap()
+    .read_to_string(&mut output)
+    .unwrap();
+  let mut status = 0;
+  unsafe { libc::waitpid(pid as i32, &mut status, 0) };
+
+  assert!(
+    output.contains(&format!("fd3={}", socket_inode(second.as_raw_fd()))),
+    "output: {output}"
+  );
+  assert!(
+    output.contains(&format!("fd4={}", socket_inode(first.as_raw_fd()))),
+    "output: {output}"
+  );
+  assert!(output.contains("pid-ok"), "output: {output}");
+  assert!(output.contains("names=second:first"), "output: {output}");
+
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn accepted_connection_is_the_service_stdio() {
+  let (client, connection) = UnixStream::pair().unwrap();
+
+  let handle = spawn_supervised(
+    "/bin/sh".into(),
+    vec![
+      Ustr::from("-c"),
+      Ustr::from(r#"read line; echo "got $line""#),
+    ],
+    HashMap::new(),
+    None,
+    None,
+    Vec::new(),
+    Some(connection.as_raw_fd()),
+    ServiceIsolation::default(),
+    None,
+    Vec::new(),
+    Vec::new(),
+    Vec::new(),
+  )
+  .expect("spawn supervised service");
+  let pid = handle.pid().unwrap();
+  // rind closes its copy once the instance is up.
+  drop(connection);
+
+  (&client).write_all(b"hello\n").unwrap();
+  let mut reply = String::new();
+  BufReader::new(&client).read_line(&mut reply).unwrap();
+  let mut status = 0;
+  unsafe { libc::waitpid(pid as i32, &mut status, 0) };
+
+  assert_eq!(reply, "got hello\n");
+  assert_eq!(libc::WEXITSTATUS(status), 0);
+}
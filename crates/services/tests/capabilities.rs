@@ -0,0 +1,121 @@
+use rind_core::prelude::*;
+use rind_services::namespaces::{
+  apply_capabilities, cap_names, cap_number, drop_bounding_set, effective_capabilities,
+  prepare_capabilities,
+};
+use rind_services::services::CapabilityPolicy;
+use std::os::unix::process::CommandExt;
+use std::process::{Child, Command};
+
+mod common;
+use common::is_root;
+
+fn spawn_with(policy: CapabilityPolicy, uid: Option<u32>) -> Child {
+  let mut cmd = Command::new("sleep");
+  cmd.arg("5");
+  unsafe {
+    cmd.pre_exec(move || {
+      let caps = Some(&policy);
+      drop_bounding_set(caps)?;
+      prepare_capabilities(caps, uid.is_some())?;
+      if let Some(uid) = uid
+        && (libc::setgid(uid) < 0 || libc::setuid(uid) < 0)
+      {
+        return Err(std::io::Error::last_os_error());
+      }
+      apply_capabilities(caps)
+    });
+  }
+  cmd.spawn().expect("spawn sleep")
+}
+
+fn finish(mut child: Child) {
+  let _ = child.kill();
+  let _ = child.wait();
+}
+
+#[test]
+fn cap_names_round_trip_through_numbers() {
+  assert_eq!(cap_number("CAP_NET_BIND_SERVICE"), Some(10));
+  assert_eq!(cap_number("sys_admin"), Some(21));
+  assert_eq!(cap_number("nope"), None);
+  assert_eq!(
+    cap_names((1 << 10) | (1 << 21)),
+    vec![Ustr::from("net_bind_service"), Ustr::from("sys_admin")]
+  );
+  assert!(effective_capabilities(std::process::id()).is_some());
+}
+
+#[test]
+fn root_service_keeps_only_listed_capabilities() {
+  if !is_root() {
+    return;
+  }
+  let child = spawn_with(
+    CapabilityPolicy {
+      drop: vec![Ustr::from("all")],
+      keep: vec![Ustr::from("net_bind_service"), Ustr::from("kill")],
+      ..Default::default()
+    },
+    None,
+  );
+  std::thread::sleep(std::time::Duration::from_millis(100));
+  let caps = effective_capabilities(child.id());
+  finish(child);
+
+  assert_eq!(
+    caps,
+    Some(vec![Ustr::from("kill"), Ustr::from("net_bind_service")])
+  );
+}
+
+#[test]
+fn unprivileged_service_keeps_ambient_capability() {
+  if !is_root() {
+    return;
+  }
+  let child = spawn_with(
+    CapabilityPolicy {
+      drop: vec![Ustr::from("all")],
+      ambient: vec![Ustr::from("net_bind_service")],
+      no_new_privileges: true,
+      ..Default::default()
+    },
+    Some(65534),
+  );
+  std::thread::sleep(std::time::Duration::from_millis(100));
+  let caps = effective_capabilities(child.id());
+  let status = std::fs::read_to_string(format!("/proc/{}/status", child.id())).unwrap_or_default();
+  finish(child);
+
+  assert_eq!(caps, Some(vec![Ustr::from("net_bind_service")]));
+  assert!(
+    status
+      .lines()
+      .any(|line| line.starts_with("NoNewPrivs:") && line.ends_with('1'))
+  );
+}
+
+#[test]
+fn unprivileged_service_keeps_inheritable_capability() {
+  if !is_root() {
+    return;
+  }
+  let child = spawn_with(
+    CapabilityPolicy {
+      drop: vec![Ustr::from("all")],
+      inheritable: vec![Ustr::from("net_bind_service")],
+      ..Default::default()
+    },
+    Some(65534),
+  );
+  std::thread::sleep(std::time::Duration::from_millis(100));
+  let status = std::fs::read_to_string(format!("/proc/{}/status", child.id())).unwrap_or_default();
+  finish(child);
+
+  let inheritable = status
+    .lines()
+    .find_map(|line| line.strip_prefix("CapInh:"))
+    .and_then(|mask| u64::from_str_radix(mask.trim(), 16).ok());
+  assert_eq!(inheritable, Some(1 << 10));
+}
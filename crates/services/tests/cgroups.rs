@@ -0,0 +1,152 @@
+use rind_core::prelude::*;
+use rind_services::cgroups::{
+  CgroupUsage, DEFAULT_MEMORY_PRESSURE, MemoryEvents, Pressure, apply_cgroup, open_psi_trigger,
+  parse_io_stat,
+};
+use rind_services::services::ServiceCgroup;
+
+mod common;
+use common::{is_root, scratch};
+
+fn read(dir: &std::path::Path, file: &str) -> String {
+  std::fs::read_to_string(dir.join(file)).unwrap()
+}
+
+#[test]
+fn apply_writes_every_configured_interface_file() {
+  let dir = scratch("cgroup", "apply");
+  let cgroup = ServiceCgroup {
+    memory_max: Some(Ustr::from("128M")),
+    memory_high: Some(Ustr::from("96M")),
+    memory_swap_max: Some(Ustr::from("0")),
+    cpu_weight: Some(Ustr::from("50")),
+    io_weight: Some(Ustr::from("default 200")),
+    cpuset_cpus: Some(Ustr::from("0")),
+    cpuset_mems: Some(Ustr::from("0")),
+    ..Default::default()
+  };
+
+  apply_cgroup(Some(&dir), Some(&cgroup), 4242).unwrap();
+
+  assert_eq!(read(&dir, "memory.max"), "128M");
+  assert_eq!(read(&dir, "memory.high"), "96M");
+  assert_eq!(read(&dir, "memory.swap.max"), "0");
+  assert_eq!(read(&dir, "cpu.weight"), "50");
+  assert_eq!(read(&dir, "io.weight"), "default 200");
+  assert_eq!(read(&dir, "cpuset.cpus"), "0");
+  assert_eq!(read(&dir, "cpuset.mems"), "0");
+  assert_eq!(read(&dir, "cgroup.procs"), "4242");
+  assert!(!dir.join("pids.max").exists());
+
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn rejected_writes_are_reported() {
+  let dir = scratch("cgroup", "reject");
+  // A directory in place of the interface file makes the write fail.
+  std::fs::create_dir_all(dir.join("cpu.max")).unwrap();
+  let cgroup = ServiceCgroup {
+    cpu_max: Some(Ustr::from("50000 100000")),
+    ..Default::default()
+  };
+
+  let err = apply_cgroup(Some(&dir), Some(&cgroup), 1).unwrap_err();
+  assert!(err.to_string().contains("cpu.max"), "error: {err}");
+  assert!(!dir.join("cgroup.procs").exists());
+
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn usage_is_parsed_from_interface_files() {
+  let dir = scratch("cgroup", "usage");
+  std::fs::write(dir.join("memory.current"), "1048576\n").unwrap();
+  std::fs::write(
+    dir.join("cpu.stat"),
+    "usage_usec 2500000\nuser_usec 2000000\nsystem_usec 500000\nnr_periods 0\n",
+  )
+  .unwrap();
+  std::fs::write(
+    dir.join("io.stat"),
+    "8:0 rbytes=4096 wbytes=8192 rios=1 wios=2 dbytes=0 dios=0\n\
+     8:16 rbytes=1024 wbytes=0 rios=1 wios=0 dbytes=0 dios=0\n",
+  )
+  .unwrap();
+  std::fs::write(dir.join("pids.current"), "3\n").unwrap();
+
+  let usage = CgroupUsage::read(&dir).unwrap();
+  assert_eq!(
+    usage,
+    CgroupUsage {
+      memory_current: Some(1048576),
+      cpu_usage_usec: Some(2500000),
+      cpu_user_usec: Some(2000000),
+      cpu_system_usec: Some(500000),
+      io_read_bytes: Some(5120),
+      io_write_bytes: Some(8192),
+      pids_current: Some(3),
+    }
+  );
+
+  std::fs::remove_file(dir.join("io.stat")).unwrap();
+  std::fs::remove_file(dir.join("memory.current")).unwrap();
+  let partial = CgroupUsage::read(&dir).unwrap();
+  assert_eq!(partial.io_read_bytes, None);
+  assert_eq!(partial.memory_current, None);
+  assert_eq!(partial.pids_current, Some(3));
+
+  assert_eq!(CgroupUsage::read(&dir.join("missing")), None);
+  assert_eq!(parse_io_stat(""), (0, 0));
+
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn memory_events_and_pressure_are_parsed() {
+  let events = MemoryEvents::parse("low 0\nhigh 12\nmax 3\noom 2\noom_kill 1\noom_group_kill 0\n");
+  assert_eq!(
+    events,
+    MemoryEvents {
+      high: 12,
+      max: 3,
+      oom: 2,
+      oom_kill: 1,
+    }
+  );
+
+  let pressure = Pressure::parse(
+    "some avg10=12.50 avg60=3.00 avg300=0.50 total=123\nfull avg10=4.25 avg60=1.00 avg300=0.10 total=45\n",
+  );
+  assert_eq!(pressure.some_avg10, 12.5);
+  assert_eq!(pressure.full_avg10, 4.25);
+
+  let dir = scratch("cgroup", "events");
+  std::fs::write(dir.join("memory.events"), "oom_kill 7\n").unwrap();
+  let file = std::fs::File::open(dir.join("memory.events")).unwrap();
+  assert_eq!(MemoryEvents::read_fd(&file).unwrap().oom_kill, 7);
+  // Every read starts at the beginning of the file.
+  assert_eq!(MemoryEvents::read_fd(&file).unwrap().oom_kill, 7);
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn pressure_trigger_defaults_and_can_be_disabled() {
+  let mut cgroup = ServiceCgroup::default();
+  assert_eq!(cgroup.pressure_trigger(), Some(DEFAULT_MEMORY_PRESSURE));
+  cgroup.memory_pressure = Some(Ustr::from("full 100000 2000000"));
+  assert_eq!(cgroup.pressure_trigger(), Some("full 100000 2000000"));
+  cgroup.memory_pressure = Some(Ustr::from("off"));
+  assert_eq!(cgroup.pressure_trigger(), None);
+}
+
+#[test]
+fn psi_triggers_are_armed_on_pressure_files() {
+  let system = std::path::Path::new("/proc/pressure/memory");
+  if !is_root() || !system.exists() {
+    return;
+  }
+  let fd = open_psi_trigger(system, DEFAULT_MEMORY_PRESSURE).unwrap();
+  assert!(Pressure::read_fd(&fd).is_some());
+  assert!(open_psi_trigger(system, "sideways 1 2").is_err());
+}
@@ -0,0 +1,28 @@
+// Every test crate pulls in this module but only uses some of it.
+#![allow(dead_code)]
+
+use std::io::Read;
+use std::os::fd::OwnedFd;
+use std::path::PathBuf;
+
+/// Tests that set up namespaces, cgroups or capabilities skip themselves
+/// unless they run as root.
+pub fn is_root() -> bool {
+  unsafe { libc::geteuid() == 0 }
+}
+
+/// Blocks until an instance's exit fd reports its exit code.
+pub fn wait_exit(fd: OwnedFd) -> i32 {
+  let mut file = std::fs::File::from(fd);
+  let mut buf = [0u8; 4];
+  file.read_exact(&mut buf).expect("exit code");
+  i32::from_be_bytes(buf)
+}
+
+/// A fresh, empty directory under the temp dir, unique to this test process.
+pub fn scratch(kind: &str, name: &str) -> PathBuf {
+  let dir = std::env::temp_dir().join(format!("rind-{kind}-{name}-{}", std::process::id()));
+  let _ = std::fs::remove_dir_all(&dir);
+  std::fs::create_dir_all(&dir).unwrap();
+  dir
+}
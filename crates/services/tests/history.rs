@@ -0,0 +1,55 @@
+use rind_core::prelude::*;
+use rind_services::history::{EXIT_HISTORY_LEN, ExitHistory, ExitRecord, ExitStatus, ExitTrigger};
+use rind_services::services::StopOutcome;
+use std::time::Duration;
+
+fn record(code: i32) -> ExitRecord {
+  ExitRecord::new(
+    Ustr::from("svc"),
+    ExitStatus::Code(code),
+    ExitTrigger::Exited,
+    None,
+    Duration::from_millis(1500),
+  )
+}
+
+#[test]
+fn signal_exits_map_to_shell_codes() {
+  assert_eq!(ExitStatus::Code(3).code(), 3);
+  let segv = ExitStatus::Signal {
+    signal: 11,
+    core_dumped: true,
+  };
+  assert_eq!(segv.code(), 139);
+
+  let killed = ExitRecord::new(
+    Ustr::from("svc"),
+    ExitStatus::Signal {
+      signal: 9,
+      core_dumped: false,
+    },
+    ExitTrigger::Watchdog,
+    Some(StopOutcome::Killed),
+    Duration::from_millis(2500),
+  );
+  assert_eq!(killed.runtime_ms, 2500);
+  assert!(killed.timestamp > 0);
+}
+
+#[test]
+fn history_is_bounded_and_keyed_by_scoped_name() {
+  let mut history = ExitHistory::default();
+  for code in 0..(EXIT_HISTORY_LEN as i32 + 5) {
+    history.push("units:svc", record(code));
+  }
+
+  let codes: Vec<i32> = history
+    .get("units:svc@static")
+    .map(|r| r.status.code())
+    .collect();
+  assert_eq!(codes.len(), EXIT_HISTORY_LEN);
+  assert_eq!(codes.first(), Some(&5));
+  assert_eq!(codes.last(), Some(&(EXIT_HISTORY_LEN as i32 + 4)));
+
+  assert_eq!(history.get("units:svc@other").count(), 0);
+}
@@ -0,0 +1,113 @@
+use nix::sys::signal::Signal;
+use rind_core::prelude::Ustr;
+use rind_services::ima::{IMA_PANIC_CODE, ImaHandle};
+use rind_services::{ImaContext, ImaModules, InstanceHandle, is_virtual_pid, register_ima_module};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::time::Duration;
+
+mod common;
+use common::wait_exit;
+
+fn greet(ctx: &mut ImaContext) -> i32 {
+  let who = ctx.args.first().map(|x| x.to_string()).unwrap_or_default();
+  let _ = writeln!(ctx.stdout, "hello {who}");
+  let _ = writeln!(ctx.stderr, "env {}", ctx.envs.len());
+  4
+}
+
+fn echo_line(ctx: &mut ImaContext) -> i32 {
+  let mut line = String::new();
+  let _ = BufReader::new(&mut ctx.stdin).read_line(&mut line);
+  let _ = write!(ctx.stdout, "{line}");
+  0
+}
+
+fn wait_for_cancel(ctx: &mut ImaContext) -> i32 {
+  loop {
+    if let Some(sig) = ctx.cancelled() {
+      return 128 + sig as i32;
+    }
+    std::thread::sleep(Duration::from_millis(5));
+  }
+}
+
+fn ignore_cancel(_ctx: &mut ImaContext) -> i32 {
+  std::thread::sleep(Duration::from_secs(2));
+  0
+}
+
+fn explode(_ctx: &mut ImaContext) -> i32 {
+  panic!("module failure");
+}
+
+#[test]
+fn ima_module_streams_output_and_exit_code() {
+  register_ima_module("test-greet", greet);
+  let entry = ImaModules::resolve("test-greet").expect("registered module");
+
+  let mut envs = HashMap::new();
+  envs.insert(Ustr::from("A"), Ustr::from("1"));
+  let mut handle =
+    ImaHandle::start("test-greet", entry, vec![Ustr::from("rind")], envs).expect("start");
+  assert!(is_virtual_pid(handle.pid().unwrap()));
+
+  let mut stdout = String::new();
+  handle
+    .take_stdout()
+    .unwrap()
+    .read_to_string(&mut stdout)
+    .unwrap();
+  let mut stderr = String::new();
+  handle
+    .take_stderr()
+    .unwrap()
+    .read_to_string(&mut stderr)
+    .unwrap();
+
+  assert_eq!(stdout, "hello rind\n");
+  assert_eq!(stderr, "env 1\n");
+  assert_eq!(wait_exit(handle.take_exit_fd().unwrap()), 4);
+}
+
+#[test]
+fn ima_module_reads_stdin() {
+  let mut handle = ImaHandle::start("echo", echo_line, vec![], HashMap::new()).expect("start");
+  handle.take_stdin().unwrap().write_all(b"ping\n").unwrap();
+
+  let mut stdout = String::new();
+  handle
+    .take_stdout()
+    .unwrap()
+    .read_to_string(&mut stdout)
+    .unwrap();
+  assert_eq!(stdout, "ping\n");
+  assert_eq!(wait_exit(handle.take_exit_fd().unwrap()), 0);
+}
+
+#[test]
+fn ima_kill_cancels_cooperatively() {
+  let mut handle =
+    ImaHandle::start("cancel", wait_for_cancel, vec![], HashMap::new()).expect("start");
+  handle.kill(Signal::SIGTERM).unwrap();
+  assert_eq!(wait_exit(handle.take_exit_fd().unwrap()), 128 + 15);
+}
+
+#[test]
+fn ima_sigkill_forces_the_exit() {
+  let mut handle =
+    ImaHandle::start("stubborn", ignore_cancel, vec![], HashMap::new()).expect("start");
+  handle.kill(Signal::SIGKILL).unwrap();
+  assert_eq!(wait_exit(handle.take_exit_fd().unwrap()), 128 + 9);
+}
+
+#[test]
+fn ima_panic_reports_failure() {
+  let mut handle = ImaHandle::start("explode", explode, vec![], HashMap::new()).expect("start");
+  assert_eq!(wait_exit(handle.take_exit_fd().unwrap()), IMA_PANIC_CODE);
+}
+
+#[test]
+fn ima_unknown_module_is_not_resolved() {
+  assert!(ImaModules::resolve("test-missing-module").is_none());
+}
@@ -0,0 +1,121 @@
+use rind_core::prelude::*;
+use rind_primitives::utils::netlink;
+use rind_services::executors::NamespaceNetworkConfig;
+use rind_services::namespaces::{spawn_supervised, validate_namespaces};
+use rind_services::services::{ServiceIsolation, ServiceNamespaces};
+use std::collections::HashMap;
+use std::io::Read;
+use std::net::Ipv4Addr;
+use std::path::Path;
+
+mod common;
+use common::is_root;
+
+fn link(address: &str, nat: bool) -> NamespaceNetworkConfig {
+  toml::from_str(&format!(
+    r#"
+address = "{address}"
+gateway = "10.231.0.1"
+host-address = "10.231.0.1/24"
+nat = {nat}
+"#
+  ))
+  .unwrap()
+}
+
+fn net_isolation() -> ServiceIsolation {
+  ServiceIsolation {
+    namespaces: Some(ServiceNamespaces {
+      net: true,
+      ..Default::default()
+    }),
+    ..Default::default()
+  }
+}
+
+/// `/proc/net/route` prints addresses as host-order hex of the raw octets.
+fn route_hex(addr: Ipv4Addr) -> String {
+  format!("{:08X}", u32::from_ne_bytes(addr.octets()))
+}
+
+#[test]
+fn network_config_defaults_and_validation() {
+  let net = link("10.231.0.2/24", false);
+  assert_eq!(net.name, "eth0");
+  assert_eq!(net.method, "static");
+
+  let ns = ServiceNamespaces {
+    net: true,
+    ..Default::default()
+  };
+  assert!(validate_namespaces(Some(&ns), std::slice::from_ref(&net)).is_ok());
+  assert!(validate_namespaces(None, std::slice::from_ref(&net)).is_err());
+
+  let mut dhcp = net.clone();
+  dhcp.method = "dhcp".into();
+  assert!(validate_namespaces(Some(&ns), &[dhcp]).is_err());
+
+  let mut bad = net;
+  bad.address = Some("10.231.0.300/24".into());
+  assert!(validate_namespaces(Some(&ns), &[bad]).is_err());
+}
+
+#[test]
+fn veth_link_is_addressed_routed_and_torn_down() {
+  if !is_root() {
+    return;
+  }
+  let mut handle = spawn_supervised(
+    "/bin/cat".into(),
+    vec![
+      Ustr::from("/proc/net/route"),
+      Ustr::from("/proc/net/fib_trie"),
+    ],
+    HashMap::new(),
+    None,
+    None,
+    Vec::new(),
+    None,
+    net_isolation(),
+    None,
+    Vec::new(),
+    Vec::new(),
+    vec![link("10.231.0.2/24", true)],
+  )
+  .expect("spawn supervised service");
+  let pid = handle.pid().unwrap();
+  let host = format!("ve{pid}n0");
+
+  assert!(Path::new("/sys/class/net").join(&host).exists());
+  // The NAT table is in place while the instance lives.
+  assert!(
+    netlink::add_masquerade(&format!("rind_{host}"), Ipv4Addr::new(10, 231, 0, 2), 24).is_err()
+  );
+
+  let mut output = String::new();
+  handle
+    .take_stdout()
+    .unwrap()
+    .read_to_string(&mut output)
+    .unwrap();
+  let mut status = 0;
+  unsafe { libc::waitpid(pid as i32, &mut status, 0) };
+  assert_eq!(libc::WEXITSTATUS(status), 0, "output: {output}");
+
+  let default_route = format!(
+    "eth0\t{}\t{}",
+    route_hex(Ipv4Addr::UNSPECIFIED),
+    route_hex(Ipv4Addr::new(10, 231, 0, 1))
+  );
+  assert!(output.contains(&default_route), "output: {output}");
+  assert!(output.contains("10.231.0.2"), "output: {output}");
+
+  drop(handle);
+  // Peer removal finishes asynchronously once the namespace is gone.
+  let gone = (0..50).any(|_| {
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    !Path::new("/sys/class/net").join(&host).exists()
+  });
+  assert!(gone, "host link {host} still exists");
+  assert!(netlink::delete_nft_table(&format!("rind_{host}")).is_err());
+}
@@ -0,0 +1,86 @@
+use rind_services::notify::{
+  NotifyCommand, NotifySocket, drain_notify_fd, parse_notify_message, pid_in_instance,
+};
+use std::os::unix::net::UnixDatagram;
+
+#[test]
+fn notify_message_parses_known_assignments() {
+  let commands =
+    parse_notify_message(b"READY=1\nSTATUS=serving 3 clients\nMAINPID=4242\nWATCHDOG=1\n");
+  assert_eq!(
+    commands,
+    vec![
+      NotifyCommand::Ready,
+      NotifyCommand::Status("serving 3 clients".into()),
+      NotifyCommand::MainPid(4242),
+      NotifyCommand::Watchdog,
+    ]
+  );
+}
+
+#[test]
+fn notify_message_ignores_unknown_and_malformed() {
+  let commands = parse_notify_message(b"READY=0\nERRNO=2\nMAINPID=abc\ngarbage\nSTOPPING=1");
+  assert_eq!(commands, vec![NotifyCommand::Stopping]);
+}
+
+#[test]
+fn notify_socket_receives_datagrams() {
+  let dir = std::env::temp_dir().join(format!("rind-notify-test-{}", std::process::id()));
+  let socket = NotifySocket::bind_in(&dir, "demo@static", None).expect("bind notify socket");
+  let fd = socket.raw_fd();
+
+  let client = UnixDatagram::unbound().unwrap();
+  client.send_to(b"STATUS=booting", &socket.path).unwrap();
+  client.send_to(b"READY=1", &socket.path).unwrap();
+
+  let me = std::process::id();
+  assert_eq!(
+    drain_notify_fd(fd, |sender| sender == me),
+    vec![
+      NotifyCommand::Status("booting".into()),
+      NotifyCommand::Ready
+    ]
+  );
+  assert!(drain_notify_fd(fd, |_| true).is_empty());
+
+  // datagrams from anyone else are dropped
+  client.send_to(b"MAINPID=1", &socket.path).unwrap();
+  assert!(drain_notify_fd(fd, |sender| sender != me).is_empty());
+
+  drop(socket);
+  let _ = std::fs::remove_dir(dir);
+}
+
+#[test]
+fn notify_socket_unlinks_its_path_unless_handed_over() {
+  let dir = std::env::temp_dir().join(format!("rind-notify-drop-{}", std::process::id()));
+  let socket = NotifySocket::bind_in(&dir, "demo@static", None).expect("bind notify socket");
+  let path = socket.path.clone();
+  assert!(path.exists());
+  drop(socket);
+  assert!(!path.exists());
+
+  let socket = NotifySocket::bind_in(&dir, "demo@static", None).expect("bind notify socket");
+  let (path, _fd) = socket.into_fd();
+  assert!(path.exists());
+  std::fs::remove_file(&path).unwrap();
+  let _ = std::fs::remove_dir(dir);
+}
+
+#[test]
+fn instance_membership_follows_the_process_tree() {
+  let mut child = std::process::Command::new("sleep")
+    .arg("5")
+    .spawn()
+    .unwrap();
+  let me = std::process::id();
+
+  assert!(pid_in_instance(child.id(), &[me], None));
+  assert!(pid_in_instance(me, &[me], None));
+  assert!(!pid_in_instance(me, &[child.id()], None));
+  assert!(!pid_in_instance(1, &[me], None));
+
+  let _ = child.kill();
+  let _ = child.wait();
+}
@@ -0,0 +1,155 @@
+use nix::sys::signal::Signal;
+use rind_services::remote::{
+  FrameKind, MAX_FRAME_LEN, REMOTE_PID_FLAG, RemoteAgent, RemoteHandle, RemoteSpawn, read_frame,
+};
+use rind_services::{InstanceHandle, LOST_EXIT_CODE};
+use std::io::{Cursor, Read, Write};
+use std::net::TcpListener;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+mod common;
+use common::wait_exit;
+
+fn start_agent_with(token: Option<&str>) -> String {
+  let agent = RemoteAgent::bind("127.0.0.1:0", token.map(String::from)).expect("agent should bind");
+  let addr = agent.local_addr().expect("agent addr").to_string();
+  std::thread::spawn(move || agent.serve());
+  addr
+}
+
+fn start_agent() -> String {
+  start_agent_with(None)
+}
+
+fn sh(script: &str) -> RemoteSpawn {
+  RemoteSpawn {
+    exec: "/bin/sh".into(),
+    args: vec!["-c".into(), script.into()],
+    envs: vec![("RIND_REMOTE_TEST".into(), "yes".into())],
+    working_dir: None,
+  }
+}
+
+#[test]
+fn remote_spawn_streams_output_and_exit_code() {
+  let addr = start_agent();
+  let mut handle = RemoteHandle::connect(
+    &addr,
+    None,
+    &sh("echo out $RIND_REMOTE_TEST; echo err >&2; exit 3"),
+  )
+  .expect("spawn");
+
+  let pid = handle.pid().expect("remote pid");
+  assert_ne!(pid & REMOTE_PID_FLAG, 0);
+
+  let mut stdout = String::new();
+  handle
+    .take_stdout()
+    .unwrap()
+    .read_to_string(&mut stdout)
+    .unwrap();
+  let mut stderr = String::new();
+  handle
+    .take_stderr()
+    .unwrap()
+    .read_to_string(&mut stderr)
+    .unwrap();
+
+  assert_eq!(stdout, "out yes\n");
+  assert_eq!(stderr, "err\n");
+  assert_eq!(wait_exit(handle.take_exit_fd().unwrap()), 3);
+}
+
+#[test]
+fn remote_stdin_is_forwarded() {
+  let addr = start_agent();
+  let mut handle = RemoteHandle::connect(&addr, None, &sh("cat")).expect("spawn");
+
+  let mut stdin = handle.take_stdin().unwrap();
+  stdin.write_all(b"ping\n").unwrap();
+  drop(stdin);
+
+  let mut stdout = String::new();
+  handle
+    .take_stdout()
+    .unwrap()
+    .read_to_string(&mut stdout)
+    .unwrap();
+  assert_eq!(stdout, "ping\n");
+  assert_eq!(wait_exit(handle.take_exit_fd().unwrap()), 0);
+}
+
+#[test]
+fn remote_kill_reports_signal_exit() {
+  let addr = start_agent();
+  let mut handle = RemoteHandle::connect(&addr, None, &sh("exec sleep 30")).expect("spawn");
+
+  handle.kill(Signal::SIGTERM).expect("signal frame");
+  assert_eq!(wait_exit(handle.take_exit_fd().unwrap()), 128 + 15);
+}
+
+#[test]
+fn remote_spawn_failure_is_reported() {
+  let addr = start_agent();
+  let spawn = RemoteSpawn {
+    exec: "/nonexistent/rind-remote-test".into(),
+    ..Default::default()
+  };
+  assert!(RemoteHandle::connect(&addr, None, &spawn).is_err());
+}
+
+#[test]
+fn remote_start_does_not_wait_for_the_agent() {
+  // connections land in the backlog but are never answered
+  let silent = TcpListener::bind("127.0.0.1:0").unwrap();
+  let addr = silent.local_addr().unwrap().to_string();
+
+  let (tx, rx) = mpsc::channel();
+  let started = Instant::now();
+  let mut handle = RemoteHandle::start(&addr, None, &sh("exit 0"), move |spawned| {
+    let _ = tx.send(spawned.is_ok());
+  })
+  .expect("start");
+  assert!(started.elapsed() < Duration::from_secs(1));
+
+  assert_eq!(wait_exit(handle.take_exit_fd().unwrap()), LOST_EXIT_CODE);
+  assert_eq!(rx.recv(), Ok(false));
+}
+
+#[test]
+fn remote_agent_requires_the_token() {
+  let addr = start_agent_with(Some("s3cret"));
+  assert!(RemoteHandle::connect(&addr, None, &sh("exit 0")).is_err());
+  assert!(RemoteHandle::connect(&addr, Some("wrong"), &sh("exit 0")).is_err());
+
+  let mut handle = RemoteHandle::connect(&addr, Some("s3cret"), &sh("exit 4")).expect("spawn");
+  assert_eq!(wait_exit(handle.take_exit_fd().unwrap()), 4);
+}
+
+#[test]
+fn remote_agent_refuses_open_addresses_without_a_token() {
+  assert!(RemoteAgent::bind("0.0.0.0:0", None).is_err());
+  assert!(RemoteAgent::bind("0.0.0.0:0", Some("s3cret".into())).is_ok());
+}
+
+#[test]
+fn oversized_frames_are_rejected_before_reading() {
+  let mut frame = vec![FrameKind::Spawn as u8];
+  frame.extend_from_slice(&(MAX_FRAME_LEN as u32 + 1).to_be_bytes());
+  assert!(read_frame(&mut Cursor::new(frame)).is_err());
+}
+
+#[test]
+fn remote_ids_are_allocated_by_rind() {
+  let (a, b) = (start_agent(), start_agent());
+  let mut first = RemoteHandle::connect(&a, None, &sh("exit 0")).expect("spawn");
+  let mut second = RemoteHandle::connect(&b, None, &sh("exit 0")).expect("spawn");
+
+  assert_ne!(first.pid(), second.pid());
+  assert_ne!(first.id, 0);
+  assert_eq!(first.host, a);
+  wait_exit(first.take_exit_fd().unwrap());
+  wait_exit(second.take_exit_fd().unwrap());
+}
@@ -0,0 +1,224 @@
+use rind_core::prelude::*;
+use rind_primitives::mounts::NamespaceMountEntry;
+use rind_services::namespaces::{spawn_supervised, validate_namespaces};
+use rind_services::services::{RootfsOverlay, ServiceIsolation, ServiceNamespaces};
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+mod common;
+use common::{is_root, scratch};
+
+fn copy_into(root: &Path, file: &Path, as_path: &Path) {
+  let target = root.join(as_path.strip_prefix("/").unwrap());
+  std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+  std::fs::copy(file, target).unwrap();
+}
+
+/// Builds a tiny image with `/bin/sh` and the libraries it links against.
+fn image(name: &str) -> PathBuf {
+  let root = scratch("rootfs", name);
+  let sh = std::fs::canonicalize("/bin/sh").unwrap();
+  copy_into(&root, &sh, Path::new("/bin/sh"));
+  let ldd = Command::new("ldd").arg(&sh).output().unwrap();
+  for line in String::from_utf8_lossy(&ldd.stdout).lines() {
+    let lib = match line.split_once("=>") {
+      Some((_, rest)) => rest.split_whitespace().next(),
+      None => line.split_whitespace().next(),
+    };
+    if let Some(lib) = lib.filter(|lib| lib.starts_with('/')) {
+      copy_into(&root, Path::new(lib), Path::new(lib));
+    }
+  }
+  for dir in ["proc", "sys", "dev", "data"] {
+    std::fs::create_dir(root.join(dir)).unwrap();
+  }
+  std::fs::write(root.join("marker"), "from-image\n").unwrap();
+  root
+}
+
+fn rootfs_isolation(root: &Path, overlay: Option<RootfsOverlay>) -> ServiceIsolation {
+  ServiceIsolation {
+    namespaces: Some(ServiceNamespaces {
+      rootfs: Some(Ustr::from(root.to_string_lossy().as_ref())),
+      overlay,
+      ..Default::default()
+    }),
+    ..Default::default()
+  }
+}
+
+/// Runs `script` under `/bin/sh` inside the isolation and returns its exit
+/// code and stdout.
+fn run(
+  isolation: ServiceIsolation,
+  mounts: Vec<NamespaceMountEntry>,
+  script: &str,
+) -> (i32, String) {
+  let mut handle = spawn_supervised(
+    "/bin/sh".into(),
+    vec![Ustr::from("-c"), Ustr::from(script)],
+    HashMap::new(),
+    None,
+    None,
+    Vec::new(),
+    None,
+    isolation,
+    None,
+    Vec::new(),
+    mounts,
+    Vec::new(),
+  )
+  .expect("spawn supervised service");
+  let pid = handle.pid().unwrap();
+  let mut output = String::new();
+  handle
+    .take_stdout()
+    .unwrap()
+    .read_to_string(&mut output)
+    .unwrap();
+  let mut status = 0;
+  unsafe { libc::waitpid(pid as i32, &mut status, 0) };
+  (libc::WEXITSTATUS(status), output)
+}
+
+#[test]
+fn rootfs_settings_are_validated() {
+  let root = scratch("rootfs", "validate");
+  let ns = |rootfs: Option<&Path>, propagation: Option<&str>, overlay| ServiceNamespaces {
+    rootfs: rootfs.map(|p| Ustr::from(p.to_string_lossy().as_ref())),
+    propagation: propagation.map(Ustr::from),
+    overlay,
+    ..Default::default()
+  };
+
+  assert!(validate_namespaces(Some(&ns(Some(&root), Some("slave"), None)), &[]).is_ok());
+  assert!(validate_namespaces(Some(&ns(Some(&root), Some("shared"), None)), &[]).is_err());
+  assert!(validate_namespaces(Some(&ns(None, Some("sideways"), None)), &[]).is_err());
+  assert!(validate_namespaces(Some(&ns(Some(&root.join("missing")), None, None)), &[]).is_err());
+  assert!(validate_namespaces(Some(&ns(Some(Path::new("relative")), None, None)), &[]).is_err());
+
+  let overlay = RootfsOverlay {
+    upper: Ustr::from(root.to_string_lossy().as_ref()),
+    work: Ustr::from("/nonexistent-rind-work"),
+  };
+  assert!(validate_namespaces(Some(&ns(None, None, Some(overlay.clone()))), &[]).is_err());
+  assert!(validate_namespaces(Some(&ns(Some(&root), None, Some(overlay))), &[]).is_err());
+
+  let _ = std::fs::remove_dir_all(root);
+}
+
+#[test]
+fn service_runs_from_read_only_image_with_api_mounts_and_binds() {
+  if !is_root() {
+    return;
+  }
+  let root = image("ro");
+  let shared = scratch("rootfs", "ro-bind");
+  std::fs::write(shared.join("note"), "from-host\n").unwrap();
+
+  let mounts = vec![NamespaceMountEntry {
+    source: Some(shared.to_string_lossy().into_owned()),
+    target: "/data".to_string(),
+    fstype: None,
+    flags: Some(vec!["MS_BIND".to_string()]),
+    data: None,
+    create: None,
+    propagation: Some("private".to_string()),
+  }];
+  let script = r#"
+read marker < /marker; echo "$marker"
+read note < /data/note; echo "$note"
+if echo x 2>/dev/null > /written; then echo writable; else echo read-only; fi
+for node in /dev/*; do echo "$node"; done
+read comm < /proc/self/comm; echo "comm=$comm"
+test -d /sys/kernel && echo sysfs
+while read -r id parent dev fsroot point opts rest; do
+  case "$point" in /sys | /sys/*)
+    case ",$opts," in *,ro,*) ;; *) echo "sys-writable $point" ;; esac ;;
+  esac
+done < /proc/self/mountinfo
+"#;
+  let (code, output) = run(rootfs_isolation(&root, None), mounts, script);
+
+  assert_eq!(code, 0, "output: {output}");
+  for expected in [
+    "from-image",
+    "from-host",
+    "read-only",
+    "/dev/null",
+    "/dev/urandom",
+    "/dev/pts",
+    "comm=sh",
+    "sysfs",
+  ] {
+    assert!(output.contains(expected), "missing {expected}: {output}");
+  }
+  assert!(!output.contains("sys-writable"), "output: {output}");
+  assert!(!root.join("written").exists());
+  // The host side of the image is untouched by the API mounts.
+  assert_eq!(std::fs::read_dir(root.join("dev")).unwrap().count(), 0);
+
+  let _ = std::fs::remove_dir_all(root);
+  let _ = std::fs::remove_dir_all(shared);
+}
+
+#[test]
+fn overlay_keeps_writes_in_the_upper_dir() {
+  if !is_root() {
+    return;
+  }
+  let root = image("overlay");
+  let layers = scratch("rootfs", "overlay-layers");
+  for dir in ["upper", "work"] {
+    std::fs::create_dir(layers.join(dir)).unwrap();
+  }
+  let overlay = RootfsOverlay {
+    upper: Ustr::from(layers.join("upper").to_string_lossy().as_ref()),
+    work: Ustr::from(layers.join("work").to_string_lossy().as_ref()),
+  };
+
+  let (code, output) = run(
+    rootfs_isolation(&root, Some(overlay)),
+    Vec::new(),
+    "echo changed > /marker && read marker < /marker && echo \"$marker\"",
+  );
+
+  assert_eq!(code, 0, "output: {output}");
+  assert!(output.contains("changed"), "output: {output}");
+  assert_eq!(
+    std::fs::read_to_string(root.join("marker")).unwrap(),
+    "from-image\n"
+  );
+  assert_eq!(
+    std::fs::read_to_string(layers.join("upper/marker")).unwrap(),
+    "changed\n"
+  );
+
+  let _ = std::fs::remove_dir_all(root);
+  let _ = std::fs::remove_dir_all(layers);
+}
+
+#[test]
+fn pid_namespace_gets_its_own_proc() {
+  if !is_root() {
+    return;
+  }
+  let root = image("pid");
+  let mut isolation = rootfs_isolation(&root, None);
+  if let Some(ns) = isolation.namespaces.as_mut() {
+    ns.pid = true;
+  }
+
+  let (code, output) = run(
+    isolation,
+    Vec::new(),
+    "read comm < /proc/1/comm; echo \"pid1=$comm\"",
+  );
+
+  assert_eq!(code, 0, "output: {output}");
+  assert!(output.contains("pid1=sh"), "output: {output}");
+
+  let _ = std::fs::remove_dir_all(root);
+}
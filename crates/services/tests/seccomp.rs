@@ -0,0 +1,210 @@
+use rind_core::prelude::*;
+use rind_services::namespaces::apply_seccomp;
+use rind_services::seccomp::{
+  SeccompAction, SeccompFilter, SeccompProgram, resolve_syscalls, syscall_number,
+};
+use rind_services::services::SeccompPolicy;
+use std::os::unix::process::CommandExt;
+use std::process::Command;
+
+fn policy(toml_src: &str) -> SeccompPolicy {
+  toml::from_str(toml_src).expect("policy should parse")
+}
+
+/// Forks, installs `filter` in the child and reports how the child ended:
+/// `Ok(exit code)` or `Err(signal)`.
+fn run_filtered(filter: &SeccompFilter, body: fn() -> i32) -> Result<i32, i32> {
+  let pid = unsafe { libc::fork() };
+  assert!(pid >= 0, "fork failed");
+  if pid == 0 {
+    let code = match filter.install() {
+      Ok(()) => body(),
+      Err(_) => 200,
+    };
+    unsafe { libc::_exit(code) }
+  }
+  let mut status = 0;
+  assert_eq!(unsafe { libc::waitpid(pid, &mut status, 0) }, pid);
+  if libc::WIFSIGNALED(status) {
+    Err(libc::WTERMSIG(status))
+  } else {
+    Ok(libc::WEXITSTATUS(status))
+  }
+}
+
+/// Returns the errno of a raw `getppid`, or 0 when it succeeded.
+fn getppid_errno() -> i32 {
+  let rc = unsafe { libc::syscall(libc::SYS_getppid) };
+  if rc < 0 {
+    std::io::Error::last_os_error()
+      .raw_os_error()
+      .unwrap_or(255)
+  } else {
+    0
+  }
+}
+
+#[test]
+fn actions_parse_names_numbers_and_kill() {
+  let parse = |s: &str| SeccompAction::try_from(s.to_string());
+  assert_eq!(parse("EACCES"), Ok(SeccompAction::Errno(libc::EACCES)));
+  assert_eq!(parse("errno:38"), Ok(SeccompAction::Errno(38)));
+  assert_eq!(parse("kill"), Ok(SeccompAction::Kill));
+  assert_eq!(parse("log"), Ok(SeccompAction::Log));
+  assert!(parse("explode").is_err());
+  assert_eq!(String::from(SeccompAction::Errno(libc::EPERM)), "EPERM");
+}
+
+#[test]
+fn groups_expand_and_unknown_names_are_rejected() {
+  let io = resolve_syscalls(&["@network-io"]).unwrap();
+  assert!(io.contains(&(syscall_number("socket").unwrap() as u32)));
+  assert!(io.contains(&(syscall_number("connect").unwrap() as u32)));
+
+  let service = resolve_syscalls(&["@system-service"]).unwrap();
+  assert!(io.is_subset(&service));
+
+  assert!(resolve_syscalls(&["definitely_not_a_syscall"]).is_err());
+  assert!(resolve_syscalls(&["@no-such-group"]).is_err());
+}
+
+#[test]
+fn compile_rejects_bad_policies_and_skips_empty_ones() {
+  assert!(
+    SeccompFilter::compile(&SeccompPolicy::default())
+      .unwrap()
+      .is_none()
+  );
+  assert!(
+    SeccompFilter::compile(&policy("profile = \"strict\""))
+      .unwrap()
+      .is_none()
+  );
+  assert!(SeccompFilter::compile(&policy("profile = \"strict\"\ndeny = [\"mount\"]")).is_err());
+  assert!(SeccompFilter::compile(&policy("profile = \"bogus\"")).is_err());
+  assert!(SeccompFilter::compile(&policy("allow = [\"@basic-io\"]")).is_err());
+  assert!(SeccompFilter::compile(&policy("allow = [\"@default\"]\ndeny = [\"execve\"]")).is_err());
+  assert!(matches!(
+    SeccompProgram::prepare(&policy("profile = \"strict\"")),
+    Ok(Some(SeccompProgram::Strict))
+  ));
+  assert!(
+    SeccompFilter::compile(&policy("profile = \"default\""))
+      .unwrap()
+      .is_some()
+  );
+}
+
+#[test]
+fn denied_syscall_fails_with_configured_errno() {
+  let filter = SeccompFilter::compile(&policy(
+    r#"
+deny = ["getppid"]
+action = "EACCES"
+"#,
+  ))
+  .unwrap()
+  .unwrap();
+
+  assert_eq!(run_filtered(&filter, getppid_errno), Ok(libc::EACCES));
+}
+
+#[test]
+fn kill_action_terminates_with_sigsys() {
+  let filter = SeccompFilter::compile(&policy(
+    r#"
+deny = ["getppid"]
+action = "kill"
+"#,
+  ))
+  .unwrap()
+  .unwrap();
+
+  assert_eq!(run_filtered(&filter, getppid_errno), Err(libc::SIGSYS));
+}
+
+#[test]
+fn allow_list_denies_everything_else() {
+  let filter = SeccompFilter::compile(&policy(
+    r#"
+allow = ["@default", "@basic-io"]
+deny = ["getppid"]
+action = "errno:77"
+"#,
+  ))
+  .unwrap()
+  .unwrap();
+
+  fn body() -> i32 {
+    // getpid is in @default, getppid was subtracted, mkdirat isn't listed.
+    if unsafe { libc::syscall(libc::SYS_getpid) } < 0 {
+      return 1;
+    }
+    let dir = c"/nonexistent-seccomp-test";
+    let rc = unsafe { libc::syscall(libc::SYS_mkdirat, libc::AT_FDCWD, dir.as_ptr(), 0o700) };
+    let mkdir_errno = std::io::Error::last_os_error().raw_os_error().unwrap_or(0);
+    if rc >= 0 || mkdir_errno != 77 {
+      return 2;
+    }
+    getppid_errno()
+  }
+
+  assert_eq!(run_filtered(&filter, body), Ok(77));
+}
+
+#[test]
+fn spawned_command_sees_denied_syscall() {
+  let seccomp = SeccompProgram::prepare(&policy(
+    r#"
+deny = ["mkdir", "mkdirat"]
+action = "EROFS"
+"#,
+  ))
+  .unwrap();
+  let dir = std::env::temp_dir().join(format!("rind-seccomp-test-{}", std::process::id()));
+  let _ = std::fs::remove_dir(&dir);
+
+  let mut cmd = Command::new("mkdir");
+  cmd.arg(&dir);
+  unsafe {
+    cmd.pre_exec(move || apply_seccomp(seccomp.as_ref()));
+  }
+  let output = cmd.output().expect("spawn mkdir");
+
+  assert!(!output.status.success());
+  assert!(!dir.exists());
+  assert!(
+    String::from_utf8_lossy(&output.stderr).contains("Read-only file system"),
+    "stderr: {}",
+    String::from_utf8_lossy(&output.stderr)
+  );
+}
+
+#[test]
+fn policy_file_is_loaded_and_inline_settings_layer_over_it() {
+  let path = std::env::temp_dir().join(format!("rind-seccomp-policy-{}.toml", std::process::id()));
+  std::fs::write(
+    &path,
+    r#"
+deny = ["@mount"]
+action = "kill"
+"#,
+  )
+  .unwrap();
+
+  let inline = SeccompPolicy {
+    path: Some(Ustr::from(path.to_string_lossy().as_ref())),
+    deny: vec![Ustr::from("reboot")],
+    action: Some(SeccompAction::Errno(libc::EPERM)),
+    ..Default::default()
+  };
+  let resolved = inline.resolve().unwrap();
+  assert_eq!(resolved.path, None);
+  assert_eq!(
+    resolved.deny,
+    vec![Ustr::from("@mount"), Ustr::from("reboot")]
+  );
+  assert_eq!(resolved.action, Some(SeccompAction::Errno(libc::EPERM)));
+
+  let _ = std::fs::remove_file(path);
+}
@@ -0,0 +1,151 @@
+use nix::sys::socket::{
+  AddressFamily, MsgFlags, SockFlag, SockType, SockaddrIn6, UnixAddr, accept, connect, getsockname,
+  recv, send, socket,
+};
+use rind_services::sockets::{
+  RateWindow, SocketMetadata, SocketRateLimit, SocketType, create_socket, parse_inet_listen,
+  parse_netlink_listen,
+};
+use std::io::Write;
+use std::net::{Ipv4Addr, TcpStream};
+use std::os::fd::AsRawFd;
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+use std::time::{Duration, Instant};
+
+fn meta(r#type: SocketType, listen: &str) -> SocketMetadata {
+  SocketMetadata {
+    name: "test".into(),
+    listen: listen.to_string(),
+    r#type,
+    ..Default::default()
+  }
+}
+
+#[test]
+fn listen_values_parse() {
+  assert_eq!(
+    parse_netlink_listen("kobject-uevent 1").unwrap(),
+    (libc::NETLINK_KOBJECT_UEVENT, 1)
+  );
+  assert_eq!(
+    parse_netlink_listen("sock_diag").unwrap(),
+    (libc::NETLINK_SOCK_DIAG, 0)
+  );
+  assert_eq!(parse_netlink_listen("31 4").unwrap(), (31, 4));
+  assert!(parse_netlink_listen("nonsense").is_err());
+
+  assert_eq!(parse_inet_listen("8080").unwrap().to_string(), "[::]:8080");
+  assert_eq!(
+    parse_inet_listen("127.0.0.1:53").unwrap().to_string(),
+    "127.0.0.1:53"
+  );
+}
+
+#[test]
+fn fifo_is_created_with_mode() {
+  let dir = std::env::temp_dir().join(format!("rind-fifo-{}", std::process::id()));
+  let path = dir.join("in.fifo");
+  let mut fifo = meta(SocketType::Fifo, path.to_str().unwrap());
+  fifo.mode = Some("0620".into());
+
+  let fd = create_socket(&fifo).unwrap();
+  let stat = std::fs::metadata(&path).unwrap();
+  assert!(stat.file_type().is_fifo());
+  assert_eq!(stat.permissions().mode() & 0o777, 0o620);
+
+  std::fs::OpenOptions::new()
+    .write(true)
+    .open(&path)
+    .unwrap()
+    .write_all(b"ping")
+    .unwrap();
+  let mut buf = [0u8; 8];
+  let n = nix::unistd::read(&fd, &mut buf).unwrap();
+  assert_eq!(&buf[..n], b"ping");
+
+  let _ = std::fs::remove_dir_all(dir);
+}
+
+#[test]
+fn abstract_seqpacket_keeps_message_boundaries() {
+  let name = format!("rind-test-{}", std::process::id());
+  let listener = create_socket(&meta(SocketType::Seqpacket, &format!("@{name}"))).unwrap();
+
+  let client = socket(
+    AddressFamily::Unix,
+    SockType::SeqPacket,
+    SockFlag::empty(),
+    None,
+  )
+  .unwrap();
+  connect(
+    client.as_raw_fd(),
+    &UnixAddr::new_abstract(name.as_bytes()).unwrap(),
+  )
+  .unwrap();
+  send(client.as_raw_fd(), b"one", MsgFlags::empty()).unwrap();
+  send(client.as_raw_fd(), b"two", MsgFlags::empty()).unwrap();
+
+  let conn = accept(listener.as_raw_fd()).unwrap();
+  let mut buf = [0u8; 16];
+  let n = recv(conn, &mut buf, MsgFlags::empty()).unwrap();
+  assert_eq!(&buf[..n], b"one");
+  let n = recv(conn, &mut buf, MsgFlags::empty()).unwrap();
+  assert_eq!(&buf[..n], b"two");
+  let _ = nix::unistd::close(conn);
+}
+
+#[test]
+fn bare_port_listens_dual_stack() {
+  let mut tcp = meta(SocketType::Tcp, "0");
+  tcp.backlog = Some(4);
+  tcp.keepalive = true;
+  let listener = create_socket(&tcp).unwrap();
+  let port = getsockname::<SockaddrIn6>(listener.as_raw_fd())
+    .unwrap()
+    .port();
+
+  let _client = TcpStream::connect((Ipv4Addr::LOCALHOST, port)).unwrap();
+  let mut accepted = Err(nix::errno::Errno::EAGAIN);
+  for _ in 0..50 {
+    accepted = accept(listener.as_raw_fd());
+    if accepted.is_ok() {
+      break;
+    }
+    std::thread::sleep(std::time::Duration::from_millis(10));
+  }
+  let _ = nix::unistd::close(accepted.expect("IPv4 client on an IPv6 listener"));
+}
+
+#[test]
+fn netlink_socket_binds() {
+  assert!(create_socket(&meta(SocketType::Netlink, "route")).is_ok());
+  assert!(create_socket(&meta(SocketType::Netlink, "nonsense")).is_err());
+}
+
+#[test]
+fn rate_window_holds_until_the_oldest_event_ages_out() {
+  let limit = SocketRateLimit {
+    count: 2,
+    interval_ms: 1_000,
+  };
+  let ms = Duration::from_millis;
+  let start = Instant::now();
+  let mut window = RateWindow::default();
+
+  for at in [start, start + ms(100)] {
+    assert_eq!(window.throttled(&limit, at), None);
+    window.record(at);
+  }
+  assert_eq!(window.throttled(&limit, start + ms(300)), Some(ms(700)));
+  assert_eq!(window.throttled(&limit, start + ms(1_000)), None);
+
+  let closed = SocketRateLimit {
+    count: 0,
+    interval_ms: 500,
+  };
+  assert_eq!(
+    RateWindow::default().throttled(&closed, start),
+    Some(ms(500))
+  );
+}
@@ -1,5 +1,7 @@
-use rind_services::parse_duration;
-use std::time::Duration;
+use rind_core::prelude::StatePersistence;
+use rind_services::timers::{TimerMode, TimerStamps, coalesce};
+use rind_services::{parse_calendar, parse_duration};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 #[test]
 fn parse_duration_supports_units_and_raw_seconds() {
@@ -7,6 +9,7 @@ fn parse_duration_supports_units_and_raw_seconds() {
   assert_eq!(parse_duration("3m"), Some(Duration::from_secs(180)));
   assert_eq!(parse_duration("2h"), Some(Duration::from_secs(7200)));
   assert_eq!(parse_duration("1d"), Some(Duration::from_secs(86400)));
+  assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
   assert_eq!(parse_duration("12"), Some(Duration::from_secs(12)));
 }
 
@@ -26,3 +29,120 @@ fn parse_duration_property_seconds_equivalence() {
     assert_eq!(with_suffix, raw);
   }
 }
+
+fn utc(days: u64, secs: u64) -> SystemTime {
+  UNIX_EPOCH + Duration::from_secs(days * 86400 + secs)
+}
+
+#[test]
+fn parse_calendar_expands_shorthands() {
+  assert_eq!(
+    parse_calendar("daily").unwrap(),
+    parse_calendar("*-*-* 00:00:00").unwrap()
+  );
+  assert_eq!(
+    parse_calendar("weekly").unwrap(),
+    parse_calendar("Mon *-*-* 00:00:00").unwrap()
+  );
+}
+
+#[test]
+fn parse_calendar_rejects_invalid_expressions() {
+  assert!(parse_calendar("").is_err());
+  assert!(parse_calendar("Funday").is_err());
+  assert!(parse_calendar("*-13-01").is_err());
+  assert!(parse_calendar("*:0/0").is_err());
+  assert!(parse_calendar("25:00").is_err());
+}
+
+#[test]
+fn calendar_next_elapse_steps_through_quarter_hours() {
+  let spec = parse_calendar("*:0/15 UTC").unwrap();
+  // 1970-01-01 is a Thursday.
+  assert_eq!(spec.next_elapse(utc(0, 0)), Some(utc(0, 15 * 60)));
+  assert_eq!(spec.next_elapse(utc(0, 15 * 60)), Some(utc(0, 30 * 60)));
+  assert_eq!(spec.next_elapse(utc(0, 3599)), Some(utc(0, 3600)));
+}
+
+#[test]
+fn calendar_next_elapse_skips_weekends() {
+  let spec = parse_calendar("Mon..Fri *-*-* 09:00:00 UTC").unwrap();
+  // Friday 1970-01-02 10:00 rolls over to Monday 1970-01-05 09:00.
+  assert_eq!(spec.next_elapse(utc(1, 10 * 3600)), Some(utc(4, 9 * 3600)));
+  assert_eq!(spec.next_elapse(utc(4, 0)), Some(utc(4, 9 * 3600)));
+}
+
+#[test]
+fn calendar_next_elapse_handles_month_ends_and_leap_days() {
+  let spec = parse_calendar("*-02-29 12:00 UTC").unwrap();
+  // 1972-02-29 is day 789 since the epoch.
+  assert_eq!(spec.next_elapse(utc(0, 0)), Some(utc(789, 12 * 3600)));
+
+  let spec = parse_calendar("*-*-31 UTC").unwrap();
+  // February has no 31st; the next match after 1970-02-01 is 1970-03-31.
+  assert_eq!(spec.next_elapse(utc(31, 0)), Some(utc(89, 0)));
+}
+
+#[test]
+fn calendar_next_elapse_ends_with_fixed_years() {
+  let spec = parse_calendar("1970-01-02 UTC").unwrap();
+  assert_eq!(spec.next_elapse(utc(0, 0)), Some(utc(1, 0)));
+  assert_eq!(spec.next_elapse(utc(1, 0)), None);
+}
+
+#[test]
+fn coalesce_rounds_up_to_the_accuracy_window() {
+  let minute = Duration::from_secs(60);
+  assert_eq!(
+    coalesce(Duration::from_secs(61), minute),
+    Duration::from_secs(120)
+  );
+  assert_eq!(
+    coalesce(Duration::from_secs(120), minute),
+    Duration::from_secs(120)
+  );
+  assert_eq!(
+    coalesce(Duration::from_secs(61), Duration::ZERO),
+    Duration::from_secs(61)
+  );
+}
+
+#[test]
+fn timer_mode_detects_missed_elapses() {
+  let interval = TimerMode::Interval(Duration::from_secs(3600));
+  assert!(!interval.missed(utc(0, 0), utc(0, 3599)));
+  assert!(interval.missed(utc(0, 0), utc(0, 3600)));
+
+  let daily = TimerMode::Calendar(parse_calendar("daily UTC").unwrap());
+  assert!(!daily.missed(utc(0, 3600), utc(0, 86399)));
+  assert!(daily.missed(utc(0, 3600), utc(3, 0)));
+
+  let once = TimerMode::Once(Duration::from_secs(1));
+  assert!(!once.missed(utc(0, 0), utc(10, 0)));
+}
+
+#[test]
+fn timer_stamps_persist_only_persistent_timers() {
+  let path = std::env::temp_dir().join(format!(
+    "rind-timer-stamps-{}-{}.state",
+    std::process::id(),
+    SystemTime::now()
+      .duration_since(UNIX_EPOCH)
+      .unwrap_or_default()
+      .as_nanos()
+  ));
+
+  let persistence = StatePersistence::new(path.clone());
+  let mut stamps = TimerStamps::new(persistence.clone());
+  stamps.record("backup:nightly", utc(2, 0), true);
+  stamps.record("backup:poll", utc(2, 60), false);
+  assert_eq!(stamps.last("backup:poll@static"), Some(utc(2, 60)));
+  stamps.flush().expect("flush should work");
+  persistence.shutdown();
+
+  let reloaded = TimerStamps::new(StatePersistence::new(path.clone()));
+  assert_eq!(reloaded.last("backup:nightly"), Some(utc(2, 0)));
+  assert_eq!(reloaded.last("backup:poll"), None);
+
+  let _ = std::fs::remove_file(path);
+}
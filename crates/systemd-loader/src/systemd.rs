@@ -6,8 +6,10 @@ use rind_core::prelude::*;
 use rind_flow::{FlowFacet, FlowFacetMetadata, FlowItem, FlowPayloadType};
 use rind_plugins::prelude::*;
 use rind_primitives::mounts::{Mount, MountMetadata};
+use rind_services::calendar::parse_calendar;
 use rind_services::services::{
-  RestartPolicy, RunOption, RunOptions, Service, ServiceCgroup, ServiceMetadata, ServiceSpace,
+  RestartPolicy, RetryLimit, RunOption, RunOptions, Service, ServiceCgroup, ServiceMetadata,
+  ServiceSpace, ServiceType,
 };
 use rind_services::sockets::{Socket, SocketMetadata, SocketType};
 use rind_services::timers::{Timer, TimerMetadata};
@@ -218,40 +220,17 @@ fn parse_on_calendar(value: &str) -> Option<String> {
   if value.is_empty() {
     return None;
   }
-  let lower = value.to_ascii_lowercase();
-
-  for (prefix, dur) in [
-    ("minutely", "1m"),
-    ("hourly", "1h"),
-    ("daily", "1d"),
-    ("weekly", "7d"),
-    ("monthly", "30d"),
-    ("quarterly", "90d"),
-    ("semi-annually", "180d"),
-    ("yearly", "365d"),
-    ("annually", "365d"),
-  ] {
-    if let Some(rest) = lower.strip_prefix(prefix) {
-      if rest.trim().is_empty() {
-        return Some(dur.to_string());
-      }
-    }
-  }
-  None
+  parse_calendar(value).ok().map(|_| value.to_string())
 }
 
 fn timer_duration_from(tmr: &HashMap<String, Vec<String>>) -> Option<String> {
   for key in [
-    "OnCalendar",
     "OnBootSec",
     "OnUnitActiveSec",
     "OnUnitInactiveSec",
     "OnActiveSec",
   ] {
     if let Some(v) = first_value(tmr, key) {
-      if let Some(d) = parse_on_calendar(v) {
-        return Some(d);
-      }
       if let Some(d) = parse_time_spec(v) {
         return Some(d);
       }
@@ -440,6 +419,17 @@ fn build_service_meta(
 
   meta.restart = first_value(svc, "Restart").and_then(systemd_restart_to_policy);
 
+  meta.pre_start = systemd_commands(svc, "ExecStartPre");
+  meta.post_start = systemd_commands(svc, "ExecStartPost");
+  meta.stop = systemd_commands(svc, "ExecStop");
+  meta.reload = systemd_commands(svc, "ExecReload");
+
+  match first_value(svc, "Type") {
+    Some("notify") => meta.r#type = ServiceType::Notify,
+    Some("oneshot") => meta.r#type = ServiceType::Job,
+    _ => {}
+  }
+
   if let Some(wd) = first_value(svc, "WorkingDirectory") {
     meta.working_dir = Some(Ustr::from(wd));
   }
@@ -495,6 +485,16 @@ fn build_socket_meta(
   meta.r#type = kind.unwrap_or(SocketType::Tcp);
   meta.listen = listen.unwrap_or_else(|| "/".to_string());
 
+  meta.backlog = first_value(sock, "Backlog").and_then(|v| v.parse().ok());
+  meta.reuse_port = first_value(sock, "ReusePort").is_some_and(systemd_bool);
+  meta.mode = first_value(sock, "SocketMode").map(Ustr::from);
+  meta.user = first_value(sock, "SocketUser").map(Ustr::from);
+  meta.group = first_value(sock, "SocketGroup").map(Ustr::from);
+  meta.bind_to_device = first_value(sock, "BindToDevice").map(Ustr::from);
+  meta.keepalive = first_value(sock, "KeepAlive").is_some_and(systemd_bool);
+  meta.freebind = first_value(sock, "FreeBind").is_some_and(systemd_bool);
+  meta.ipv6_only = first_value(sock, "BindIPv6Only") == Some("ipv6-only");
+
   if !start_on.is_empty() {
     meta.start_on = Some(start_on.iter().map(|s| facet_item(s)).collect());
   }
@@ -503,36 +503,69 @@ fn build_socket_meta(
 }
 
 fn socket_listen(sock: &HashMap<String, Vec<String>>) -> (Option<SocketType>, Option<String>) {
+  // Paths and `@abstract` names are unix sockets; anything else is inet.
+  let is_unix = |v: &str| v.starts_with('/') || v.starts_with('@');
   if let Some(v) = first_value(sock, "ListenStream") {
-    return (Some(SocketType::Tcp), Some(v.to_string()));
+    let kind = if is_unix(v) {
+      SocketType::Uds
+    } else {
+      SocketType::Tcp
+    };
+    return (Some(kind), Some(v.to_string()));
   }
   if let Some(v) = first_value(sock, "ListenDatagram") {
-    return (Some(SocketType::Udp), Some(v.to_string()));
+    let kind = if is_unix(v) {
+      SocketType::UdsDgram
+    } else {
+      SocketType::Udp
+    };
+    return (Some(kind), Some(v.to_string()));
   }
   if let Some(v) = first_value(sock, "ListenSequentialPacket") {
-    return (Some(SocketType::Tcp), Some(v.to_string()));
+    return (Some(SocketType::Seqpacket), Some(v.to_string()));
   }
   if let Some(v) = first_value(sock, "ListenUNIXSocket") {
     return (Some(SocketType::Uds), Some(v.to_string()));
   }
   if let Some(v) = first_value(sock, "ListenUNIXGRAM") {
-    return (Some(SocketType::Udp), Some(v.to_string()));
+    return (Some(SocketType::UdsDgram), Some(v.to_string()));
   }
   if let Some(v) = first_value(sock, "ListenFIFO") {
-    return (None, Some(v.to_string()));
+    return (Some(SocketType::Fifo), Some(v.to_string()));
+  }
+  if let Some(v) = first_value(sock, "ListenNetlink") {
+    return (Some(SocketType::Netlink), Some(v.to_string()));
   }
   (None, None)
 }
 
+fn systemd_bool(value: &str) -> bool {
+  matches!(value, "yes" | "true" | "on" | "1")
+}
+
 fn build_timer_meta(
   name: &str,
   tmr: &HashMap<String, Vec<String>>,
   after: &[String],
 ) -> Option<TimerMetadata> {
-  let duration = timer_duration_from(tmr)?;
   let mut meta = TimerMetadata::default();
   meta.name = Ustr::from(name);
-  meta.duration = Ustr::from(&duration);
+  if let Some(expr) = first_value(tmr, "OnCalendar").and_then(parse_on_calendar) {
+    meta.on_calendar = Some(Ustr::from(&expr));
+  } else {
+    meta.duration = Ustr::from(&timer_duration_from(tmr)?);
+    meta.repeat = first_value(tmr, "OnUnitActiveSec").is_some();
+  }
+  meta.randomized_delay = first_value(tmr, "RandomizedDelaySec")
+    .and_then(parse_time_spec)
+    .map(|d| Ustr::from(&d));
+  meta.accuracy = first_value(tmr, "AccuracySec")
+    .and_then(parse_time_spec)
+    .map(|d| Ustr::from(&d));
+  meta.persistent = first_value(tmr, "Persistent").is_some_and(systemd_bool);
+  // Like systemd, a timer activates the service of the same name by default.
+  let unit = first_value(tmr, "Unit").map_or(name, |unit| unit.trim_end_matches(".service"));
+  meta.unit = Some(Ustr::from(format!("{unit}:{unit}")));
   if !after.is_empty() {
     meta.after = Some(after.iter().map(|s| Ustr::from(s)).collect());
   }
@@ -569,6 +602,25 @@ fn build_mount_meta(
   meta
 }
 
+fn systemd_commands(svc: &HashMap<String, Vec<String>>, key: &str) -> Option<Vec<RunOption>> {
+  let commands = all_values(svc, key)
+    .iter()
+    .filter_map(|v| {
+      // `-` ignores failures; the other prefixes (`@`, `+`, `!`, `:`) have
+      // no rind equivalent.
+      let prefix = v.len() - v.trim_start_matches(['-', '@', '+', '!', ':']).len();
+      let (exec, args) = systemd_exec(&v[prefix..])?;
+      Some(RunOption {
+        exec: Ustr::from(&exec),
+        args: args.iter().map(|a| Ustr::from(a)).collect(),
+        ignore_failure: v[..prefix].contains('-'),
+        ..Default::default()
+      })
+    })
+    .collect::<Vec<_>>();
+  (!commands.is_empty()).then_some(commands)
+}
+
 fn collect_env(svc: &HashMap<String, Vec<String>>) -> HashMap<String, String> {
   let mut env: HashMap<String, String> = HashMap::new();
   for v in all_values(svc, "Environment") {
@@ -583,6 +635,9 @@ fn facet_item(name: &str) -> FlowItem {
     impulse: None,
     target: None,
     branch: None,
+    debounce: None,
+    hold: None,
+    on: None,
   }
 }
 
@@ -591,9 +646,9 @@ fn systemd_restart_to_policy(value: &str) -> Option<RestartPolicy> {
     "no" | "" => None,
     "always" => Some(RestartPolicy::Bool(true)),
     "on-success" | "on-failure" | "on-abnormal" | "on-abort" | "on-watchdog" => {
-      Some(RestartPolicy::OnFailure {
+      Some(RestartPolicy::OnFailure(RetryLimit {
         max_retries: u32::MAX,
-      })
+      }))
     }
     _ => None,
   }
@@ -720,9 +775,9 @@ Description = A \\
     );
     assert_eq!(
       systemd_restart_to_policy("on-failure"),
-      Some(RestartPolicy::OnFailure {
+      Some(RestartPolicy::OnFailure(RetryLimit {
         max_retries: u32::MAX
-      })
+      }))
     );
     assert_eq!(systemd_restart_to_policy("no"), None);
   }
@@ -737,11 +792,15 @@ Description = A \\
   }
 
   #[test]
-  fn parse_on_calendar_supports_keywords() {
-    assert_eq!(parse_on_calendar("daily"), Some("1d".to_string()));
-    assert_eq!(parse_on_calendar("weekly"), Some("7d".to_string()));
-    assert_eq!(parse_on_calendar("hourly"), Some("1h".to_string()));
-    assert_eq!(parse_on_calendar("yearly"), Some("365d".to_string()));
+  fn parse_on_calendar_keeps_calendar_expressions() {
+    assert_eq!(parse_on_calendar("daily"), Some("daily".to_string()));
+    assert_eq!(parse_on_calendar(" weekly "), Some("weekly".to_string()));
+    assert_eq!(
+      parse_on_calendar("Mon..Fri *-*-* 09:00:00"),
+      Some("Mon..Fri *-*-* 09:00:00".to_string())
+    );
+    assert_eq!(parse_on_calendar("*:0/15"), Some("*:0/15".to_string()));
+    assert_eq!(parse_on_calendar("not a calendar"), None);
   }
 
   #[test]
@@ -772,6 +831,61 @@ ListenUNIXSocket=/run/foo.sock
     assert_eq!(sock[0].listen, "/run/foo.sock");
   }
 
+  #[test]
+  fn load_socket_maps_listen_kinds_and_options() {
+    let mut m = build_metadata();
+    let src = "\
+[Socket]
+ListenFIFO=/run/foo.fifo
+SocketMode=0600
+SocketGroup=wheel
+Backlog=64
+";
+    load_into("fifo", &parse_ini(src), &mut m);
+    let sock = &m.get_in_group::<Socket>("fifo").unwrap()[0];
+    assert_eq!(sock.r#type, SocketType::Fifo);
+    assert_eq!(sock.mode.as_ref().map(|m| m.as_str()), Some("0600"));
+    assert_eq!(sock.group.as_ref().map(|g| g.as_str()), Some("wheel"));
+    assert_eq!(sock.backlog, Some(64));
+
+    let src = "\
+[Socket]
+ListenNetlink=kobject-uevent 1
+";
+    load_into("uevent", &parse_ini(src), &mut m);
+    let sock = &m.get_in_group::<Socket>("uevent").unwrap()[0];
+    assert_eq!(sock.r#type, SocketType::Netlink);
+    assert_eq!(sock.listen, "kobject-uevent 1");
+
+    let src = "\
+[Socket]
+ListenStream=[::]:80
+ReusePort=yes
+KeepAlive=true
+FreeBind=yes
+BindIPv6Only=ipv6-only
+BindToDevice=eth0
+";
+    load_into("web6", &parse_ini(src), &mut m);
+    let sock = &m.get_in_group::<Socket>("web6").unwrap()[0];
+    assert_eq!(sock.r#type, SocketType::Tcp);
+    assert!(sock.reuse_port && sock.keepalive && sock.freebind && sock.ipv6_only);
+    assert_eq!(
+      sock.bind_to_device.as_ref().map(|d| d.as_str()),
+      Some("eth0")
+    );
+
+    let (kind, _) =
+      socket_listen(&parse_ini("[Socket]\nListenStream=@rind/abstract\n").sections[0].1);
+    assert_eq!(kind, Some(SocketType::Uds));
+    let (kind, _) =
+      socket_listen(&parse_ini("[Socket]\nListenDatagram=/run/log.sock\n").sections[0].1);
+    assert_eq!(kind, Some(SocketType::UdsDgram));
+    let (kind, _) =
+      socket_listen(&parse_ini("[Socket]\nListenSequentialPacket=/run/sp\n").sections[0].1);
+    assert_eq!(kind, Some(SocketType::Seqpacket));
+  }
+
   #[test]
   fn load_timer_inserts_metadata() {
     let mut m = build_metadata();
@@ -784,7 +898,10 @@ OnCalendar=daily
     let tmr = m.get_in_group::<Timer>("daily-rotate").unwrap();
     assert_eq!(tmr.len(), 1);
     assert_eq!(tmr[0].name.as_str(), "daily-rotate");
-    assert_eq!(tmr[0].duration.as_str(), "1d");
+    assert_eq!(
+      tmr[0].on_calendar.as_ref().map(|s| s.as_str()),
+      Some("daily")
+    );
   }
 
   #[test]
@@ -798,6 +915,50 @@ OnBootSec=30s
     load_into("boot-timer", &ini, &mut m);
     let tmr = m.get_in_group::<Timer>("boot-timer").unwrap();
     assert_eq!(tmr[0].duration.as_str(), "30s");
+    assert!(!tmr[0].repeat);
+  }
+
+  #[test]
+  fn load_timer_with_repeat_jitter_and_persistence() {
+    let mut m = build_metadata();
+    let src = "\
+[Timer]
+OnUnitActiveSec=15m
+RandomizedDelaySec=30s
+AccuracySec=1m
+Persistent=true
+";
+    let ini = parse_ini(src);
+    load_into("sync", &ini, &mut m);
+    let tmr = m.get_in_group::<Timer>("sync").unwrap();
+    assert_eq!(tmr[0].duration.as_str(), "15m");
+    assert!(tmr[0].repeat);
+    assert!(tmr[0].persistent);
+    assert_eq!(
+      tmr[0].randomized_delay.as_ref().map(|s| s.as_str()),
+      Some("30s")
+    );
+    assert_eq!(tmr[0].accuracy.as_ref().map(|s| s.as_str()), Some("1m"));
+  }
+
+  #[test]
+  fn load_timer_links_its_service() {
+    let mut m = build_metadata();
+    load_into("rotate", &parse_ini("[Timer]\nOnCalendar=daily\n"), &mut m);
+    let tmr = m.get_in_group::<Timer>("rotate").unwrap();
+    assert_eq!(
+      tmr[0].unit.as_ref().map(|s| s.as_str()),
+      Some("rotate:rotate")
+    );
+
+    let mut m = build_metadata();
+    let src = "[Timer]\nOnCalendar=daily\nUnit=logrotate.service\n";
+    load_into("rotate", &parse_ini(src), &mut m);
+    let tmr = m.get_in_group::<Timer>("rotate").unwrap();
+    assert_eq!(
+      tmr[0].unit.as_ref().map(|s| s.as_str()),
+      Some("logrotate:logrotate")
+    );
   }
 
   #[test]
@@ -994,6 +1155,7 @@ Requires=basic.target
       rind_flow::FlowItem::Detailed { facet, .. } => {
         facet.as_ref().map(|u| u.to_string()).unwrap_or_default()
       }
+      _ => String::new(),
     }
   }
 
@@ -1227,6 +1389,51 @@ ExecStart=/usr/bin/requires-basic
     );
   }
 
+  #[test]
+  fn exec_hooks_map_to_command_lists() {
+    let mut m = build_metadata();
+    let src = "\
+[Service]
+ExecStartPre=-/usr/bin/mkdir -p /run/daemon
+ExecStartPre=/usr/bin/daemon --check
+ExecStart=/usr/bin/daemon
+ExecReload=/bin/kill -HUP $MAINPID
+";
+    let ini = parse_ini(src);
+    load_into("daemon", &ini, &mut m);
+
+    let svc = m.get_in_group::<Service>("daemon").unwrap();
+    let pre = svc[0].pre_start.as_ref().expect("pre-start commands");
+    assert_eq!(pre.len(), 2);
+    assert_eq!(pre[0].exec.as_str(), "/usr/bin/mkdir");
+    assert!(pre[0].ignore_failure && !pre[1].ignore_failure);
+    assert_eq!(pre[1].args, vec![Ustr::from("--check")]);
+    let reload = &svc[0].reload.as_ref().unwrap()[0];
+    assert_eq!(reload.exec.as_str(), "/bin/kill");
+    // `$MAINPID` is expanded when the hook runs
+    assert_eq!(
+      reload.args,
+      vec![Ustr::from("-HUP"), Ustr::from("$MAINPID")]
+    );
+    assert!(svc[0].stop.is_none());
+    assert!(svc[0].post_start.is_none());
+  }
+
+  #[test]
+  fn notify_type_maps_to_notify_service() {
+    let mut m = build_metadata();
+    let src = "\
+[Service]
+Type=notify
+ExecStart=/usr/bin/daemon
+";
+    let ini = parse_ini(src);
+    load_into("daemon", &ini, &mut m);
+
+    let svc = m.get_in_group::<Service>("daemon").unwrap();
+    assert_eq!(svc[0].r#type, ServiceType::Notify);
+  }
+
   #[test]
   fn service_with_no_targets_has_no_start_on() {
     let mut m = build_metadata();
@@ -147,7 +147,7 @@ impl SeatRuntime {
   }
 
   fn has_login_required(&self, sm: &FacetGraph, seat: &str) -> bool {
-    sm.facets.get("seat:login_required").map_or(false, |x| {
+    sm.get("seat:login_required").map_or(false, |x| {
       x.iter().any(|x| {
         x.payload
           .get_json_field_as::<String>("seat")
@@ -170,9 +170,9 @@ impl SeatRuntime {
         .dispatch(dispatch)?;
     }
 
-    if sm.facets.get("seat:taken").map_or(true, |x| {
+    if sm.get("seat:taken").map_or(true, |x| {
       !x.iter().any(|x| x.payload.to_string_payload() == seat)
-    }) && sm.facets.get("rind:user_session").map_or(true, |x| {
+    }) && sm.get("rind:user_session").map_or(true, |x| {
       !x.iter().any(|x| {
         x.payload
           .get_json_field_as::<String>("seat")
@@ -269,7 +269,6 @@ impl SeatRuntime {
       .ok_or(CoreError::RuntimeStopped)?;
 
     if let Some(target_seat) = sm
-      .facets
       .get("seat:active")
       .and_then(|instances| instances.first())
       .map(|x| x.payload.to_string_payload())
@@ -310,7 +309,7 @@ impl SeatRuntime {
         ))?
         .resolve(
           "boot",
-          SeatPayload::Taken(sm.facets.get("seat:taken").map_or(Default::default(), |x| {
+          SeatPayload::Taken(sm.get("seat:taken").map_or(Default::default(), |x| {
             x.iter()
               .map(|x| x.payload.to_string_payload().to_ustr())
               .collect()
@@ -418,7 +417,7 @@ impl SeatRuntime {
         .singleton::<FacetGraph>(FacetGraph::KEY)
         .ok_or(CoreError::RuntimeStopped)?;
 
-      if sm.facets.get("seat:active").map_or(true, |x| {
+      if sm.get("seat:active").map_or(true, |x| {
         !x.iter().any(|x| x.payload.to_string_payload() == seat_name)
       }) {
         FlowRuntime::actions
@@ -469,8 +468,7 @@ fn handle_ipc_seat(
   match payload {
     SeatPayload::Check => {
       return Ok(Message::ok(
-        sm.facets
-          .get("seat:active")
+        sm.get("seat:active")
           .and_then(|x| x.first().map(|x| x.payload.to_string_payload()))
           .unwrap_or("seat0".to_string()),
       ));
@@ -491,36 +489,31 @@ fn handle_ipc_seat(
       RuntimePayload::default().insert("seat", seat),
     )?,
     SeatPayload::List => {
-      let taken: Vec<String> = sm.facets.get("seat:taken").map_or(Vec::new(), |x| {
+      let taken: Vec<String> = sm.get("seat:taken").map_or(Vec::new(), |x| {
         x.iter().map(|x| x.payload.to_string_payload()).collect()
       });
-      let login_required: Vec<String> =
-        sm.facets
-          .get("seat:login_required")
-          .map_or(Vec::new(), |x| {
-            x.iter()
-              .filter_map(|x| x.payload.get_json_field_as::<String>("seat"))
-              .collect()
-          });
+      let login_required: Vec<String> = sm.get("seat:login_required").map_or(Vec::new(), |x| {
+        x.iter()
+          .filter_map(|x| x.payload.get_json_field_as::<String>("seat"))
+          .collect()
+      });
       let active = sm
-        .facets
         .get("seat:active")
         .and_then(|x| x.first())
         .map(|x| x.payload.to_string_payload())
         .unwrap_or_default();
-      let sessions: Vec<serde_json::Value> =
-        sm.facets.get("seat:session").map_or(Vec::new(), |x| {
-          x.iter()
-            .filter_map(|x| {
-              let v = x.payload.to_json();
-              Some(json!({
-                "seat": v.get("seat")?,
-                "session": v.get("session")?,
-                "user": v.get("user")?,
-              }))
-            })
-            .collect()
-        });
+      let sessions: Vec<serde_json::Value> = sm.get("seat:session").map_or(Vec::new(), |x| {
+        x.iter()
+          .filter_map(|x| {
+            let v = x.payload.to_json();
+            Some(json!({
+              "seat": v.get("seat")?,
+              "session": v.get("session")?,
+              "user": v.get("user")?,
+            }))
+          })
+          .collect()
+      });
 
       return Ok(Message::ok(
         serde_json::to_string(&json!({